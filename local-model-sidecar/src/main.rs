@@ -24,6 +24,8 @@ enum SidecarRequest {
     Completion(PromptRequest),
     #[serde(rename = "embedding")]
     Embedding(EmbeddingRequest),
+    #[serde(rename = "rerank")]
+    Rerank(RerankRequest),
 }
 
 /// Request structure for prompts sent to the sidecar via stdin
@@ -44,6 +46,17 @@ struct EmbeddingRequest {
     model: Option<String>,
 }
 
+/// Request structure for cross-encoder reranking
+#[derive(Debug, Deserialize)]
+struct RerankRequest {
+    /// The query passages are scored against
+    query: String,
+    /// Passages to score, in the order scores are returned
+    passages: Vec<String>,
+    /// Optional model specification (defaults to ms-marco-MiniLM-L-6-v2)
+    model: Option<String>,
+}
+
 /// Unified response structure for both completions and embeddings
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
@@ -52,6 +65,8 @@ enum SidecarResponse {
     Completion(CompletionResponse),
     #[serde(rename = "embedding")]
     Embedding(EmbeddingResponse),
+    #[serde(rename = "rerank")]
+    Rerank(RerankResponse),
 }
 
 /// Response structure for completions sent back via stdout
@@ -78,11 +93,24 @@ struct EmbeddingResponse {
     error: Option<String>,
 }
 
+/// Response structure for reranking sent back via stdout
+#[derive(Debug, Serialize)]
+struct RerankResponse {
+    /// Relevance of each passage to the query (0.0 to 1.0)
+    scores: Vec<f32>,
+    /// Success status
+    success: bool,
+    /// Error message if any
+    error: Option<String>,
+}
+
 /// Model file paths and configuration constants
 const PHI3_MODEL_ID: &str = "microsoft/Phi-3-mini-4k-instruct";
 const PHI3_REVISION: &str = "main";
 const EMBEDDING_MODEL_ID: &str = "sentence-transformers/all-MiniLM-L6-v2";
 const EMBEDDING_REVISION: &str = "main";
+const RERANKER_MODEL_ID: &str = "cross-encoder/ms-marco-MiniLM-L-6-v2";
+const RERANKER_MAX_TOKENS: usize = 512; // BERT position embedding limit
 const DEFAULT_MAX_TOKENS: usize = 100;
 const DEFAULT_TEMPERATURE: f64 = 0.7;
 const DEFAULT_TOP_P: f64 = 0.9;
@@ -104,6 +132,12 @@ struct ModelEngine {
     config: Option<Phi3Config>,
     /// Embedding model configuration
     embedding_config: Option<BertConfig>,
+    /// Cross-encoder tokenizer for reranking
+    reranker_tokenizer: Option<Arc<Tokenizer>>,
+    /// Cross-encoder BERT encoder
+    reranker_model: Option<Arc<BertModel>>,
+    /// Cross-encoder relevance head over the [CLS] token
+    reranker_classifier: Option<candle_nn::Linear>,
     /// Model configuration parameters
     max_seq_len: usize,
     temperature: f64,
@@ -112,6 +146,10 @@ struct ModelEngine {
     is_loaded: bool,
     /// Embedding model loading status
     embedding_loaded: bool,
+    /// Reranker model loading status
+    reranker_loaded: bool,
+    /// Whether loading the reranker was tried; a failed load is not retried
+    reranker_load_attempted: bool,
 }
 
 impl ModelEngine {
@@ -128,11 +166,16 @@ impl ModelEngine {
             embedding_model: None,
             config: None,
             embedding_config: None,
+            reranker_tokenizer: None,
+            reranker_model: None,
+            reranker_classifier: None,
             max_seq_len: 2048,
             temperature: DEFAULT_TEMPERATURE,
             top_p: DEFAULT_TOP_P,
             is_loaded: false,
             embedding_loaded: false,
+            reranker_loaded: false,
+            reranker_load_attempted: false,
         })
     }
     
@@ -241,6 +284,87 @@ impl ModelEngine {
         Ok(())
     }
     
+    /// Load the cross-encoder reranker (cross-encoder/ms-marco-MiniLM-L-6-v2) from HuggingFace Hub
+    async fn load_reranker_model(&mut self) -> Result<()> {
+        info!("Loading cross-encoder reranker from HuggingFace Hub: {}", RERANKER_MODEL_ID);
+        
+        let api = Api::new()?;
+        let repo = api.model(RERANKER_MODEL_ID.to_string());
+        
+        let config_path = repo.get("config.json").await
+            .map_err(|e| anyhow::anyhow!("Failed to download reranker config.json: {}", e))?;
+        let tokenizer_path = repo.get("tokenizer.json").await
+            .map_err(|e| anyhow::anyhow!("Failed to download reranker tokenizer.json: {}", e))?;
+        let model_path = repo.get("model.safetensors").await
+            .map_err(|e| anyhow::anyhow!("Failed to download reranker model.safetensors: {}", e))?;
+        
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow::anyhow!("Failed to load reranker tokenizer: {}", e))?;
+        
+        let config_content = std::fs::read_to_string(&config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read reranker config file: {}", e))?;
+        let config: BertConfig = serde_json::from_str(&config_content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse reranker config: {}", e))?;
+        let hidden_size = serde_json::from_str::<serde_json::Value>(&config_content)?["hidden_size"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Reranker config has no hidden_size"))? as usize;
+        
+        // Sequence classification checkpoint: BERT encoder under "bert", one-logit head under "classifier"
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[&model_path], DType::F32, &self.device)? };
+        let model = BertModel::load(vb.pp("bert"), &config)
+            .map_err(|e| anyhow::anyhow!("Failed to initialize reranker encoder: {}", e))?;
+        let classifier = candle_nn::linear(hidden_size, 1, vb.pp("classifier"))
+            .map_err(|e| anyhow::anyhow!("Failed to initialize reranker head: {}", e))?;
+        
+        self.reranker_tokenizer = Some(Arc::new(tokenizer));
+        self.reranker_model = Some(Arc::new(model));
+        self.reranker_classifier = Some(classifier);
+        self.reranker_loaded = true;
+        info!("✅ Cross-encoder reranker loaded successfully and ready for inference!");
+        
+        Ok(())
+    }
+    
+    /// Load the reranker on the first rerank request instead of at startup, so sessions
+    /// that never rerank skip the download
+    async fn ensure_reranker_loaded(&mut self) {
+        if self.reranker_loaded || self.reranker_load_attempted {
+            return;
+        }
+        self.reranker_load_attempted = true;
+        if let Err(e) = self.load_reranker_model().await {
+            warn!("Reranker loading failed: {}. Rerank requests will report an error.", e);
+        }
+    }
+    
+    /// Relevance of each passage to the query (0.0 to 1.0) from the cross-encoder.
+    /// There is no placeholder: without the model, callers keep their own ranking.
+    fn rerank(&self, query: &str, passages: &[String]) -> Result<Vec<f32>> {
+        let (Some(tokenizer), Some(model), Some(classifier)) =
+            (&self.reranker_tokenizer, &self.reranker_model, &self.reranker_classifier)
+        else {
+            return Err(anyhow::anyhow!("Reranker model not loaded"));
+        };
+        
+        passages
+            .iter()
+            .map(|passage| {
+                let encoding = tokenizer.encode((query, passage.as_str()), true)
+                    .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+                let length = encoding.get_ids().len().min(RERANKER_MAX_TOKENS);
+                
+                let input_ids = Tensor::new(&encoding.get_ids()[..length], &self.device)?.unsqueeze(0)?;
+                let token_type_ids = Tensor::new(&encoding.get_type_ids()[..length], &self.device)?.unsqueeze(0)?;
+                
+                // Score the [CLS] token, then squash the logit to a 0-1 relevance
+                let hidden = model.forward(&input_ids, &token_type_ids)?;
+                let cls = hidden.get(0)?.get(0)?.unsqueeze(0)?;
+                let logit = candle_nn::Module::forward(classifier, &cls)?.flatten_all()?.to_vec1::<f32>()?[0];
+                Ok(1.0 / (1.0 + (-logit).exp()))
+            })
+            .collect()
+    }
+    
     /// Generate completion using the loaded Phi-3-mini model
     async fn generate_completion(&self, context: &str, max_tokens: Option<usize>) -> Result<String> {
         debug!("Generating completion for context: '{}...'", 
@@ -553,6 +677,27 @@ async fn process_prompt(engine: &ModelEngine, request: PromptRequest) -> Result<
     }
 }
 
+/// Score a rerank request's passages with the cross-encoder
+fn process_rerank(engine: &ModelEngine, request: RerankRequest) -> RerankResponse {
+    debug!("Reranking {} passages (model: {:?})", request.passages.len(), request.model);
+    
+    match engine.rerank(&request.query, &request.passages) {
+        Ok(scores) => RerankResponse {
+            scores,
+            success: true,
+            error: None,
+        },
+        Err(e) => {
+            error!("Rerank error: {}", e);
+            RerankResponse {
+                scores: Vec::new(),
+                success: false,
+                error: Some(format!("Rerank error: {}", e)),
+            }
+        }
+    }
+}
+
 /// Main sidecar event loop
 /// Listens on stdin for JSON prompt requests and writes JSON responses to stdout
 #[tokio::main]
@@ -582,6 +727,16 @@ async fn main() -> Result<()> {
         
         debug!("Received input: {}", line);
         
+        // Rerank requests are tagged; other input is a plain prompt request
+        if let Ok(SidecarRequest::Rerank(request)) = serde_json::from_str::<SidecarRequest>(&line) {
+            engine.ensure_reranker_loaded().await;
+            let response_json = serde_json::to_string(&SidecarResponse::Rerank(process_rerank(&engine, request)))?;
+            stdout.write_all(response_json.as_bytes()).await?;
+            stdout.write_all(b"\n").await?;
+            stdout.flush().await?;
+            continue;
+        }
+        
         // Parse the prompt request
         let response = match serde_json::from_str::<PromptRequest>(&line) {
             Ok(request) => {
@@ -658,6 +813,20 @@ mod tests {
         assert!(response.completion.contains("Implementation") || response.completion.contains("implementation"));
     }
     
+    #[test]
+    fn test_rerank_without_model_reports_error() {
+        let engine = ModelEngine::new().unwrap();
+        let line = r#"{"type":"rerank","query":"sqlite","passages":["SQLite notes"]}"#;
+        let Ok(SidecarRequest::Rerank(request)) = serde_json::from_str::<SidecarRequest>(line) else {
+            panic!("rerank request should parse");
+        };
+        
+        let response = process_rerank(&engine, request);
+        assert!(!response.success);
+        assert!(response.scores.is_empty());
+        assert!(response.error.unwrap().contains("not loaded"));
+    }
+    
     #[tokio::test]
     async fn test_candle_placeholder_completion() {
         let engine = ModelEngine::new().unwrap();
@@ -1,6 +1,9 @@
+use crate::application::ai_provider::AiProvider;
 use crate::application::ai_provider_manager::{AiProviderManager, utils};
-use crate::infrastructure::credential_manager;
-use crate::infrastructure::{BedrockProvider, GeminiProvider, LocalProvider};
+use crate::infrastructure::credential_manager::{self, CredentialManager};
+use crate::infrastructure::{
+    AnthropicProvider, AnthropicProviderConfig, BedrockProvider, GeminiProvider, LocalProvider, OpenAiCompatibleProvider,
+};
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, State};
 use std::collections::HashMap;
@@ -257,6 +260,135 @@ pub async fn validate_gemini_credentials(
     }
 }
 
+/// Set the OpenAI API key used by the OpenAI-compatible provider
+#[tauri::command]
+pub async fn set_openai_credentials(api_key: String) -> Result<CredentialResponse, String> {
+    // Validate input parameters
+    if api_key.trim().is_empty() {
+        return Ok(CredentialResponse {
+            success: false,
+            message: "API key cannot be empty".to_string(),
+            details: None,
+        });
+    }
+
+    // Store the key in the keychain, where the provider reads it from
+    if let Err(e) = CredentialManager::new().store_openai_api_key(api_key.trim()) {
+        return Ok(CredentialResponse {
+            success: false,
+            message: format!("Failed to store OpenAI API key: {}", e),
+            details: None,
+        });
+    }
+
+    match validate_openai_credentials_internal().await {
+        Ok(true) => Ok(CredentialResponse {
+            success: true,
+            message: "OpenAI API key stored and validated successfully".to_string(),
+            details: Some({
+                let mut details = HashMap::new();
+                details.insert("api_key_prefix".to_string(),
+                    serde_json::Value::String(format!("{}***", &api_key[..8.min(api_key.len())])));
+                details
+            }),
+        }),
+        Ok(false) => Ok(CredentialResponse {
+            success: false,
+            message: "API key stored but validation failed. Please check your OpenAI API key.".to_string(),
+            details: None,
+        }),
+        Err(e) => Ok(CredentialResponse {
+            success: false,
+            message: format!("API key stored but validation failed: {}", e),
+            details: None,
+        }),
+    }
+}
+
+/// Set the Anthropic API key used by the Anthropic provider
+#[tauri::command]
+pub async fn set_anthropic_credentials(api_key: String) -> Result<CredentialResponse, String> {
+    // Validate input parameters
+    let api_key = api_key.trim().to_string();
+    if api_key.is_empty() {
+        return Ok(CredentialResponse {
+            success: false,
+            message: "API key cannot be empty".to_string(),
+            details: None,
+        });
+    }
+
+    // Store the key in the keychain, where the provider reads it from
+    if let Err(e) = CredentialManager::new().store_anthropic_api_key(&api_key) {
+        return Ok(CredentialResponse {
+            success: false,
+            message: format!("Failed to store Anthropic API key: {}", e),
+            details: None,
+        });
+    }
+
+    match validate_anthropic_credentials_internal(&api_key).await {
+        Ok(true) => Ok(CredentialResponse {
+            success: true,
+            message: "Anthropic API key stored and validated successfully".to_string(),
+            details: Some({
+                let mut details = HashMap::new();
+                details.insert("api_key_prefix".to_string(),
+                    serde_json::Value::String(format!("{}***", &api_key[..8.min(api_key.len())])));
+                details
+            }),
+        }),
+        Ok(false) => Ok(CredentialResponse {
+            success: false,
+            message: "API key stored but validation failed. Please check your Anthropic API key.".to_string(),
+            details: None,
+        }),
+        Err(e) => Ok(CredentialResponse {
+            success: false,
+            message: format!("API key stored but validation failed: {}", e),
+            details: None,
+        }),
+    }
+}
+
+/// Validate the stored Anthropic API key
+#[tauri::command]
+pub async fn validate_anthropic_credentials() -> Result<CredentialResponse, String> {
+    let api_key = match CredentialManager::new().retrieve_anthropic_api_key() {
+        Ok(api_key) => api_key,
+        Err(e) => {
+            return Ok(CredentialResponse {
+                success: false,
+                message: format!("Failed to retrieve Anthropic API key: {}", e),
+                details: None,
+            });
+        }
+    };
+
+    match validate_anthropic_credentials_internal(&api_key).await {
+        Ok(true) => Ok(CredentialResponse {
+            success: true,
+            message: "Anthropic API key is valid".to_string(),
+            details: Some({
+                let mut details = HashMap::new();
+                details.insert("api_key_prefix".to_string(),
+                    serde_json::Value::String(format!("{}***", &api_key[..8.min(api_key.len())])));
+                details
+            }),
+        }),
+        Ok(false) => Ok(CredentialResponse {
+            success: false,
+            message: "Anthropic API key validation failed".to_string(),
+            details: None,
+        }),
+        Err(e) => Ok(CredentialResponse {
+            success: false,
+            message: format!("Anthropic API key validation error: {}", e),
+            details: None,
+        }),
+    }
+}
+
 /// Select active AI provider
 #[tauri::command]
 pub async fn select_active_provider(
@@ -328,6 +460,9 @@ pub async fn list_ai_providers(
         }
     };
 
+    // The active provider reports availability for its own configuration
+    let active_info = provider_manager.get_active_provider_info().await.ok();
+
     // Get list of registered providers
     match provider_manager.list_registered_providers().await {
         Ok(providers_info) => {
@@ -353,6 +488,14 @@ pub async fn list_ai_providers(
                             .await
                             .is_ok()
                     }
+                    // Ollama needs no credentials; the provider probes its server
+                    "ollama" => info.is_available,
+                    // A server configured without an API key (use_api_key: false) needs none
+                    "openai_compatible" => match &active_info {
+                        Some(active) if active.provider_type == "openai_compatible" => active.is_available,
+                        _ => CredentialManager::new().retrieve_openai_api_key().is_ok(),
+                    },
+                    "anthropic" => CredentialManager::new().retrieve_anthropic_api_key().is_ok(),
                     _ => false,
                 };
 
@@ -481,6 +624,19 @@ async fn validate_gemini_credentials_internal(api_key: &str) -> Result<bool, Str
     }
 }
 
+/// Internal function to validate the stored OpenAI API key by listing models
+async fn validate_openai_credentials_internal() -> Result<bool, String> {
+    OpenAiCompatibleProvider::new().validate_configuration().await.map_err(|e| e.to_string())
+}
+
+/// Internal function to validate an Anthropic API key by listing models with it
+async fn validate_anthropic_credentials_internal(api_key: &str) -> Result<bool, String> {
+    AnthropicProvider::with_api_key(AnthropicProviderConfig::default(), api_key.to_string())
+        .validate_configuration()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,6 +49,9 @@ pub struct StreamChunk {
     pub metadata: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// Receives the chunks of a streamed response in order; an error stops the stream
+pub type StreamSink<'a> = dyn Fn(StreamChunk) -> Result<(), AiProviderError> + Send + Sync + 'a;
+
 /// Error types for AI provider operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AiProviderError {
@@ -178,6 +181,25 @@ pub trait AiProvider: Send + Sync {
         app_handle: &tauri::AppHandle,
     ) -> Result<(), AiProviderError>;
 
+    /// Streaming interface that hands chunks to `sink` instead of the frontend
+    /// 
+    /// Used when the caller needs the streamed text itself, e.g. to resolve the
+    /// citations of an answer once it is complete. The default implementation sends
+    /// the complete `invoke_model` response as one chunk, then the final chunk.
+    /// 
+    /// # Arguments
+    /// * `context` - Conversation context including messages and configuration
+    /// * `sink` - Receives every chunk, ending with one where `is_final` is set
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
+    ) -> Result<(), AiProviderError> {
+        let response = self.invoke_model(context).await?;
+        sink(StreamChunk { content: response, is_final: false, metadata: None })?;
+        sink(StreamChunk { content: String::new(), is_final: true, metadata: None })
+    }
+
     /// Non-streaming interface for AI model invocation
     /// 
     /// This method returns the complete response as a single string.
@@ -293,6 +315,15 @@ pub mod utils {
         }
     }
 
+    /// Send a stream chunk to the frontend as an `ai_stream_chunk` event
+    pub fn emit_stream_chunk(app_handle: &tauri::AppHandle, chunk: &StreamChunk) -> Result<(), AiProviderError> {
+        use tauri::Manager;
+
+        app_handle
+            .emit_all("ai_stream_chunk", chunk)
+            .map_err(|e| AiProviderError::ProviderError(format!("Failed to emit stream chunk: {}", e)))
+    }
+
     /// Merge two model configurations, with the second taking precedence
     pub fn merge_configs(base: ModelConfig, override_config: ModelConfig) -> ModelConfig {
         ModelConfig {
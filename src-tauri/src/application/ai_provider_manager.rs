@@ -9,8 +9,8 @@
 
 use crate::application::ai_provider::{AiProvider, AiProviderError, AiProviderFactory};
 use crate::infrastructure::{
-    BedrockProvider, BedrockProviderFactory, GeminiProvider, GeminiProviderFactory,
-    LocalProvider, LocalProviderFactory,
+    AnthropicProviderFactory, BedrockProvider, BedrockProviderFactory, GeminiProvider, GeminiProviderFactory,
+    LocalProvider, LocalProviderFactory, OllamaProviderFactory, OpenAiCompatibleProviderFactory,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -63,6 +63,9 @@ impl AiProviderManager {
         factory_registry.insert("local".to_string(), Box::new(LocalProviderFactory) as Box<dyn AiProviderFactory>);
         factory_registry.insert("bedrock".to_string(), Box::new(BedrockProviderFactory) as Box<dyn AiProviderFactory>);
         factory_registry.insert("gemini".to_string(), Box::new(GeminiProviderFactory) as Box<dyn AiProviderFactory>);
+        factory_registry.insert("openai_compatible".to_string(), Box::new(OpenAiCompatibleProviderFactory) as Box<dyn AiProviderFactory>);
+        factory_registry.insert("ollama".to_string(), Box::new(OllamaProviderFactory) as Box<dyn AiProviderFactory>);
+        factory_registry.insert("anthropic".to_string(), Box::new(AnthropicProviderFactory) as Box<dyn AiProviderFactory>);
         
         let factory_registry = Arc::new(Mutex::new(factory_registry));
         let active_provider_type = Arc::new(Mutex::new("local".to_string()));
@@ -354,6 +357,9 @@ impl Clone for Box<dyn AiProviderFactory> {
             "local" => Box::new(LocalProviderFactory),
             "bedrock" => Box::new(BedrockProviderFactory),
             "gemini" => Box::new(GeminiProviderFactory),
+            "openai_compatible" => Box::new(OpenAiCompatibleProviderFactory),
+            "ollama" => Box::new(OllamaProviderFactory),
+            "anthropic" => Box::new(AnthropicProviderFactory),
             _ => panic!("Unknown provider type for cloning"),
         }
     }
@@ -384,12 +390,15 @@ mod tests {
         let manager = AiProviderManager::new();
         let providers = manager.list_registered_providers().await.unwrap();
         
-        assert_eq!(providers.len(), 3);
+        assert_eq!(providers.len(), 6);
         
         let provider_types: Vec<String> = providers.iter().map(|p| p.provider_type.clone()).collect();
         assert!(provider_types.contains(&"local".to_string()));
         assert!(provider_types.contains(&"bedrock".to_string()));
         assert!(provider_types.contains(&"gemini".to_string()));
+        assert!(provider_types.contains(&"openai_compatible".to_string()));
+        assert!(provider_types.contains(&"ollama".to_string()));
+        assert!(provider_types.contains(&"anthropic".to_string()));
         
         // Check that local is the active provider
         let active_provider = providers.iter().find(|p| p.is_active).unwrap();
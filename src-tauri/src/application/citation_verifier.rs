@@ -0,0 +1,382 @@
+// Citation Verifier
+// Checks a generated answer against the excerpts it cites. The answer is split into
+// sentences, and every sentence that cites a source is scored against the cited
+// excerpts by keyword overlap and, when an embedder is available, embedding
+// similarity, which can lift a paraphrase but never lowers the overlap score.
+// A sentence is supported by the best of its cited sources.
+
+use crate::application::conversation_retrieval::keywords;
+use crate::application::grounded_answer::{cited_markers, AnswerCitation};
+use crate::infrastructure::db_layer::{dot_product, normalize};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Embeds texts for similarity, one vector per text in the same order
+pub trait TextEmbedder: Send + Sync {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// Settings of citation verification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CitationVerificationConfig {
+    /// Whether answers are verified at all
+    pub enabled: bool,
+    /// Share of the blended score that comes from keyword overlap; the rest comes from
+    /// embedding similarity. A claim scores the better of the overlap and the blend,
+    /// and without embeddings the overlap alone.
+    pub lexical_weight: f32,
+    /// Claims scoring at least this (0.0 to 1.0) are supported
+    pub supported_threshold: f32,
+    /// Claims scoring at least this (0.0 to 1.0), but below `supported_threshold`, are weak
+    pub weak_threshold: f32,
+    /// Longest the embedder is waited for before claims are judged on overlap alone
+    pub embedding_timeout_ms: u64,
+}
+
+impl Default for CitationVerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lexical_weight: 0.5,
+            supported_threshold: 0.6,
+            weak_threshold: 0.35,
+            embedding_timeout_ms: 5_000,
+        }
+    }
+}
+
+/// How well the cited sources back a claim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimSupport {
+    Supported,
+    Weak,
+    Unsupported,
+}
+
+/// A cited sentence of an answer and how well its sources back it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedClaim {
+    /// The sentence as written, citation markers included
+    pub sentence: String,
+    /// Markers the sentence cites
+    pub markers: Vec<usize>,
+    /// Cited marker whose excerpt backs the sentence best; None when no cited marker
+    /// belongs to a source of the answer
+    pub best_marker: Option<usize>,
+    /// Share of the sentence's keywords found in the best excerpt (0.0 to 1.0)
+    pub lexical_overlap: f32,
+    /// Embedding similarity with the best excerpt, when embeddings were available
+    pub embedding_similarity: Option<f32>,
+    /// Combined score the support level was judged on
+    pub score: f32,
+    pub support: ClaimSupport,
+}
+
+/// Verification of every cited sentence of an answer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerificationReport {
+    pub claims: Vec<VerifiedClaim>,
+    pub supported: usize,
+    pub weak: usize,
+    pub unsupported: usize,
+    /// Sentences that cite nothing and so were not checked
+    pub uncited_sentences: usize,
+    /// Why embedding similarity was left out, when it was
+    pub embedding_error: Option<String>,
+}
+
+/// Sentences of an answer. Sentences end at `.`, `!` or `?` followed by whitespace
+/// and at line breaks; markers right after a full stop ("... yearly. [2]") are kept
+/// with the sentence they follow.
+pub fn split_sentences(answer: &str) -> Vec<String> {
+    let mut pieces = Vec::new();
+    for line in answer.lines() {
+        let mut current = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            current.push(c);
+            if matches!(c, '.' | '!' | '?') && chars.peek().is_none_or(|next| next.is_whitespace()) {
+                pieces.push(std::mem::take(&mut current));
+            }
+        }
+        pieces.push(current);
+    }
+
+    let mut sentences: Vec<String> = Vec::new();
+    for piece in pieces {
+        let mut rest = piece.trim();
+        // Move leading marker groups to the previous sentence
+        while let (Some(previous), true) = (sentences.last_mut(), rest.starts_with('[')) {
+            let Some(close) = rest.find(']') else {
+                break;
+            };
+            if cited_markers(&rest[..=close]).is_empty() {
+                break;
+            }
+            previous.push(' ');
+            previous.push_str(&rest[..=close]);
+            rest = rest[close + 1..].trim_start();
+        }
+        if !rest.is_empty() {
+            sentences.push(rest.to_string());
+        }
+    }
+    sentences
+}
+
+/// Text of a sentence without its citation markers
+fn claim_text(sentence: &str) -> String {
+    let mut text = String::new();
+    let mut rest = sentence;
+    while let Some(open) = rest.find('[') {
+        let close = rest[open..].find(']').map(|close| open + close);
+        match close {
+            Some(close) if !cited_markers(&rest[open..=close]).is_empty() => {
+                text.push_str(&rest[..open]);
+                rest = &rest[close + 1..];
+            }
+            _ => {
+                text.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Share of the claim's keywords that occur in the excerpt
+pub fn lexical_overlap(claim: &str, excerpt: &str) -> f32 {
+    let claim_terms = keywords(claim);
+    if claim_terms.is_empty() {
+        return 0.0;
+    }
+    let excerpt_terms: HashSet<String> = keywords(excerpt).into_iter().collect();
+    let found = claim_terms.iter().filter(|term| excerpt_terms.contains(*term)).count();
+    found as f32 / claim_terms.len() as f32
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    (a.len() == b.len() && !a.is_empty()).then(|| dot_product(&normalize(a), &normalize(b)))
+}
+
+/// Embed texts on a worker thread, giving up after `timeout`. An embedder that times
+/// out finishes in the background and its vectors are discarded.
+pub fn embed_with_timeout(
+    embedder: Arc<dyn TextEmbedder>,
+    texts: Vec<String>,
+    timeout: Duration,
+) -> Result<Vec<Vec<f32>>, String> {
+    let expected = texts.len();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone after a timeout, so a late result is dropped
+        let _ = sender.send(embedder.embed(&texts));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(embeddings)) if embeddings.len() == expected => Ok(embeddings),
+        Ok(Ok(embeddings)) => Err(format!("Expected {} embeddings, got {}", expected, embeddings.len())),
+        Ok(Err(e)) => Err(e),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(format!("Embedding timed out after {} ms", timeout.as_millis())),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err("Embedder stopped without returning vectors".to_string()),
+    }
+}
+
+/// Verify every cited sentence of `answer` against the excerpts of `citations`.
+/// Without an embedder, or when it fails or times out, claims are judged on keyword
+/// overlap alone.
+pub fn verify_citations(
+    answer: &str,
+    citations: &[AnswerCitation],
+    embedder: Option<Arc<dyn TextEmbedder>>,
+    config: &CitationVerificationConfig,
+) -> VerificationReport {
+    let excerpts: HashMap<usize, &str> = citations.iter().map(|citation| (citation.marker, citation.text.as_str())).collect();
+    let mut report = VerificationReport::default();
+
+    let mut claims: Vec<(String, String, Vec<usize>)> = Vec::new();
+    for sentence in split_sentences(answer) {
+        let markers = cited_markers(&sentence);
+        if markers.is_empty() {
+            report.uncited_sentences += 1;
+        } else {
+            claims.push((claim_text(&sentence), sentence, markers));
+        }
+    }
+
+    // Embed the claims and the cited excerpts in one call
+    let mut texts: Vec<String> = claims.iter().map(|(claim, _, _)| claim.clone()).collect();
+    let mut excerpt_index: HashMap<usize, usize> = HashMap::new();
+    for (marker, excerpt) in &excerpts {
+        excerpt_index.insert(*marker, texts.len());
+        texts.push(excerpt.to_string());
+    }
+    let embeddings = match embedder {
+        Some(_) if claims.is_empty() => None,
+        Some(embedder) => match embed_with_timeout(embedder, texts, Duration::from_millis(config.embedding_timeout_ms)) {
+            Ok(embeddings) => Some(embeddings),
+            Err(e) => {
+                report.embedding_error = Some(e);
+                None
+            }
+        },
+        None => {
+            report.embedding_error = Some("No embedder available".to_string());
+            None
+        }
+    };
+
+    for (index, (claim, sentence, markers)) in claims.into_iter().enumerate() {
+        let mut best: Option<(usize, f32, Option<f32>, f32)> = None;
+        for marker in &markers {
+            let Some(excerpt) = excerpts.get(marker) else {
+                continue;
+            };
+            let overlap = lexical_overlap(&claim, excerpt);
+            let similarity = embeddings
+                .as_ref()
+                .and_then(|embeddings| cosine_similarity(&embeddings[index], &embeddings[excerpt_index[marker]]));
+            // Similarity only adds support, so an unrelated or opposed vector leaves the overlap
+            let score = match similarity {
+                Some(similarity) => overlap.max(config.lexical_weight * overlap + (1.0 - config.lexical_weight) * similarity),
+                None => overlap,
+            };
+            if best.is_none_or(|(_, _, _, best_score)| score > best_score) {
+                best = Some((*marker, overlap, similarity, score));
+            }
+        }
+
+        let (best_marker, lexical_overlap, embedding_similarity, score) = match best {
+            Some((marker, overlap, similarity, score)) => (Some(marker), overlap, similarity, score),
+            None => (None, 0.0, None, 0.0),
+        };
+        let support = if score >= config.supported_threshold {
+            report.supported += 1;
+            ClaimSupport::Supported
+        } else if score >= config.weak_threshold {
+            report.weak += 1;
+            ClaimSupport::Weak
+        } else {
+            report.unsupported += 1;
+            ClaimSupport::Unsupported
+        };
+        report.claims.push(VerifiedClaim {
+            sentence,
+            markers,
+            best_marker,
+            lexical_overlap,
+            embedding_similarity,
+            score,
+            support,
+        });
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation(marker: usize, text: &str) -> AnswerCitation {
+        AnswerCitation {
+            marker,
+            document_id: marker as i64,
+            path: None,
+            title: format!("Doc {}", marker),
+            byte_start: None,
+            byte_end: None,
+            text: text.to_string(),
+            passage: None,
+        }
+    }
+
+    /// Embeds texts as indicator vectors over groups of synonyms
+    #[derive(Clone)]
+    struct VocabularyEmbedder(&'static [&'static [&'static str]]);
+
+    impl TextEmbedder for VocabularyEmbedder {
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let terms = keywords(text);
+                    self.0.iter().map(|group| if terms.iter().any(|term| group.contains(&term.as_str())) { 1.0 } else { 0.0 }).collect()
+                })
+                .collect())
+        }
+    }
+
+    /// Points the excerpts of the test citations one way and every claim the other
+    struct OpposedEmbedder;
+
+    impl TextEmbedder for OpposedEmbedder {
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+            let excerpt = |text: &String| text.contains("billed") || text.contains("SQLite");
+            Ok(texts.iter().map(|text| if excerpt(text) { vec![1.0] } else { vec![-1.0] }).collect())
+        }
+    }
+
+    /// Answers long after any reasonable timeout
+    struct SlowEmbedder(VocabularyEmbedder);
+
+    impl TextEmbedder for SlowEmbedder {
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+            thread::sleep(Duration::from_secs(1));
+            self.0.embed(texts)
+        }
+    }
+
+    #[test]
+    fn test_split_sentences_keeps_trailing_markers() {
+        let sentences = split_sentences("Plans renew yearly [1]. Discounts apply to teams. [2][3]\n- Version 2.5 is current! Next?");
+        assert_eq!(
+            sentences,
+            vec!["Plans renew yearly [1].", "Discounts apply to teams. [2] [3]", "- Version 2.5 is current!", "Next?"]
+        );
+        assert_eq!(claim_text("Discounts apply [2][3] to teams. [see note]"), "Discounts apply to teams. [see note]");
+    }
+
+    #[test]
+    fn test_claims_are_judged_against_cited_excerpts() {
+        let citations = vec![
+            citation(1, "Team plans renew yearly and are billed in advance."),
+            citation(2, "The SQLite schema is migrated on startup."),
+        ];
+        let answer = "Team plans renew yearly [1]. The database schema migrates on startup [1][2]. \
+                      Plans include phone support [2]. This is all.";
+        let config = CitationVerificationConfig::default();
+
+        let report = verify_citations(answer, &citations, None, &config);
+        let support: Vec<ClaimSupport> = report.claims.iter().map(|claim| claim.support).collect();
+        assert_eq!(support, vec![ClaimSupport::Supported, ClaimSupport::Weak, ClaimSupport::Unsupported]);
+        assert_eq!(report.claims[1].best_marker, Some(2));
+        assert_eq!((report.supported, report.weak, report.unsupported, report.uncited_sentences), (1, 1, 1, 1));
+        assert!(report.embedding_error.is_some());
+
+        // Embedding similarity lifts a paraphrase the keywords alone judge weak
+        let embedder = VocabularyEmbedder(&[&["schema"], &["migrated", "migrates"], &["startup"], &["database", "sqlite"]]);
+        let report = verify_citations(answer, &citations, Some(Arc::new(embedder.clone())), &config);
+        assert_eq!(report.claims[1].support, ClaimSupport::Supported);
+        assert!(report.claims[1].embedding_similarity.is_some());
+        assert_eq!(report.embedding_error, None);
+
+        // Dissimilar vectors never score a claim below its keyword overlap
+        let opposed = Arc::new(OpposedEmbedder);
+        let report = verify_citations(answer, &citations, Some(opposed), &config);
+        assert_eq!(report.claims[0].support, ClaimSupport::Supported);
+        assert_eq!(report.claims[0].score, report.claims[0].lexical_overlap);
+
+        // An embedder that does not answer in time leaves the overlap alone
+        let slow = Arc::new(SlowEmbedder(embedder));
+        let config = CitationVerificationConfig { embedding_timeout_ms: 10, ..Default::default() };
+        let report = verify_citations(answer, &citations, Some(slow), &config);
+        assert_eq!(report.claims[1].support, ClaimSupport::Weak);
+        assert!(report.embedding_error.is_some_and(|e| e.contains("timed out")));
+    }
+}
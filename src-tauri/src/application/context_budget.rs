@@ -0,0 +1,264 @@
+// Context Budget
+// Builds the retrieved-context block sent to an AI provider within a token budget.
+// Every excerpt gets a numbered citation marker ("[1]") and an entry in a source
+// map, so answers can cite it and the UI can link the citation back to the document.
+// Excerpts are wrapped in escaped <document> blocks under an instruction to treat
+// them as data.
+
+use crate::application::injection_guard::{escape_block, UNTRUSTED_CONTEXT_INSTRUCTION};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Share of a provider's context window given to retrieved context; the rest is
+/// left for the system prompt, conversation and answer
+pub const CONTEXT_WINDOW_SHARE: f64 = 0.5;
+
+const CONTEXT_FOOTER: &str = "\nEnd of Context\n";
+
+/// Rough token count of a text: about four ASCII characters per token, and one
+/// token per other character (CJK, emoji, accented letters), which errs on the
+/// generous side for most tokenizers
+pub fn estimate_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), ch| {
+        if ch.is_ascii() { (ascii + 1, other) } else { (ascii, other + 1) }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Context token budget for a provider with the given context window
+pub fn context_tokens_for_window(window_tokens: u32) -> usize {
+    (window_tokens as f64 * CONTEXT_WINDOW_SHARE) as usize
+}
+
+/// Character limit that lets a token budget be filled: `estimate_tokens` counts at
+/// most four bytes per token, so the token budget always binds first
+pub fn context_chars_for_tokens(tokens: usize) -> usize {
+    tokens.saturating_mul(4)
+}
+
+/// Part of the context block an excerpt is placed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextSection {
+    /// Documents the prompt referenced explicitly
+    Referenced,
+    /// Results of hybrid retrieval
+    Retrieved,
+}
+
+impl ContextSection {
+    fn heading(&self) -> &'static str {
+        match self {
+            ContextSection::Referenced => "Referenced Documents:\n\n",
+            ContextSection::Retrieved => "Retrieved Context:\n\n",
+        }
+    }
+}
+
+/// A passage offered to the context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextExcerpt {
+    /// document_content.id (the document key)
+    pub document_id: i64,
+    pub path: Option<String>,
+    pub title: String,
+    /// Byte range of the excerpt in the source document, when known
+    pub byte_start: Option<usize>,
+    pub byte_end: Option<usize>,
+    pub text: String,
+}
+
+/// Where a citation marker points
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CitationSource {
+    /// Marker number; the context cites this source as `[marker]`
+    pub marker: usize,
+    /// document_content.id (the document key)
+    pub document_id: i64,
+    pub path: Option<String>,
+    pub title: String,
+    /// Byte offset of the excerpt in the source document
+    pub byte_start: Option<usize>,
+    /// Byte offset one past the end of the excerpt
+    pub byte_end: Option<usize>,
+    /// Estimated tokens the excerpt takes up in the context
+    pub tokens: usize,
+    /// Excerpt as it appears in the context
+    #[serde(default)]
+    pub text: String,
+}
+
+/// Assembled context block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuiltContext {
+    pub text: String,
+    pub citations: Vec<CitationSource>,
+    pub tokens: usize,
+    /// Whether any excerpt was left out for lack of budget
+    pub truncated: bool,
+}
+
+/// Fills a token budget (and a character limit) with excerpts, greedily in the
+/// order they are offered
+pub struct ContextBuilder {
+    token_budget: usize,
+    char_limit: usize,
+    tokens: usize,
+    chars: usize,
+    sections: Vec<(ContextSection, Vec<String>)>,
+    citations: Vec<CitationSource>,
+    markers: HashMap<(i64, Option<usize>, Option<usize>), usize>,
+    truncated: bool,
+}
+
+impl ContextBuilder {
+    pub fn new(token_budget: usize, char_limit: usize) -> Self {
+        Self {
+            token_budget,
+            char_limit,
+            tokens: estimate_tokens(UNTRUSTED_CONTEXT_INSTRUCTION) + estimate_tokens(CONTEXT_FOOTER),
+            chars: UNTRUSTED_CONTEXT_INSTRUCTION.len() + CONTEXT_FOOTER.len(),
+            sections: Vec::new(),
+            citations: Vec::new(),
+            markers: HashMap::new(),
+            truncated: false,
+        }
+    }
+
+    /// Add an excerpt if it fits, returning its citation marker. An excerpt already
+    /// in the context keeps its marker and is not repeated.
+    pub fn add(&mut self, section: ContextSection, excerpt: ContextExcerpt) -> Option<usize> {
+        let key = (excerpt.document_id, excerpt.byte_start, excerpt.byte_end);
+        if let Some(marker) = self.markers.get(&key) {
+            return Some(*marker);
+        }
+
+        let marker = self.citations.len() + 1;
+        let label = match &excerpt.path {
+            Some(path) => format!("{} ({})", excerpt.title, path),
+            None => excerpt.title.clone(),
+        };
+        let text = excerpt.text.trim().to_string();
+        let entry = format!("[{}] {}\n<document>\n{}\n</document>\n", marker, escape_block(&label), escape_block(&text));
+        let opens_section = self.sections.last().is_none_or(|(last, _)| *last != section);
+        let heading = if opens_section { section.heading() } else { "" };
+
+        let tokens = estimate_tokens(&entry) + estimate_tokens(heading);
+        let chars = entry.len() + heading.len() + 1;
+        if self.tokens + tokens > self.token_budget || self.chars + chars > self.char_limit {
+            self.truncated = true;
+            return None;
+        }
+
+        self.tokens += tokens;
+        self.chars += chars;
+        if opens_section {
+            self.sections.push((section, Vec::new()));
+        }
+        if let Some((_, entries)) = self.sections.last_mut() {
+            entries.push(entry);
+        }
+        self.markers.insert(key, marker);
+        self.citations.push(CitationSource {
+            marker,
+            document_id: excerpt.document_id,
+            path: excerpt.path,
+            title: excerpt.title,
+            byte_start: excerpt.byte_start,
+            byte_end: excerpt.byte_end,
+            tokens,
+            text,
+        });
+        Some(marker)
+    }
+
+    pub fn finish(self) -> BuiltContext {
+        if self.citations.is_empty() {
+            return BuiltContext { truncated: self.truncated, ..Default::default() };
+        }
+
+        let sections: Vec<String> = self
+            .sections
+            .iter()
+            .map(|(section, entries)| format!("{}{}", section.heading(), entries.join("\n")))
+            .collect();
+        BuiltContext {
+            text: format!("{}{}{}", UNTRUSTED_CONTEXT_INSTRUCTION, sections.join("\n"), CONTEXT_FOOTER),
+            citations: self.citations,
+            tokens: self.tokens,
+            truncated: self.truncated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excerpt(document_id: i64, start: usize, text: &str) -> ContextExcerpt {
+        ContextExcerpt {
+            document_id,
+            path: Some(format!("doc{}.md", document_id)),
+            title: format!("Doc {}", document_id),
+            byte_start: Some(start),
+            byte_end: Some(start + text.len()),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("東京タワー"), 5);
+        assert_eq!(context_tokens_for_window(4096), 2048);
+        assert!(estimate_tokens(&"x".repeat(context_chars_for_tokens(100))) <= 100);
+        assert!(estimate_tokens(&"東".repeat(context_chars_for_tokens(100) / 3)) > 100);
+    }
+
+    #[test]
+    fn test_builder_numbers_citations_and_maps_sources() {
+        let mut builder = ContextBuilder::new(1000, 10_000);
+        assert_eq!(builder.add(ContextSection::Referenced, excerpt(7, 0, "Referenced text.")), Some(1));
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(3, 40, "First passage.")), Some(2));
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(4, 0, "Second passage.")), Some(3));
+        // The same passage offered again keeps its marker
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(3, 40, "First passage.")), Some(2));
+
+        let built = builder.finish();
+        assert!(!built.truncated);
+        assert_eq!(
+            built.text,
+            format!(
+                "{}Referenced Documents:\n\n[1] Doc 7 (doc7.md)\n<document>\nReferenced text.\n</document>\n\
+                 \nRetrieved Context:\n\n[2] Doc 3 (doc3.md)\n<document>\nFirst passage.\n</document>\n\
+                 \n[3] Doc 4 (doc4.md)\n<document>\nSecond passage.\n</document>\n\
+                 \nEnd of Context\n",
+                UNTRUSTED_CONTEXT_INSTRUCTION
+            )
+        );
+        assert_eq!(built.citations.len(), 3);
+        assert_eq!((built.citations[1].byte_start, built.citations[1].byte_end), (Some(40), Some(54)));
+        assert!(built.tokens <= 1000);
+        assert_eq!(built.citations.iter().map(|c| c.marker).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_builder_respects_budget_greedily() {
+        let long = "word ".repeat(100);
+        let budget = 60 + estimate_tokens(UNTRUSTED_CONTEXT_INSTRUCTION);
+        let mut builder = ContextBuilder::new(budget, 10_000);
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(1, 0, "Short passage.")), Some(1));
+        // Too large for what is left, but a smaller excerpt after it still fits
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(2, 0, &long)), None);
+        assert_eq!(builder.add(ContextSection::Retrieved, excerpt(3, 0, "Another short one.")), Some(2));
+
+        let built = builder.finish();
+        assert!(built.truncated);
+        assert!(built.tokens <= budget);
+        assert_eq!(built.citations.iter().map(|c| c.document_id).collect::<Vec<_>>(), vec![1, 3]);
+
+        let empty = ContextBuilder::new(1, 10_000).finish();
+        assert_eq!(empty.text, "");
+    }
+}
@@ -0,0 +1,302 @@
+// Conversation Retrieval
+// Retrieval for chat follow-ups. A question like "what about the second one?" is
+// rewritten into a standalone search query from the recent messages, either with a
+// keyword heuristic or a language model, and documents cited in earlier answers are
+// carried forward with a weight that decays with every turn.
+
+use crate::application::ai_provider::{Message, MessageRole};
+use crate::application::query_expansion::QueryExpander;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Metadata key of an assistant message that lists the document keys
+/// (document_content.id) its answer cited, e.g. `{"cited_document_ids": [3, 7]}`
+pub const CITED_DOCUMENTS_KEY: &str = "cited_document_ids";
+
+/// Most terms taken from earlier messages by the heuristic rewrite
+const MAX_TOPIC_TERMS: usize = 4;
+
+/// Most content words a question referring back with a pronoun may have of its own;
+/// a longer question names its subject and stands alone
+const MAX_FOLLOW_UP_TERMS: usize = 2;
+
+/// Characters of each earlier message shown to the model
+const MAX_MESSAGE_CHARS: usize = 500;
+
+const REWRITE_INSTRUCTIONS: &str =
+    "You rewrite the last question of a conversation into a standalone search query. Reply with the query only.";
+
+/// Openings of questions that continue the previous one
+const FOLLOW_UP_OPENERS: &[&str] = &["what about", "how about", "and ", "also ", "what else", "tell me more", "more on", "same for"];
+
+/// Pronouns that stand for something named earlier
+const ANAPHORIC_WORDS: &[&str] = &["it", "its", "they", "them", "their", "that", "this", "those", "these", "former", "latter"];
+
+/// Words that point back at something said earlier, left out of the search terms
+const REFERRING_WORDS: &[&str] = &[
+    "it", "its", "that", "this", "those", "these", "they", "them", "their", "one", "ones", "former", "latter",
+    "first", "second", "third", "last", "same", "above", "previous", "other", "else", "more",
+];
+
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "all", "also", "an", "and", "any", "are", "as", "at", "be", "but", "by", "can", "could", "did",
+    "do", "does", "for", "from", "give", "had", "has", "have", "how", "i", "if", "in", "is", "me", "my", "of", "on",
+    "or", "our", "please", "show", "so", "tell", "the", "there", "to", "us", "was", "we", "were", "what", "when",
+    "where", "which", "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// How follow-up questions are turned into standalone queries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryRewriteMode {
+    /// Search the follow-up together with any topic term of the previous question
+    #[default]
+    Heuristic,
+    /// Ask a language model, falling back to the heuristic when it fails
+    Model,
+}
+
+/// Settings of conversation-aware retrieval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationRetrievalConfig {
+    /// Number of most recent messages considered
+    pub max_messages: usize,
+    pub rewrite: QueryRewriteMode,
+    /// Weight of documents cited in the previous answer, multiplied again for every
+    /// earlier answer; 0.0 turns carrying documents forward off
+    pub citation_decay: f64,
+    /// Carried documents weighing less than this are left out
+    pub min_carry_weight: f64,
+}
+
+impl Default for ConversationRetrievalConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: 6,
+            rewrite: QueryRewriteMode::Heuristic,
+            citation_decay: 0.5,
+            min_carry_weight: 0.1,
+        }
+    }
+}
+
+/// A document cited earlier in the conversation and carried into this retrieval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CarriedDocument {
+    /// document_content.id (the document key)
+    pub document_id: i64,
+    /// Share of the top result's score the document is boosted by (0.0 to 1.0)
+    pub weight: f64,
+    /// Answers since the document was last cited; 1 is the previous answer
+    pub turns_ago: usize,
+}
+
+/// The last `max_messages` messages, leaving out system messages
+pub fn recent_messages(messages: &[Message], max_messages: usize) -> Vec<&Message> {
+    let mut recent: Vec<&Message> = messages
+        .iter()
+        .rev()
+        .filter(|message| !matches!(message.role, MessageRole::System))
+        .take(max_messages)
+        .collect();
+    recent.reverse();
+    recent
+}
+
+/// Content words of a text, lowercased and without duplicates
+pub(crate) fn keywords(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_') {
+        let word = word.trim_matches(['-', '_']).to_lowercase();
+        if word.chars().count() < 2 || STOP_WORDS.contains(&word.as_str()) || REFERRING_WORDS.contains(&word.as_str()) {
+            continue;
+        }
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// Whether a question depends on earlier messages to make sense: it opens like a
+/// continuation ("what about", "and ..."), has no content words of its own, or names
+/// at most `MAX_FOLLOW_UP_TERMS` things itself and refers back with a pronoun or
+/// "the second one"
+pub fn is_follow_up(question: &str) -> bool {
+    let lowered = question.trim().to_lowercase();
+    if FOLLOW_UP_OPENERS.iter().any(|opener| lowered.starts_with(opener)) {
+        return true;
+    }
+    let own_terms = keywords(question).len();
+    if own_terms == 0 {
+        return true;
+    }
+    if own_terms > MAX_FOLLOW_UP_TERMS {
+        return false;
+    }
+
+    let words: Vec<&str> = lowered.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    let pronoun = words.iter().any(|word| ANAPHORIC_WORDS.contains(word));
+    let pointer = words
+        .windows(2)
+        .any(|pair| matches!(pair[1], "one" | "ones") && REFERRING_WORDS.contains(&pair[0]));
+    pronoun || pointer
+}
+
+/// Standalone query for a follow-up: the topic terms of the most recent earlier user
+/// message as one OR group, so a document needs only one of them, followed by the
+/// question's own terms. None when the question stands alone or there is no earlier
+/// topic.
+pub fn heuristic_standalone_query(history: &[&Message], question: &str) -> Option<String> {
+    if !is_follow_up(question) {
+        return None;
+    }
+
+    let topic: Vec<String> = history
+        .iter()
+        .rev()
+        .filter(|message| matches!(message.role, MessageRole::User))
+        .map(|message| keywords(&message.content))
+        .find(|terms| !terms.is_empty())?
+        .into_iter()
+        .take(MAX_TOPIC_TERMS)
+        .collect();
+
+    let mut parts = vec![topic.join(" OR ")];
+    for term in keywords(question) {
+        if !topic.contains(&term) {
+            parts.push(term);
+        }
+    }
+    Some(parts.join(" "))
+}
+
+/// Standalone query for a follow-up written by `generator`. Only the first line of
+/// the reply is used.
+pub fn model_standalone_query(generator: &dyn QueryExpander, history: &[&Message], question: &str) -> Result<String, String> {
+    let transcript: Vec<String> = history
+        .iter()
+        .map(|message| {
+            let speaker = if matches!(message.role, MessageRole::User) { "User" } else { "Assistant" };
+            let content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+            format!("{}: {}", speaker, content.trim())
+        })
+        .collect();
+    let prompt = format!(
+        "Conversation:\n{}\n\nLast question: {}\n\nWrite a search query that finds the documents answering the last \
+         question without needing the conversation.",
+        transcript.join("\n"),
+        question.trim()
+    );
+
+    let reply = generator.generate(REWRITE_INSTRUCTIONS, &prompt)?;
+    let query = reply
+        .lines()
+        .map(|line| line.trim().trim_matches(['"', '\'', '`']).trim())
+        .find(|line| !line.is_empty())
+        .ok_or_else(|| "Model returned an empty query".to_string())?;
+    Ok(query.to_string())
+}
+
+/// Documents cited by earlier answers (via `CITED_DOCUMENTS_KEY`), weighted by
+/// `citation_decay` for every answer since, most recent citation first
+pub fn carried_documents(history: &[&Message], config: &ConversationRetrievalConfig) -> Vec<CarriedDocument> {
+    if config.citation_decay <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut carried: Vec<CarriedDocument> = Vec::new();
+    let mut seen: HashSet<i64> = HashSet::new();
+    let mut turns_ago = 0;
+    for message in history.iter().rev() {
+        if !matches!(message.role, MessageRole::Assistant) {
+            continue;
+        }
+        turns_ago += 1;
+        let weight = config.citation_decay.min(1.0).powi(turns_ago as i32);
+        if weight < config.min_carry_weight {
+            break;
+        }
+
+        let cited = message
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(CITED_DOCUMENTS_KEY))
+            .and_then(|value| value.as_array());
+        for document_id in cited.into_iter().flatten().filter_map(|value| value.as_i64()) {
+            // A later citation outweighs an earlier one of the same document
+            if seen.insert(document_id) {
+                carried.push(CarriedDocument { document_id, weight, turns_ago });
+            }
+        }
+    }
+    carried
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn message(role: MessageRole, content: &str, cited: &[i64]) -> Message {
+        let metadata = (!cited.is_empty()).then(|| HashMap::from([(CITED_DOCUMENTS_KEY.to_string(), json!(cited))]));
+        Message { role, content: content.to_string(), metadata }
+    }
+
+    #[test]
+    fn test_heuristic_rewrites_follow_ups() {
+        let messages = vec![
+            message(MessageRole::System, "You are helpful.", &[]),
+            message(MessageRole::User, "Which pricing plans do we offer?", &[]),
+            message(MessageRole::Assistant, "Starter and Team.", &[4]),
+        ];
+        let history = recent_messages(&messages, 6);
+        assert_eq!(history.len(), 2);
+
+        assert_eq!(
+            heuristic_standalone_query(&history, "What about the second one?").as_deref(),
+            Some("pricing OR plans OR offer")
+        );
+        assert_eq!(
+            heuristic_standalone_query(&history, "and annual discounts?").as_deref(),
+            Some("pricing OR plans OR offer annual discounts")
+        );
+        assert_eq!(
+            heuristic_standalone_query(&history, "What does it cost?").as_deref(),
+            Some("pricing OR plans OR offer cost")
+        );
+        // A question that stands on its own is searched as written
+        assert_eq!(heuristic_standalone_query(&history, "How is the SQLite schema migrated?"), None);
+        assert_eq!(heuristic_standalone_query(&history, "Which other databases support full text search?"), None);
+        assert_eq!(heuristic_standalone_query(&history, "What is the first step of a deployment?"), None);
+        assert_eq!(heuristic_standalone_query(&[], "What about it?"), None);
+    }
+
+    #[test]
+    fn test_cited_documents_decay() {
+        let messages = vec![
+            message(MessageRole::User, "Pricing plans?", &[]),
+            message(MessageRole::Assistant, "...", &[4, 5]),
+            message(MessageRole::User, "Discounts?", &[]),
+            message(MessageRole::Assistant, "...", &[5]),
+            message(MessageRole::User, "What about the second one?", &[]),
+        ];
+        let history = recent_messages(&messages, 6);
+
+        let carried = carried_documents(&history, &ConversationRetrievalConfig::default());
+        assert_eq!(
+            carried,
+            vec![
+                CarriedDocument { document_id: 5, weight: 0.5, turns_ago: 1 },
+                CarriedDocument { document_id: 4, weight: 0.25, turns_ago: 2 },
+            ]
+        );
+
+        let config = ConversationRetrievalConfig { min_carry_weight: 0.3, ..Default::default() };
+        assert_eq!(carried_documents(&history, &config).len(), 1);
+        let config = ConversationRetrievalConfig { citation_decay: 0.0, ..Default::default() };
+        assert!(carried_documents(&history, &config).is_empty());
+    }
+}
@@ -21,7 +21,29 @@ pub use ai_credential_commands::*;
 pub mod ai_provider_state_manager;
 pub mod credential_manager;
 pub mod hybrid_rag_service;
+pub mod score_fusion;
+pub mod context_budget;
+pub mod reranker;
+pub mod query_expansion;
+pub mod query_embedding;
+pub mod conversation_retrieval;
+pub mod rag_evaluation;
+pub mod rag_config_store;
+pub mod grounded_answer;
+pub mod citation_verifier;
+pub mod injection_guard;
 
 pub use ai_provider_state_manager::*;
 pub use credential_manager::*;
 pub use hybrid_rag_service::*;
+pub use score_fusion::*;
+pub use context_budget::*;
+pub use reranker::*;
+pub use query_expansion::*;
+pub use query_embedding::*;
+pub use conversation_retrieval::*;
+pub use rag_evaluation::*;
+pub use rag_config_store::*;
+pub use grounded_answer::*;
+pub use citation_verifier::*;
+pub use injection_guard::*;
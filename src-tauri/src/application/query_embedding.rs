@@ -0,0 +1,116 @@
+// Query Embedding
+// Embeds search queries for the vector arm of hybrid retrieval. A query vector is only
+// comparable to document vectors of the same model, so the embedder names the model
+// and dimension it produces and retrieval searches only vectors stored under them.
+// Documents are embedded into vector_index with the same embedder.
+
+use crate::application::citation_verifier::TextEmbedder;
+use crate::infrastructure::db_layer::{DatabaseConnection, VectorIndex, VectorIndexRepository};
+use std::sync::{Arc, RwLock};
+
+/// Embeds query text with the model that embedded the indexed documents
+pub trait QueryEmbedder: Send + Sync {
+    /// `embedding_model` of the stored vectors this embedder's output is comparable to
+    fn model(&self) -> &str;
+
+    /// Number of components of every vector the embedder returns
+    fn dimension(&self) -> usize;
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String>;
+}
+
+/// Embed `text`, rejecting a vector whose length differs from the embedder's dimension
+pub fn embed_query_text(embedder: &dyn QueryEmbedder, text: &str) -> Result<Vec<f32>, String> {
+    let embedding = embedder.embed(text)?;
+    if embedding.len() != embedder.dimension() {
+        return Err(format!(
+            "Embedding model '{}' returned {} dimensions, expected {}",
+            embedder.model(),
+            embedding.len(),
+            embedder.dimension()
+        ));
+    }
+    Ok(embedding)
+}
+
+/// Embed a document with `embedder` and store the vector under the embedder's model,
+/// replacing the document's earlier vector of that model. Returns the vector_index id.
+pub fn index_document(db: &DatabaseConnection, embedder: &dyn QueryEmbedder, document_id: i64, text: &str) -> Result<i64, String> {
+    let embedding = embed_query_text(embedder, text)?;
+    let now = chrono::Utc::now().timestamp() as u64;
+    VectorIndexRepository::new(db).upsert(&VectorIndex {
+        id: None,
+        document_id,
+        dimension: embedding.len() as i32,
+        embedding,
+        embedding_model: embedder.model().to_string(),
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// A query embedder used where texts are embedded in batches, such as citation
+/// verification; each text is embedded on its own
+struct BatchQueryEmbedder(Arc<dyn QueryEmbedder>);
+
+impl TextEmbedder for BatchQueryEmbedder {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        texts.iter().map(|text| embed_query_text(self.0.as_ref(), text)).collect()
+    }
+}
+
+/// The query embedder retrieval uses, shared across commands; empty until one is set up
+#[derive(Default)]
+pub struct QueryEmbedderSlot {
+    embedder: RwLock<Option<Arc<dyn QueryEmbedder>>>,
+}
+
+impl QueryEmbedderSlot {
+    pub fn get(&self) -> Option<Arc<dyn QueryEmbedder>> {
+        self.embedder.read().ok().and_then(|embedder| embedder.clone())
+    }
+
+    /// Replace the embedder, or clear it with None
+    pub fn set(&self, embedder: Option<Arc<dyn QueryEmbedder>>) {
+        if let Ok(mut slot) = self.embedder.write() {
+            *slot = embedder;
+        }
+    }
+
+    /// The embedder as a `TextEmbedder`, when one is set
+    pub fn text_embedder(&self) -> Option<Arc<dyn TextEmbedder>> {
+        self.get().map(|embedder| Arc::new(BatchQueryEmbedder(embedder)) as Arc<dyn TextEmbedder>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LengthEmbedder;
+
+    impl QueryEmbedder for LengthEmbedder {
+        fn model(&self) -> &str {
+            "length-test"
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+            Ok(vec![text.len() as f32, 1.0])
+        }
+    }
+
+    #[test]
+    fn test_slot_embeds_texts_with_its_query_embedder() {
+        let slot = QueryEmbedderSlot::default();
+        assert!(slot.text_embedder().is_none());
+
+        slot.set(Some(Arc::new(LengthEmbedder)));
+        let embedder = slot.text_embedder().expect("embedder is set");
+        let texts = vec!["car".to_string(), "trucks".to_string()];
+        assert_eq!(embedder.embed(&texts).unwrap(), vec![vec![3.0, 1.0], vec![6.0, 1.0]]);
+    }
+}
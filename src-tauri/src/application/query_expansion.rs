@@ -0,0 +1,239 @@
+// Query Expansion
+// Optional step before vector retrieval: a language model rewrites a short query
+// into alternative phrasings, or writes a hypothetical answer passage (HyDE), and
+// each variant is embedded and searched alongside the original query.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Number of expanded queries kept by the default cache
+const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+const REWRITE_INSTRUCTIONS: &str =
+    "You rewrite search queries for a document search engine. Reply with the rewritten queries only.";
+
+const HYDE_INSTRUCTIONS: &str =
+    "You write short passages that answer questions as if quoted from a project document. Reply with the passage only.";
+
+/// Generates text for query expansion, e.g. with the active AI provider
+pub trait QueryExpander: Send + Sync {
+    fn generate(&self, instructions: &str, prompt: &str) -> Result<String, String>;
+}
+
+/// How a query is expanded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpansionMode {
+    /// Alternative phrasings of the query
+    #[default]
+    Rewrite,
+    /// A hypothetical passage answering the query
+    Hyde,
+    /// Both rewrites and a hypothetical passage
+    Both,
+}
+
+/// Settings of the query expansion step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryExpansionConfig {
+    /// Whether queries are expanded at all
+    pub enabled: bool,
+    pub mode: ExpansionMode,
+    /// Maximum number of rewritten queries
+    pub max_variants: usize,
+}
+
+impl Default for QueryExpansionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: ExpansionMode::Rewrite,
+            max_variants: 3,
+        }
+    }
+}
+
+/// Variants of `query` generated by `expander`: rewritten queries first, then the
+/// hypothetical passage. The original query is never among them.
+pub fn expand_query(expander: &dyn QueryExpander, query: &str, config: &QueryExpansionConfig) -> Result<Vec<String>, String> {
+    let query = query.trim();
+    let mut variants = Vec::new();
+
+    if matches!(config.mode, ExpansionMode::Rewrite | ExpansionMode::Both) && config.max_variants > 0 {
+        let prompt = format!(
+            "Rewrite the search query below into {} alternative queries that use different wording, \
+             synonyms or related terms. Write one query per line.\n\nQuery: {}",
+            config.max_variants, query
+        );
+        let response = expander.generate(REWRITE_INSTRUCTIONS, &prompt)?;
+        variants.extend(parse_rewrites(&response, query, config.max_variants));
+    }
+
+    if matches!(config.mode, ExpansionMode::Hyde | ExpansionMode::Both) {
+        let prompt = format!("Write a passage of three to five sentences that answers this question:\n\n{}", query);
+        let passage = expander.generate(HYDE_INSTRUCTIONS, &prompt)?;
+        let passage = passage.trim();
+        if !passage.is_empty() {
+            variants.push(passage.to_string());
+        }
+    }
+
+    Ok(variants)
+}
+
+/// Queries from a one-per-line model response, without list markers, quotes,
+/// duplicates or the original query
+fn parse_rewrites(response: &str, query: &str, limit: usize) -> Vec<String> {
+    let mut rewrites: Vec<String> = Vec::new();
+    for line in response.lines() {
+        let line = line.trim();
+        // "1." and "2)" numbering, but not a leading number such as "2024 pricing"
+        let unnumbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+        let line = match unnumbered.strip_prefix(['.', ')']) {
+            Some(rest) if unnumbered.len() < line.len() => rest,
+            _ => line,
+        };
+        let line = line
+            .trim_start_matches(['-', '*', '•'])
+            .trim()
+            .trim_matches(['"', '\'', '`'])
+            .trim();
+        if line.is_empty() || line.ends_with(':') || line.eq_ignore_ascii_case(query) {
+            continue;
+        }
+        if rewrites.iter().any(|rewrite| rewrite.eq_ignore_ascii_case(line)) {
+            continue;
+        }
+        rewrites.push(line.to_string());
+        if rewrites.len() == limit {
+            break;
+        }
+    }
+    rewrites
+}
+
+/// Cached variants by key, and the keys in insertion order
+type CacheEntries = (HashMap<String, Vec<String>>, VecDeque<String>);
+
+/// Expanded queries by query and settings, so repeated searches skip the model.
+/// The oldest entry is evicted once the cache is full.
+pub struct QueryExpansionCache {
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+}
+
+impl Default for QueryExpansionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl QueryExpansionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    /// Cached variants of `query`, expanding and caching it on a miss. Failed
+    /// expansions are not cached.
+    pub fn get_or_expand(
+        &self,
+        expander: &dyn QueryExpander,
+        query: &str,
+        config: &QueryExpansionConfig,
+    ) -> Result<Vec<String>, String> {
+        let key = format!("{:?}:{}:{}", config.mode, config.max_variants, query.trim().to_lowercase());
+        if let Some(variants) = self.lock()?.0.get(&key) {
+            return Ok(variants.clone());
+        }
+
+        // The lock is not held while the model runs
+        let variants = expand_query(expander, query, config)?;
+
+        let mut guard = self.lock()?;
+        let (entries, order) = &mut *guard;
+        if self.capacity > 0 && entries.insert(key.clone(), variants.clone()).is_none() {
+            order.push_back(key);
+            while order.len() > self.capacity {
+                if let Some(oldest) = order.pop_front() {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        Ok(variants)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().map(|guard| guard.0.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, CacheEntries>, String> {
+        self.entries.lock().map_err(|e| format!("Failed to access query expansion cache: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Replies with a fixed rewrite list or passage and counts its calls
+    struct ScriptedExpander {
+        calls: AtomicUsize,
+    }
+
+    impl QueryExpander for ScriptedExpander {
+        fn generate(&self, instructions: &str, _prompt: &str) -> Result<String, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if instructions == HYDE_INSTRUCTIONS {
+                Ok("  Pricing risks include discounting pressure and churn.  ".to_string())
+            } else {
+                Ok("Here are the queries:\n1. \"price risk factors\"\n2) Pricing Risks\n- pricing threats\n- price risk factors\n* margin pressure".to_string())
+            }
+        }
+    }
+
+    fn expander() -> ScriptedExpander {
+        ScriptedExpander { calls: AtomicUsize::new(0) }
+    }
+
+    #[test]
+    fn test_expand_query_modes() {
+        let expander = expander();
+        let config = QueryExpansionConfig { enabled: true, ..Default::default() };
+        let variants = expand_query(&expander, "pricing risks", &config).unwrap();
+        // List markers, quotes, the original query and duplicates are dropped
+        assert_eq!(variants, vec!["price risk factors", "pricing threats", "margin pressure"]);
+        assert_eq!(parse_rewrites("2024 pricing\n3. 2025 pricing", "pricing", 5), vec!["2024 pricing", "2025 pricing"]);
+
+        let config = QueryExpansionConfig { mode: ExpansionMode::Both, max_variants: 1, ..config };
+        let variants = expand_query(&expander, "pricing risks", &config).unwrap();
+        assert_eq!(variants, vec!["price risk factors", "Pricing risks include discounting pressure and churn."]);
+    }
+
+    #[test]
+    fn test_expansion_cache() {
+        let expander = expander();
+        let cache = QueryExpansionCache::new(1);
+        let config = QueryExpansionConfig { mode: ExpansionMode::Hyde, ..Default::default() };
+
+        let first = cache.get_or_expand(&expander, "Pricing risks", &config).unwrap();
+        let second = cache.get_or_expand(&expander, " pricing risks ", &config).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(expander.calls.load(Ordering::SeqCst), 1);
+
+        // A different mode is a different entry, and evicts the oldest
+        cache.get_or_expand(&expander, "pricing risks", &QueryExpansionConfig::default()).unwrap();
+        cache.get_or_expand(&expander, "pricing risks", &config).unwrap();
+        assert_eq!(expander.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.len(), 1);
+    }
+}
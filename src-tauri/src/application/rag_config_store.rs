@@ -0,0 +1,233 @@
+// RAG Config Store
+// Persisted HybridRagConfig: database-wide defaults, and per-project overrides that
+// keep only the settings a project changed, so later changes to the defaults still
+// reach the settings it left alone. Configs are read per request, so saved changes
+// apply without a restart.
+
+use crate::application::hybrid_rag_service::HybridRagConfig;
+use crate::infrastructure::db_layer::{DatabaseConnection, SettingsRepository};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// database_settings / project_settings key holding the config
+const CONFIG_KEY: &str = "hybrid_rag";
+
+/// A setting whose value differs from the one it would inherit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigDifference {
+    /// Dotted path of the setting, e.g. `rerank.top_n`
+    pub field: String,
+    /// Inherited value
+    pub default: Value,
+    pub value: Value,
+}
+
+/// Reads and stores hybrid RAG configs
+pub struct RagConfigStore<'a> {
+    settings: SettingsRepository<'a>,
+}
+
+impl<'a> RagConfigStore<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self {
+            settings: SettingsRepository::new(db),
+        }
+    }
+
+    /// Database-wide defaults: the saved ones, else the built-in defaults
+    pub fn defaults(&self) -> Result<HybridRagConfig, String> {
+        match self.settings.get(CONFIG_KEY)? {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid stored RAG config: {}", e)),
+            None => Ok(HybridRagConfig::default()),
+        }
+    }
+
+    /// Config of a project: the defaults with the project's overrides applied.
+    /// Without a project, the defaults.
+    pub fn config(&self, project_id: Option<&str>) -> Result<HybridRagConfig, String> {
+        let defaults = self.defaults()?;
+        let Some(project_id) = project_id else {
+            return Ok(defaults);
+        };
+        let Some(json) = self.settings.get_for_project(project_id, CONFIG_KEY)? else {
+            return Ok(defaults);
+        };
+
+        let overrides: Value = serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid stored RAG config for project {}: {}", project_id, e))?;
+        let mut config = Value::Object(to_object(&defaults)?);
+        merge_overrides(&mut config, overrides);
+        serde_json::from_value(config)
+            .map_err(|e| format!("Invalid stored RAG config for project {}: {}", project_id, e))
+    }
+
+    /// Save a project's config, or the defaults without a project. A project keeps
+    /// only the settings that differ from the defaults. Returns the saved config.
+    pub fn save(&self, project_id: Option<&str>, config: &HybridRagConfig) -> Result<HybridRagConfig, String> {
+        let Some(project_id) = project_id else {
+            let json = serde_json::to_string(config).map_err(|e| format!("Failed to serialize RAG config: {}", e))?;
+            self.settings.set(CONFIG_KEY, &json)?;
+            return self.defaults();
+        };
+
+        let defaults = Value::Object(to_object(&self.defaults()?)?);
+        match collect_overrides(&defaults, &Value::Object(to_object(config)?)) {
+            Some(overrides) => {
+                let json = serde_json::to_string(&overrides).map_err(|e| format!("Failed to serialize RAG config: {}", e))?;
+                self.settings.set_for_project(project_id, CONFIG_KEY, &json)?;
+            }
+            None => {
+                self.settings.delete_for_project(project_id, CONFIG_KEY)?;
+            }
+        }
+        self.config(Some(project_id))
+    }
+
+    /// Drop a project's overrides, or the saved defaults without a project.
+    /// Returns the config now in effect.
+    pub fn reset(&self, project_id: Option<&str>) -> Result<HybridRagConfig, String> {
+        match project_id {
+            Some(project_id) => self.settings.delete_for_project(project_id, CONFIG_KEY)?,
+            None => self.settings.delete(CONFIG_KEY)?,
+        };
+        self.config(project_id)
+    }
+
+    /// Settings a project's config changes from the defaults, or that the saved
+    /// defaults change from the built-in defaults without a project
+    pub fn diff(&self, project_id: Option<&str>) -> Result<Vec<ConfigDifference>, String> {
+        let (inherited, config) = match project_id {
+            Some(_) => (self.defaults()?, self.config(project_id)?),
+            None => (HybridRagConfig::default(), self.defaults()?),
+        };
+
+        let mut differences = Vec::new();
+        collect_differences("", &Value::Object(to_object(&inherited)?), &Value::Object(to_object(&config)?), &mut differences);
+        Ok(differences)
+    }
+}
+
+fn to_object(config: &HybridRagConfig) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(config).map_err(|e| format!("Failed to serialize RAG config: {}", e))? {
+        Value::Object(fields) => Ok(fields),
+        _ => Err("RAG config did not serialize to an object".to_string()),
+    }
+}
+
+/// Differing leaves of two values, descending into objects on both sides
+fn collect_differences(path: &str, inherited: &Value, value: &Value, differences: &mut Vec<ConfigDifference>) {
+    match (inherited, value) {
+        (Value::Object(inherited), Value::Object(value)) => {
+            let mut fields: Vec<&String> = inherited.keys().chain(value.keys()).collect();
+            fields.sort();
+            fields.dedup();
+            for field in fields {
+                let path = if path.is_empty() { field.clone() } else { format!("{}.{}", path, field) };
+                let missing = Value::Null;
+                collect_differences(
+                    &path,
+                    inherited.get(field).unwrap_or(&missing),
+                    value.get(field).unwrap_or(&missing),
+                    differences,
+                );
+            }
+        }
+        _ if inherited != value => differences.push(ConfigDifference {
+            field: path.to_string(),
+            default: inherited.clone(),
+            value: value.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// The parts of `value` that differ from `inherited`, descending into objects on both
+/// sides so an object keeps only its changed fields. None when nothing differs.
+fn collect_overrides(inherited: &Value, value: &Value) -> Option<Value> {
+    match (inherited, value) {
+        (Value::Object(inherited), Value::Object(value)) => {
+            let overrides: Map<String, Value> = value
+                .iter()
+                .filter_map(|(field, value)| {
+                    let changed = match inherited.get(field) {
+                        Some(inherited) => collect_overrides(inherited, value)?,
+                        None => value.clone(),
+                    };
+                    Some((field.clone(), changed))
+                })
+                .collect();
+            (!overrides.is_empty()).then_some(Value::Object(overrides))
+        }
+        _ if inherited != value => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// Apply `overrides` to `target`, merging objects field by field so settings an
+/// override leaves out keep their inherited values
+fn merge_overrides(target: &mut Value, overrides: Value) {
+    match (target, overrides) {
+        (Value::Object(target), Value::Object(overrides)) => {
+            for (field, value) in overrides {
+                match target.get_mut(&field) {
+                    Some(existing) => merge_overrides(existing, value),
+                    None => {
+                        target.insert(field, value);
+                    }
+                }
+            }
+        }
+        (target, overrides) => *target = overrides,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::reranker::RerankConfig;
+    use crate::core::Project;
+    use crate::infrastructure::db_layer::{MigrationManager, ProjectRepository};
+    use serde_json::json;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_project_overrides_follow_defaults() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = DatabaseConnection::new();
+        db.initialize(temp_file.path()).unwrap();
+        MigrationManager::new(&db).migrate().unwrap();
+        let project = Project::new("project".to_string(), "Project".to_string(), "/project".to_string());
+        ProjectRepository::new(&db).create(&project).unwrap();
+        let store = RagConfigStore::new(&db);
+        let project_id = Some("project");
+
+        // Nothing saved: built-in defaults everywhere, and no differences
+        assert_eq!(store.config(project_id).unwrap().max_results, HybridRagConfig::default().max_results);
+        assert!(store.diff(project_id).unwrap().is_empty());
+
+        let rerank = RerankConfig { enabled: true, ..Default::default() };
+        let config = HybridRagConfig { max_results: 4, rerank, ..store.config(project_id).unwrap() };
+        assert_eq!(store.save(project_id, &config).unwrap().max_results, 4);
+        let fields: Vec<String> = store.diff(project_id).unwrap().into_iter().map(|d| d.field).collect();
+        assert_eq!(fields, vec!["max_results", "rerank.enabled"]);
+
+        // A later change to the defaults reaches the settings the project left alone,
+        // including the unchanged fields of a section it overrides part of
+        let rerank = RerankConfig { top_n: 3, ..Default::default() };
+        let defaults = HybridRagConfig { max_results: 7, max_candidates: 60, rerank, ..HybridRagConfig::default() };
+        store.save(None, &defaults).unwrap();
+        let config = store.config(project_id).unwrap();
+        assert_eq!((config.max_results, config.max_candidates, config.rerank.enabled), (4, 60, true));
+        assert_eq!(config.rerank.top_n, 3);
+        let stored = SettingsRepository::new(&db).get_for_project("project", CONFIG_KEY).unwrap().unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&stored).unwrap(), json!({"max_results": 4, "rerank": {"enabled": true}}));
+        assert_eq!(store.config(None).unwrap().max_results, 7);
+        let global = store.diff(None).unwrap();
+        assert_eq!(global[0], ConfigDifference { field: "max_candidates".to_string(), default: json!(50), value: json!(60) });
+
+        // Resetting the project falls back to the defaults
+        assert_eq!(store.reset(project_id).unwrap().max_results, 7);
+        assert!(store.diff(project_id).unwrap().is_empty());
+        assert_eq!(store.reset(None).unwrap().max_results, HybridRagConfig::default().max_results);
+    }
+}
@@ -0,0 +1,573 @@
+// RAG Evaluation
+// Measures retrieval quality against a golden set: queries paired with the documents
+// (and optionally passages) that should be retrieved for them. Each HybridRagConfig
+// variant is scored with recall@k, MRR and nDCG@k, and the reports are stored so runs
+// of the same golden set can be compared over time.
+
+use crate::application::hybrid_rag_service::{ContextAssembly, HybridRagConfig, HybridRagService};
+use crate::core::{normalize_path, Document, Project};
+use crate::infrastructure::db_layer::{
+    DatabaseConnection, DocumentContentRepository, DocumentRepository, ProjectRepository, RagEvalRepository,
+    RetrievalFilter, StoredEvalReport,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Rank cutoff used when none is given
+pub const DEFAULT_EVAL_K: usize = 10;
+
+fn default_relevance() -> u32 {
+    1
+}
+
+/// A document that should be retrieved for a golden query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpectedDocument {
+    /// Path of the document within the project
+    pub path: String,
+    /// Text the retrieved passages or context of the document should contain
+    #[serde(default)]
+    pub passage: Option<String>,
+    /// Graded relevance for nDCG: 1 is relevant, higher is more relevant
+    #[serde(default = "default_relevance")]
+    pub relevance: u32,
+}
+
+/// A query with its expected documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoldenQuery {
+    /// Stable name of the query for comparing runs; defaults to the query text
+    #[serde(default)]
+    pub id: Option<String>,
+    pub query: String,
+    pub expected: Vec<ExpectedDocument>,
+}
+
+impl GoldenQuery {
+    pub fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.query)
+    }
+}
+
+/// A named set of golden queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoldenSet {
+    pub name: String,
+    pub queries: Vec<GoldenQuery>,
+}
+
+impl GoldenSet {
+    /// Parse and validate a golden set
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let set: GoldenSet = serde_json::from_str(json).map_err(|e| format!("Invalid golden set: {}", e))?;
+        set.validate()?;
+        Ok(set)
+    }
+
+    /// Load a golden set from a JSON file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read golden set {}: {}", path.display(), e))?;
+        Self::from_json(&json)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Golden set name cannot be empty".to_string());
+        }
+        if self.queries.is_empty() {
+            return Err(format!("Golden set '{}' has no queries", self.name));
+        }
+        for query in &self.queries {
+            if query.query.trim().is_empty() {
+                return Err(format!("Golden set '{}' has an empty query", self.name));
+            }
+            if query.expected.is_empty() {
+                return Err(format!("Golden query '{}' expects no documents", query.key()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A labelled retrieval config to evaluate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigVariant {
+    pub label: String,
+    #[serde(default)]
+    pub config: HybridRagConfig,
+}
+
+impl ConfigVariant {
+    /// The default config, labelled "default"
+    pub fn baseline() -> Self {
+        Self {
+            label: "default".to_string(),
+            config: HybridRagConfig::default(),
+        }
+    }
+}
+
+/// Scores of one golden query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryEvaluation {
+    pub query_id: String,
+    pub query: String,
+    /// Paths of the top-k retrieved documents, best first
+    pub retrieved: Vec<String>,
+    pub recall_at_k: f64,
+    pub reciprocal_rank: f64,
+    pub ndcg_at_k: f64,
+    /// Share of expected passages found in the top k; None when none are expected
+    pub passage_recall_at_k: Option<f64>,
+    /// Retrieval error; the query scores zero
+    pub error: Option<String>,
+}
+
+/// Mean scores over a golden set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalMetrics {
+    pub recall_at_k: f64,
+    pub mrr: f64,
+    pub ndcg_at_k: f64,
+    /// Over the queries that expect passages
+    pub passage_recall_at_k: Option<f64>,
+    pub queries: usize,
+    pub failed_queries: usize,
+}
+
+impl EvalMetrics {
+    fn from_queries(queries: &[QueryEvaluation]) -> Self {
+        let count = queries.len();
+        let mean = |values: Vec<f64>| if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+        let passages: Vec<f64> = queries.iter().filter_map(|q| q.passage_recall_at_k).collect();
+        Self {
+            recall_at_k: mean(queries.iter().map(|q| q.recall_at_k).collect()),
+            mrr: mean(queries.iter().map(|q| q.reciprocal_rank).collect()),
+            ndcg_at_k: mean(queries.iter().map(|q| q.ndcg_at_k).collect()),
+            passage_recall_at_k: (!passages.is_empty()).then(|| mean(passages)),
+            queries: count,
+            failed_queries: queries.iter().filter(|q| q.error.is_some()).count(),
+        }
+    }
+}
+
+/// Result of running a golden set with one config variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalReport {
+    /// Id of the stored report; None until stored
+    pub id: Option<i64>,
+    pub project_id: String,
+    pub golden_set: String,
+    pub label: String,
+    pub k: usize,
+    pub config: HybridRagConfig,
+    pub metrics: EvalMetrics,
+    pub queries: Vec<QueryEvaluation>,
+    pub created_at: u64,
+}
+
+impl EvalReport {
+    fn to_stored(&self) -> Result<StoredEvalReport, String> {
+        Ok(StoredEvalReport {
+            id: self.id.unwrap_or_default(),
+            project_id: self.project_id.clone(),
+            golden_set: self.golden_set.clone(),
+            label: self.label.clone(),
+            k: self.k,
+            recall_at_k: self.metrics.recall_at_k,
+            mrr: self.metrics.mrr,
+            ndcg_at_k: self.metrics.ndcg_at_k,
+            report: serde_json::to_string(self).map_err(|e| format!("Failed to serialize report: {}", e))?,
+            created_at: self.created_at,
+        })
+    }
+
+    fn from_stored(stored: &StoredEvalReport) -> Result<Self, String> {
+        let mut report: EvalReport = serde_json::from_str(&stored.report)
+            .map_err(|e| format!("Failed to read stored report {}: {}", stored.id, e))?;
+        report.id = Some(stored.id);
+        report.created_at = stored.created_at;
+        Ok(report)
+    }
+}
+
+/// Metric changes from one report to another of the same golden set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalComparison {
+    pub baseline_id: Option<i64>,
+    pub candidate_id: Option<i64>,
+    pub recall_delta: f64,
+    pub mrr_delta: f64,
+    pub ndcg_delta: f64,
+    /// Queries whose nDCG went up
+    pub improved: Vec<String>,
+    /// Queries whose nDCG went down
+    pub regressed: Vec<String>,
+}
+
+/// Compare a candidate report against a baseline, query by query
+pub fn compare_reports(baseline: &EvalReport, candidate: &EvalReport) -> EvalComparison {
+    let before: HashMap<&str, f64> = baseline.queries.iter().map(|q| (q.query_id.as_str(), q.ndcg_at_k)).collect();
+    let mut improved = Vec::new();
+    let mut regressed = Vec::new();
+    for query in &candidate.queries {
+        let Some(previous) = before.get(query.query_id.as_str()) else {
+            continue;
+        };
+        if query.ndcg_at_k > previous + f64::EPSILON {
+            improved.push(query.query_id.clone());
+        } else if query.ndcg_at_k < previous - f64::EPSILON {
+            regressed.push(query.query_id.clone());
+        }
+    }
+
+    EvalComparison {
+        baseline_id: baseline.id,
+        candidate_id: candidate.id,
+        recall_delta: candidate.metrics.recall_at_k - baseline.metrics.recall_at_k,
+        mrr_delta: candidate.metrics.mrr - baseline.metrics.mrr,
+        ndcg_delta: candidate.metrics.ndcg_at_k - baseline.metrics.ndcg_at_k,
+        improved,
+        regressed,
+    }
+}
+
+/// Share of the expected documents among the first `k` retrieved paths
+pub fn recall_at_k(retrieved: &[String], expected: &[ExpectedDocument], k: usize) -> f64 {
+    if expected.is_empty() {
+        return 0.0;
+    }
+    let top = &retrieved[..k.min(retrieved.len())];
+    let found = expected.iter().filter(|e| top.contains(&normalize_path(&e.path))).count();
+    found as f64 / expected.len() as f64
+}
+
+/// 1 / rank of the first expected document retrieved, or 0 when none is
+pub fn reciprocal_rank(retrieved: &[String], expected: &[ExpectedDocument]) -> f64 {
+    retrieved
+        .iter()
+        .position(|path| expected.iter().any(|e| normalize_path(&e.path) == *path))
+        .map(|index| 1.0 / (index + 1) as f64)
+        .unwrap_or(0.0)
+}
+
+/// Normalized discounted cumulative gain of the first `k` retrieved paths, with
+/// gain 2^relevance - 1
+pub fn ndcg_at_k(retrieved: &[String], expected: &[ExpectedDocument], k: usize) -> f64 {
+    let gain = |relevance: u32| 2f64.powi(relevance.min(16) as i32) - 1.0;
+    let relevance: HashMap<String, u32> = expected.iter().map(|e| (normalize_path(&e.path), e.relevance)).collect();
+
+    let dcg: f64 = retrieved
+        .iter()
+        .take(k)
+        .enumerate()
+        .map(|(index, path)| gain(relevance.get(path).copied().unwrap_or(0)) / (index as f64 + 2.0).log2())
+        .sum();
+
+    let mut ideal: Vec<u32> = relevance.values().copied().collect();
+    ideal.sort_unstable_by(|a, b| b.cmp(a));
+    let ideal_dcg: f64 = ideal
+        .iter()
+        .take(k)
+        .enumerate()
+        .map(|(index, relevance)| gain(*relevance) / (index as f64 + 2.0).log2())
+        .sum();
+
+    if ideal_dcg > 0.0 { dcg / ideal_dcg } else { 0.0 }
+}
+
+/// Lowercased text with runs of whitespace collapsed, for passage matching
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Runs golden sets against a project and stores the reports
+pub struct RagEvaluator<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> RagEvaluator<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Evaluate one config variant. Retrieval is restricted to the project.
+    pub fn evaluate(&self, project_id: &str, golden_set: &GoldenSet, variant: &ConfigVariant, k: usize) -> Result<EvalReport, String> {
+        let k = k.max(1);
+        let paths = self.document_paths(project_id)?;
+        let filter = RetrievalFilter::for_project(project_id);
+        let service = HybridRagService::with_config(self.db, variant.config.clone());
+
+        let queries: Vec<QueryEvaluation> = golden_set
+            .queries
+            .iter()
+            .map(|golden| match service.retrieve_context(&golden.query, &filter) {
+                Ok(context) => Self::score_query(golden, &context, &paths, k),
+                Err(e) => {
+                    warn!("Golden query '{}' failed: {}", golden.key(), e);
+                    QueryEvaluation {
+                        query_id: golden.key().to_string(),
+                        query: golden.query.clone(),
+                        retrieved: Vec::new(),
+                        recall_at_k: 0.0,
+                        reciprocal_rank: 0.0,
+                        ndcg_at_k: 0.0,
+                        passage_recall_at_k: golden.expected.iter().any(|e| e.passage.is_some()).then_some(0.0),
+                        error: Some(e),
+                    }
+                }
+            })
+            .collect();
+
+        let metrics = EvalMetrics::from_queries(&queries);
+        info!(
+            "Evaluated '{}' with '{}': recall@{} {:.3}, MRR {:.3}, nDCG@{} {:.3}",
+            golden_set.name, variant.label, k, metrics.recall_at_k, metrics.mrr, k, metrics.ndcg_at_k
+        );
+
+        Ok(EvalReport {
+            id: None,
+            project_id: project_id.to_string(),
+            golden_set: golden_set.name.clone(),
+            label: variant.label.clone(),
+            k,
+            config: variant.config.clone(),
+            metrics,
+            queries,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+    }
+
+    /// Evaluate every variant and store the reports
+    pub fn run(&self, project_id: &str, golden_set: &GoldenSet, variants: &[ConfigVariant], k: usize) -> Result<Vec<EvalReport>, String> {
+        let repository = RagEvalRepository::new(self.db);
+        variants
+            .iter()
+            .map(|variant| {
+                let mut report = self.evaluate(project_id, golden_set, variant, k)?;
+                report.id = Some(repository.insert(&report.to_stored()?)?);
+                Ok(report)
+            })
+            .collect()
+    }
+
+    /// Stored reports of a project, newest first
+    pub fn reports(&self, project_id: &str, golden_set: Option<&str>, limit: usize) -> Result<Vec<EvalReport>, String> {
+        RagEvalRepository::new(self.db)
+            .find_by_project(project_id, golden_set, limit)?
+            .iter()
+            .map(EvalReport::from_stored)
+            .collect()
+    }
+
+    /// A stored report by id
+    pub fn report(&self, id: i64) -> Result<Option<EvalReport>, String> {
+        RagEvalRepository::new(self.db).get(id)?.as_ref().map(EvalReport::from_stored).transpose()
+    }
+
+    fn score_query(golden: &GoldenQuery, context: &ContextAssembly, paths: &HashMap<i64, String>, k: usize) -> QueryEvaluation {
+        let retrieved: Vec<String> = context
+            .source_documents
+            .iter()
+            .map(|result| paths.get(&result.document_id).cloned().unwrap_or_else(|| format!("#{}", result.document_id)))
+            .collect();
+
+        // A passage counts when its document is in the top k and the text reached the
+        // document's passages or the assembled context
+        let context_text = normalize_text(&context.context_text);
+        let expected_passages: Vec<(&ExpectedDocument, &String)> =
+            golden.expected.iter().filter_map(|e| e.passage.as_ref().map(|passage| (e, passage))).collect();
+        let passage_recall_at_k = (!expected_passages.is_empty()).then(|| {
+            let found = expected_passages
+                .iter()
+                .filter(|(expected, passage)| {
+                    let passage = normalize_text(passage);
+                    let path = normalize_path(&expected.path);
+                    context.source_documents.iter().zip(&retrieved).take(k).any(|(result, retrieved_path)| {
+                        *retrieved_path == path
+                            && (result.passages.iter().any(|p| normalize_text(&p.text).contains(&passage))
+                                || context_text.contains(&passage))
+                    })
+                })
+                .count();
+            found as f64 / expected_passages.len() as f64
+        });
+
+        QueryEvaluation {
+            query_id: golden.key().to_string(),
+            query: golden.query.clone(),
+            recall_at_k: recall_at_k(&retrieved, &golden.expected, k),
+            reciprocal_rank: reciprocal_rank(&retrieved, &golden.expected),
+            ndcg_at_k: ndcg_at_k(&retrieved, &golden.expected, k),
+            passage_recall_at_k,
+            retrieved: retrieved.into_iter().take(k).collect(),
+            error: None,
+        }
+    }
+
+    /// Project-relative path of every document key in the project
+    fn document_paths(&self, project_id: &str) -> Result<HashMap<i64, String>, String> {
+        let content_repo = DocumentContentRepository::new(self.db);
+        let mut paths = HashMap::new();
+        for document in DocumentRepository::new(self.db).find_by_project_id(project_id)? {
+            if let Some(key) = content_repo.key_for_document(&document.id)? {
+                paths.insert(key, normalize_path(&document.path));
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// A document of an evaluation fixture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FixtureDocument {
+    pub path: String,
+    /// Defaults to the first heading, else the file stem
+    #[serde(default)]
+    pub title: Option<String>,
+    pub content: String,
+}
+
+/// A self-contained corpus with its golden set, so evaluation runs without
+/// documents on disk, e.g. in unit tests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalFixture {
+    pub project_id: String,
+    pub documents: Vec<FixtureDocument>,
+    pub golden_set: GoldenSet,
+}
+
+impl EvalFixture {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let fixture: EvalFixture = serde_json::from_str(json).map_err(|e| format!("Invalid evaluation fixture: {}", e))?;
+        fixture.golden_set.validate()?;
+        Ok(fixture)
+    }
+
+    /// Create the fixture's project and documents, with their content indexed
+    pub fn install(&self, db: &DatabaseConnection) -> Result<(), String> {
+        let project = Project::new(self.project_id.clone(), self.project_id.clone(), format!("/{}", self.project_id));
+        ProjectRepository::new(db).create(&project)?;
+
+        let content_repo = DocumentContentRepository::new(db);
+        for fixture in &self.documents {
+            let document_id = format!("{}/{}", self.project_id, normalize_path(&fixture.path));
+            let document = Document::new(document_id.clone(), self.project_id.clone(), fixture.path.clone());
+            DocumentRepository::new(db).create(&document)?;
+
+            let title = fixture
+                .title
+                .clone()
+                .unwrap_or_else(|| DocumentContentRepository::extract_title(&fixture.content, &fixture.path));
+            content_repo.upsert(&document_id, &title, &fixture.content, None)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::score_fusion::FusionStrategy;
+    use crate::infrastructure::db_layer::MigrationManager;
+    use tempfile::NamedTempFile;
+
+    const FIXTURE: &str = r##"{
+        "project_id": "eval",
+        "documents": [
+            {"path": "pricing.md", "content": "# Pricing\n\nStarter and Team plans. Annual billing saves 20 percent."},
+            {"path": "hours.md", "content": "# Office Hours\n\nOpen nine to five on weekdays."},
+            {"path": "sync.md", "content": "# Sync\n\nThe sync service retries failed uploads."}
+        ],
+        "golden_set": {
+            "name": "smoke",
+            "queries": [
+                {"id": "billing", "query": "annual billing", "expected": [{"path": "pricing.md", "passage": "saves 20 percent", "relevance": 2}]},
+                {"query": "office hours", "expected": [{"path": "hours.md"}]},
+                {"query": "refund policy", "expected": [{"path": "./pricing.md"}]}
+            ]
+        }
+    }"##;
+
+    fn expected(paths: &[(&str, u32)]) -> Vec<ExpectedDocument> {
+        paths
+            .iter()
+            .map(|(path, relevance)| ExpectedDocument { path: path.to_string(), passage: None, relevance: *relevance })
+            .collect()
+    }
+
+    #[test]
+    fn test_ranking_metrics() {
+        let retrieved: Vec<String> = ["c.md", "a.md", "b.md"].iter().map(|p| p.to_string()).collect();
+        let relevant = expected(&[("a.md", 1), ("b.md", 1), ("d.md", 1)]);
+
+        assert!((recall_at_k(&retrieved, &relevant, 2) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((recall_at_k(&retrieved, &relevant, 10) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(reciprocal_rank(&retrieved, &relevant), 0.5);
+        assert_eq!(reciprocal_rank(&retrieved, &expected(&[("z.md", 1)])), 0.0);
+
+        // A perfect ranking scores 1; the graded document at rank 2 scores less
+        let graded = expected(&[("a.md", 2), ("c.md", 1)]);
+        let ideal: Vec<String> = vec!["a.md".to_string(), "c.md".to_string()];
+        assert!((ndcg_at_k(&ideal, &graded, 10) - 1.0).abs() < 1e-9);
+        let ndcg = ndcg_at_k(&retrieved, &graded, 10);
+        assert!(ndcg > 0.0 && ndcg < 1.0);
+    }
+
+    #[test]
+    fn test_golden_set_validation() {
+        assert!(GoldenSet::from_json(r#"{"name": "empty", "queries": []}"#).is_err());
+        assert!(GoldenSet::from_json(r#"{"name": "x", "queries": [{"query": "q", "expected": []}]}"#).is_err());
+        let set = GoldenSet::from_json(r#"{"name": "x", "queries": [{"query": "q", "expected": [{"path": "a.md"}]}]}"#).unwrap();
+        assert_eq!(set.queries[0].expected[0].relevance, 1);
+        assert_eq!(set.queries[0].key(), "q");
+    }
+
+    #[test]
+    fn test_evaluation_reports_are_stored_and_compared() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = DatabaseConnection::new();
+        db.initialize(temp_file.path()).unwrap();
+        MigrationManager::new(&db).migrate().unwrap();
+
+        let fixture = EvalFixture::from_json(FIXTURE).unwrap();
+        fixture.install(&db).unwrap();
+        let evaluator = RagEvaluator::new(&db);
+        let variants = vec![
+            ConfigVariant::baseline(),
+            ConfigVariant {
+                label: "weighted".to_string(),
+                config: HybridRagConfig { fusion: FusionStrategy::WeightedSum, ..Default::default() },
+            },
+        ];
+
+        let reports = evaluator.run("eval", &fixture.golden_set, &variants, 3).unwrap();
+        assert_eq!(reports.len(), 2);
+        let baseline = &reports[0];
+        assert_eq!(baseline.metrics.queries, 3);
+        assert_eq!(baseline.queries[0].query_id, "billing");
+        assert_eq!(baseline.queries[0].retrieved.first().map(String::as_str), Some("pricing.md"));
+        assert_eq!(baseline.queries[0].passage_recall_at_k, Some(1.0));
+        // Two of the three queries find their document first; "refund policy" finds nothing
+        assert!((baseline.metrics.mrr - 2.0 / 3.0).abs() < 1e-9);
+        assert!((baseline.metrics.recall_at_k - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(baseline.metrics.passage_recall_at_k, Some(1.0));
+
+        let stored = evaluator.reports("eval", Some("smoke"), 10).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].label, "weighted");
+        let reloaded = evaluator.report(baseline.id.unwrap()).unwrap().unwrap();
+        assert_eq!(reloaded.queries, baseline.queries);
+
+        let comparison = compare_reports(&reports[0], &reports[1]);
+        assert_eq!(comparison.baseline_id, baseline.id);
+        assert!(comparison.regressed.is_empty());
+    }
+}
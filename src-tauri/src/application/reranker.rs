@@ -0,0 +1,124 @@
+// Reranker
+// Optional stage after score fusion: a cross-encoder scores (query, passage) pairs
+// and the top results are reordered by that score. A reranker that is slow or fails
+// leaves the fused order in place.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Scores how well passages answer a query, one score per passage in the same
+/// order; higher is more relevant
+pub trait PassageReranker: Send + Sync {
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, String>;
+}
+
+/// Settings of the rerank stage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerankConfig {
+    /// Whether fused results are reranked at all
+    pub enabled: bool,
+    /// Number of top fused results that are rescored and reordered
+    pub top_n: usize,
+    /// Reranked results scoring below this (0.0 to 1.0) are dropped
+    pub min_score: Option<f32>,
+    /// How long to wait for scores before keeping the fused order
+    pub timeout_ms: u64,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            top_n: 10,
+            min_score: None,
+            timeout_ms: 2000,
+        }
+    }
+}
+
+/// What the rerank stage did to a result list
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RerankStatus {
+    /// Reranking is disabled or there was nothing to rerank
+    #[default]
+    Skipped,
+    /// The top results were reordered; `dropped` fell below the score cutoff
+    Reranked { reranked: usize, dropped: usize },
+    /// Scores did not arrive in time, so the fused order was kept
+    TimedOut,
+    /// The reranker failed, so the fused order was kept
+    Failed { reason: String },
+}
+
+/// Score passages on a worker thread, giving up after `timeout`. A reranker that
+/// times out finishes in the background and its scores are discarded.
+pub fn score_with_timeout(
+    reranker: Arc<dyn PassageReranker>,
+    query: &str,
+    passages: Vec<String>,
+    timeout: Duration,
+) -> Result<Vec<f32>, RerankStatus> {
+    let expected = passages.len();
+    let query = query.to_string();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone after a timeout, so a late result is dropped
+        let _ = sender.send(reranker.score(&query, &passages));
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(scores)) if scores.len() == expected => Ok(scores),
+        Ok(Ok(scores)) => Err(RerankStatus::Failed {
+            reason: format!("Reranker returned {} scores for {} passages", scores.len(), expected),
+        }),
+        Ok(Err(reason)) => Err(RerankStatus::Failed { reason }),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(RerankStatus::TimedOut),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(RerankStatus::Failed {
+            reason: "Reranker stopped without returning scores".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scores passages by length, after an optional delay
+    struct LengthReranker {
+        delay: Duration,
+    }
+
+    impl PassageReranker for LengthReranker {
+        fn score(&self, _query: &str, passages: &[String]) -> Result<Vec<f32>, String> {
+            thread::sleep(self.delay);
+            Ok(passages.iter().map(|p| p.len() as f32).collect())
+        }
+    }
+
+    struct FailingReranker;
+
+    impl PassageReranker for FailingReranker {
+        fn score(&self, _query: &str, _passages: &[String]) -> Result<Vec<f32>, String> {
+            Err("model not loaded".to_string())
+        }
+    }
+
+    #[test]
+    fn test_score_with_timeout() {
+        let passages = vec!["a".to_string(), "abc".to_string()];
+        let fast = Arc::new(LengthReranker { delay: Duration::ZERO });
+        assert_eq!(score_with_timeout(fast, "q", passages.clone(), Duration::from_secs(5)), Ok(vec![1.0, 3.0]));
+
+        let slow = Arc::new(LengthReranker { delay: Duration::from_millis(500) });
+        assert_eq!(score_with_timeout(slow, "q", passages.clone(), Duration::from_millis(10)), Err(RerankStatus::TimedOut));
+
+        assert_eq!(
+            score_with_timeout(Arc::new(FailingReranker), "q", passages, Duration::from_secs(5)),
+            Err(RerankStatus::Failed { reason: "model not loaded".to_string() })
+        );
+    }
+}
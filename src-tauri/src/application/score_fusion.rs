@@ -0,0 +1,224 @@
+// Score Fusion
+// Merges the lexical (FTS5 BM25) and vector similarity rankings of hybrid retrieval
+// into one score where higher is better, keeping a per-result breakdown for debugging.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Rank constant commonly used for reciprocal rank fusion
+pub const DEFAULT_RRF_K: f64 = 60.0;
+
+/// How lexical and vector evidence are combined into a single ranking
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Sum of weight / (k + rank) over the rankings a document appears in.
+    /// Uses positions only, so BM25 and cosine scales never mix.
+    ReciprocalRank { k: f64 },
+    /// Weighted sum of scores min-max normalized to 0-1 within each ranking
+    WeightedSum,
+    /// BM25 only; vector similarity is reported but not scored
+    LexicalOnly,
+    /// Vector similarity only; documents without an embedding are dropped
+    VectorOnly,
+}
+
+impl Default for FusionStrategy {
+    fn default() -> Self {
+        FusionStrategy::ReciprocalRank { k: DEFAULT_RRF_K }
+    }
+}
+
+/// How a result's fused score was made up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// 1-based position in the lexical ranking
+    pub lexical_rank: Option<usize>,
+    /// 1-based position in the vector ranking
+    pub vector_rank: Option<usize>,
+    /// Raw FTS5 BM25 score (lower is better)
+    pub bm25: Option<f64>,
+    pub vector_similarity: Option<f32>,
+    /// Part of the fused score from lexical evidence
+    pub lexical_contribution: f64,
+    /// Part of the fused score from vector evidence
+    pub vector_contribution: f64,
+    /// Part of the score from being cited earlier in the conversation
+    #[serde(default)]
+    pub conversation_contribution: f64,
+}
+
+impl ScoreBreakdown {
+    /// Fused score; higher is better
+    pub fn score(&self) -> f64 {
+        self.lexical_contribution + self.vector_contribution + self.conversation_contribution
+    }
+}
+
+/// A document's fused score
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FusedScore {
+    pub document_id: i64,
+    pub score: f64,
+    pub breakdown: ScoreBreakdown,
+}
+
+impl FusionStrategy {
+    /// Fuse a lexical ranking (best first, with BM25 scores) and vector similarities
+    /// into one list sorted by descending score. Ties keep lexical order, and
+    /// vector-only documents follow in order of similarity.
+    pub fn fuse(
+        &self,
+        lexical: &[(i64, f64)],
+        vector: &HashMap<i64, f32>,
+        lexical_weight: f64,
+        vector_weight: f64,
+    ) -> Vec<FusedScore> {
+        let mut vector_ranking: Vec<(i64, f32)> = vector.iter().map(|(&id, &sim)| (id, sim)).collect();
+        vector_ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut order: Vec<i64> = lexical.iter().map(|(id, _)| *id).collect();
+        let mut breakdowns: HashMap<i64, ScoreBreakdown> = HashMap::new();
+        for (index, (id, bm25)) in lexical.iter().enumerate() {
+            let entry = breakdowns.entry(*id).or_default();
+            entry.lexical_rank = Some(index + 1);
+            entry.bm25 = Some(*bm25);
+        }
+        for (index, (id, similarity)) in vector_ranking.iter().enumerate() {
+            let entry = breakdowns.entry(*id).or_insert_with(|| {
+                order.push(*id);
+                ScoreBreakdown::default()
+            });
+            entry.vector_rank = Some(index + 1);
+            entry.vector_similarity = Some(*similarity);
+        }
+
+        // BM25 is better when lower, so it is negated before normalizing
+        let bm25_range = value_range(lexical.iter().map(|(_, bm25)| -*bm25));
+        let similarity_range = value_range(vector_ranking.iter().map(|(_, sim)| *sim as f64));
+
+        for breakdown in breakdowns.values_mut() {
+            let lexical_normalized = breakdown.bm25.map(|bm25| normalize(-bm25, bm25_range));
+            let vector_normalized = breakdown.vector_similarity.map(|sim| normalize(sim as f64, similarity_range));
+
+            let (lexical_contribution, vector_contribution) = match self {
+                FusionStrategy::ReciprocalRank { k } => (
+                    breakdown.lexical_rank.map_or(0.0, |rank| lexical_weight / (k + rank as f64)),
+                    breakdown.vector_rank.map_or(0.0, |rank| vector_weight / (k + rank as f64)),
+                ),
+                FusionStrategy::WeightedSum => (
+                    lexical_normalized.map_or(0.0, |score| lexical_weight * score),
+                    vector_normalized.map_or(0.0, |score| vector_weight * score),
+                ),
+                FusionStrategy::LexicalOnly => (lexical_normalized.unwrap_or(0.0), 0.0),
+                FusionStrategy::VectorOnly => (0.0, vector_normalized.unwrap_or(0.0)),
+            };
+            breakdown.lexical_contribution = lexical_contribution;
+            breakdown.vector_contribution = vector_contribution;
+        }
+
+        let mut fused: Vec<FusedScore> = order
+            .into_iter()
+            .filter_map(|document_id| {
+                let breakdown = breakdowns[&document_id];
+                let scored = match self {
+                    FusionStrategy::LexicalOnly => breakdown.lexical_rank.is_some(),
+                    FusionStrategy::VectorOnly => breakdown.vector_rank.is_some(),
+                    _ => true,
+                };
+                scored.then(|| FusedScore { document_id, score: breakdown.score(), breakdown })
+            })
+            .collect();
+
+        // Stable sort, so equal scores stay in lexical order
+        fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+        fused
+    }
+}
+
+/// Minimum and maximum of a set of scores
+fn value_range(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |range, value| match range {
+        None => Some((value, value)),
+        Some((min, max)) => Some((min.min(value), max.max(value))),
+    })
+}
+
+/// Min-max normalize to 0-1; a ranking whose scores are all equal normalizes to 1
+fn normalize(value: f64, range: Option<(f64, f64)>) -> f64 {
+    match range {
+        Some((min, max)) if max > min => (value - min) / (max - min),
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (Vec<(i64, f64)>, HashMap<i64, f32>) {
+        // BM25 from FTS5 is negative, more negative is better
+        let lexical = vec![(1, -8.0), (2, -5.0), (3, -2.0)];
+        let vector = HashMap::from([(3, 0.9), (2, 0.5), (4, 0.7)]);
+        (lexical, vector)
+    }
+
+    fn ids(fused: &[FusedScore]) -> Vec<i64> {
+        fused.iter().map(|f| f.document_id).collect()
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let (lexical, vector) = fixture();
+        let fused = FusionStrategy::default().fuse(&lexical, &vector, 0.5, 0.5);
+
+        // Document 2 and 3 appear in both rankings and beat single-ranking documents
+        assert_eq!(ids(&fused), vec![3, 2, 1, 4]);
+        let top = fused[0].breakdown;
+        assert_eq!((top.lexical_rank, top.vector_rank), (Some(3), Some(1)));
+        assert!((top.lexical_contribution - 0.5 / 63.0).abs() < 1e-12);
+        assert!((top.vector_contribution - 0.5 / 61.0).abs() < 1e-12);
+        assert_eq!(fused[0].score, top.score());
+        assert_eq!(fused[3].breakdown.bm25, None);
+    }
+
+    #[test]
+    fn test_weighted_sum_is_min_max_normalized() {
+        let (lexical, vector) = fixture();
+        let fused = FusionStrategy::WeightedSum.fuse(&lexical, &vector, 0.4, 0.6);
+        let by_id: HashMap<i64, ScoreBreakdown> = fused.iter().map(|f| (f.document_id, f.breakdown)).collect();
+
+        assert!((by_id[&1].lexical_contribution - 0.4).abs() < 1e-9);
+        assert_eq!(by_id[&3].lexical_contribution, 0.0);
+        assert!((by_id[&3].vector_contribution - 0.6).abs() < 1e-9);
+        assert_eq!(by_id[&2].vector_contribution, 0.0);
+        assert!(fused.iter().all(|f| (0.0..=1.0).contains(&f.score)));
+        assert!(fused.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn test_single_arm_strategies() {
+        let (lexical, vector) = fixture();
+
+        let fused = FusionStrategy::LexicalOnly.fuse(&lexical, &vector, 0.4, 0.6);
+        assert_eq!(ids(&fused), vec![1, 2, 3]);
+        assert_eq!(fused[2].breakdown.vector_similarity, Some(0.9));
+        assert!(fused.iter().all(|f| f.breakdown.vector_contribution == 0.0));
+
+        let fused = FusionStrategy::VectorOnly.fuse(&lexical, &vector, 0.4, 0.6);
+        assert_eq!(ids(&fused), vec![3, 4, 2]);
+
+        // A ranking with a single entry, or equal scores, normalizes to 1
+        let fused = FusionStrategy::LexicalOnly.fuse(&[(7, -1.0)], &HashMap::new(), 0.4, 0.6);
+        assert_eq!(fused[0].score, 1.0);
+        assert!(FusionStrategy::VectorOnly.fuse(&[(7, -1.0)], &HashMap::new(), 0.4, 0.6).is_empty());
+    }
+
+    #[test]
+    fn test_fusion_strategy_serde() {
+        let json = serde_json::to_string(&FusionStrategy::default()).unwrap();
+        assert_eq!(json, r#"{"kind":"reciprocal_rank","k":60.0}"#);
+        let parsed: FusionStrategy = serde_json::from_str(r#"{"kind":"weighted_sum"}"#).unwrap();
+        assert_eq!(parsed, FusionStrategy::WeightedSum);
+    }
+}
@@ -1,10 +1,13 @@
 // Application Services
 // Service layer that coordinates domain logic and infrastructure
 
-use crate::core::{Project, Document, LocalAiEngine, CompletionRequest, CompletionResponse};
+use crate::core::{Project, Document, LocalAiEngine, CompletionRequest, CompletionResponse, RerankRequest};
+use crate::application::reranker::PassageReranker;
+use crate::application::query_expansion::QueryExpander;
+use crate::application::ai_provider::{AiProvider, utils::create_simple_context};
 use crate::infrastructure::{DatabaseManager, FilesystemManager, ProjectRepository};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::{info, warn, error};
 use anyhow::Result;
 use uuid::Uuid;
@@ -183,4 +186,88 @@ impl LocalAiService {
             format!("Failed to start AI sidecar: {}", e)
         })
     }
+
+    /// Reranker backed by the sidecar's cross-encoder, sharing this service's engine
+    pub fn reranker(&self) -> SidecarReranker {
+        SidecarReranker {
+            engine: Arc::clone(&self.engine),
+        }
+    }
+}
+
+/// Longest a rerank reply is awaited. The rerank stage gives up much sooner; this
+/// only bounds how long an abandoned worker thread lingers.
+const RERANK_REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Passage reranker that scores (query, passage) pairs with the local sidecar
+pub struct SidecarReranker {
+    engine: Arc<Mutex<LocalAiEngine>>,
+}
+
+impl PassageReranker for SidecarReranker {
+    /// Blocks until the sidecar answers; the rerank stage calls this off the async
+    /// runtime, on its own worker thread. The engine is locked only to send the
+    /// request, so a reply that is slow or never comes does not hold up other callers.
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, String> {
+        let request = RerankRequest {
+            query: query.to_string(),
+            passages: passages.to_vec(),
+            model: None,
+        };
+
+        let reply = {
+            let mut engine = self.engine.lock().map_err(|e| {
+                error!("Failed to acquire engine lock for reranking: {}", e);
+                "Failed to access AI engine".to_string()
+            })?;
+
+            tauri::async_runtime::block_on(engine.send_rerank(request)).map_err(|e| {
+                error!("AI rerank error: {}", e);
+                format!("AI reranking failed: {}", e)
+            })?
+        };
+
+        let response = reply.recv_timeout(RERANK_REPLY_TIMEOUT).map_err(|e| match e {
+            std::sync::mpsc::RecvTimeoutError::Timeout => "Sidecar did not answer the rerank request".to_string(),
+            std::sync::mpsc::RecvTimeoutError::Disconnected => "Sidecar stopped before answering the rerank request".to_string(),
+        })?;
+
+        if response.success {
+            Ok(response.scores)
+        } else {
+            Err(response.error.unwrap_or_else(|| "Unknown AI error".to_string()))
+        }
+    }
+}
+
+/// Query expander backed by an AI provider, usually the active one
+pub struct ProviderQueryExpander {
+    provider: Box<dyn AiProvider>,
+}
+
+impl ProviderQueryExpander {
+    pub fn new(provider: Box<dyn AiProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+impl QueryExpander for ProviderQueryExpander {
+    /// Blocks until the provider answers. The call runs on a scoped thread, so this
+    /// is safe to use from commands running on the async runtime.
+    fn generate(&self, instructions: &str, prompt: &str) -> Result<String, String> {
+        let mut context = create_simple_context(prompt.to_string(), Some(instructions.to_string()));
+        context.config.temperature = Some(0.3);
+        context.config.max_tokens = Some(300);
+
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| tauri::async_runtime::block_on(self.provider.invoke_model(context)))
+                .join()
+                .map_err(|_| "Query expansion thread panicked".to_string())?
+                .map_err(|e| {
+                    warn!("Query expansion failed: {}", e);
+                    format!("Query expansion failed: {}", e)
+                })
+        })
+    }
 }
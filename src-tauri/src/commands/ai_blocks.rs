@@ -65,7 +65,7 @@ pub async fn search_ai_blocks(
     ai_blocks_service: State<'_, Arc<AiBlocksService>>,
     query: String,
     limit: Option<i32>,
-) -> Result<Vec<AiBlock>, String> {
+) -> Result<AiBlockSearchResult, String> {
     ai_blocks_service.search_ai_blocks(&query, limit).await
 }
 
@@ -307,8 +307,8 @@ mod tests {
         let result = search_ai_blocks(state, "summarize".to_string(), Some(10)).await;
         assert!(result.is_ok());
         
-        let blocks = result.unwrap();
-        assert!(!blocks.is_empty()); // Should find system blocks
+        let result = result.unwrap();
+        assert!(!result.blocks.is_empty()); // Should find system blocks
     }
 
     #[tokio::test]
@@ -6,6 +6,7 @@
 
 use crate::services::database_optimization_service::*;
 use crate::infrastructure::db_layer::query_optimizer::*;
+use crate::infrastructure::db_layer::{VectorStorageReport, VectorStorageSettings};
 use tauri::State;
 use std::sync::Arc;
 
@@ -50,6 +51,31 @@ pub async fn validate_database_health(
     optimization_service.validate_database_health().await
 }
 
+/// Tauri command to convert stored vectors to compact storage and report bytes saved
+#[tauri::command]
+pub async fn compact_vector_storage(
+    optimization_service: State<'_, Arc<DatabaseOptimizationService>>,
+) -> Result<VectorStorageReport, String> {
+    optimization_service.compact_vector_storage().await
+}
+
+/// Tauri command to get the vector storage settings
+#[tauri::command]
+pub async fn get_vector_storage_settings(
+    optimization_service: State<'_, Arc<DatabaseOptimizationService>>,
+) -> Result<VectorStorageSettings, String> {
+    optimization_service.get_vector_storage_settings().await
+}
+
+/// Tauri command to opt into (or out of) quantized vector storage and convert stored vectors
+#[tauri::command]
+pub async fn set_vector_storage_settings(
+    settings: VectorStorageSettings,
+    optimization_service: State<'_, Arc<DatabaseOptimizationService>>,
+) -> Result<VectorStorageReport, String> {
+    optimization_service.set_vector_storage_settings(settings).await
+}
+
 /// Tauri command to get detailed query analysis for a specific query
 #[tauri::command]
 pub async fn analyze_specific_query(
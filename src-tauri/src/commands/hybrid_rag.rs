@@ -2,16 +2,58 @@
 // Implements the @ context command for the frontend
 
 use crate::application::hybrid_rag_service::{HybridRagService, HybridRagConfig, ContextAssembly};
-use crate::infrastructure::db_layer::DatabaseConnection;
+use crate::application::ai_provider::{Message, StreamChunk, utils};
+use crate::application::ai_provider_manager::AiProviderManager;
+use crate::application::citation_verifier::{verify_citations, VerificationReport};
+use crate::application::conversation_retrieval::{recent_messages, ConversationRetrievalConfig, QueryRewriteMode};
+use crate::application::grounded_answer::{
+    answer_citations, answer_message, grounded_prompt, has_relevant_context, is_not_found, AnswerCitation,
+    GroundedAnswerConfig, NOT_FOUND_ANSWER,
+};
+use crate::application::injection_guard::InjectionGuardConfig;
+use crate::application::rag_config_store::{ConfigDifference, RagConfigStore};
+use crate::application::rag_evaluation::{
+    compare_reports, ConfigVariant, EvalComparison, EvalReport, GoldenSet, RagEvaluator, DEFAULT_EVAL_K,
+};
+use crate::application::query_embedding::{index_document, QueryEmbedder, QueryEmbedderSlot};
+use crate::application::query_expansion::{ExpansionMode, QueryExpansionCache, QueryExpansionConfig};
+use crate::application::reranker::RerankConfig;
+use crate::application::score_fusion::FusionStrategy;
+use crate::application::services::{LocalAiService, ProviderQueryExpander};
+use crate::infrastructure::db_layer::{
+    CorrectedSearchResults, DatabaseConnection, DocumentMatches, FTS5Repository, PassageMatch, QueryParseError,
+    DocumentContentRepository, RetrievalFilter, SearchIndexManager, SearchIndexRebuild, SearchIndexSettings,
+    SearchQuery,
+};
+use crate::infrastructure::{OllamaEmbedder, OllamaProviderConfig};
+use crate::services::{ContentConsistencyReport, ContentSyncReport, DocumentContentService};
 use serde::{Deserialize, Serialize};
-use tauri::State;
-use tracing::{info, error};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+use tracing::{info, warn, error};
 
 /// Request structure for context retrieval
 #[derive(Debug, Deserialize)]
 pub struct ContextRequest {
     pub query: String,
+    /// Config for this request only; the saved config of `project_id` when omitted
     pub config: Option<HybridRagConfig>,
+    /// Restrict retrieval to matching documents (e.g. the current project)
+    pub filter: Option<RetrievalFilter>,
+    /// Project that `@path`, `@folder/` and `@"Title"` references in the query resolve against
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Context window of the target provider in tokens; when set, the context token
+    /// budget is derived from it instead of the configured `max_context_tokens`
+    #[serde(default)]
+    pub context_window_tokens: Option<u32>,
+    /// Turns query expansion on or off for this request, overriding the config
+    #[serde(default)]
+    pub expand_query: Option<bool>,
+    /// Earlier messages of the chat, oldest first. When given, a follow-up `query` is
+    /// rewritten against them and documents cited in earlier answers are carried forward.
+    #[serde(default)]
+    pub conversation: Vec<Message>,
 }
 
 /// Response structure for context retrieval
@@ -28,6 +70,10 @@ pub struct ContextResponse {
 pub async fn retrieve_context(
     request: ContextRequest,
     db_state: State<'_, DatabaseConnection>,
+    ai_service: State<'_, LocalAiService>,
+    provider_manager: State<'_, AiProviderManager>,
+    expansion_cache: State<'_, QueryExpansionCache>,
+    query_embedder: State<'_, QueryEmbedderSlot>,
 ) -> Result<ContextResponse, String> {
     info!("Received context retrieval request for query: '{}'", request.query);
 
@@ -39,15 +85,12 @@ pub async fn retrieve_context(
         });
     }
 
-    // Create hybrid RAG service
-    let service = if let Some(config) = request.config {
-        HybridRagService::with_config(&db_state, config)
-    } else {
-        HybridRagService::new(&db_state)
-    };
+    let config = request_config(&request, &db_state)?;
+    let service = request_service(config, &request, &db_state, &ai_service, &provider_manager, &expansion_cache, &query_embedder);
 
     // Perform hybrid RAG retrieval
-    match service.retrieve_context(&request.query) {
+    let retrieved = retrieve_for_request(&service, &request);
+    match retrieved {
         Ok(context_assembly) => {
             info!("Context retrieval successful. Context length: {} characters", 
                   context_assembly.total_length);
@@ -69,6 +112,317 @@ pub async fn retrieve_context(
     }
 }
 
+/// Config of a retrieval request: its own config, else the saved config of its project,
+/// budgeted for the target provider when it is known
+fn request_config(request: &ContextRequest, db: &DatabaseConnection) -> Result<HybridRagConfig, String> {
+    let mut config = match &request.config {
+        Some(config) => config.clone(),
+        None => RagConfigStore::new(db).config(request.project_id.as_deref())?,
+    };
+    if let Some(window_tokens) = request.context_window_tokens {
+        config = config.for_context_window(window_tokens);
+    }
+    if let Some(expand_query) = request.expand_query {
+        config.query_expansion.enabled = expand_query;
+    }
+    Ok(config)
+}
+
+/// Hybrid RAG service for a retrieval request
+fn request_service<'a>(
+    config: HybridRagConfig,
+    request: &ContextRequest,
+    db: &'a DatabaseConnection,
+    ai_service: &LocalAiService,
+    provider_manager: &AiProviderManager,
+    expansion_cache: &'a QueryExpansionCache,
+    query_embedder: &QueryEmbedderSlot,
+) -> HybridRagService<'a> {
+    let expand = config.query_expansion.enabled;
+    let rewrite_with_model = !request.conversation.is_empty() && config.conversation.rewrite == QueryRewriteMode::Model;
+    let mut service = HybridRagService::with_config(db, config)
+        .with_reranker(Arc::new(ai_service.reranker()));
+    if let Some(embedder) = query_embedder.get() {
+        service = service.with_query_embedder(embedder);
+    }
+
+    // Queries are expanded and follow-ups rewritten with the active provider; without
+    // one, only the original query is searched and follow-ups use the heuristic rewrite
+    if expand || rewrite_with_model {
+        match provider_manager.get_active_provider() {
+            Ok(provider) => {
+                let generator = Arc::new(ProviderQueryExpander::new(provider));
+                if expand {
+                    service = service.with_query_expander(generator.clone(), expansion_cache);
+                }
+                if rewrite_with_model {
+                    service = service.with_query_rewriter(generator);
+                }
+            }
+            Err(e) => warn!("Query expansion and model rewrites skipped, no active AI provider: {}", e),
+        }
+    }
+    service
+}
+
+/// Retrieve the context of a request, against its conversation when it has one
+fn retrieve_for_request(service: &HybridRagService, request: &ContextRequest) -> Result<ContextAssembly, String> {
+    let filter = request.filter.clone().unwrap_or_default();
+    let project_id = request.project_id.as_deref();
+    if request.conversation.is_empty() {
+        service.retrieve_context_for_prompt(&request.query, project_id, &filter)
+    } else {
+        service.retrieve_context_for_conversation(&request.conversation, &request.query, project_id, &filter)
+    }
+}
+
+/// Response structure for project question answering
+#[derive(Debug, Serialize)]
+pub struct AskProjectResponse {
+    pub success: bool,
+    /// Complete answer; `NOT_FOUND_ANSWER` when the project does not hold one
+    pub answer: String,
+    /// Whether the answer came from the project's documents
+    pub found: bool,
+    /// Sources the answer cites, in order of first citation
+    pub citations: Vec<AnswerCitation>,
+    /// The answer as an assistant message listing its cited documents; append it to
+    /// `conversation` of follow-up questions to carry the citations forward
+    pub message: Option<Message>,
+    /// How well the cited excerpts back each cited sentence; None when verification
+    /// is off or nothing was found
+    pub verification: Option<VerificationReport>,
+    /// Context the answer was grounded in
+    pub context: Option<ContextAssembly>,
+    pub error: Option<String>,
+}
+
+impl AskProjectResponse {
+    fn failed(error: String) -> Self {
+        Self {
+            success: false,
+            answer: String::new(),
+            found: false,
+            citations: Vec::new(),
+            message: None,
+            verification: None,
+            context: None,
+            error: Some(error),
+        }
+    }
+
+    fn not_found(context: ContextAssembly) -> Self {
+        Self {
+            success: true,
+            answer: NOT_FOUND_ANSWER.to_string(),
+            found: false,
+            citations: Vec::new(),
+            message: Some(answer_message(NOT_FOUND_ANSWER, &[])),
+            verification: None,
+            context: Some(context),
+            error: None,
+        }
+    }
+}
+
+/// Tauri command to answer a question from the project's documents. The answer is
+/// streamed as `ai_stream_chunk` events while it is generated and returned with its
+/// citations once complete. Questions the retrieved documents do not answer get
+/// `NOT_FOUND_ANSWER`.
+#[tauri::command]
+pub async fn ask_project(
+    request: ContextRequest,
+    app_handle: AppHandle,
+    db_state: State<'_, DatabaseConnection>,
+    ai_service: State<'_, LocalAiService>,
+    provider_manager: State<'_, AiProviderManager>,
+    expansion_cache: State<'_, QueryExpansionCache>,
+    query_embedder: State<'_, QueryEmbedderSlot>,
+) -> Result<AskProjectResponse, String> {
+    info!("Received project question: '{}'", request.query);
+
+    if request.query.trim().is_empty() {
+        return Ok(AskProjectResponse::failed("Question cannot be empty".to_string()));
+    }
+
+    let config = request_config(&request, &db_state)?;
+    let answer_config = config.answer.clone();
+    let max_messages = config.conversation.max_messages;
+    let context = {
+        let service = request_service(config, &request, &db_state, &ai_service, &provider_manager, &expansion_cache, &query_embedder);
+        match retrieve_for_request(&service, &request) {
+            Ok(context) => context,
+            Err(e) => {
+                error!("Context retrieval for project question failed: {}", e);
+                return Ok(AskProjectResponse::failed(e));
+            }
+        }
+    };
+
+    // Nothing relevant to ground an answer in: say so rather than let the model guess
+    if !has_relevant_context(&context, &answer_config) {
+        info!("No relevant documents for project question; answering not found");
+        for chunk in [NOT_FOUND_ANSWER, ""] {
+            let chunk = StreamChunk { content: chunk.to_string(), is_final: chunk.is_empty(), metadata: None };
+            utils::emit_stream_chunk(&app_handle, &chunk).map_err(|e| e.to_string())?;
+        }
+        return Ok(AskProjectResponse::not_found(context));
+    }
+
+    let provider = match provider_manager.get_active_provider() {
+        Ok(provider) => provider,
+        Err(e) => return Ok(AskProjectResponse::failed(format!("No active AI provider: {}", e))),
+    };
+    let history = recent_messages(&request.conversation, max_messages);
+    let prompt = grounded_prompt(&request.query, &context, &history, &answer_config);
+
+    // Forward chunks to the frontend as they arrive, keeping the text for the citations
+    let streamed = Mutex::new(String::new());
+    let result = provider
+        .invoke_model_stream_to(prompt, &|chunk| {
+            streamed.lock().unwrap().push_str(&chunk.content);
+            utils::emit_stream_chunk(&app_handle, &chunk)
+        })
+        .await;
+    if let Err(e) = result {
+        error!("Answering project question failed: {}", e);
+        return Ok(AskProjectResponse::failed(e.to_string()));
+    }
+
+    let answer = streamed.into_inner().unwrap().trim().to_string();
+    if is_not_found(&answer) {
+        return Ok(AskProjectResponse::not_found(context));
+    }
+    let citations = answer_citations(&answer, &context);
+    info!("Answered project question citing {} sources", citations.len());
+
+    // Claims are also compared to their excerpts by embedding when an embedder is set up
+    let verification = answer_config
+        .verification
+        .enabled
+        .then(|| verify_citations(&answer, &citations, query_embedder.text_embedder(), &answer_config.verification));
+
+    Ok(AskProjectResponse {
+        success: true,
+        message: Some(answer_message(&answer, &citations)),
+        answer,
+        found: true,
+        citations,
+        verification,
+        context: Some(context),
+        error: None,
+    })
+}
+
+/// Response structure for passage retrieval
+#[derive(Debug, Serialize)]
+pub struct PassagesResponse {
+    pub success: bool,
+    pub passages: Vec<PassageMatch>,
+    /// Spelling-corrected query used when the original query matched nothing
+    pub suggested_query: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Tauri command to retrieve the passages (document chunks) that best match a query
+#[tauri::command]
+pub async fn retrieve_passages(
+    request: ContextRequest,
+    db_state: State<'_, DatabaseConnection>,
+    query_embedder: State<'_, QueryEmbedderSlot>,
+) -> Result<PassagesResponse, String> {
+    info!("Received passage retrieval request for query: '{}'", request.query);
+
+    if request.query.trim().is_empty() {
+        return Ok(PassagesResponse {
+            success: false,
+            passages: Vec::new(),
+            suggested_query: None,
+            error: Some("Query cannot be empty".to_string()),
+        });
+    }
+
+    let config = match request.config {
+        Some(config) => config,
+        None => RagConfigStore::new(&db_state).config(request.project_id.as_deref())?,
+    };
+    // Passages are ranked by similarity to the query, so without an embedder there are none
+    let mut service = HybridRagService::with_config(&db_state, config);
+    if let Some(embedder) = query_embedder.get() {
+        service = service.with_query_embedder(embedder);
+    }
+
+    let filter = request.filter.unwrap_or_default();
+    match service.retrieve_passages(&request.query, &filter) {
+        Ok((passages, suggested_query)) => Ok(PassagesResponse {
+            success: true,
+            passages,
+            suggested_query,
+            error: None,
+        }),
+        Err(e) => {
+            error!("Passage retrieval failed: {}", e);
+            Ok(PassagesResponse {
+                success: false,
+                passages: Vec::new(),
+                suggested_query: None,
+                error: Some(e),
+            })
+        }
+    }
+}
+
+/// Tauri command to parse search box input, so the UI can show syntax errors
+/// with their position before running a search
+#[tauri::command]
+pub async fn parse_search_query(query: String) -> Result<SearchQuery, QueryParseError> {
+    SearchQuery::parse(&query)
+}
+
+/// Tauri command to find the byte and line/column ranges of every match of a query,
+/// for in-editor highlighting and "jump to next match" across a project
+#[tauri::command]
+pub async fn find_match_ranges(
+    query: String,
+    filter: Option<RetrievalFilter>,
+    limit: Option<i32>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<Vec<DocumentMatches>, String> {
+    let parsed = SearchQuery::parse(&query).map_err(|e| e.to_string())?;
+    FTS5Repository::new(&db_state).find_match_ranges(&parsed, limit, &filter.unwrap_or_default())
+}
+
+/// Tauri command for the document search box: full-text results with snippets,
+/// retried with a "did you mean" correction when the query as typed matches nothing
+#[tauri::command]
+pub async fn search_documents(
+    query: String,
+    filter: Option<RetrievalFilter>,
+    limit: Option<i32>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<CorrectedSearchResults, String> {
+    FTS5Repository::new(&db_state).search_with_correction(&query, limit, &filter.unwrap_or_default())
+}
+
+/// Tauri command to get the search index tokenizer settings
+#[tauri::command]
+pub async fn get_search_index_settings(
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<SearchIndexSettings, String> {
+    SearchIndexManager::new(&db_state).settings()
+}
+
+/// Tauri command to change the search index settings, rebuilding affected indexes
+#[tauri::command]
+pub async fn update_search_index_settings(
+    settings: SearchIndexSettings,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<SearchIndexRebuild, String> {
+    info!("Updating search index settings: {:?}", settings);
+
+    SearchIndexManager::new(&db_state).apply(&settings)
+}
+
 /// Tauri command to test hybrid RAG functionality
 #[tauri::command]
 pub async fn test_hybrid_rag(
@@ -94,10 +448,50 @@ pub async fn test_hybrid_rag(
     }
 }
 
-/// Tauri command to get hybrid RAG configuration
+/// Tauri command to get the hybrid RAG configuration of a project (the saved
+/// defaults with the project's overrides), or the defaults without a project
 #[tauri::command]
-pub async fn get_hybrid_rag_config() -> Result<HybridRagConfig, String> {
-    Ok(HybridRagConfig::default())
+pub async fn get_hybrid_rag_config(
+    project_id: Option<String>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<HybridRagConfig, String> {
+    RagConfigStore::new(&db_state).config(project_id.as_deref())
+}
+
+/// Tauri command to save the hybrid RAG configuration of a project, or the defaults
+/// without a project. Takes effect from the next retrieval.
+#[tauri::command]
+pub async fn set_hybrid_rag_config(
+    config: HybridRagConfig,
+    project_id: Option<String>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<HybridRagConfig, String> {
+    validate_hybrid_rag_config(config.clone()).await?;
+    info!("Saving hybrid RAG config for {}", project_id.as_deref().unwrap_or("all projects"));
+
+    RagConfigStore::new(&db_state).save(project_id.as_deref(), &config)
+}
+
+/// Tauri command to drop a project's hybrid RAG overrides, or the saved defaults
+/// without a project; returns the configuration now in effect
+#[tauri::command]
+pub async fn reset_hybrid_rag_config(
+    project_id: Option<String>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<HybridRagConfig, String> {
+    info!("Resetting hybrid RAG config for {}", project_id.as_deref().unwrap_or("all projects"));
+
+    RagConfigStore::new(&db_state).reset(project_id.as_deref())
+}
+
+/// Tauri command to list the settings a project overrides, or that the saved
+/// defaults change from the built-in ones without a project
+#[tauri::command]
+pub async fn diff_hybrid_rag_config(
+    project_id: Option<String>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<Vec<ConfigDifference>, String> {
+    RagConfigStore::new(&db_state).diff(project_id.as_deref())
 }
 
 /// Tauri command to validate hybrid RAG configuration
@@ -110,6 +504,10 @@ pub async fn validate_hybrid_rag_config(
         return Err("max_candidates must be between 1 and 1000".to_string());
     }
     
+    if config.max_vector_candidates < 0 || config.max_vector_candidates > 1000 {
+        return Err("max_vector_candidates must be between 0 and 1000".to_string());
+    }
+    
     if config.max_results <= 0 || config.max_results > config.max_candidates {
         return Err("max_results must be between 1 and max_candidates".to_string());
     }
@@ -133,6 +531,72 @@ pub async fn validate_hybrid_rag_config(
     if config.max_context_length == 0 || config.max_context_length > 50000 {
         return Err("max_context_length must be between 1 and 50000".to_string());
     }
+
+    if config.max_context_tokens == 0 {
+        return Err("max_context_tokens must be at least 1".to_string());
+    }
+
+    if config.max_reference_length == 0 || config.max_reference_length > config.max_context_length {
+        return Err("max_reference_length must be between 1 and max_context_length".to_string());
+    }
+
+    if let FusionStrategy::ReciprocalRank { k } = config.fusion {
+        if !(k > 0.0 && k.is_finite()) {
+            return Err("Reciprocal rank fusion k must be a positive number".to_string());
+        }
+    }
+
+    if config.rerank.enabled {
+        if config.rerank.top_n == 0 || config.rerank.top_n > 100 {
+            return Err("rerank.top_n must be between 1 and 100".to_string());
+        }
+
+        if config.rerank.timeout_ms == 0 || config.rerank.timeout_ms > 30000 {
+            return Err("rerank.timeout_ms must be between 1 and 30000".to_string());
+        }
+
+        if config.rerank.min_score.is_some_and(|score| !(0.0..=1.0).contains(&score)) {
+            return Err("rerank.min_score must be between 0.0 and 1.0".to_string());
+        }
+    }
+
+    let conversation = &config.conversation;
+    if conversation.max_messages == 0 || conversation.max_messages > 50 {
+        return Err("conversation.max_messages must be between 1 and 50".to_string());
+    }
+
+    if !(0.0..=1.0).contains(&conversation.citation_decay) || !(0.0..=1.0).contains(&conversation.min_carry_weight) {
+        return Err("conversation.citation_decay and min_carry_weight must be between 0.0 and 1.0".to_string());
+    }
+
+    let answer = &config.answer;
+    if !(0.0..=1.0).contains(&answer.min_similarity) || !(0.0..=1.0).contains(&answer.min_rerank_score) {
+        return Err("answer.min_similarity and min_rerank_score must be between 0.0 and 1.0".to_string());
+    }
+
+    if answer.max_answer_tokens == 0 || !(0.0..=1.0).contains(&answer.temperature) {
+        return Err("answer.max_answer_tokens must be at least 1 and answer.temperature between 0.0 and 1.0".to_string());
+    }
+
+    let verification = &answer.verification;
+    if !(0.0..=1.0).contains(&verification.lexical_weight)
+        || !(0.0..=1.0).contains(&verification.weak_threshold)
+        || !(verification.weak_threshold..=1.0).contains(&verification.supported_threshold)
+    {
+        return Err(
+            "answer.verification weights and thresholds must be between 0.0 and 1.0, with weak_threshold <= supported_threshold"
+                .to_string(),
+        );
+    }
+
+    if !(0.0..=1.0).contains(&config.injection_guard.down_rank_factor) {
+        return Err("injection_guard.down_rank_factor must be between 0.0 and 1.0".to_string());
+    }
+
+    let expansion = &config.query_expansion;
+    if expansion.enabled && expansion.mode != ExpansionMode::Hyde && !(1..=10).contains(&expansion.max_variants) {
+        return Err("query_expansion.max_variants must be between 1 and 10".to_string());
+    }
     
     Ok(true)
 }
@@ -161,6 +625,164 @@ pub async fn get_hybrid_rag_stats(
     Ok(stats)
 }
 
+/// Tauri command to re-read a project's documents from disk into the search index
+#[tauri::command]
+pub async fn sync_project_content(
+    project_id: String,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<ContentSyncReport, String> {
+    info!("Syncing document content for project {}", project_id);
+
+    DocumentContentService::new(&db_state).sync_project(&project_id)
+}
+
+/// Tauri command to compare the search index with a project's files
+#[tauri::command]
+pub async fn check_content_consistency(
+    project_id: String,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<ContentConsistencyReport, String> {
+    info!("Checking content consistency for project {}", project_id);
+
+    let report = DocumentContentService::new(&db_state).check_consistency(&project_id)?;
+    if !report.is_consistent() {
+        info!(
+            "Project {} content drift: {} missing, {} stale, {} missing files, FTS5 consistent: {}",
+            project_id,
+            report.missing_content.len(),
+            report.stale_content.len(),
+            report.missing_files.len(),
+            report.fts_index_consistent
+        );
+    }
+    Ok(report)
+}
+
+/// Embedding model retrieval embeds queries with
+#[derive(Debug, Serialize)]
+pub struct QueryEmbedderInfo {
+    /// Model the document vectors are stored under
+    pub model: String,
+    pub dimension: usize,
+}
+
+/// Tauri command to embed queries and documents with an Ollama embedding model.
+/// `config` is an OllamaProviderConfig; the default local setup when omitted.
+#[tauri::command]
+pub async fn use_ollama_embeddings(
+    config: Option<serde_json::Value>,
+    query_embedder: State<'_, QueryEmbedderSlot>,
+) -> Result<QueryEmbedderInfo, String> {
+    let config: OllamaProviderConfig = match config {
+        Some(config) => serde_json::from_value(config).map_err(|e| format!("Invalid Ollama config: {}", e))?,
+        None => OllamaProviderConfig::default(),
+    };
+    let embedder = OllamaEmbedder::connect(config).await.map_err(|e| e.to_string())?;
+    let info = QueryEmbedderInfo { model: embedder.model().to_string(), dimension: embedder.dimension() };
+    info!("Retrieval now embeds with {} ({} dimensions)", info.model, info.dimension);
+
+    query_embedder.set(Some(Arc::new(embedder)));
+    Ok(info)
+}
+
+/// Tauri command to embed a project's documents with the configured query embedder,
+/// so the vector arm of retrieval can find them. Returns the number of documents embedded.
+#[tauri::command]
+pub async fn embed_project_documents(
+    project_id: String,
+    db_state: State<'_, DatabaseConnection>,
+    query_embedder: State<'_, QueryEmbedderSlot>,
+) -> Result<usize, String> {
+    let embedder = query_embedder
+        .get()
+        .ok_or_else(|| "No embedding model is configured; call use_ollama_embeddings first".to_string())?;
+    info!("Embedding documents of project {} with {}", project_id, embedder.model());
+
+    let documents = DocumentContentRepository::new(&db_state).find_by_project_id(&project_id)?;
+    for document in &documents {
+        let text = format!("{}\n\n{}", document.title, document.content);
+        index_document(&db_state, embedder.as_ref(), document.id, &text)
+            .map_err(|e| format!("Failed to embed '{}': {}", document.title, e))?;
+    }
+    Ok(documents.len())
+}
+
+/// Request structure for a retrieval evaluation run
+#[derive(Debug, Deserialize)]
+pub struct RagEvalRequest {
+    pub project_id: String,
+    /// JSON file with the golden set; ignored when `golden_set` is given
+    #[serde(default)]
+    pub golden_set_path: Option<String>,
+    #[serde(default)]
+    pub golden_set: Option<GoldenSet>,
+    /// Configs to evaluate; the default config when empty
+    #[serde(default)]
+    pub variants: Vec<ConfigVariant>,
+    /// Rank cutoff of recall@k and nDCG@k
+    #[serde(default)]
+    pub k: Option<usize>,
+}
+
+/// Tauri command to run a golden set against a project with one or more configs.
+/// Every report is stored for later comparison.
+#[tauri::command]
+pub async fn run_rag_evaluation(
+    request: RagEvalRequest,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<Vec<EvalReport>, String> {
+    let golden_set = match (request.golden_set, request.golden_set_path) {
+        (Some(golden_set), _) => golden_set,
+        (None, Some(path)) => GoldenSet::load(std::path::Path::new(&path))?,
+        (None, None) => return Err("Either golden_set or golden_set_path is required".to_string()),
+    };
+    let variants = if request.variants.is_empty() { vec![ConfigVariant::baseline()] } else { request.variants };
+    for variant in &variants {
+        validate_hybrid_rag_config(variant.config.clone())
+            .await
+            .map_err(|e| format!("Invalid config '{}': {}", variant.label, e))?;
+    }
+
+    info!(
+        "Evaluating golden set '{}' ({} queries) on project {} with {} configs",
+        golden_set.name,
+        golden_set.queries.len(),
+        request.project_id,
+        variants.len()
+    );
+    RagEvaluator::new(&db_state).run(&request.project_id, &golden_set, &variants, request.k.unwrap_or(DEFAULT_EVAL_K))
+}
+
+/// Tauri command to list the stored evaluation reports of a project, newest first
+#[tauri::command]
+pub async fn get_rag_eval_reports(
+    project_id: String,
+    golden_set: Option<String>,
+    limit: Option<usize>,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<Vec<EvalReport>, String> {
+    RagEvaluator::new(&db_state).reports(&project_id, golden_set.as_deref(), limit.unwrap_or(20))
+}
+
+/// Tauri command to compare two stored evaluation reports
+#[tauri::command]
+pub async fn compare_rag_eval_reports(
+    baseline_id: i64,
+    candidate_id: i64,
+    db_state: State<'_, DatabaseConnection>,
+) -> Result<EvalComparison, String> {
+    let evaluator = RagEvaluator::new(&db_state);
+    let baseline = evaluator.report(baseline_id)?.ok_or_else(|| format!("Evaluation report {} not found", baseline_id))?;
+    let candidate = evaluator.report(candidate_id)?.ok_or_else(|| format!("Evaluation report {} not found", candidate_id))?;
+    if baseline.golden_set != candidate.golden_set {
+        return Err(format!(
+            "Reports ran different golden sets ('{}' and '{}')",
+            baseline.golden_set, candidate.golden_set
+        ));
+    }
+    Ok(compare_reports(&baseline, &candidate))
+}
+
 /// Statistics for hybrid RAG system
 #[derive(Debug, Serialize)]
 pub struct HybridRagStats {
@@ -174,7 +796,11 @@ pub struct HybridRagStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::infrastructure::db_layer::{DatabaseConnection, MigrationManager};
+    use crate::core::{Document, Project};
+    use crate::infrastructure::db_layer::{
+        DatabaseConnection, DocumentEmbedding, DocumentRepository, EmbeddingRepository, MigrationManager,
+        ProjectRepository,
+    };
     use tempfile::NamedTempFile;
 
     fn create_test_db() -> DatabaseConnection {
@@ -191,17 +817,80 @@ mod tests {
         db
     }
 
-    #[tokio::test]
-    async fn test_retrieve_context_command() {
-        let db = create_test_db();
-        let db_state = tauri::State::from(&db);
-        
+    /// Embeds text as counts of three keywords, so similarity follows shared keywords
+    struct KeywordEmbedder;
+
+    impl QueryEmbedder for KeywordEmbedder {
+        fn model(&self) -> &str {
+            "keyword-test"
+        }
+
+        fn dimension(&self) -> usize {
+            3
+        }
+
+        fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+            let text = text.to_lowercase();
+            Ok(["car", "truck", "office"].iter().map(|word| text.matches(word).count() as f32 + 0.01).collect())
+        }
+    }
+
+    /// Create project "project" with a document per `(path, title, content)` and
+    /// return the document keys
+    fn create_project_fixture(db: &DatabaseConnection, documents: &[(&str, &str, &str)]) -> Vec<i64> {
+        let project = Project::new("project".to_string(), "Project".to_string(), "/project".to_string());
+        ProjectRepository::new(db).create(&project).unwrap();
+        documents
+            .iter()
+            .map(|(path, title, content)| {
+                let document = Document::new(path.to_string(), project.id.clone(), path.to_string());
+                DocumentRepository::new(db).create(&document).unwrap();
+                DocumentContentRepository::new(db).upsert(path, title, content, None).unwrap()
+            })
+            .collect()
+    }
+
+    /// Run the retrieve_context command for a query with default settings and
+    /// no active provider
+    async fn run_retrieve_context(db: &DatabaseConnection, query: &str) -> ContextResponse {
+        run_retrieve_context_with(db, query, &QueryEmbedderSlot::default()).await
+    }
+
+    /// Run the retrieve_context command with the query embedder of `query_embedder`
+    async fn run_retrieve_context_with(
+        db: &DatabaseConnection,
+        query: &str,
+        query_embedder: &QueryEmbedderSlot,
+    ) -> ContextResponse {
         let request = ContextRequest {
-            query: "test query".to_string(),
+            query: query.to_string(),
             config: None,
+            filter: None,
+            project_id: None,
+            context_window_tokens: None,
+            expand_query: None,
+            conversation: Vec::new(),
         };
         
-        let response = retrieve_context(request, db_state).await.expect("Command failed");
+        let ai_service = LocalAiService::new();
+        let provider_manager = AiProviderManager::new();
+        let expansion_cache = QueryExpansionCache::default();
+        retrieve_context(
+            request,
+            tauri::State::from(db),
+            tauri::State::from(&ai_service),
+            tauri::State::from(&provider_manager),
+            tauri::State::from(&expansion_cache),
+            tauri::State::from(query_embedder),
+        )
+        .await
+        .expect("Command failed")
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_context_command() {
+        let db = create_test_db();
+        let response = run_retrieve_context(&db, "test query").await;
         
         // Should succeed even with no documents
         assert!(response.success);
@@ -211,28 +900,123 @@ mod tests {
     #[tokio::test]
     async fn test_empty_query() {
         let db = create_test_db();
-        let db_state = tauri::State::from(&db);
-        
-        let request = ContextRequest {
-            query: "".to_string(),
-            config: None,
-        };
-        
-        let response = retrieve_context(request, db_state).await.expect("Command failed");
+        let response = run_retrieve_context(&db, "").await;
         
         assert!(!response.success);
         assert!(response.error.is_some());
     }
 
+    #[tokio::test]
+    async fn test_embedded_documents_reach_the_vector_arm() {
+        let db = create_test_db();
+        let keys = create_project_fixture(&db, &[
+            ("vehicles.md", "Automobiles", "# Automobiles\n\nCars and trucks."),
+            ("hours.md", "Office Hours", "# Office Hours\n\nOpen nine to five."),
+        ]);
+        // vector_index still declares the legacy foreign key on documents
+        db.execute("PRAGMA foreign_keys = OFF", &[]).expect("Failed to disable foreign keys");
+
+        let query_embedder = QueryEmbedderSlot::default();
+        let error = embed_project_documents("project".to_string(), tauri::State::from(&db), tauri::State::from(&query_embedder))
+            .await
+            .expect_err("Embedding needs a configured embedder");
+        assert!(error.contains("No embedding model"));
+
+        query_embedder.set(Some(Arc::new(KeywordEmbedder)));
+        let embedded = embed_project_documents("project".to_string(), tauri::State::from(&db), tauri::State::from(&query_embedder))
+            .await
+            .expect("Embedding failed");
+        assert_eq!(embedded, 2);
+
+        // "pickup" matches nothing lexically, so only the vector arm can find the document
+        let context = run_retrieve_context_with(&db, "pickup truck", &query_embedder).await.context.expect("No context");
+        let ids: Vec<i64> = context.source_documents.iter().map(|r| r.document_id).collect();
+        assert_eq!(ids, vec![keys[0]]);
+        assert_eq!(context.arm_contribution.vector_candidates, 1);
+
+        let context = run_retrieve_context(&db, "pickup truck").await.context.expect("No context");
+        assert!(context.source_documents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_passages_are_retrieved_with_the_query_embedder() {
+        let db = create_test_db();
+        let keys = create_project_fixture(&db, &[("vehicles.md", "Automobiles", "# Automobiles\n\nCars and trucks.\n\nOffice opening times.")]);
+        // document_embeddings still declares the legacy foreign key on documents
+        db.execute("PRAGMA foreign_keys = OFF", &[]).expect("Failed to disable foreign keys");
+        for (index, text) in ["Cars and trucks.", "Office opening times."].iter().enumerate() {
+            let chunk = DocumentEmbedding {
+                id: None,
+                document_id: keys[0],
+                chunk_index: index as i32,
+                content_hash: "hash".to_string(),
+                content_text: text.to_string(),
+                embedding_vector: KeywordEmbedder.embed(text).unwrap(),
+                embedding_model: KeywordEmbedder.model().to_string(),
+                chunk_start: None,
+                chunk_end: None,
+                created_at: 0,
+                updated_at: 0,
+            };
+            EmbeddingRepository::new(&db).upsert_embedding(&chunk).expect("Failed to store chunk");
+        }
+        let request = || ContextRequest {
+            query: "trucks".to_string(),
+            config: None,
+            filter: None,
+            project_id: None,
+            context_window_tokens: None,
+            expand_query: None,
+            conversation: Vec::new(),
+        };
+
+        let query_embedder = QueryEmbedderSlot::default();
+        let response = retrieve_passages(request(), tauri::State::from(&db), tauri::State::from(&query_embedder))
+            .await
+            .expect("Command failed");
+        assert!(response.success && response.passages.is_empty());
+
+        query_embedder.set(Some(Arc::new(KeywordEmbedder)));
+        let response = retrieve_passages(request(), tauri::State::from(&db), tauri::State::from(&query_embedder))
+            .await
+            .expect("Command failed");
+        assert!(response.success);
+        let texts: Vec<&str> = response.passages.iter().map(|passage| passage.text.as_str()).collect();
+        assert_eq!(texts, vec!["Cars and trucks."]);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_query_is_reported() {
+        // Prompts are free text, so retrieval searches malformed input as plain words
+        let db = create_test_db();
+        let response = run_retrieve_context(&db, "\"unterminated").await;
+        assert!(response.success);
+
+        // The search box still gets the error with its position
+        let error = parse_search_query("\"unterminated".to_string()).await.expect_err("quote should be rejected");
+        assert!(error.to_string().contains("Unterminated quote"));
+        let error = parse_search_query("state:unknown".to_string()).await.expect_err("state should be rejected");
+        assert_eq!(error.position, 0);
+    }
+
     #[tokio::test]
     async fn test_config_validation() {
         let valid_config = HybridRagConfig {
             max_candidates: 20,
+            max_vector_candidates: 20,
             max_results: 10,
             similarity_threshold: 0.5,
             lexical_weight: 0.4,
             vector_weight: 0.6,
             max_context_length: 4000,
+            max_context_tokens: 1000,
+            max_reference_length: 2000,
+            fusion: FusionStrategy::default(),
+            rerank: RerankConfig::default(),
+            query_expansion: QueryExpansionConfig::default(),
+            conversation: ConversationRetrievalConfig::default(),
+            answer: GroundedAnswerConfig::default(),
+            injection_guard: InjectionGuardConfig::default(),
         };
         
         let result = validate_hybrid_rag_config(valid_config).await;
@@ -242,15 +1026,66 @@ mod tests {
         // Test invalid config
         let invalid_config = HybridRagConfig {
             max_candidates: -1,
+            max_vector_candidates: 20,
             max_results: 10,
             similarity_threshold: 0.5,
             lexical_weight: 0.4,
             vector_weight: 0.6,
             max_context_length: 4000,
+            max_context_tokens: 1000,
+            max_reference_length: 2000,
+            fusion: FusionStrategy::default(),
+            rerank: RerankConfig::default(),
+            query_expansion: QueryExpansionConfig::default(),
+            conversation: ConversationRetrievalConfig::default(),
+            answer: GroundedAnswerConfig::default(),
+            injection_guard: InjectionGuardConfig::default(),
         };
         
         let result = validate_hybrid_rag_config(invalid_config).await;
         assert!(result.is_err());
+
+        let invalid_fusion = HybridRagConfig {
+            fusion: FusionStrategy::ReciprocalRank { k: 0.0 },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_fusion).await;
+        assert!(result.is_err());
+
+        let invalid_rerank = HybridRagConfig {
+            rerank: RerankConfig { enabled: true, top_n: 0, ..RerankConfig::default() },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_rerank).await;
+        assert!(result.is_err());
+
+        let invalid_expansion = HybridRagConfig {
+            query_expansion: QueryExpansionConfig { enabled: true, max_variants: 0, ..QueryExpansionConfig::default() },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_expansion).await;
+        assert!(result.is_err());
+
+        let invalid_conversation = HybridRagConfig {
+            conversation: ConversationRetrievalConfig { citation_decay: 1.5, ..ConversationRetrievalConfig::default() },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_conversation).await;
+        assert!(result.is_err());
+
+        let invalid_answer = HybridRagConfig {
+            answer: GroundedAnswerConfig { min_similarity: -0.1, ..GroundedAnswerConfig::default() },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_answer).await;
+        assert!(result.is_err());
+
+        let invalid_guard = HybridRagConfig {
+            injection_guard: InjectionGuardConfig { down_rank_factor: 2.0, ..InjectionGuardConfig::default() },
+            ..HybridRagConfig::default()
+        };
+        let result = validate_hybrid_rag_config(invalid_guard).await;
+        assert!(result.is_err());
     }
 
     #[tokio::test]
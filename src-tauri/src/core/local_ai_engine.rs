@@ -3,7 +3,7 @@ use std::sync::{Arc, Mutex};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use serde::{Deserialize, Serialize};
 use tauri::api::process::{Command, CommandEvent};
 use anyhow::{Result, anyhow};
@@ -13,7 +13,9 @@ use tracing::{info, warn, error, debug};
 use notify::{Watcher, RecursiveMode, Event, EventKind, RecommendedWatcher};
 use notify::event::{CreateKind, ModifyKind, RemoveKind};
 use futures_util::StreamExt;
-use crate::infrastructure::db_layer::{DatabaseConnection, repositories::{DocumentRepository, EmbeddingRepository, DocumentEmbedding, EmbeddingJob, EmbeddingJobStatus}};
+use crate::infrastructure::db_layer::{DatabaseConnection, repositories::{EmbeddingRepository, VectorIndexRepository, DocumentEmbedding, EmbeddingJob, EmbeddingJobStatus}};
+use crate::services::DocumentContentService;
+use crate::core::text_chunker::{chunk_text, ChunkerConfig};
 use std::fs;
 
 /// Request structure for AI completion
@@ -49,6 +51,23 @@ pub struct EmbeddingResponse {
     pub dimension: usize,
 }
 
+/// Request structure for cross-encoder reranking
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RerankRequest {
+    pub query: String,
+    pub passages: Vec<String>,
+    pub model: Option<String>,
+}
+
+/// Response structure from cross-encoder reranking
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RerankResponse {
+    /// Relevance of each passage to the query (0.0 to 1.0), in request order
+    pub scores: Vec<f32>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
 /// Unified sidecar request structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
@@ -57,6 +76,8 @@ pub enum SidecarRequest {
     Completion(CompletionRequest),
     #[serde(rename = "embedding")]
     Embedding(EmbeddingRequest),
+    #[serde(rename = "rerank")]
+    Rerank(RerankRequest),
 }
 
 /// Unified sidecar response structure
@@ -67,8 +88,14 @@ pub enum SidecarResponse {
     Completion(CompletionResponse),
     #[serde(rename = "embedding")]
     Embedding(EmbeddingResponse),
+    #[serde(rename = "rerank")]
+    Rerank(RerankResponse),
 }
 
+/// Callers waiting for rerank replies, oldest request first. The sidecar answers
+/// requests in order, so each reply goes to the front of the queue.
+type PendingReranks = Arc<Mutex<VecDeque<std::sync::mpsc::Sender<RerankResponse>>>>;
+
 /// File change event for background processing
 #[derive(Debug, Clone)]
 pub struct FileChangeEvent {
@@ -134,6 +161,8 @@ pub struct LocalAiEngine {
     // Background embedding processing
     embedding_processor_handle: Option<tokio::task::JoinHandle<()>>,
     embedding_processor_shutdown: Option<mpsc::UnboundedSender<()>>,
+    // Rerank requests written to the sidecar and not yet answered
+    pending_reranks: PendingReranks,
 }
 
 impl LocalAiEngine {
@@ -152,6 +181,7 @@ impl LocalAiEngine {
             db_connection: None,
             embedding_processor_handle: None,
             embedding_processor_shutdown: None,
+            pending_reranks: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
     
@@ -170,6 +200,7 @@ impl LocalAiEngine {
             db_connection: None,
             embedding_processor_handle: None,
             embedding_processor_shutdown: None,
+            pending_reranks: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -188,6 +219,7 @@ impl LocalAiEngine {
             db_connection: Some(db_connection),
             embedding_processor_handle: None,
             embedding_processor_shutdown: None,
+            pending_reranks: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -206,6 +238,7 @@ impl LocalAiEngine {
             db_connection: Some(db_connection),
             embedding_processor_handle: None,
             embedding_processor_shutdown: None,
+            pending_reranks: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -238,11 +271,22 @@ impl LocalAiEngine {
 
         // Spawn a task to monitor sidecar events
         let state_clone = Arc::clone(&self.state);
+        let pending_reranks = Arc::clone(&self.pending_reranks);
         tokio::spawn(async move {
             while let Some(event) = rx.recv().await {
                 match event {
                     CommandEvent::Stdout(data) => {
-                        debug!("Sidecar stdout: {}", String::from_utf8_lossy(&data));
+                        let line = String::from_utf8_lossy(&data);
+                        // Log lines share stdout with replies and are not JSON
+                        match serde_json::from_str::<SidecarResponse>(line.trim()) {
+                            Ok(SidecarResponse::Rerank(response)) => {
+                                if let Some(waiting) = pending_reranks.lock().unwrap().pop_front() {
+                                    // The caller may have given up; its reply is dropped
+                                    let _ = waiting.send(response);
+                                }
+                            }
+                            _ => debug!("Sidecar stdout: {}", line),
+                        }
                     },
                     CommandEvent::Stderr(data) => {
                         warn!("Sidecar stderr: {}", String::from_utf8_lossy(&data));
@@ -256,6 +300,8 @@ impl LocalAiEngine {
                         warn!("Sidecar terminated with code: {:?}", payload.code);
                         let mut state = state_clone.lock().unwrap();
                         *state = SidecarState::Stopped;
+                        // No replies will come; waiting callers see the channel close
+                        pending_reranks.lock().unwrap().clear();
                     },
                 }
             }
@@ -273,6 +319,7 @@ impl LocalAiEngine {
             child.kill().map_err(|e| anyhow!("Failed to kill sidecar process: {}", e))?;
             info!("Sidecar process terminated");
         }
+        self.pending_reranks.lock().unwrap().clear();
 
         // Update state
         {
@@ -432,6 +479,34 @@ impl LocalAiEngine {
         embedding
     }
 
+    /// Send (query, passage) pairs to the sidecar's cross-encoder, starting the sidecar
+    /// if needed, and return the channel the scores arrive on. Awaiting the reply does
+    /// not need the engine, so callers should release it before waiting.
+    pub async fn send_rerank(&mut self, request: RerankRequest) -> Result<std::sync::mpsc::Receiver<RerankResponse>> {
+        if !self.is_ready() {
+            warn!("Sidecar not ready for reranking, starting...");
+            self.start_sidecar().await?;
+        }
+        let child = self
+            .sidecar_command
+            .as_mut()
+            .ok_or_else(|| anyhow!("Sidecar is not running"))?;
+        
+        info!("Reranking {} passages", request.passages.len());
+        let mut line = serde_json::to_string(&SidecarRequest::Rerank(request))?;
+        line.push('\n');
+        
+        // Queue the reply slot before writing, so a fast reply cannot arrive first
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut pending = self.pending_reranks.lock().unwrap();
+        pending.push_back(sender);
+        if let Err(e) = child.write(line.as_bytes()) {
+            pending.pop_back();
+            return Err(anyhow!("Failed to send rerank request to sidecar: {}", e));
+        }
+        Ok(receiver)
+    }
+
     /// Health check for the AI engine
     pub fn health_check(&self) -> Result<bool> {
         let state = self.get_state();
@@ -604,13 +679,17 @@ impl LocalAiEngine {
                 // Generate content hash
                 let content_hash = EmbeddingRepository::generate_content_hash(&content);
                 
-                // Find or create document record
-                let document_repo = DocumentRepository::new(db_connection);
                 let embedding_repo = EmbeddingRepository::new(db_connection);
                 
-                // For now, we'll use a placeholder document ID
-                // In a real implementation, this would be resolved from the file path
-                let document_id = 1i64; // TODO: Resolve actual document ID from file path
+                // Store the file's text for FTS5; its document key is also the embedding document ID
+                let content_service = DocumentContentService::new(db_connection);
+                let document_id = match content_service.sync_file(&event.path).map_err(|e| anyhow!(e))? {
+                    Some(document_id) => document_id,
+                    None => {
+                        debug!("File is not a project document, skipping: {:?}", event.path);
+                        return Ok(());
+                    }
+                };
                 
                 // Check if embeddings already exist for this content
                 if embedding_repo.embeddings_exist_for_content(document_id, &content_hash)? {
@@ -639,29 +718,34 @@ impl LocalAiEngine {
                 // Update job status to processing
                 embedding_repo.update_job_status(job_id, EmbeddingJobStatus::Processing, None)?;
                 
-                // Generate embedding (using fallback for now)
-                let embedding_vector = Self::generate_fallback_embedding_static(&content);
+                // Content changed, so chunks from the previous version are stale
+                embedding_repo.delete_embeddings_for_document(document_id)?;
                 
-                // Create document embedding
-                let document_embedding = DocumentEmbedding {
-                    id: None,
-                    document_id,
-                    chunk_index: 0, // Single chunk for now
-                    content_hash: content_hash.clone(),
-                    content_text: content.clone(),
-                    embedding_vector,
-                    embedding_model: "all-MiniLM-L6-v2".to_string(),
-                    chunk_start: Some(0),
-                    chunk_end: Some(content.len() as i32),
-                    created_at: event.timestamp.duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default().as_secs(),
-                    updated_at: event.timestamp.duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or_default().as_secs(),
-                };
-                
-                // Store embedding
-                let embedding_id = embedding_repo.upsert_embedding(&document_embedding)?;
-                info!("Stored embedding {} for document {}", embedding_id, document_id);
+                // Embed each passage separately so retrieval can return the matching chunk
+                let chunks = chunk_text(&content, &ChunkerConfig::default());
+                for chunk in &chunks {
+                    // Generate embedding (using fallback for now)
+                    let embedding_vector = Self::generate_fallback_embedding_static(&chunk.text);
+                    
+                    let document_embedding = DocumentEmbedding {
+                        id: None,
+                        document_id,
+                        chunk_index: chunk.index as i32,
+                        content_hash: content_hash.clone(),
+                        content_text: chunk.text.clone(),
+                        embedding_vector,
+                        embedding_model: "all-MiniLM-L6-v2".to_string(),
+                        chunk_start: Some(chunk.start as i32),
+                        chunk_end: Some(chunk.end as i32),
+                        created_at: event.timestamp.duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default().as_secs(),
+                        updated_at: event.timestamp.duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default().as_secs(),
+                    };
+                    
+                    embedding_repo.upsert_embedding(&document_embedding)?;
+                }
+                info!("Stored {} chunk embeddings for document {}", chunks.len(), document_id);
                 
                 // Update job status to completed
                 embedding_repo.update_job_status(job_id, EmbeddingJobStatus::Completed, None)?;
@@ -670,11 +754,15 @@ impl LocalAiEngine {
             }
             
             FileEventType::Deleted => {
-                // Handle file deletion - remove embeddings
+                // Handle file deletion - remove stored content and embeddings
                 info!("File deleted, cleaning up embeddings: {:?}", event.path);
                 
-                // TODO: Implement embedding cleanup for deleted files
-                // This would require resolving document ID from file path
+                let content_service = DocumentContentService::new(db_connection);
+                if let Some(document_id) = content_service.remove_file(&event.path).map_err(|e| anyhow!(e))? {
+                    let embedding_repo = EmbeddingRepository::new(db_connection);
+                    embedding_repo.delete_embeddings_for_document(document_id).map_err(|e| anyhow!(e))?;
+                    VectorIndexRepository::new(db_connection).delete_by_document_id(document_id).map_err(|e| anyhow!(e))?;
+                }
             }
         }
         
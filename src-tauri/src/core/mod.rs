@@ -6,9 +6,13 @@ pub mod entities;
 pub mod value_objects;
 pub mod local_ai_engine;
 pub mod transitions;
+pub mod text_chunker;
+pub mod prompt_references;
 
 // Re-export commonly used types
 pub use entities::*;
 pub use value_objects::*;
 pub use local_ai_engine::*;
 pub use transitions::*;
+pub use text_chunker::*;
+pub use prompt_references::*;
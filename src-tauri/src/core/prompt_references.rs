@@ -0,0 +1,183 @@
+// Prompt References
+// Parses explicit `@` references to project documents out of a prompt:
+// `@path/to/doc.md` (a file), `@folder/` (every document under a folder) and
+// `@"Doc Title"` (a document by its title).
+
+use serde::{Deserialize, Serialize};
+
+/// Punctuation that ends a sentence rather than a path ("see @notes.md.")
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}', '\'', '"'];
+
+/// What a reference points at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReferenceTarget {
+    /// A document by its project-relative path
+    File { path: String },
+    /// Every document whose path starts with this folder (ends with "/")
+    Folder { path: String },
+    /// A document by its title
+    Title { title: String },
+    /// A reference that could not be parsed, such as an unterminated quote
+    Malformed { reason: String },
+}
+
+/// An `@` reference as written in the prompt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptReference {
+    /// The reference as typed, including the `@`
+    pub raw: String,
+    pub target: ReferenceTarget,
+    /// Byte offset of the `@` in the prompt
+    pub start: usize,
+    /// Byte offset one past the end of the reference
+    pub end: usize,
+}
+
+/// A prompt split into its free text and its references
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedPrompt {
+    /// The prompt with every reference removed, for retrieval
+    pub text: String,
+    pub references: Vec<PromptReference>,
+}
+
+/// Extract the `@` references of a prompt. An `@` only starts a reference at the
+/// start of the prompt or after whitespace, so e-mail addresses are left alone.
+pub fn parse_prompt(prompt: &str) -> ParsedPrompt {
+    let mut references = Vec::new();
+    let mut text = String::new();
+    let mut copied_up_to = 0;
+    let mut previous: Option<char> = None;
+
+    let mut chars = prompt.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let at_word_start = previous.is_none_or(char::is_whitespace);
+        previous = Some(ch);
+        if ch != '@' || !at_word_start {
+            continue;
+        }
+
+        let body_start = start + 1;
+        let (target, end) = if prompt[body_start..].starts_with('"') {
+            let title_start = body_start + 1;
+            match prompt[title_start..].find('"') {
+                Some(length) => {
+                    let title = prompt[title_start..title_start + length].trim().to_string();
+                    (ReferenceTarget::Title { title }, title_start + length + 1)
+                }
+                None => (
+                    ReferenceTarget::Malformed { reason: "Unterminated quote".to_string() },
+                    prompt.len(),
+                ),
+            }
+        } else {
+            let token_end = prompt[body_start..]
+                .find(char::is_whitespace)
+                .map_or(prompt.len(), |offset| body_start + offset);
+            let token = prompt[body_start..token_end].trim_end_matches(TRAILING_PUNCTUATION);
+            if token.is_empty() {
+                continue;
+            }
+
+            let path = normalize_path(token);
+            let target = if token.ends_with('/') || token.ends_with('\\') {
+                ReferenceTarget::Folder { path }
+            } else {
+                ReferenceTarget::File { path }
+            };
+            (target, body_start + token.len())
+        };
+
+        if let ReferenceTarget::Title { title } = &target {
+            if title.is_empty() {
+                continue;
+            }
+        }
+
+        text.push_str(&prompt[copied_up_to..start]);
+        copied_up_to = end;
+        references.push(PromptReference {
+            raw: prompt[start..end].to_string(),
+            target,
+            start,
+            end,
+        });
+
+        // Resume scanning after the reference
+        while chars.peek().is_some_and(|(index, _)| *index < end) {
+            previous = chars.next().map(|(_, ch)| ch);
+        }
+    }
+    text.push_str(&prompt[copied_up_to..]);
+
+    ParsedPrompt {
+        text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        references,
+    }
+}
+
+/// Project-relative path with forward slashes and no leading "./" or "/"
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut path = path.as_str();
+    loop {
+        if let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        } else if let Some(rest) = path.strip_prefix('/') {
+            path = rest;
+        } else {
+            return path.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(prompt: &str) -> Vec<ReferenceTarget> {
+        parse_prompt(prompt).references.into_iter().map(|r| r.target).collect()
+    }
+
+    #[test]
+    fn test_parse_reference_kinds() {
+        let parsed = parse_prompt("Compare @specs/api.md with @notes/ and @\"Launch Plan\", please.");
+
+        assert_eq!(parsed.text, "Compare with and , please.");
+        assert_eq!(
+            parsed.references.iter().map(|r| r.target.clone()).collect::<Vec<_>>(),
+            vec![
+                ReferenceTarget::File { path: "specs/api.md".to_string() },
+                ReferenceTarget::Folder { path: "notes/".to_string() },
+                ReferenceTarget::Title { title: "Launch Plan".to_string() },
+            ]
+        );
+        let first = &parsed.references[0];
+        assert_eq!(first.raw, "@specs/api.md");
+        assert_eq!((first.start, first.end), (8, 21));
+    }
+
+    #[test]
+    fn test_parse_ignores_non_references() {
+        // E-mail addresses, a lone "@" and empty titles are plain text
+        let parsed = parse_prompt("Mail me@example.com @ or @\"\" about it");
+        assert!(parsed.references.is_empty());
+        assert_eq!(parsed.text, "Mail me@example.com @ or @\"\" about it");
+    }
+
+    #[test]
+    fn test_parse_paths_and_punctuation() {
+        assert_eq!(
+            targets("See @./docs\\intro.md. Then @/archive/old/"),
+            vec![
+                ReferenceTarget::File { path: "docs/intro.md".to_string() },
+                ReferenceTarget::Folder { path: "archive/old/".to_string() },
+            ]
+        );
+        assert_eq!(
+            targets("Summarize @\"Roadmap"),
+            vec![ReferenceTarget::Malformed { reason: "Unterminated quote".to_string() }]
+        );
+    }
+}
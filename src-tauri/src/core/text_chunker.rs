@@ -0,0 +1,205 @@
+// Text Chunker
+// Splits markdown documents into passage-sized chunks with byte offsets
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration for splitting documents into passages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkerConfig {
+    /// Maximum size of a chunk in bytes
+    pub max_chunk_bytes: usize,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            max_chunk_bytes: 1500,
+        }
+    }
+}
+
+/// A passage of a document, addressed by its byte range in the source text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextChunk {
+    pub index: usize,
+    /// Byte offset of the first character of the chunk
+    pub start: usize,
+    /// Byte offset one past the last character of the chunk
+    pub end: usize,
+    pub text: String,
+}
+
+/// Split text into chunks along paragraph boundaries.
+/// Paragraphs are merged up to `max_chunk_bytes`; a markdown heading always starts
+/// a new chunk, and oversized paragraphs are split at whitespace. `content[start..end]`
+/// of every chunk equals its text.
+pub fn chunk_text(content: &str, config: &ChunkerConfig) -> Vec<TextChunk> {
+    let max_bytes = config.max_chunk_bytes.max(1);
+
+    let mut spans = Vec::new();
+    for (start, end) in paragraph_spans(content) {
+        split_span(content, start, end, max_bytes, &mut spans);
+    }
+
+    let mut chunks: Vec<TextChunk> = Vec::new();
+    let mut current: Option<(usize, usize)> = None;
+
+    for (start, end) in spans {
+        let is_heading = content[start..end].starts_with('#');
+        current = match current {
+            Some((chunk_start, _)) if !is_heading && end - chunk_start <= max_bytes => {
+                Some((chunk_start, end))
+            }
+            Some((chunk_start, chunk_end)) => {
+                push_chunk(content, chunk_start, chunk_end, &mut chunks);
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+
+    if let Some((chunk_start, chunk_end)) = current {
+        push_chunk(content, chunk_start, chunk_end, &mut chunks);
+    }
+
+    chunks
+}
+
+fn push_chunk(content: &str, start: usize, end: usize, chunks: &mut Vec<TextChunk>) {
+    chunks.push(TextChunk {
+        index: chunks.len(),
+        start,
+        end,
+        text: content[start..end].to_string(),
+    });
+}
+
+/// Byte ranges of paragraphs (runs of non-blank lines), trimmed of surrounding whitespace
+fn paragraph_spans(content: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut paragraph_start: Option<usize> = None;
+    let mut paragraph_end = 0;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if let Some(start) = paragraph_start.take() {
+                spans.push((start, paragraph_end));
+            }
+        } else {
+            let leading = line.len() - line.trim_start().len();
+            if paragraph_start.is_none() {
+                paragraph_start = Some(offset + leading);
+            }
+            paragraph_end = offset + leading + trimmed.len();
+        }
+        offset += line.len();
+    }
+
+    if let Some(start) = paragraph_start {
+        spans.push((start, paragraph_end));
+    }
+
+    spans
+}
+
+/// Split a span longer than `max_bytes` at whitespace (or any char boundary if none)
+fn split_span(content: &str, mut start: usize, end: usize, max_bytes: usize, spans: &mut Vec<(usize, usize)>) {
+    while end - start > max_bytes {
+        let mut limit = start + max_bytes;
+        while !content.is_char_boundary(limit) {
+            limit -= 1;
+        }
+
+        let window = &content[start..limit];
+        let split_at = match window.rfind(char::is_whitespace) {
+            Some(position) if position > 0 => start + position,
+            _ if limit > start => limit,
+            // A single character wider than max_bytes
+            _ => start + content[start..].chars().next().map(|c| c.len_utf8()).unwrap_or(1),
+        };
+
+        spans.push((start, split_at));
+
+        start = split_at;
+        while start < end && content[start..].starts_with(char::is_whitespace) {
+            start += content[start..].chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+        }
+    }
+
+    if start < end {
+        spans.push((start, end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_chunk_bytes: usize) -> ChunkerConfig {
+        ChunkerConfig { max_chunk_bytes }
+    }
+
+    #[test]
+    fn test_offsets_match_text() {
+        let content = "# Title\n\nFirst paragraph.\nStill first.\n\n\nSecond paragraph.\n";
+        let chunks = chunk_text(content, &config(1000));
+
+        for chunk in &chunks {
+            assert_eq!(&content[chunk.start..chunk.end], chunk.text);
+        }
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "# Title\n\nFirst paragraph.\nStill first.\n\n\nSecond paragraph.");
+    }
+
+    #[test]
+    fn test_headings_start_new_chunks() {
+        let content = "Intro.\n\n## Section A\n\nBody A.\n\n## Section B\n\nBody B.";
+        let chunks = chunk_text(content, &config(1000));
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Intro.", "## Section A\n\nBody A.", "## Section B\n\nBody B."]);
+        assert_eq!(chunks.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_paragraphs_respect_max_size() {
+        let content = "aaaa aaaa\n\nbbbb bbbb\n\ncccc cccc";
+        let chunks = chunk_text(content, &config(22));
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].text, "aaaa aaaa\n\nbbbb bbbb");
+        assert_eq!(chunks[1].text, "cccc cccc");
+    }
+
+    #[test]
+    fn test_long_paragraph_split_at_whitespace() {
+        let content = "one two three four five six seven";
+        let chunks = chunk_text(content, &config(10));
+
+        for chunk in &chunks {
+            assert!(chunk.text.len() <= 10);
+            assert_eq!(&content[chunk.start..chunk.end], chunk.text);
+        }
+        assert_eq!(chunks[0].text, "one two");
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        let content = "héllo wörld ünïcödé ẞtraße 日本語のテキスト";
+        let chunks = chunk_text(content, &config(8));
+
+        for chunk in &chunks {
+            assert_eq!(&content[chunk.start..chunk.end], chunk.text);
+        }
+        let rejoined: String = chunks.iter().map(|c| c.text.replace(' ', "")).collect();
+        assert_eq!(rejoined, content.replace(' ', ""));
+    }
+
+    #[test]
+    fn test_empty_content() {
+        assert!(chunk_text("", &ChunkerConfig::default()).is_empty());
+        assert!(chunk_text("\n\n  \n", &ChunkerConfig::default()).is_empty());
+    }
+}
@@ -0,0 +1,744 @@
+/// AnthropicProvider Implementation
+///
+/// This module implements the AnthropicProvider struct that conforms to the AiProvider trait,
+/// calling the Anthropic Messages API directly, so Claude models are reachable without AWS
+/// credentials. The API key is read from the OS keychain through the credential_manager
+/// module.
+///
+/// The provider supports system prompts, stop sequences and streaming through server-sent
+/// events, reports token usage, and maps 401/429/529 responses to typed AiProviderError
+/// values. The base URL is configurable so tests can point it at a local stub.
+
+use crate::application::ai_provider::{
+    AiProvider, AiProviderError, AiProviderFactory, ConversationContext, MessageRole, ProviderCapabilities,
+    ProviderInfo, StreamChunk, StreamSink, utils,
+};
+use crate::infrastructure::credential_manager::CredentialManager;
+use crate::infrastructure::http_provider::{error_details, status_error, StreamLines};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tokio::time::Duration;
+use tracing::{debug, error, info};
+
+/// Configuration for the AnthropicProvider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnthropicProviderConfig {
+    /// Default model to use for completions
+    pub default_model: String,
+    /// API base URL, without the version segment
+    pub base_url: String,
+    /// Value of the anthropic-version header
+    pub api_version: String,
+    /// Maximum timeout for API requests (seconds)
+    pub request_timeout: u64,
+    /// Whether to use streaming by default
+    pub enable_streaming: bool,
+    /// Tokens to generate when the request does not say; the API requires a limit
+    pub default_max_tokens: u32,
+}
+
+impl Default for AnthropicProviderConfig {
+    fn default() -> Self {
+        Self {
+            default_model: "claude-3-5-sonnet-latest".to_string(),
+            base_url: "https://api.anthropic.com".to_string(),
+            api_version: "2023-06-01".to_string(),
+            request_timeout: 120,
+            enable_streaming: true,
+            default_max_tokens: 4096,
+        }
+    }
+}
+
+/// Tokens a request consumed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub input_tokens: u32,
+    #[serde(default)]
+    pub output_tokens: u32,
+}
+
+/// A complete Messages API response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnthropicCompletion {
+    /// Text of the response's text blocks, joined
+    pub text: String,
+    /// Why generation stopped ("end_turn", "max_tokens", "stop_sequence", ...)
+    pub stop_reason: Option<String>,
+    /// The stop sequence that ended generation, when one did
+    pub stop_sequence: Option<String>,
+    pub usage: TokenUsage,
+}
+
+/// Messages API response structure
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+    #[serde(default)]
+    usage: TokenUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    text: Option<String>,
+}
+
+/// Server-sent event of a streaming response
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: StreamMessage },
+    ContentBlockDelta { delta: StreamDelta },
+    MessageDelta { delta: StreamStop, #[serde(default)] usage: TokenUsage },
+    MessageStop,
+    Error { error: ApiError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamMessage {
+    #[serde(default)]
+    usage: TokenUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamStop {
+    stop_reason: Option<String>,
+    stop_sequence: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(rename = "type")]
+    error_type: String,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListResponse {
+    data: Vec<ModelListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListEntry {
+    id: String,
+}
+
+/// Map an API error type (e.g. "overloaded_error") to an AiProviderError
+fn map_error_type(error_type: &str, message: String) -> AiProviderError {
+    match error_type {
+        "authentication_error" | "permission_error" => AiProviderError::AuthenticationError(message),
+        "rate_limit_error" => AiProviderError::RateLimitError(message),
+        "overloaded_error" | "api_error" => AiProviderError::ServiceUnavailable(message),
+        "invalid_request_error" | "not_found_error" | "request_too_large" => AiProviderError::InvalidRequest(message),
+        _ => AiProviderError::ProviderError(message),
+    }
+}
+
+/// Map an HTTP error response to an AiProviderError: 401 is an authentication error,
+/// 429 a rate limit and 529 an overloaded API; other statuses go by the error type of
+/// the body when it has one
+fn map_error_response(status: u16, body: &str) -> AiProviderError {
+    let (message, error_type) = error_details(body);
+    let message = format!("{} - {}", status, message);
+
+    match (status, error_type) {
+        (401 | 403 | 429 | 529, _) | (_, None) => status_error(status, message),
+        (_, Some(error_type)) => map_error_type(&error_type, message),
+    }
+}
+
+/// AnthropicProvider struct implementing the AiProvider trait
+///
+/// This provider manages communication with the Anthropic Messages API, providing both
+/// streaming and non-streaming interfaces with credential management.
+pub struct AnthropicProvider {
+    /// HTTP client for API requests
+    client: Client,
+    /// Provider configuration
+    config: AnthropicProviderConfig,
+    /// Credential manager for API key
+    credential_manager: CredentialManager,
+    /// API key used instead of the keychain's, when set
+    api_key: Option<String>,
+    /// Provider capabilities
+    capabilities: ProviderCapabilities,
+}
+
+impl AnthropicProvider {
+    /// Create a new AnthropicProvider with default configuration
+    pub fn new() -> Self {
+        Self::with_config(AnthropicProviderConfig::default())
+    }
+
+    /// Create a new AnthropicProvider with custom configuration
+    pub fn with_config(config: AnthropicProviderConfig) -> Self {
+        let capabilities = ProviderCapabilities {
+            supports_streaming: config.enable_streaming,
+            supports_functions: false, // Not implemented in current version
+            supports_images: false,    // Only text content is sent
+            supports_system_messages: true,
+            max_context_length: Some(200000), // Claude 3 and later
+        };
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client,
+            config,
+            credential_manager: CredentialManager::new(),
+            api_key: None,
+            capabilities,
+        }
+    }
+
+    /// Create an AnthropicProvider that uses `api_key` instead of the keychain, e.g. to
+    /// check a key before storing it
+    pub fn with_api_key(config: AnthropicProviderConfig, api_key: String) -> Self {
+        Self {
+            api_key: Some(api_key),
+            ..Self::with_config(config)
+        }
+    }
+
+    /// Get API key from credential manager
+    fn get_api_key(&self) -> Result<String, AiProviderError> {
+        if let Some(api_key) = &self.api_key {
+            return Ok(api_key.clone());
+        }
+        self.credential_manager
+            .retrieve_anthropic_api_key()
+            .map_err(|e| {
+                error!("Failed to retrieve Anthropic API key: {}", e);
+                AiProviderError::AuthenticationError(format!(
+                    "Failed to retrieve Anthropic API key: {}",
+                    e
+                ))
+            })
+    }
+
+    /// Build API URL for the given endpoint path
+    fn build_api_url(&self, path: &str) -> String {
+        format!("{}/v1/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Add the API key and version headers
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, AiProviderError> {
+        Ok(request
+            .header("x-api-key", self.get_api_key()?)
+            .header("anthropic-version", &self.config.api_version))
+    }
+
+    /// Convert conversation context to a Messages API payload
+    fn context_to_payload(&self, context: &ConversationContext, stream: bool) -> Value {
+        let mut system_parts: Vec<&str> = context.system_prompt.iter().map(String::as_str).collect();
+        let mut messages: Vec<(&str, String)> = Vec::new();
+
+        for message in &context.messages {
+            let (role, content) = match message.role {
+                MessageRole::System => {
+                    system_parts.push(&message.content);
+                    continue;
+                }
+                MessageRole::User => ("user", message.content.clone()),
+                MessageRole::Assistant => ("assistant", message.content.clone()),
+                // Convert function messages to user messages for Claude
+                MessageRole::Function => ("user", format!("Function result: {}", message.content)),
+            };
+            // Turns alternate, so consecutive messages of one role are joined
+            match messages.last_mut() {
+                Some((last_role, last_content)) if *last_role == role => {
+                    last_content.push_str("\n\n");
+                    last_content.push_str(&content);
+                }
+                _ => messages.push((role, content)),
+            }
+        }
+
+        let messages: Vec<Value> = messages
+            .into_iter()
+            .map(|(role, content)| json!({"role": role, "content": content}))
+            .collect();
+
+        let mut payload = json!({
+            "model": self.config.default_model,
+            "messages": messages,
+            "max_tokens": context.config.max_tokens.unwrap_or(self.config.default_max_tokens),
+            "stream": stream,
+        });
+
+        if !system_parts.is_empty() {
+            payload["system"] = json!(system_parts.join("\n\n"));
+        }
+        // Newer models reject temperature and top_p together; temperature wins
+        match (context.config.temperature, context.config.top_p) {
+            (Some(temperature), _) => payload["temperature"] = json!(temperature),
+            (None, Some(top_p)) => payload["top_p"] = json!(top_p),
+            (None, None) => {}
+        }
+        if let Some(stop_sequences) = &context.config.stop_sequences {
+            payload["stop_sequences"] = json!(stop_sequences);
+        }
+        for (key, value) in &context.config.extra_params {
+            payload[key.as_str()] = value.clone();
+        }
+
+        payload
+    }
+
+    /// Send a request and turn error statuses into typed errors
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AiProviderError> {
+        let response = request.send().await.map_err(|e| {
+            error!("AnthropicProvider: Request failed: {}", e);
+            AiProviderError::NetworkError(format!("Anthropic request failed: {}", e))
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            error!("AnthropicProvider: Request failed: {} - {}", status, error_text);
+            return Err(map_error_response(status, &error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Post a Messages API request
+    async fn post_messages(&self, payload: &Value) -> Result<Response, AiProviderError> {
+        let request = self.client.post(self.build_api_url("messages")).json(payload);
+        self.send(self.authorize(request)?).await
+    }
+
+    /// Complete a conversation, returning the text along with stop reason and token usage
+    pub async fn create_message(&self, context: &ConversationContext) -> Result<AnthropicCompletion, AiProviderError> {
+        let payload = self.context_to_payload(context, false);
+        let response = self.post_messages(&payload).await?;
+
+        let message: MessagesResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse response JSON: {}", e))
+        })?;
+
+        let text = message
+            .content
+            .into_iter()
+            .filter(|block| block.block_type == "text")
+            .filter_map(|block| block.text)
+            .collect();
+        info!(
+            "AnthropicProvider: Used {} input and {} output tokens",
+            message.usage.input_tokens, message.usage.output_tokens
+        );
+
+        Ok(AnthropicCompletion {
+            text,
+            stop_reason: message.stop_reason,
+            stop_sequence: message.stop_sequence,
+            usage: message.usage,
+        })
+    }
+
+    /// Send a streaming chunk to `sink`, with any extra metadata
+    fn send_stream_chunk(
+        &self,
+        sink: &StreamSink<'_>,
+        content: String,
+        is_final: bool,
+        extra: HashMap<String, Value>,
+    ) -> Result<(), AiProviderError> {
+        let chunk = StreamChunk {
+            content,
+            is_final,
+            metadata: Some({
+                let mut metadata = extra;
+                metadata.insert("provider".to_string(), Value::String("anthropic".to_string()));
+                metadata.insert("model".to_string(), Value::String(self.config.default_model.clone()));
+                metadata.insert("timestamp".to_string(), Value::Number(
+                    serde_json::Number::from(chrono::Utc::now().timestamp())
+                ));
+                metadata
+            }),
+        };
+
+        sink(chunk)
+    }
+}
+
+/// Metadata of a final chunk: stop reason, stop sequence and token usage
+fn completion_metadata(stop_reason: Option<String>, stop_sequence: Option<String>, usage: TokenUsage) -> HashMap<String, Value> {
+    HashMap::from([
+        ("stop_reason".to_string(), json!(stop_reason)),
+        ("stop_sequence".to_string(), json!(stop_sequence)),
+        ("input_tokens".to_string(), json!(usage.input_tokens)),
+        ("output_tokens".to_string(), json!(usage.output_tokens)),
+    ])
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    async fn get_provider_info(&self) -> ProviderInfo {
+        // Check if API key is available
+        let is_available = self.get_api_key().is_ok();
+
+        ProviderInfo {
+            name: "anthropic".to_string(),
+            display_name: "Anthropic".to_string(),
+            description: "Claude models through the Anthropic Messages API".to_string(),
+            is_available,
+            supported_models: vec![self.config.default_model.clone()],
+            capabilities: self.capabilities.clone(),
+        }
+    }
+
+    async fn validate_configuration(&self) -> Result<bool, AiProviderError> {
+        // Listing models checks the API key without generating anything
+        self.get_available_models().await.map(|_| true)
+    }
+
+    async fn invoke_model_stream(
+        &self,
+        context: ConversationContext,
+        app_handle: &AppHandle,
+    ) -> Result<(), AiProviderError> {
+        self.invoke_model_stream_to(context, &|chunk| {
+            utils::emit_stream_chunk(app_handle, &chunk).inspect_err(|e| error!("AnthropicProvider: {}", e))
+        })
+        .await
+    }
+
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
+    ) -> Result<(), AiProviderError> {
+        debug!("AnthropicProvider: Starting streaming completion");
+
+        if !self.config.enable_streaming {
+            let completion = self.create_message(&context).await?;
+            self.send_stream_chunk(sink, completion.text, false, HashMap::new())?;
+            let metadata = completion_metadata(completion.stop_reason, completion.stop_sequence, completion.usage);
+            return self.send_stream_chunk(sink, String::new(), true, metadata);
+        }
+
+        let payload = self.context_to_payload(&context, true);
+        let response = self.post_messages(&payload).await?;
+
+        // Server-sent events: "event: <name>" and "data: <json>" lines; the data carries
+        // its own type, so event names are skipped
+        let mut usage = TokenUsage::default();
+        let (mut stop_reason, mut stop_sequence) = (None, None);
+        let mut lines = StreamLines::new(response.bytes_stream());
+        while let Some(line) = lines.next_line().await? {
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+
+            let event: StreamEvent = serde_json::from_str(data.trim()).map_err(|e| {
+                AiProviderError::ProviderError(format!("Failed to parse stream event: {}", e))
+            })?;
+            match event {
+                StreamEvent::MessageStart { message } => usage = message.usage,
+                StreamEvent::ContentBlockDelta { delta } => {
+                    if let Some(text) = delta.text.filter(|text| !text.is_empty()) {
+                        self.send_stream_chunk(sink, text, false, HashMap::new())?;
+                    }
+                }
+                StreamEvent::MessageDelta { delta, usage: delta_usage } => {
+                    // Output tokens are cumulative
+                    usage.output_tokens = delta_usage.output_tokens;
+                    stop_reason = delta.stop_reason;
+                    stop_sequence = delta.stop_sequence;
+                }
+                StreamEvent::MessageStop => break,
+                StreamEvent::Error { error } => {
+                    return Err(map_error_type(&error.error_type, error.message));
+                }
+                StreamEvent::Other => {}
+            }
+        }
+
+        info!(
+            "AnthropicProvider: Streaming completion used {} input and {} output tokens",
+            usage.input_tokens, usage.output_tokens
+        );
+        // Send final chunk with stop reason and usage
+        self.send_stream_chunk(sink, String::new(), true, completion_metadata(stop_reason, stop_sequence, usage))
+    }
+
+    async fn invoke_model(&self, context: ConversationContext) -> Result<String, AiProviderError> {
+        debug!("AnthropicProvider: Starting non-streaming completion");
+        let completion = self.create_message(&context).await?;
+        info!("AnthropicProvider: Non-streaming completion successful");
+        Ok(completion.text)
+    }
+
+    async fn get_available_models(&self) -> Result<Vec<String>, AiProviderError> {
+        let request = self.client.get(self.build_api_url("models"));
+        let response = self.send(self.authorize(request)?).await?;
+
+        let listing: ModelListResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse model list: {}", e))
+        })?;
+
+        Ok(listing.data.into_iter().map(|model| model.id).collect())
+    }
+
+    async fn test_connection(&self) -> Result<bool, AiProviderError> {
+        self.validate_configuration().await
+    }
+
+    fn get_config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "default_model": {
+                    "type": "string",
+                    "description": "Default model to use for completions",
+                    "default": "claude-3-5-sonnet-latest"
+                },
+                "base_url": {
+                    "type": "string",
+                    "description": "API base URL, without the version segment",
+                    "default": "https://api.anthropic.com"
+                },
+                "api_version": {
+                    "type": "string",
+                    "description": "Value of the anthropic-version header",
+                    "default": "2023-06-01"
+                },
+                "request_timeout": {
+                    "type": "integer",
+                    "description": "Maximum timeout for API requests in seconds",
+                    "default": 120,
+                    "minimum": 10,
+                    "maximum": 600
+                },
+                "enable_streaming": {
+                    "type": "boolean",
+                    "description": "Whether to use streaming by default",
+                    "default": true
+                },
+                "default_max_tokens": {
+                    "type": "integer",
+                    "description": "Tokens to generate when the request does not set a limit",
+                    "default": 4096,
+                    "minimum": 1
+                }
+            },
+            "required": ["default_model"]
+        })
+    }
+
+    fn clone_provider(&self) -> Box<dyn AiProvider> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for AnthropicProvider {
+    fn clone(&self) -> Self {
+        Self {
+            api_key: self.api_key.clone(),
+            ..Self::with_config(self.config.clone())
+        }
+    }
+}
+
+/// Factory for creating AnthropicProvider instances
+pub struct AnthropicProviderFactory;
+
+impl AiProviderFactory for AnthropicProviderFactory {
+    fn create_provider(&self, config: serde_json::Value) -> Result<Box<dyn AiProvider>, AiProviderError> {
+        let provider_config: AnthropicProviderConfig = serde_json::from_value(config)
+            .map_err(|e| AiProviderError::ConfigurationError(format!("Invalid configuration: {}", e)))?;
+
+        Ok(Box::new(AnthropicProvider::with_config(provider_config)))
+    }
+
+    fn provider_type(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        AnthropicProvider::new().get_config_schema()
+    }
+}
+
+impl Default for AnthropicProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ai_provider::{Message, ModelConfig};
+    use crate::infrastructure::http_provider::stub_server;
+    use std::sync::Mutex;
+
+    fn provider_for(base_url: String) -> AnthropicProvider {
+        let config = AnthropicProviderConfig { base_url, ..Default::default() };
+        AnthropicProvider::with_api_key(config, "sk-ant-test".to_string())
+    }
+
+    fn error_body(error_type: &str, message: &str) -> String {
+        json!({"type": "error", "error": {"type": error_type, "message": message}}).to_string()
+    }
+
+    #[test]
+    fn test_context_to_payload() {
+        let provider = AnthropicProvider::new();
+        let context = ConversationContext {
+            messages: vec![
+                Message { role: MessageRole::System, content: "Answer briefly.".to_string(), metadata: None },
+                Message { role: MessageRole::User, content: "Hi".to_string(), metadata: None },
+                Message { role: MessageRole::Function, content: "42".to_string(), metadata: None },
+                Message { role: MessageRole::Assistant, content: "Hello".to_string(), metadata: None },
+            ],
+            system_prompt: Some("You are helpful.".to_string()),
+            config: ModelConfig { stop_sequences: Some(vec!["END".to_string()]), max_tokens: None, ..ModelConfig::default() },
+        };
+
+        let payload = provider.context_to_payload(&context, false);
+        assert_eq!(payload["system"], "You are helpful.\n\nAnswer briefly.");
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(payload["messages"][0]["content"], "Hi\n\nFunction result: 42");
+        assert_eq!(payload["messages"][1]["role"], "assistant");
+        assert_eq!(payload["max_tokens"], json!(4096));
+        assert_eq!(payload["stop_sequences"], json!(["END"]));
+        assert!(payload.get("temperature").is_some() && payload.get("top_p").is_none());
+        assert_eq!(provider.build_api_url("messages"), "https://api.anthropic.com/v1/messages");
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let unauthorized = map_error_response(401, &error_body("authentication_error", "invalid x-api-key"));
+        assert!(matches!(unauthorized, AiProviderError::AuthenticationError(ref message) if message == "401 - invalid x-api-key"));
+        assert!(matches!(map_error_response(429, &error_body("rate_limit_error", "Slow down")), AiProviderError::RateLimitError(_)));
+        assert!(matches!(map_error_response(529, &error_body("overloaded_error", "Overloaded")), AiProviderError::ServiceUnavailable(_)));
+        assert!(matches!(map_error_response(400, &error_body("invalid_request_error", "bad")), AiProviderError::InvalidRequest(_)));
+        assert!(matches!(map_error_response(502, "Bad gateway"), AiProviderError::ServiceUnavailable(_)));
+    }
+
+    #[tokio::test]
+    async fn test_messages_against_stub_server() {
+        let message = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "Plans renew "}, {"type": "text", "text": "yearly."}],
+            "stop_reason": "stop_sequence",
+            "stop_sequence": "END",
+            "usage": {"input_tokens": 12, "output_tokens": 5}
+        });
+        let (base_url, server) = stub_server::start(vec![
+            (200, "application/json", message.to_string()),
+            (529, "application/json", error_body("overloaded_error", "Overloaded")),
+        ])
+        .await;
+        let provider = provider_for(base_url);
+        let context = utils::create_simple_context("When do plans renew?".to_string(), Some("Be brief.".to_string()));
+
+        let completion = provider.create_message(&context).await.unwrap();
+        assert_eq!(completion.text, "Plans renew yearly.");
+        assert_eq!(completion.stop_sequence.as_deref(), Some("END"));
+        assert_eq!(completion.usage, TokenUsage { input_tokens: 12, output_tokens: 5 });
+        let error = provider.invoke_model(context).await.unwrap_err();
+        assert!(matches!(error, AiProviderError::ServiceUnavailable(_)));
+
+        let requests = server.await.unwrap();
+        let request = requests[0].to_lowercase();
+        assert!(requests[0].starts_with("POST /v1/messages HTTP/1.1"));
+        assert!(request.contains("x-api-key: sk-ant-test"));
+        assert!(request.contains("anthropic-version: 2023-06-01"));
+        assert!(requests[0].contains(r#""system":"Be brief.""#));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_against_stub_server() {
+        let events = [
+            ("message_start", json!({"type": "message_start", "message": {"id": "msg_1", "usage": {"input_tokens": 9, "output_tokens": 1}}})),
+            ("content_block_start", json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}})),
+            ("ping", json!({"type": "ping"})),
+            ("content_block_delta", json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hel"}})),
+            ("content_block_delta", json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "lo"}})),
+            ("content_block_stop", json!({"type": "content_block_stop", "index": 0})),
+            ("message_delta", json!({"type": "message_delta", "delta": {"stop_reason": "end_turn", "stop_sequence": null}, "usage": {"output_tokens": 3}})),
+            ("message_stop", json!({"type": "message_stop"})),
+        ];
+        let body = events.iter().map(|(name, data)| format!("event: {}\ndata: {}\n\n", name, data)).collect::<String>();
+        let overloaded = format!("event: error\ndata: {}\n\n", error_body("overloaded_error", "Overloaded"));
+        // A stream cut off after an unterminated last event, without message_stop
+        let unterminated = events[..7].iter().map(|(name, data)| format!("event: {}\ndata: {}", name, data)).collect::<Vec<_>>().join("\n\n");
+        let (base_url, server) = stub_server::start(vec![
+            (200, "text/event-stream", body),
+            (200, "text/event-stream", overloaded),
+            (200, "text/event-stream", unterminated),
+        ])
+        .await;
+        let provider = provider_for(base_url);
+
+        let chunks = Mutex::new(Vec::new());
+        let sink = |chunk: StreamChunk| {
+            chunks.lock().unwrap().push(chunk);
+            Ok(())
+        };
+        let context = utils::create_simple_context("Hi".to_string(), None);
+        provider.invoke_model_stream_to(context.clone(), &sink).await.unwrap();
+
+        let chunks = chunks.into_inner().unwrap();
+        let texts: Vec<(&str, bool)> = chunks.iter().map(|chunk| (chunk.content.as_str(), chunk.is_final)).collect();
+        assert_eq!(texts, vec![("Hel", false), ("lo", false), ("", true)]);
+        let metadata = chunks[2].metadata.as_ref().unwrap();
+        assert_eq!(metadata["stop_reason"], "end_turn");
+        assert_eq!((metadata["input_tokens"].clone(), metadata["output_tokens"].clone()), (json!(9), json!(3)));
+
+        // Errors sent mid-stream are typed like HTTP errors
+        let error = provider.invoke_model_stream_to(context.clone(), &|_| Ok(())).await.unwrap_err();
+        assert!(matches!(error, AiProviderError::ServiceUnavailable(_)));
+
+        let last = Mutex::new(None);
+        provider.invoke_model_stream_to(context, &|chunk| {
+            *last.lock().unwrap() = chunk.metadata;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(last.into_inner().unwrap().unwrap()["stop_reason"], "end_turn");
+        assert!(server.await.unwrap()[0].contains(r#""stream":true"#));
+    }
+
+    #[test]
+    fn test_factory_creation() {
+        let factory = AnthropicProviderFactory;
+        assert_eq!(factory.provider_type(), "anthropic");
+
+        let config = serde_json::json!({
+            "default_model": "claude-3-5-haiku-latest",
+            "base_url": "http://127.0.0.1:9"
+        });
+
+        let provider = factory.create_provider(config).unwrap();
+        let info = tokio_test::block_on(provider.get_provider_info());
+        assert_eq!(info.name, "anthropic");
+        assert_eq!(info.supported_models, vec!["claude-3-5-haiku-latest"]);
+    }
+}
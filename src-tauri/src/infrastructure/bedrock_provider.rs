@@ -6,7 +6,7 @@
 
 use crate::application::ai_provider::{
     AiProvider, AiProviderError, AiProviderFactory, ConversationContext, Message, MessageRole,
-    ModelConfig, ProviderCapabilities, ProviderInfo, StreamChunk,
+    ModelConfig, ProviderCapabilities, ProviderInfo, StreamChunk, StreamSink, utils,
 };
 use crate::infrastructure::credential_manager::CredentialManager;
 use async_trait::async_trait;
@@ -219,10 +219,10 @@ impl BedrockProvider {
         ))
     }
 
-    /// Send streaming chunks to the frontend via Tauri
+    /// Send a streaming chunk to `sink`
     async fn send_stream_chunk(
         &self,
-        app_handle: &AppHandle,
+        sink: &StreamSink<'_>,
         content: String,
         is_final: bool,
     ) -> Result<(), AiProviderError> {
@@ -241,14 +241,7 @@ impl BedrockProvider {
             }),
         };
 
-        app_handle
-            .emit_all("ai_stream_chunk", &chunk)
-            .map_err(|e| {
-                error!("Failed to emit stream chunk: {}", e);
-                AiProviderError::ProviderError(format!("Failed to emit stream chunk: {}", e))
-            })?;
-
-        Ok(())
+        sink(chunk)
     }
 }
 
@@ -327,6 +320,17 @@ impl AiProvider for BedrockProvider {
         &self,
         context: ConversationContext,
         app_handle: &AppHandle,
+    ) -> Result<(), AiProviderError> {
+        self.invoke_model_stream_to(context, &|chunk| {
+            utils::emit_stream_chunk(app_handle, &chunk).inspect_err(|e| error!("BedrockProvider: {}", e))
+        })
+        .await
+    }
+
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
     ) -> Result<(), AiProviderError> {
         debug!("BedrockProvider: Starting streaming completion");
 
@@ -363,7 +367,7 @@ impl AiProvider for BedrockProvider {
                             if let Some(delta) = chunk_json["delta"].as_object() {
                                 if let Some(text) = delta["text"].as_str() {
                                     if !text.is_empty() {
-                                        self.send_stream_chunk(app_handle, text.to_string(), false).await?;
+                                        self.send_stream_chunk(sink, text.to_string(), false).await?;
                                     }
                                 }
                             }
@@ -381,7 +385,7 @@ impl AiProvider for BedrockProvider {
         }
 
         // Send final chunk
-        self.send_stream_chunk(app_handle, "".to_string(), true).await?;
+        self.send_stream_chunk(sink, "".to_string(), true).await?;
 
         info!("BedrockProvider: Streaming completion successful");
         Ok(())
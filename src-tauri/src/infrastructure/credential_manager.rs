@@ -10,6 +10,7 @@ pub mod service_names {
     pub const AWS_BEDROCK: &str = "project-yarn-aws-bedrock";
     pub const GOOGLE_GEMINI: &str = "project-yarn-gemini";
     pub const OPENAI_API: &str = "project-yarn-openai";
+    pub const ANTHROPIC_API: &str = "project-yarn-anthropic";
 }
 
 /// Credential manager for secure storage
@@ -195,6 +196,23 @@ impl CredentialManager {
         )
     }
     
+    /// Store Anthropic API credentials
+    pub fn store_anthropic_api_key(&self, api_key: &str) -> Result<(), String> {
+        self.store_credential(
+            service_names::ANTHROPIC_API,
+            "api_key",
+            api_key,
+        )
+    }
+    
+    /// Retrieve Anthropic API credentials
+    pub fn retrieve_anthropic_api_key(&self) -> Result<String, String> {
+        self.retrieve_credential(
+            service_names::ANTHROPIC_API,
+            "api_key",
+        )
+    }
+    
     /// Get a list of all stored credential services for debugging/management
     pub fn list_credential_services(&self) -> Vec<&'static str> {
         vec![
@@ -202,6 +220,7 @@ impl CredentialManager {
             service_names::AWS_BEDROCK,
             service_names::GOOGLE_GEMINI,
             service_names::OPENAI_API,
+            service_names::ANTHROPIC_API,
         ]
     }
     
@@ -214,7 +233,7 @@ impl CredentialManager {
             // Try to delete common usernames/keys for each service
             let common_keys = match service {
                 service_names::AWS_BEDROCK => vec!["access_key_id", "secret_access_key", "region"],
-                service_names::GOOGLE_GEMINI | service_names::OPENAI_API => vec!["api_key"],
+                service_names::GOOGLE_GEMINI | service_names::OPENAI_API | service_names::ANTHROPIC_API => vec!["api_key"],
                 _ => vec!["default"],
             };
             
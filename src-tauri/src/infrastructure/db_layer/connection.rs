@@ -2,13 +2,21 @@
 // Handles SQLite database connections and initialization
 
 use rusqlite::{Connection, Result as SqliteResult};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use tracing::warn;
+use super::flat_vector_store::FlatVectorStore;
+use super::hnsw_index::HnswIndex;
 
 /// Database connection manager with connection pooling
 pub struct DatabaseConnection {
     connection: Arc<Mutex<Option<Connection>>>,
     database_path: Option<PathBuf>,
+    /// Loaded HNSW indexes over vector_index, keyed by embedding model
+    ann_indexes: Arc<Mutex<HashMap<String, HnswIndex>>>,
+    /// Loaded flat vector stores over vector_index, keyed by embedding model
+    flat_stores: Arc<Mutex<HashMap<String, FlatVectorStore>>>,
 }
 
 impl DatabaseConnection {
@@ -17,6 +25,8 @@ impl DatabaseConnection {
         Self {
             connection: Arc::new(Mutex::new(None)),
             database_path: None,
+            ann_indexes: Arc::new(Mutex::new(HashMap::new())),
+            flat_stores: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -94,8 +104,70 @@ impl DatabaseConnection {
         self.database_path.as_deref()
     }
 
+    /// Access the loaded HNSW indexes (keyed by embedding model)
+    pub fn with_ann_indexes<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut HashMap<String, HnswIndex>) -> R,
+    {
+        let mut indexes_guard = self.ann_indexes.lock().unwrap();
+        f(&mut indexes_guard)
+    }
+
+    /// Write any modified HNSW indexes next to the database file
+    pub fn flush_ann_indexes(&self) -> Result<(), String> {
+        let database_path = match self.database_path.as_deref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        self.with_ann_indexes(|indexes| {
+            for (model, index) in indexes.iter_mut() {
+                if index.is_dirty() {
+                    index.save(&HnswIndex::index_path_for(database_path, model))?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Access the loaded flat vector stores (keyed by embedding model)
+    pub fn with_flat_stores<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut HashMap<String, FlatVectorStore>) -> R,
+    {
+        let mut stores_guard = self.flat_stores.lock().unwrap();
+        f(&mut stores_guard)
+    }
+
+    /// Write any modified flat vector stores next to the database file
+    pub fn flush_flat_stores(&self) -> Result<(), String> {
+        let database_path = match self.database_path.as_deref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        self.with_flat_stores(|stores| {
+            for (model, store) in stores.iter_mut() {
+                if store.is_dirty() {
+                    store.save(&FlatVectorStore::path_for(database_path, model))?;
+                }
+            }
+            Ok(())
+        })
+    }
+
     /// Close the database connection
     pub fn close(&mut self) -> Result<(), String> {
+        if let Err(e) = self.flush_ann_indexes() {
+            warn!("Failed to persist HNSW indexes on close: {}", e);
+        }
+        self.with_ann_indexes(|indexes| indexes.clear());
+
+        if let Err(e) = self.flush_flat_stores() {
+            warn!("Failed to persist flat vector stores on close: {}", e);
+        }
+        self.with_flat_stores(|stores| stores.clear());
+
         let mut connection_guard = self.connection.lock().unwrap();
         if let Some(conn) = connection_guard.take() {
             // SQLite connection is automatically closed when dropped
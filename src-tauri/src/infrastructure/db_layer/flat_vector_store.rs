@@ -0,0 +1,530 @@
+// Flat Vector Store
+// Contiguous memory-mapped f32 matrix for exact brute-force vector search
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use super::vector_math::{dot_product, normalize, VectorSearchHit, VectorSourceStamp};
+
+/// Magic header written at the start of every flat vector file
+const FLAT_FILE_MAGIC: &[u8; 8] = b"YRNFLAT1";
+
+/// Fixed header size; keeps the ID map and matrix 8-byte aligned
+const HEADER_LEN: usize = 48;
+
+/// Bytes per ID map entry (vector_id and document_id as i64)
+const ID_ENTRY_LEN: usize = 16;
+
+/// Rows scored per parallel task
+const SCORE_BATCH_ROWS: usize = 1024;
+
+/// Exact vector store for one embedding model.
+///
+/// Vectors are L2-normalized and stored row-major in a memory-mapped file
+/// (`<db>.<model>.vectors`) together with a vector_id/document_id map, so a query
+/// is one parallel pass of dot products over contiguous memory. Writes made after
+/// the file was built are kept in an in-memory overlay until the next `save`.
+pub struct FlatVectorStore {
+    dimension: usize,
+    base: Option<Mmap>,
+    /// Decoded copy of the matrix for targets where the mapping can't be viewed as f32
+    base_decoded: Option<Vec<f32>>,
+    base_ids: Vec<(i64, i64)>,
+    base_positions: HashMap<i64, usize>,
+    /// Base rows deleted or superseded by an overlay entry
+    base_removed: HashSet<i64>,
+    overlay: Vec<(i64, i64, Vec<f32>)>,
+    overlay_positions: HashMap<i64, usize>,
+    source_stamp: VectorSourceStamp,
+    dirty: bool,
+}
+
+impl FlatVectorStore {
+    /// Create an empty in-memory store
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            base: None,
+            base_decoded: None,
+            base_ids: Vec::new(),
+            base_positions: HashMap::new(),
+            base_removed: HashSet::new(),
+            overlay: Vec::new(),
+            overlay_positions: HashMap::new(),
+            source_stamp: VectorSourceStamp::default(),
+            dirty: false,
+        }
+    }
+
+    /// Build a store from (vector_id, document_id, embedding) triples
+    pub fn build<I>(dimension: usize, entries: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (i64, i64, Vec<f32>)>,
+    {
+        let mut store = Self::new(dimension);
+        for (vector_id, document_id, embedding) in entries {
+            store.upsert(vector_id, document_id, &embedding)?;
+        }
+        Ok(store)
+    }
+
+    /// Path of the flat vector file for a database file and embedding model
+    pub fn path_for(database_path: &Path, embedding_model: &str) -> PathBuf {
+        let sanitized: String = embedding_model
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+
+        let mut file_name = database_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(format!(".{}.vectors", sanitized));
+
+        database_path.with_file_name(file_name)
+    }
+
+    /// Vector dimension accepted by this store
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Number of live vectors
+    pub fn len(&self) -> usize {
+        self.base_ids.len() - self.base_removed.len() + self.overlay.len()
+    }
+
+    /// Whether the store holds no live vectors
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether a vector with this vector_index.id is present
+    pub fn contains(&self, vector_id: i64) -> bool {
+        self.overlay_positions.contains_key(&vector_id)
+            || (self.base_positions.contains_key(&vector_id) && !self.base_removed.contains(&vector_id))
+    }
+
+    /// Whether the store changed since it was last saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Snapshot of the source table recorded at build/save time
+    pub fn source_stamp(&self) -> VectorSourceStamp {
+        self.source_stamp
+    }
+
+    /// Record the source table snapshot this store corresponds to
+    pub fn set_source_stamp(&mut self, stamp: VectorSourceStamp) {
+        if self.source_stamp != stamp {
+            self.source_stamp = stamp;
+            self.dirty = true;
+        }
+    }
+
+    /// Insert or replace the vector for a vector_index row
+    pub fn upsert(&mut self, vector_id: i64, document_id: i64, embedding: &[f32]) -> Result<(), String> {
+        if embedding.len() != self.dimension {
+            return Err(format!(
+                "Embedding dimension {} does not match store dimension {}",
+                embedding.len(),
+                self.dimension
+            ));
+        }
+
+        let vector = normalize(embedding);
+        if self.base_positions.contains_key(&vector_id) {
+            self.base_removed.insert(vector_id);
+        }
+
+        match self.overlay_positions.get(&vector_id) {
+            Some(&position) => self.overlay[position] = (vector_id, document_id, vector),
+            None => {
+                self.overlay_positions.insert(vector_id, self.overlay.len());
+                self.overlay.push((vector_id, document_id, vector));
+            }
+        }
+
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Remove the vector for a vector_index row; returns whether it was present
+    pub fn remove(&mut self, vector_id: i64) -> bool {
+        let mut removed = false;
+
+        if let Some(position) = self.overlay_positions.remove(&vector_id) {
+            self.overlay.swap_remove(position);
+            if let Some(moved) = self.overlay.get(position) {
+                self.overlay_positions.insert(moved.0, position);
+            }
+            removed = true;
+        }
+
+        if self.base_positions.contains_key(&vector_id) && self.base_removed.insert(vector_id) {
+            removed = true;
+        }
+
+        if removed {
+            self.dirty = true;
+        }
+        removed
+    }
+
+    /// Exact top-k search by cosine similarity
+    pub fn search(&self, query: &[f32], k: usize, threshold: Option<f32>) -> Result<Vec<VectorSearchHit>, String> {
+        self.search_filtered(query, k, threshold, None)
+    }
+
+    /// Exact top-k search restricted to the given vector ids (all vectors when None)
+    pub fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        threshold: Option<f32>,
+        allowed: Option<&HashSet<i64>>,
+    ) -> Result<Vec<VectorSearchHit>, String> {
+        if query.len() != self.dimension {
+            return Err(format!(
+                "Query dimension {} does not match store dimension {}",
+                query.len(),
+                self.dimension
+            ));
+        }
+        if k == 0 || self.dimension == 0 || self.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = normalize(query);
+        let threshold = threshold.unwrap_or(f32::MIN);
+        let dimension = self.dimension;
+        let mut hits: Vec<VectorSearchHit> = Vec::new();
+
+        if let Some(matrix) = self.base_matrix() {
+            let base_ids = &self.base_ids;
+            let removed = &self.base_removed;
+
+            // Score batches of rows in parallel, keeping only each batch's top k
+            let batches: Vec<Vec<VectorSearchHit>> = matrix
+                .par_chunks(SCORE_BATCH_ROWS * dimension)
+                .enumerate()
+                .map(|(batch, rows)| {
+                    let mut batch_hits: Vec<VectorSearchHit> = rows
+                        .chunks_exact(dimension)
+                        .enumerate()
+                        .filter_map(|(offset, row)| {
+                            let (vector_id, document_id) = base_ids[batch * SCORE_BATCH_ROWS + offset];
+                            if removed.contains(&vector_id) || allowed.is_some_and(|ids| !ids.contains(&vector_id)) {
+                                return None;
+                            }
+                            let similarity = dot_product(&query, row);
+                            (similarity >= threshold).then_some(VectorSearchHit { vector_id, document_id, similarity })
+                        })
+                        .collect();
+                    keep_top_k(&mut batch_hits, k);
+                    batch_hits
+                })
+                .collect();
+
+            hits.extend(batches.into_iter().flatten());
+        }
+
+        for (vector_id, document_id, vector) in &self.overlay {
+            if allowed.is_some_and(|ids| !ids.contains(vector_id)) {
+                continue;
+            }
+            let similarity = dot_product(&query, vector);
+            if similarity >= threshold {
+                hits.push(VectorSearchHit {
+                    vector_id: *vector_id,
+                    document_id: *document_id,
+                    similarity,
+                });
+            }
+        }
+
+        keep_top_k(&mut hits, k);
+        Ok(hits)
+    }
+
+    /// Write all live vectors to disk and memory-map the result
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        let tmp_path = path.with_extension("vectors.tmp");
+        let live_count = self.len();
+
+        {
+            let file = File::create(&tmp_path)
+                .map_err(|e| format!("Failed to create flat vector file: {}", e))?;
+            let mut writer = BufWriter::new(file);
+            self.write_to(&mut writer, live_count)
+                .map_err(|e| format!("Failed to write flat vector file: {}", e))?;
+            writer.flush()
+                .map_err(|e| format!("Failed to flush flat vector file: {}", e))?;
+        }
+
+        // Windows cannot replace a mapped file, so the mapping is released there first;
+        // elsewhere it keeps pointing at the old file until the rename has succeeded
+        let released = cfg!(windows) && self.base.take().is_some();
+        if let Err(e) = std::fs::rename(&tmp_path, path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            if released {
+                // The rename failed, so the file still holds the rows the store was opened with
+                self.base = Some(map_file(path)?);
+            }
+            return Err(format!("Failed to move flat vector file into place: {}", e));
+        }
+
+        *self = Self::open(path)?;
+        Ok(())
+    }
+
+    /// Memory-map a flat vector file
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mmap = map_file(path)?;
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != FLAT_FILE_MAGIC {
+            return Err("Unrecognized flat vector file header".to_string());
+        }
+
+        let dimension = read_u64(&mmap, 8) as usize;
+        let count = read_u64(&mmap, 16) as usize;
+        let source_stamp = VectorSourceStamp {
+            row_count: read_u64(&mmap, 24) as i64,
+            generation: read_u64(&mmap, 32) as i64,
+        };
+
+        let matrix_offset = HEADER_LEN + count * ID_ENTRY_LEN;
+        if mmap.len() != matrix_offset + count * dimension * 4 {
+            return Err("Flat vector file is truncated".to_string());
+        }
+
+        let mut base_ids = Vec::with_capacity(count);
+        let mut base_positions = HashMap::with_capacity(count);
+        for row in 0..count {
+            let offset = HEADER_LEN + row * ID_ENTRY_LEN;
+            let vector_id = read_u64(&mmap, offset) as i64;
+            let document_id = read_u64(&mmap, offset + 8) as i64;
+            base_positions.insert(vector_id, row);
+            base_ids.push((vector_id, document_id));
+        }
+
+        let mut store = Self::new(dimension);
+        store.base = Some(mmap);
+        if store.mapped_matrix().is_none() {
+            store.base_decoded = Some(
+                store.base.as_ref().map(|mmap| &mmap[matrix_offset..]).unwrap_or_default()
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            );
+        }
+        store.base_ids = base_ids;
+        store.base_positions = base_positions;
+        store.source_stamp = source_stamp;
+        Ok(store)
+    }
+
+    /// The stored matrix as f32 rows
+    fn base_matrix(&self) -> Option<&[f32]> {
+        self.mapped_matrix().or(self.base_decoded.as_deref())
+    }
+
+    /// The memory-mapped matrix viewed in place as f32 rows
+    fn mapped_matrix(&self) -> Option<&[f32]> {
+        let mmap = self.base.as_ref()?;
+        let matrix_offset = HEADER_LEN + self.base_ids.len() * ID_ENTRY_LEN;
+        let bytes = &mmap[matrix_offset..];
+
+        // The file is little-endian and mappings are page aligned, so on
+        // little-endian targets the bytes can be viewed as f32 directly
+        if cfg!(target_endian = "little") && bytes.as_ptr().align_offset(std::mem::align_of::<f32>()) == 0 {
+            // SAFETY: length and alignment were checked above; every bit pattern is a valid f32
+            Some(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const f32, bytes.len() / 4) })
+        } else {
+            None
+        }
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W, live_count: usize) -> std::io::Result<()> {
+        let live_base: Vec<usize> = (0..self.base_ids.len())
+            .filter(|&row| !self.base_removed.contains(&self.base_ids[row].0))
+            .collect();
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..8].copy_from_slice(FLAT_FILE_MAGIC);
+        header[8..16].copy_from_slice(&(self.dimension as u64).to_le_bytes());
+        header[16..24].copy_from_slice(&(live_count as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&self.source_stamp.row_count.to_le_bytes());
+        header[32..40].copy_from_slice(&self.source_stamp.generation.to_le_bytes());
+        writer.write_all(&header)?;
+
+        for &row in &live_base {
+            let (vector_id, document_id) = self.base_ids[row];
+            writer.write_all(&vector_id.to_le_bytes())?;
+            writer.write_all(&document_id.to_le_bytes())?;
+        }
+        for (vector_id, document_id, _) in &self.overlay {
+            writer.write_all(&vector_id.to_le_bytes())?;
+            writer.write_all(&document_id.to_le_bytes())?;
+        }
+
+        if let Some(matrix) = self.base_matrix() {
+            for &row in &live_base {
+                for value in &matrix[row * self.dimension..(row + 1) * self.dimension] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+        for (_, _, vector) in &self.overlay {
+            for value in vector {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Keep the k most similar hits, sorted by descending similarity
+fn keep_top_k(hits: &mut Vec<VectorSearchHit>, k: usize) {
+    let by_similarity = |a: &VectorSearchHit, b: &VectorSearchHit| {
+        b.similarity.partial_cmp(&a.similarity).unwrap_or(std::cmp::Ordering::Equal)
+    };
+
+    if hits.len() > k {
+        hits.select_nth_unstable_by(k - 1, by_similarity);
+        hits.truncate(k);
+    }
+    hits.sort_by(by_similarity);
+}
+
+/// Memory-map a whole flat vector file
+fn map_file(path: &Path) -> Result<Mmap, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open flat vector file: {}", e))?;
+    // SAFETY: the file is private to this application and only replaced via rename,
+    // so the mapped bytes are not modified while the mapping is alive
+    unsafe { Mmap::map(&file) }
+        .map_err(|e| format!("Failed to memory-map flat vector file: {}", e))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buffer = [0u8; 8];
+    buffer.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic_vectors::{exact_top_k, synthetic_vectors};
+    use tempfile::TempDir;
+
+    fn build_store(vectors: &[Vec<f32>]) -> FlatVectorStore {
+        FlatVectorStore::build(
+            vectors[0].len(),
+            vectors.iter().enumerate().map(|(i, v)| (i as i64 + 1, i as i64 + 100, v.clone())),
+        )
+        .expect("Failed to build store")
+    }
+
+    #[test]
+    fn test_search_matches_brute_force() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test.db.model.vectors");
+
+        let vectors = synthetic_vectors(3000, 24);
+        let mut store = build_store(&vectors);
+        store.save(&path).expect("Failed to save store");
+
+        for query in synthetic_vectors(5, 24) {
+            let hits = store.search(&query, 10, None).expect("Search failed");
+            let ids: Vec<i64> = hits.iter().map(|h| h.vector_id).collect();
+            assert_eq!(ids, exact_top_k(&vectors, &query, 10, 1));
+        }
+    }
+
+    #[test]
+    fn test_overlay_updates_and_removes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test.db.model.vectors");
+
+        let vectors = synthetic_vectors(50, 8);
+        let mut store = build_store(&vectors);
+        store.save(&path).expect("Failed to save store");
+        assert!(!store.is_dirty());
+
+        // Point vector 1 at vector 20's embedding and drop vector 20
+        store.upsert(1, 100, &vectors[19]).expect("Upsert failed");
+        assert!(store.remove(20));
+        assert!(!store.remove(20));
+        assert_eq!(store.len(), 49);
+
+        let hits = store.search(&vectors[19], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 1);
+
+        store.save(&path).expect("Failed to save store");
+        let reopened = FlatVectorStore::open(&path).expect("Failed to open store");
+        assert_eq!(reopened.len(), 49);
+        assert!(!reopened.contains(20));
+        let hits = reopened.search(&vectors[19], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 1);
+    }
+
+    #[test]
+    fn test_threshold_and_stamp_roundtrip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test.db.model.vectors");
+
+        let mut store = FlatVectorStore::build(2, vec![(1, 1, vec![1.0, 0.0]), (2, 2, vec![0.0, 1.0])])
+            .expect("Failed to build store");
+        store.set_source_stamp(VectorSourceStamp { row_count: 2, generation: 99 });
+        store.save(&path).expect("Failed to save store");
+
+        let reopened = FlatVectorStore::open(&path).expect("Failed to open store");
+        assert_eq!(reopened.source_stamp(), VectorSourceStamp { row_count: 2, generation: 99 });
+
+        let hits = reopened.search(&[1.0, 0.1], 10, Some(0.5)).expect("Search failed");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].vector_id, 1);
+    }
+
+    #[test]
+    fn test_failed_save_keeps_mapping() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test.db.model.vectors");
+
+        let vectors = synthetic_vectors(50, 8);
+        let mut store = build_store(&vectors);
+        store.save(&path).expect("Failed to save store");
+
+        // A non-empty directory cannot be replaced by a file
+        let blocked = temp_dir.path().join("blocked.vectors");
+        std::fs::create_dir(&blocked).unwrap();
+        std::fs::write(blocked.join("keep"), b"x").unwrap();
+        store.upsert(51, 151, &vectors[0]).expect("Failed to upsert");
+        assert!(store.save(&blocked).is_err());
+        assert!(!blocked.with_extension("vectors.tmp").exists());
+
+        // Mapped rows and the unsaved overlay are both still searchable
+        let hits = store.search(&vectors[10], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 11);
+        assert_eq!(store.len(), 51);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let mut store = FlatVectorStore::new(3);
+        assert!(store.upsert(1, 1, &[1.0]).is_err());
+        assert!(store.search(&[1.0], 1, None).is_err());
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("bad.vectors");
+        std::fs::write(&path, b"not a vector file").unwrap();
+        assert!(FlatVectorStore::open(&path).is_err());
+    }
+}
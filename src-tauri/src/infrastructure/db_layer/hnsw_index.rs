@@ -0,0 +1,781 @@
+// HNSW Approximate Nearest Neighbour Index
+// In-process Hierarchical Navigable Small World graph over vector_index embeddings
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use super::vector_math::{dot_product, normalize, VectorSearchHit, VectorSourceStamp};
+
+/// Magic header written at the start of every persisted HNSW file
+const HNSW_FILE_MAGIC: &[u8; 8] = b"YRNHNSW1";
+
+/// Upper bound on the number of layers a node can be assigned to
+const MAX_LEVEL: usize = 16;
+
+/// Fraction of tombstoned nodes that triggers an in-memory compaction
+const COMPACTION_RATIO: f32 = 0.25;
+
+/// Tuning parameters for the HNSW graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HnswConfig {
+    /// Maximum number of neighbours per node on the upper layers (layer 0 uses 2 * m)
+    pub m: usize,
+    /// Size of the dynamic candidate list used while inserting
+    pub ef_construction: usize,
+    /// Default size of the dynamic candidate list used while searching
+    pub ef_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+/// A single node in the graph
+#[derive(Debug, Clone)]
+struct HnswNode {
+    /// vector_index.id of the row this node was built from
+    vector_id: i64,
+    /// vector_index.document_id of the row this node was built from
+    document_id: i64,
+    /// L2-normalized embedding so that similarity is a plain dot product
+    vector: Vec<f32>,
+    /// Neighbour lists, one per layer the node participates in
+    neighbors: Vec<Vec<u32>>,
+    /// Tombstone flag; deleted nodes are still traversed but never returned
+    deleted: bool,
+}
+
+/// Candidate ordered by similarity (max-heap pops the most similar first)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    similarity: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity
+            .partial_cmp(&other.similarity)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// In-memory HNSW graph for one embedding model
+#[derive(Debug, Clone)]
+pub struct HnswIndex {
+    dimension: usize,
+    config: HnswConfig,
+    nodes: Vec<HnswNode>,
+    id_to_node: HashMap<i64, u32>,
+    entry_point: Option<u32>,
+    max_level: usize,
+    deleted_count: usize,
+    source_stamp: VectorSourceStamp,
+    dirty: bool,
+}
+
+impl HnswIndex {
+    /// Create an empty index for vectors of the given dimension
+    pub fn new(dimension: usize, config: HnswConfig) -> Self {
+        Self {
+            dimension,
+            config,
+            nodes: Vec::new(),
+            id_to_node: HashMap::new(),
+            entry_point: None,
+            max_level: 0,
+            deleted_count: 0,
+            source_stamp: VectorSourceStamp::default(),
+            dirty: false,
+        }
+    }
+
+    /// Build an index from (vector_id, document_id, embedding) triples
+    pub fn build<I>(dimension: usize, config: HnswConfig, entries: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (i64, i64, Vec<f32>)>,
+    {
+        let mut index = Self::new(dimension, config);
+        for (vector_id, document_id, embedding) in entries {
+            index.insert(vector_id, document_id, &embedding)?;
+        }
+        Ok(index)
+    }
+
+    /// Vector dimension accepted by this index
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Graph tuning parameters
+    pub fn config(&self) -> &HnswConfig {
+        &self.config
+    }
+
+    /// Number of live (non-deleted) vectors
+    pub fn len(&self) -> usize {
+        self.id_to_node.len()
+    }
+
+    /// Whether the index holds no live vectors
+    pub fn is_empty(&self) -> bool {
+        self.id_to_node.is_empty()
+    }
+
+    /// Whether a vector with this vector_index.id is present
+    pub fn contains(&self, vector_id: i64) -> bool {
+        self.id_to_node.contains_key(&vector_id)
+    }
+
+    /// Whether the index changed since it was last saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Snapshot of the source table recorded at build/save time
+    pub fn source_stamp(&self) -> VectorSourceStamp {
+        self.source_stamp
+    }
+
+    /// Record the source table snapshot this index corresponds to
+    pub fn set_source_stamp(&mut self, stamp: VectorSourceStamp) {
+        if self.source_stamp != stamp {
+            self.source_stamp = stamp;
+            self.dirty = true;
+        }
+    }
+
+    /// Insert or replace the vector for a vector_index row
+    pub fn insert(&mut self, vector_id: i64, document_id: i64, embedding: &[f32]) -> Result<(), String> {
+        if embedding.len() != self.dimension {
+            return Err(format!(
+                "Embedding dimension {} does not match index dimension {}",
+                embedding.len(),
+                self.dimension
+            ));
+        }
+
+        if self.id_to_node.contains_key(&vector_id) {
+            self.remove(vector_id);
+        }
+
+        let vector = normalize(embedding);
+        let level = random_level(vector_id, self.config.m);
+        let node_id = self.nodes.len() as u32;
+
+        self.nodes.push(HnswNode {
+            vector_id,
+            document_id,
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.id_to_node.insert(vector_id, node_id);
+        self.dirty = true;
+
+        let entry_point = match self.entry_point {
+            Some(entry_point) => entry_point,
+            None => {
+                self.entry_point = Some(node_id);
+                self.max_level = level;
+                return Ok(());
+            }
+        };
+
+        let query = self.nodes[node_id as usize].vector.clone();
+        let mut current = entry_point;
+
+        // Greedy descent through the layers above the new node's level
+        for layer in (level + 1..=self.max_level).rev() {
+            current = self.greedy_closest(&query, current, layer);
+        }
+
+        // Connect the node on every layer it participates in
+        let mut entry_points = vec![current];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.config.ef_construction, layer);
+            let max_neighbors = self.max_neighbors(layer);
+            let filtered: Vec<Candidate> = candidates
+                .iter()
+                .filter(|c| c.node != node_id)
+                .copied()
+                .collect();
+            let selected = self.select_neighbors(&filtered, max_neighbors);
+
+            self.nodes[node_id as usize].neighbors[layer] = selected.clone();
+
+            for neighbor in selected {
+                self.connect(neighbor, node_id, layer);
+            }
+
+            entry_points = candidates.iter().map(|c| c.node).collect();
+            if entry_points.is_empty() {
+                entry_points.push(current);
+            }
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node_id);
+        }
+
+        Ok(())
+    }
+
+    /// Remove the vector for a vector_index row; returns whether it was present
+    pub fn remove(&mut self, vector_id: i64) -> bool {
+        let node_id = match self.id_to_node.remove(&vector_id) {
+            Some(node_id) => node_id,
+            None => return false,
+        };
+
+        self.nodes[node_id as usize].deleted = true;
+        self.deleted_count += 1;
+        self.dirty = true;
+
+        if self.id_to_node.is_empty() {
+            self.clear();
+        } else if self.deleted_count as f32 > self.nodes.len() as f32 * COMPACTION_RATIO {
+            self.compact();
+        }
+
+        true
+    }
+
+    /// Approximate k-nearest-neighbour search by cosine similarity.
+    /// Larger `ef_search` values trade speed for recall.
+    pub fn search(&self, query: &[f32], k: usize, ef_search: Option<usize>) -> Result<Vec<VectorSearchHit>, String> {
+        if query.len() != self.dimension {
+            return Err(format!(
+                "Query dimension {} does not match index dimension {}",
+                query.len(),
+                self.dimension
+            ));
+        }
+
+        let entry_point = match self.entry_point {
+            Some(entry_point) if k > 0 => entry_point,
+            _ => return Ok(Vec::new()),
+        };
+
+        let query = normalize(query);
+        let ef = ef_search.unwrap_or(self.config.ef_search).max(k);
+
+        let mut current = entry_point;
+        for layer in (1..=self.max_level).rev() {
+            current = self.greedy_closest(&query, current, layer);
+        }
+
+        let candidates = self.search_layer(&query, &[current], ef, 0);
+
+        Ok(candidates
+            .into_iter()
+            .filter(|c| !self.nodes[c.node as usize].deleted)
+            .take(k)
+            .map(|c| {
+                let node = &self.nodes[c.node as usize];
+                VectorSearchHit {
+                    vector_id: node.vector_id,
+                    document_id: node.document_id,
+                    similarity: c.similarity,
+                }
+            })
+            .collect())
+    }
+
+    /// Path of the persisted index for a database file and embedding model
+    pub fn index_path_for(database_path: &Path, embedding_model: &str) -> PathBuf {
+        let sanitized: String = embedding_model
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+
+        let mut file_name = database_path
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(format!(".{}.hnsw", sanitized));
+
+        database_path.with_file_name(file_name)
+    }
+
+    /// Persist the index to disk
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        let tmp_path = path.with_extension("hnsw.tmp");
+        let file = File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create HNSW index file: {}", e))?;
+        let mut writer = BufWriter::new(file);
+
+        self.write_to(&mut writer)
+            .map_err(|e| format!("Failed to write HNSW index: {}", e))?;
+        writer.flush()
+            .map_err(|e| format!("Failed to flush HNSW index: {}", e))?;
+        drop(writer);
+
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| format!("Failed to move HNSW index into place: {}", e))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Load a persisted index from disk
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Failed to open HNSW index file: {}", e))?;
+        let mut reader = BufReader::new(file);
+
+        Self::read_from(&mut reader)
+            .map_err(|e| format!("Failed to read HNSW index: {}", e))
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        // Only live nodes are written; node ids are remapped densely
+        let live: Vec<u32> = (0..self.nodes.len() as u32)
+            .filter(|&n| !self.nodes[n as usize].deleted)
+            .collect();
+        let remap: HashMap<u32, u32> = live
+            .iter()
+            .enumerate()
+            .map(|(new_id, &old_id)| (old_id, new_id as u32))
+            .collect();
+
+        writer.write_all(HNSW_FILE_MAGIC)?;
+        write_u32(writer, self.dimension as u32)?;
+        write_u32(writer, self.config.m as u32)?;
+        write_u32(writer, self.config.ef_construction as u32)?;
+        write_u32(writer, self.config.ef_search as u32)?;
+        write_i64(writer, self.source_stamp.row_count)?;
+        write_i64(writer, self.source_stamp.generation)?;
+        write_u32(writer, live.len() as u32)?;
+
+        for &old_id in &live {
+            let node = &self.nodes[old_id as usize];
+            write_i64(writer, node.vector_id)?;
+            write_i64(writer, node.document_id)?;
+            for &value in &node.vector {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            write_u32(writer, node.neighbors.len() as u32)?;
+            for layer in &node.neighbors {
+                let kept: Vec<u32> = layer.iter().filter_map(|n| remap.get(n).copied()).collect();
+                write_u32(writer, kept.len() as u32)?;
+                for neighbor in kept {
+                    write_u32(writer, neighbor)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != HNSW_FILE_MAGIC {
+            return Err(invalid("unrecognized HNSW file header"));
+        }
+
+        let dimension = read_u32(reader)? as usize;
+        let config = HnswConfig {
+            m: read_u32(reader)? as usize,
+            ef_construction: read_u32(reader)? as usize,
+            ef_search: read_u32(reader)? as usize,
+        };
+        let source_stamp = VectorSourceStamp {
+            row_count: read_i64(reader)?,
+            generation: read_i64(reader)?,
+        };
+        let node_count = read_u32(reader)? as usize;
+
+        let mut index = Self::new(dimension, config);
+        index.source_stamp = source_stamp;
+        index.nodes.reserve(node_count);
+
+        for node_id in 0..node_count {
+            let vector_id = read_i64(reader)?;
+            let document_id = read_i64(reader)?;
+            let mut vector = Vec::with_capacity(dimension);
+            for _ in 0..dimension {
+                let mut bytes = [0u8; 4];
+                reader.read_exact(&mut bytes)?;
+                vector.push(f32::from_le_bytes(bytes));
+            }
+
+            let layer_count = read_u32(reader)? as usize;
+            if layer_count == 0 || layer_count > MAX_LEVEL + 1 {
+                return Err(invalid("invalid HNSW layer count"));
+            }
+            let mut neighbors = Vec::with_capacity(layer_count);
+            for _ in 0..layer_count {
+                let count = read_u32(reader)? as usize;
+                let mut layer = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let neighbor = read_u32(reader)?;
+                    if neighbor as usize >= node_count {
+                        return Err(invalid("HNSW neighbour out of range"));
+                    }
+                    layer.push(neighbor);
+                }
+                neighbors.push(layer);
+            }
+
+            let level = layer_count - 1;
+            if index.entry_point.is_none() || level > index.max_level {
+                index.entry_point = Some(node_id as u32);
+                index.max_level = level;
+            }
+
+            index.id_to_node.insert(vector_id, node_id as u32);
+            index.nodes.push(HnswNode {
+                vector_id,
+                document_id,
+                vector,
+                neighbors,
+                deleted: false,
+            });
+        }
+
+        Ok(index)
+    }
+
+    /// Maximum neighbour list length for a layer
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.config.m * 2
+        } else {
+            self.config.m
+        }
+    }
+
+    /// Add `to` to the neighbour list of `from`, pruning to the closest neighbours if full
+    fn connect(&mut self, from: u32, to: u32, layer: usize) {
+        let max_neighbors = self.max_neighbors(layer);
+        let from_node = &self.nodes[from as usize];
+        if layer >= from_node.neighbors.len() || from_node.neighbors[layer].contains(&to) {
+            return;
+        }
+
+        let mut neighbors = from_node.neighbors[layer].clone();
+        neighbors.push(to);
+
+        if neighbors.len() > max_neighbors {
+            let base = &self.nodes[from as usize].vector;
+            let mut scored: Vec<Candidate> = neighbors
+                .iter()
+                .map(|&n| Candidate {
+                    similarity: dot_product(base, &self.nodes[n as usize].vector),
+                    node: n,
+                })
+                .collect();
+            scored.sort_by(|a, b| b.cmp(a));
+            neighbors = self.select_neighbors(&scored, max_neighbors);
+        }
+
+        self.nodes[from as usize].neighbors[layer] = neighbors;
+    }
+
+    /// Pick up to `max_neighbors` from candidates sorted by descending similarity.
+    /// A candidate is preferred when it is closer to the base node than to any
+    /// neighbour already selected, which keeps links spread across directions;
+    /// remaining slots are filled with the closest pruned candidates.
+    fn select_neighbors(&self, candidates: &[Candidate], max_neighbors: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(max_neighbors);
+        let mut pruned: Vec<u32> = Vec::new();
+
+        for candidate in candidates {
+            if selected.len() >= max_neighbors {
+                break;
+            }
+            let vector = &self.nodes[candidate.node as usize].vector;
+            let diverse = selected
+                .iter()
+                .all(|&s| dot_product(vector, &self.nodes[s as usize].vector) < candidate.similarity);
+            if diverse {
+                selected.push(candidate.node);
+            } else {
+                pruned.push(candidate.node);
+            }
+        }
+
+        for node in pruned {
+            if selected.len() >= max_neighbors {
+                break;
+            }
+            selected.push(node);
+        }
+
+        selected
+    }
+
+    /// Walk greedily towards the query on a single layer
+    fn greedy_closest(&self, query: &[f32], start: u32, layer: usize) -> u32 {
+        let mut current = start;
+        let mut best = dot_product(query, &self.nodes[current as usize].vector);
+
+        loop {
+            let mut improved = false;
+            if let Some(neighbors) = self.nodes[current as usize].neighbors.get(layer) {
+                for &neighbor in neighbors {
+                    let similarity = dot_product(query, &self.nodes[neighbor as usize].vector);
+                    if similarity > best {
+                        best = similarity;
+                        current = neighbor;
+                        improved = true;
+                    }
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    /// Beam search on a single layer; returns candidates sorted by descending similarity
+    fn search_layer(&self, query: &[f32], entry_points: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = HashSet::with_capacity(ef * 8);
+        let mut candidates: BinaryHeap<Candidate> = BinaryHeap::new();
+        // Min-heap of the current best results (Reverse ordering keeps the worst on top)
+        let mut results: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+
+        for &entry in entry_points {
+            if !visited.insert(entry) {
+                continue;
+            }
+            let candidate = Candidate {
+                similarity: dot_product(query, &self.nodes[entry as usize].vector),
+                node: entry,
+            };
+            candidates.push(candidate);
+            results.push(std::cmp::Reverse(candidate));
+            if results.len() > ef {
+                results.pop();
+            }
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+            if candidate.similarity < worst && results.len() >= ef {
+                break;
+            }
+
+            let neighbors = match self.nodes[candidate.node as usize].neighbors.get(layer) {
+                Some(neighbors) => neighbors,
+                None => continue,
+            };
+
+            for &neighbor in neighbors {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+
+                let similarity = dot_product(query, &self.nodes[neighbor as usize].vector);
+                let worst = results.peek().map(|r| r.0.similarity).unwrap_or(f32::MIN);
+                if results.len() < ef || similarity > worst {
+                    let next = Candidate { similarity, node: neighbor };
+                    candidates.push(next);
+                    results.push(std::cmp::Reverse(next));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut sorted: Vec<Candidate> = results.into_iter().map(|r| r.0).collect();
+        sorted.sort_by(|a, b| b.cmp(a));
+        sorted
+    }
+
+    /// Rebuild the graph from live nodes, dropping tombstones
+    fn compact(&mut self) {
+        let live: Vec<HnswNode> = self.nodes.drain(..).filter(|n| !n.deleted).collect();
+        let stamp = self.source_stamp;
+        let mut rebuilt = Self::new(self.dimension, self.config.clone());
+
+        for node in live {
+            // Vectors are already normalized and dimension-checked
+            let _ = rebuilt.insert(node.vector_id, node.document_id, &node.vector);
+        }
+
+        rebuilt.source_stamp = stamp;
+        rebuilt.dirty = true;
+        *self = rebuilt;
+    }
+
+    fn clear(&mut self) {
+        self.nodes.clear();
+        self.id_to_node.clear();
+        self.entry_point = None;
+        self.max_level = 0;
+        self.deleted_count = 0;
+    }
+}
+
+/// Deterministic layer assignment derived from the row id (splitmix64),
+/// so rebuilding from the same table produces the same graph shape
+fn random_level(vector_id: i64, m: usize) -> usize {
+    let mut z = (vector_id as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // Uniform in (0, 1]
+    let uniform = ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+    let level_multiplier = 1.0 / (m.max(2) as f64).ln();
+    ((-uniform.ln() * level_multiplier) as usize).min(MAX_LEVEL)
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_i64<W: Write>(writer: &mut W, value: i64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> std::io::Result<i64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(i64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic_vectors::{exact_top_k, synthetic_vectors};
+    use tempfile::TempDir;
+
+    fn build_index(vectors: &[Vec<f32>]) -> HnswIndex {
+        HnswIndex::build(
+            vectors[0].len(),
+            HnswConfig::default(),
+            vectors.iter().enumerate().map(|(i, v)| (i as i64 + 1, i as i64 + 100, v.clone())),
+        )
+        .expect("Failed to build index")
+    }
+
+    #[test]
+    fn test_search_finds_exact_match() {
+        let vectors = synthetic_vectors(200, 16);
+        let index = build_index(&vectors);
+
+        let hits = index.search(&vectors[42], 1, None).expect("Search failed");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].vector_id, 43);
+        assert_eq!(hits[0].document_id, 142);
+        assert!((hits[0].similarity - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let vectors = synthetic_vectors(1000, 32);
+        let queries = synthetic_vectors(20, 32);
+        let index = build_index(&vectors);
+
+        let mut found = 0;
+        for query in &queries {
+            let expected = exact_top_k(&vectors, query, 10, 1);
+            let hits = index.search(query, 10, Some(100)).expect("Search failed");
+            found += hits.iter().filter(|h| expected.contains(&h.vector_id)).count();
+        }
+
+        let recall = found as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.9, "recall too low: {}", recall);
+    }
+
+    #[test]
+    fn test_remove_and_reinsert() {
+        let vectors = synthetic_vectors(100, 8);
+        let mut index = build_index(&vectors);
+
+        assert!(index.remove(10));
+        assert!(!index.remove(10));
+        assert_eq!(index.len(), 99);
+
+        let hits = index.search(&vectors[9], 5, None).expect("Search failed");
+        assert!(hits.iter().all(|h| h.vector_id != 10));
+
+        index.insert(10, 109, &vectors[9]).expect("Insert failed");
+        let hits = index.search(&vectors[9], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 10);
+    }
+
+    #[test]
+    fn test_compaction_after_many_deletes() {
+        let vectors = synthetic_vectors(100, 8);
+        let mut index = build_index(&vectors);
+
+        for id in 1..=60 {
+            index.remove(id);
+        }
+
+        assert_eq!(index.len(), 40);
+        let hits = index.search(&vectors[80], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 81);
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let mut index = HnswIndex::new(4, HnswConfig::default());
+        assert!(index.insert(1, 1, &[1.0, 0.0]).is_err());
+        assert!(index.search(&[1.0, 0.0], 1, None).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test.db.model.hnsw");
+
+        let vectors = synthetic_vectors(150, 12);
+        let mut index = build_index(&vectors);
+        index.remove(5);
+        index.set_source_stamp(VectorSourceStamp { row_count: 149, generation: 1234 });
+        index.save(&path).expect("Failed to save index");
+        assert!(!index.is_dirty());
+
+        let loaded = HnswIndex::load(&path).expect("Failed to load index");
+        assert_eq!(loaded.len(), 149);
+        assert_eq!(loaded.dimension(), 12);
+        assert_eq!(loaded.source_stamp().generation, 1234);
+        assert!(!loaded.contains(5));
+
+        let hits = loaded.search(&vectors[70], 1, None).expect("Search failed");
+        assert_eq!(hits[0].vector_id, 71);
+    }
+
+    #[test]
+    fn test_index_path_for() {
+        let path = HnswIndex::index_path_for(Path::new("/data/project_yarn.db"), "all-MiniLM-L6-v2");
+        assert_eq!(path, PathBuf::from("/data/project_yarn.db.all-MiniLM-L6-v2.hnsw"));
+
+        let path = HnswIndex::index_path_for(Path::new("yarn.db"), "org/model:v1");
+        assert_eq!(path, PathBuf::from("yarn.db.org_model_v1.hnsw"));
+    }
+}
@@ -0,0 +1,128 @@
+// Match Highlights
+// Locates full-text matches in the source markdown as byte and line/column ranges,
+// so the editor can highlight hits in place and jump from one match to the next.
+
+use serde::{Deserialize, Serialize};
+
+/// Private-use characters passed to FTS5 highlight() around each match; they never
+/// occur in normal text, so the marked-up copy can be aligned with the source
+pub const MATCH_START: &str = "\u{E000}";
+pub const MATCH_END: &str = "\u{E001}";
+
+/// Position in a text; both fields are 0-based. The column counts UTF-16 code
+/// units, like JavaScript string indices and editor cursor positions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// One match in a document's source text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRange {
+    /// Byte offset of the first matched byte
+    pub byte_start: usize,
+    /// Byte offset one past the end of the match
+    pub byte_end: usize,
+    pub start: TextPosition,
+    pub end: TextPosition,
+}
+
+/// All matches of a query in one document, in source order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentMatches {
+    /// document_content.id (the document key)
+    pub document_id: i64,
+    /// documents.id of the source file
+    pub source_document_id: String,
+    pub path: String,
+    pub title: String,
+    pub matches: Vec<MatchRange>,
+}
+
+/// Ranges of the spans wrapped in MATCH_START/MATCH_END in `highlighted`, a copy of
+/// `source` marked up by FTS5 highlight(). Fails if the copy does not align with the source.
+pub fn locate_matches(source: &str, highlighted: &str) -> Result<Vec<MatchRange>, String> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(usize, TextPosition)> = None;
+    let mut offset = 0;
+    let mut position = TextPosition::default();
+    let mut rest = highlighted;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix(MATCH_START) {
+            open = Some((offset, position));
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix(MATCH_END) {
+            if let Some((byte_start, start)) = open.take() {
+                if offset > byte_start {
+                    ranges.push(MatchRange { byte_start, byte_end: offset, start, end: position });
+                }
+            }
+            rest = after;
+            continue;
+        }
+
+        let ch = rest.chars().next().unwrap_or_default();
+        if !source[offset..].starts_with(ch) {
+            return Err(format!("Highlighted text diverges from the source at byte {}", offset));
+        }
+        offset += ch.len_utf8();
+        rest = &rest[ch.len_utf8()..];
+        if ch == '\n' {
+            position.line += 1;
+            position.column = 0;
+        } else {
+            position.column += ch.len_utf16();
+        }
+    }
+
+    if offset != source.len() {
+        return Err("Highlighted text is shorter than the source".to_string());
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mark(text: &str) -> String {
+        text.replace('[', MATCH_START).replace(']', MATCH_END)
+    }
+
+    #[test]
+    fn test_locate_matches_positions() {
+        let source = "# Rust\n\nRust is fast.\nRustaceans love rust";
+        let ranges = locate_matches(source, &mark("# [Rust]\n\n[Rust] is fast.\nRustaceans love [rust]")).unwrap();
+
+        assert_eq!(ranges.len(), 3);
+        assert_eq!((ranges[0].byte_start, ranges[0].byte_end), (2, 6));
+        assert_eq!(ranges[0].start, TextPosition { line: 0, column: 2 });
+        assert_eq!(ranges[1].start, TextPosition { line: 2, column: 0 });
+        assert_eq!(ranges[1].end, TextPosition { line: 2, column: 4 });
+        assert_eq!(&source[ranges[2].byte_start..ranges[2].byte_end], "rust");
+        assert_eq!(ranges[2].start, TextPosition { line: 3, column: 16 });
+    }
+
+    #[test]
+    fn test_locate_matches_multibyte_columns() {
+        // "é" is 2 bytes and 1 UTF-16 unit; "😀" is 4 bytes and 2 UTF-16 units
+        let source = "café 😀 naïve";
+        let ranges = locate_matches(source, &mark("café 😀 [naïve]")).unwrap();
+
+        assert_eq!((ranges[0].byte_start, ranges[0].byte_end), (11, 17));
+        assert_eq!(&source[ranges[0].byte_start..ranges[0].byte_end], "naïve");
+        assert_eq!(ranges[0].start, TextPosition { line: 0, column: 8 });
+        assert_eq!(ranges[0].end, TextPosition { line: 0, column: 13 });
+    }
+
+    #[test]
+    fn test_locate_matches_rejects_misaligned_text() {
+        assert!(locate_matches("hello world", &mark("[hello] there")).is_err());
+        assert!(locate_matches("hello world", &mark("[hello]")).is_err());
+        assert!(locate_matches("", "").unwrap().is_empty());
+    }
+}
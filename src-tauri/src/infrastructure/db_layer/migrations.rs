@@ -2,6 +2,34 @@
 // Handles database schema creation and versioning
 
 use crate::infrastructure::db_layer::DatabaseConnection;
+use crate::infrastructure::db_layer::search_index::SearchIndexManager;
+use crate::infrastructure::db_layer::settings_repository::SettingsRepository;
+use crate::infrastructure::db_layer::vector_quantization::{
+    decode_stored_vector, VectorStorageSettings, VECTOR_STORAGE_SETTINGS_KEY,
+};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Outcome of converting stored vectors to compact storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorStorageReport {
+    /// document_embeddings rows moved from JSON text to f32 BLOBs
+    pub embeddings_converted: usize,
+    /// vector_index rows rewritten to the configured storage layout
+    pub vectors_converted: usize,
+    /// Bytes used by embedding columns before conversion
+    pub bytes_before: i64,
+    /// Bytes used by embedding columns after conversion
+    pub bytes_after: i64,
+}
+
+impl VectorStorageReport {
+    /// Net bytes saved (negative when the new layout keeps more encodings than the old one)
+    pub fn bytes_saved(&self) -> i64 {
+        self.bytes_before - self.bytes_after
+    }
+}
 
 /// Database schema version management
 pub struct MigrationManager<'a> {
@@ -46,7 +74,14 @@ impl<'a> MigrationManager<'a> {
         
         // Migration v5: Create FTS5 full-text search index
         if current_version < 5 {
-            self.migrate_to_v5()?;
+            // v5 indexes documents.title and documents.content, which the documents table
+            // does not have, so it fails there; v10 builds the index over document_content
+            if let Err(e) = self.migrate_to_v5() {
+                if self.table_has_columns("documents", &["title", "content"])? {
+                    return Err(e);
+                }
+                warn!("Migration v5 left to v10, documents has no text columns to index: {}", e);
+            }
             self.update_schema_version(5)?;
         }
         
@@ -62,6 +97,57 @@ impl<'a> MigrationManager<'a> {
             self.update_schema_version(7)?;
         }
         
+        // Migration v8: Compact embedding storage and add quantized vector columns
+        if current_version < 8 {
+            self.migrate_to_v8()?;
+            self.update_schema_version(8)?;
+        }
+        
+        // Migration v9: Create chunk-level vector index for passage retrieval
+        if current_version < 9 {
+            self.migrate_to_v9()?;
+            self.update_schema_version(9)?;
+        }
+        
+        // Migration v10: Store document text in document_content and index it with FTS5
+        if current_version < 10 {
+            self.migrate_to_v10()?;
+            self.update_schema_version(10)?;
+        }
+        
+        // Migration v11: Per-database settings; rebuild FTS5 with the configured tokenizer
+        if current_version < 11 {
+            self.migrate_to_v11()?;
+            self.update_schema_version(11)?;
+        }
+        
+        // Migration v12: Unstemmed term indexes and fts5vocab dictionaries for spelling suggestions
+        if current_version < 12 {
+            self.migrate_to_v12()?;
+            self.update_schema_version(12)?;
+        }
+        
+        // Migration v13: Stored RAG evaluation reports for comparing retrieval configs
+        if current_version < 13 {
+            self.migrate_to_v13()?;
+            self.update_schema_version(13)?;
+        }
+        
+        // Migration v14: Per-project settings overriding the database-wide defaults
+        if current_version < 14 {
+            self.migrate_to_v14()?;
+            self.update_schema_version(14)?;
+        }
+        
+        // Migration v15: Per-model change counters used to detect stale vector index files
+        if current_version < 15 {
+            self.migrate_to_v15()?;
+            self.update_schema_version(15)?;
+        }
+        
+        // Settings may have been edited since the last start; bring the indexes in line
+        SearchIndexManager::new(self.db).ensure_current()?;
+        
         Ok(())
     }
 
@@ -519,6 +605,16 @@ impl<'a> MigrationManager<'a> {
         Ok(())
     }
 
+    /// Whether a table has every one of the given columns
+    fn table_has_columns(&self, table: &str, columns: &[&str]) -> Result<bool, String> {
+        let existing = self.db.query_map(
+            "SELECT name FROM pragma_table_info(?1)",
+            &[&table],
+            |row| row.get::<_, String>(0),
+        )?;
+        Ok(columns.iter().all(|column| existing.iter().any(|name| name == column)))
+    }
+
     /// Migration to version 6: Add database optimization indexes for RAG system performance
     /// This migration creates optimized indexes based on query analysis for critical RAG operations
     /// including document retrieval, vector search, and FTS5 queries
@@ -671,6 +767,500 @@ impl<'a> MigrationManager<'a> {
         Ok(())
     }
     
+    /// Migration to version 8: Compact embedding storage and add quantized vectors
+    /// document_embeddings gains an f32 BLOB column that replaces the JSON text copy,
+    /// and vector_index gains int8 and binary columns, filled only when quantized
+    /// storage is enabled in the vector storage settings
+    fn migrate_to_v8(&self) -> Result<(), String> {
+        let embedding_blob_sql = r#"
+            ALTER TABLE document_embeddings ADD COLUMN embedding_blob BLOB
+        "#;
+        self.db.execute(embedding_blob_sql, &[])?;
+        
+        let vector_int8_sql = r#"
+            ALTER TABLE vector_index ADD COLUMN embedding_int8 BLOB
+        "#;
+        self.db.execute(vector_int8_sql, &[])?;
+        
+        let vector_binary_sql = r#"
+            ALTER TABLE vector_index ADD COLUMN embedding_binary BLOB
+        "#;
+        self.db.execute(vector_binary_sql, &[])?;
+        
+        // Convert existing rows to the new storage layout (database_settings does not exist yet)
+        let report = self.convert_vector_storage_with(&VectorStorageSettings::default())?;
+        info!(
+            "Converted {} embeddings and {} vectors; embedding storage {} -> {} bytes ({} bytes saved)",
+            report.embeddings_converted,
+            report.vectors_converted,
+            report.bytes_before,
+            report.bytes_after,
+            report.bytes_saved()
+        );
+        
+        Ok(())
+    }
+
+    /// Migration to version 9: Create chunk_vector_index for passage-level retrieval
+    /// vector_index holds one vector per document; this table holds one row per
+    /// embedded document_embeddings chunk, kept in sync by triggers, for model and
+    /// document lookups. The vector itself is read from document_embeddings.embedding_blob.
+    fn migrate_to_v9(&self) -> Result<(), String> {
+        let chunk_vector_index_sql = r#"
+            CREATE TABLE IF NOT EXISTS chunk_vector_index (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                embedding_id INTEGER NOT NULL UNIQUE, -- document_embeddings.id, holds the vector
+                document_id INTEGER NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                embedding_model TEXT NOT NULL,
+                dimension INTEGER NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )
+        "#;
+        self.db.execute(chunk_vector_index_sql, &[])?;
+        
+        let idx_chunk_vector_document = r#"
+            CREATE INDEX IF NOT EXISTS idx_chunk_vector_index_document_id 
+            ON chunk_vector_index(document_id)
+        "#;
+        self.db.execute(idx_chunk_vector_document, &[])?;
+        
+        let idx_chunk_vector_model = r#"
+            CREATE INDEX IF NOT EXISTS idx_chunk_vector_index_model 
+            ON chunk_vector_index(embedding_model)
+        "#;
+        self.db.execute(idx_chunk_vector_model, &[])?;
+        
+        // Keep chunk vectors synchronized with document_embeddings
+        let insert_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS chunk_vector_index_insert 
+            AFTER INSERT ON document_embeddings 
+            WHEN NEW.embedding_blob IS NOT NULL
+            BEGIN
+                INSERT OR REPLACE INTO chunk_vector_index (
+                    embedding_id, document_id, chunk_index, embedding_model, dimension
+                ) VALUES (
+                    NEW.id, NEW.document_id, NEW.chunk_index,
+                    NEW.embedding_model, LENGTH(NEW.embedding_blob) / 4
+                );
+            END
+        "#;
+        self.db.execute(insert_trigger_sql, &[])?;
+        
+        let update_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS chunk_vector_index_update 
+            AFTER UPDATE OF embedding_blob, embedding_model, chunk_index, document_id ON document_embeddings 
+            WHEN NEW.embedding_blob IS NOT NULL
+            BEGIN
+                INSERT OR REPLACE INTO chunk_vector_index (
+                    embedding_id, document_id, chunk_index, embedding_model, dimension
+                ) VALUES (
+                    NEW.id, NEW.document_id, NEW.chunk_index,
+                    NEW.embedding_model, LENGTH(NEW.embedding_blob) / 4
+                );
+            END
+        "#;
+        self.db.execute(update_trigger_sql, &[])?;
+        
+        let delete_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS chunk_vector_index_delete 
+            AFTER DELETE ON document_embeddings 
+            BEGIN
+                DELETE FROM chunk_vector_index WHERE embedding_id = OLD.id;
+            END
+        "#;
+        self.db.execute(delete_trigger_sql, &[])?;
+        
+        // Populate from chunks already embedded
+        let populate_sql = r#"
+            INSERT OR IGNORE INTO chunk_vector_index (
+                embedding_id, document_id, chunk_index, embedding_model, dimension
+            )
+            SELECT id, document_id, chunk_index, embedding_model, LENGTH(embedding_blob) / 4
+            FROM document_embeddings
+            WHERE embedding_blob IS NOT NULL
+        "#;
+        self.db.execute(populate_sql, &[])?;
+        
+        Ok(())
+    }
+
+    /// Migration to version 10: Create document_content and re-point FTS5 at it
+    /// document_content holds each document's title and markdown body as synced from
+    /// disk; its integer id is the document key used as the FTS5 rowid. The earlier
+    /// documents_fts tables were external-content tables over `documents`, which
+    /// has no text columns, so they are dropped along with their triggers. This is
+    /// also where databases whose v5 could not index `documents` get their index.
+    fn migrate_to_v10(&self) -> Result<(), String> {
+        let document_content_sql = r#"
+            CREATE TABLE IF NOT EXISTS document_content (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, -- document key, used as the FTS5 rowid
+                document_id TEXT NOT NULL UNIQUE,
+                title TEXT NOT NULL,
+                content TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                file_modified_at INTEGER,
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                FOREIGN KEY (document_id) REFERENCES documents(id) ON DELETE CASCADE
+            )
+        "#;
+        self.db.execute(document_content_sql, &[])?;
+        
+        for trigger in ["documents_fts_insert", "documents_fts_update", "documents_fts_delete"] {
+            self.db.execute(&format!("DROP TRIGGER IF EXISTS {}", trigger), &[])?;
+        }
+        self.db.execute("DROP TABLE IF EXISTS documents_fts", &[])?;
+        
+        let fts5_table_sql = r#"
+            CREATE VIRTUAL TABLE documents_fts 
+            USING fts5(
+                title, 
+                content, 
+                content=document_content, 
+                content_rowid=id,
+                tokenize='porter ascii'
+            )
+        "#;
+        self.db.execute(fts5_table_sql, &[])?;
+        
+        // Keep the external-content FTS5 index in step with document_content
+        let insert_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS document_content_fts_insert 
+            AFTER INSERT ON document_content 
+            BEGIN
+                INSERT INTO documents_fts(rowid, title, content) 
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END
+        "#;
+        self.db.execute(insert_trigger_sql, &[])?;
+        
+        let update_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS document_content_fts_update 
+            AFTER UPDATE OF title, content ON document_content 
+            BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content) 
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+                INSERT INTO documents_fts(rowid, title, content) 
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END
+        "#;
+        self.db.execute(update_trigger_sql, &[])?;
+        
+        let delete_trigger_sql = r#"
+            CREATE TRIGGER IF NOT EXISTS document_content_fts_delete 
+            AFTER DELETE ON document_content 
+            BEGIN
+                INSERT INTO documents_fts(documents_fts, rowid, title, content) 
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+            END
+        "#;
+        self.db.execute(delete_trigger_sql, &[])?;
+        
+        let index_sql = r#"
+            CREATE INDEX IF NOT EXISTS idx_document_content_document_id 
+            ON document_content(document_id)
+        "#;
+        self.db.execute(index_sql, &[])?;
+        
+        Ok(())
+    }
+
+    /// Migration to version 11: Create database_settings and apply search index settings
+    /// The default tokenizer folds accents and splits Unicode words, replacing the
+    /// ASCII-only tokenizer used by earlier versions, so documents_fts is rebuilt.
+    fn migrate_to_v11(&self) -> Result<(), String> {
+        let settings_sql = r#"
+            CREATE TABLE IF NOT EXISTS database_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )
+        "#;
+        self.db.execute(settings_sql, &[])?;
+        
+        SearchIndexManager::new(self.db).ensure_current()?;
+        Ok(())
+    }
+
+    /// Migration to version 12: Create term dictionaries for "did you mean" suggestions
+    /// document_terms and ai_block_terms index words as typed (no stemming) without
+    /// positions; the fts5vocab tables over them list each term with its document count.
+    fn migrate_to_v12(&self) -> Result<(), String> {
+        let sql = r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS document_terms
+            USING fts5(
+                title,
+                content,
+                content=document_content,
+                content_rowid=id,
+                tokenize='unicode61 remove_diacritics 2',
+                detail=none
+            );
+            
+            CREATE TRIGGER IF NOT EXISTS document_content_terms_insert
+            AFTER INSERT ON document_content
+            BEGIN
+                INSERT INTO document_terms(rowid, title, content)
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS document_content_terms_update
+            AFTER UPDATE OF title, content ON document_content
+            BEGIN
+                INSERT INTO document_terms(document_terms, rowid, title, content)
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+                INSERT INTO document_terms(rowid, title, content)
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS document_content_terms_delete
+            AFTER DELETE ON document_content
+            BEGIN
+                INSERT INTO document_terms(document_terms, rowid, title, content)
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+            END;
+            
+            INSERT INTO document_terms(document_terms) VALUES('rebuild');
+            
+            CREATE VIRTUAL TABLE IF NOT EXISTS document_terms_vocab
+            USING fts5vocab(document_terms, 'row');
+            
+            CREATE VIRTUAL TABLE IF NOT EXISTS ai_block_terms
+            USING fts5(
+                name,
+                description,
+                tags,
+                content=ai_blocks,
+                tokenize='unicode61 remove_diacritics 2',
+                detail=none
+            );
+            
+            CREATE TRIGGER IF NOT EXISTS ai_blocks_terms_insert
+            AFTER INSERT ON ai_blocks
+            BEGIN
+                INSERT INTO ai_block_terms(rowid, name, description, tags)
+                VALUES (NEW.rowid, NEW.name, NEW.description, NEW.tags);
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS ai_blocks_terms_update
+            AFTER UPDATE OF name, description, tags ON ai_blocks
+            BEGIN
+                INSERT INTO ai_block_terms(ai_block_terms, rowid, name, description, tags)
+                VALUES ('delete', OLD.rowid, OLD.name, OLD.description, OLD.tags);
+                INSERT INTO ai_block_terms(rowid, name, description, tags)
+                VALUES (NEW.rowid, NEW.name, NEW.description, NEW.tags);
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS ai_blocks_terms_delete
+            AFTER DELETE ON ai_blocks
+            BEGIN
+                INSERT INTO ai_block_terms(ai_block_terms, rowid, name, description, tags)
+                VALUES ('delete', OLD.rowid, OLD.name, OLD.description, OLD.tags);
+            END;
+            
+            INSERT INTO ai_block_terms(ai_block_terms) VALUES('rebuild');
+            
+            CREATE VIRTUAL TABLE IF NOT EXISTS ai_block_terms_vocab
+            USING fts5vocab(ai_block_terms, 'row');
+        "#;
+        
+        self.db.with_connection(|conn| conn.execute_batch(sql))?;
+        Ok(())
+    }
+
+    /// Migration to version 13: Create rag_eval_reports
+    /// One row per evaluation run of a golden set with one config; the aggregate
+    /// metrics are columns so runs can be listed and compared without parsing the report.
+    fn migrate_to_v13(&self) -> Result<(), String> {
+        let sql = r#"
+            CREATE TABLE IF NOT EXISTS rag_eval_reports (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                project_id TEXT NOT NULL,
+                golden_set TEXT NOT NULL,
+                label TEXT NOT NULL,
+                k INTEGER NOT NULL,
+                recall_at_k REAL NOT NULL,
+                mrr REAL NOT NULL,
+                ndcg_at_k REAL NOT NULL,
+                report TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_rag_eval_reports_project
+            ON rag_eval_reports(project_id, golden_set, created_at);
+        "#;
+        
+        self.db.with_connection(|conn| conn.execute_batch(sql))?;
+        Ok(())
+    }
+
+    /// Migration to version 14: Create project_settings
+    /// Per-project counterpart of database_settings: a value stored here overrides the
+    /// database-wide setting of the same key for that project.
+    fn migrate_to_v14(&self) -> Result<(), String> {
+        let sql = r#"
+            CREATE TABLE IF NOT EXISTS project_settings (
+                project_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                PRIMARY KEY (project_id, key),
+                FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+            )
+        "#;
+        self.db.execute(sql, &[])?;
+        Ok(())
+    }
+
+    /// Migration to version 15: Create vector_index_generations
+    /// Per-model change counter bumped by triggers on every vector_index write. Persisted
+    /// HNSW and flat store files record it, so updates within the same second as the
+    /// build still mark them stale.
+    fn migrate_to_v15(&self) -> Result<(), String> {
+        let sql = r#"
+            CREATE TABLE IF NOT EXISTS vector_index_generations (
+                embedding_model TEXT PRIMARY KEY,
+                generation INTEGER NOT NULL DEFAULT 0
+            );
+            
+            INSERT OR IGNORE INTO vector_index_generations (embedding_model, generation)
+            SELECT DISTINCT embedding_model, 1 FROM vector_index;
+            
+            CREATE TRIGGER IF NOT EXISTS vector_index_generation_insert
+            AFTER INSERT ON vector_index BEGIN
+                INSERT INTO vector_index_generations (embedding_model, generation)
+                VALUES (NEW.embedding_model, 1)
+                ON CONFLICT(embedding_model) DO UPDATE SET generation = generation + 1;
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS vector_index_generation_update
+            AFTER UPDATE ON vector_index BEGIN
+                INSERT INTO vector_index_generations (embedding_model, generation)
+                VALUES (OLD.embedding_model, 1)
+                ON CONFLICT(embedding_model) DO UPDATE SET generation = generation + 1;
+                INSERT INTO vector_index_generations (embedding_model, generation)
+                VALUES (NEW.embedding_model, 1)
+                ON CONFLICT(embedding_model) DO UPDATE SET generation = generation + 1;
+            END;
+            
+            CREATE TRIGGER IF NOT EXISTS vector_index_generation_delete
+            AFTER DELETE ON vector_index BEGIN
+                INSERT INTO vector_index_generations (embedding_model, generation)
+                VALUES (OLD.embedding_model, 1)
+                ON CONFLICT(embedding_model) DO UPDATE SET generation = generation + 1;
+            END;
+        "#;
+        
+        self.db.with_connection(|conn| conn.execute_batch(sql))?;
+        Ok(())
+    }
+
+    /// Convert stored vectors to the layout of the saved vector storage settings
+    pub fn convert_vector_storage(&self) -> Result<VectorStorageReport, String> {
+        let settings = match SettingsRepository::new(self.db).get(VECTOR_STORAGE_SETTINGS_KEY)? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid vector storage settings: {}", e))?,
+            None => VectorStorageSettings::default(),
+        };
+        self.convert_vector_storage_with(&settings)
+    }
+
+    /// Convert rows not using the given layout: JSON embedding text becomes an f32 BLOB,
+    /// and vector_index rows gain or drop their f32, int8 and binary columns to match
+    /// the settings. A dropped f32 vector cannot be restored: such rows keep their
+    /// existing encodings until they are re-embedded.
+    /// Safe to run repeatedly; rows already in the layout are left untouched.
+    pub fn convert_vector_storage_with(&self, settings: &VectorStorageSettings) -> Result<VectorStorageReport, String> {
+        let mut report = VectorStorageReport {
+            bytes_before: self.embedding_storage_bytes()?,
+            ..Default::default()
+        };
+        
+        self.db.with_connection(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            
+            {
+                let mut select = tx.prepare(
+                    "SELECT id, embedding_vector FROM document_embeddings WHERE embedding_blob IS NULL",
+                )?;
+                let mut update = tx.prepare(
+                    "UPDATE document_embeddings SET embedding_blob = ?1, embedding_vector = '' WHERE id = ?2",
+                )?;
+                
+                let rows = select
+                    .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                
+                for (id, json) in rows {
+                    let vector: Vec<f32> = serde_json::from_str(&json).map_err(|_| {
+                        rusqlite::Error::InvalidColumnType(1, "embedding_vector".to_string(), rusqlite::types::Type::Text)
+                    })?;
+                    let blob: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+                    update.execute(params![blob, id])?;
+                    report.embeddings_converted += 1;
+                }
+            }
+            
+            {
+                let mut select = tx.prepare(
+                    "SELECT id, embedding, embedding_int8, embedding_binary, dimension FROM vector_index",
+                )?;
+                let mut update = tx.prepare(
+                    "UPDATE vector_index SET embedding = ?1, embedding_int8 = ?2, embedding_binary = ?3 WHERE id = ?4",
+                )?;
+                
+                type StoredRow = (i64, Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>, i32);
+                let rows = select
+                    .query_map([], |row| -> rusqlite::Result<StoredRow> {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                
+                for (id, full, int8, binary, dimension) in rows {
+                    let vector = decode_stored_vector(&full, int8.as_deref(), binary.as_deref(), dimension.max(0) as usize)
+                        .map_err(|_| {
+                            rusqlite::Error::InvalidColumnType(1, "embedding".to_string(), rusqlite::types::Type::Blob)
+                        })?;
+                    let (mut target_full, mut target_int8, mut target_binary) = settings.encode(&vector);
+                    if full.is_empty() {
+                        // Never store a decoded approximation as if it were full precision,
+                        // and keep the existing encodings if the settings leave nothing else
+                        target_full.clear();
+                        if target_int8.is_none() && target_binary.is_none() {
+                            target_int8 = int8.clone();
+                            target_binary = binary.clone();
+                        }
+                    }
+                    
+                    if target_full != full || target_int8 != int8 || target_binary != binary {
+                        update.execute(params![target_full, target_int8, target_binary, id])?;
+                        report.vectors_converted += 1;
+                    }
+                }
+            }
+            
+            tx.commit()
+        })?;
+        
+        report.bytes_after = self.embedding_storage_bytes()?;
+        Ok(report)
+    }
+
+    /// Total bytes held by embedding columns across both vector tables
+    fn embedding_storage_bytes(&self) -> Result<i64, String> {
+        let sql = r#"
+            SELECT
+                (SELECT COALESCE(SUM(LENGTH(embedding_vector) + COALESCE(LENGTH(embedding_blob), 0)), 0)
+                 FROM document_embeddings)
+              + (SELECT COALESCE(SUM(LENGTH(embedding) + COALESCE(LENGTH(embedding_int8), 0)
+                                     + COALESCE(LENGTH(embedding_binary), 0)), 0)
+                 FROM vector_index)
+        "#;
+        
+        self.db.query_row(sql, &[], |row| row.get(0))
+    }
+    
     /// Insert default system AI blocks for common use cases
     fn insert_default_ai_blocks(&self) -> Result<(), String> {
         let now = std::time::SystemTime::now()
@@ -779,7 +1369,7 @@ impl<'a> MigrationManager<'a> {
     pub fn needs_migration(&self) -> Result<bool, String> {
         let current_version = self.get_current_version()?;
         // Update this when adding new migrations
-        const LATEST_VERSION: i32 = 5;
+        const LATEST_VERSION: i32 = 15;
         Ok(current_version < LATEST_VERSION)
     }
 
@@ -7,6 +7,16 @@ pub mod migrations;
 pub mod query_optimizer;
 pub mod connection_manager;
 pub mod ai_blocks_repository;
+pub mod hnsw_index;
+pub mod vector_quantization;
+pub mod vector_math;
+pub mod flat_vector_store;
+pub mod search_query;
+pub mod search_index;
+pub mod term_dictionary;
+pub mod match_highlight;
+pub mod rag_eval_repository;
+pub mod settings_repository;
 
 // Re-export commonly used types
 pub use connection::*;
@@ -15,3 +25,13 @@ pub use migrations::*;
 pub use connection_manager::*;
 pub use query_optimizer::*;
 pub use ai_blocks_repository::*;
+pub use hnsw_index::*;
+pub use vector_quantization::*;
+pub use vector_math::*;
+pub use flat_vector_store::*;
+pub use search_query::*;
+pub use search_index::*;
+pub use term_dictionary::*;
+pub use match_highlight::*;
+pub use rag_eval_repository::*;
+pub use settings_repository::*;
@@ -245,7 +245,7 @@ impl<'a> QueryOptimizer<'a> {
         let rag_queries = vec![
             // FTS5 search queries
             (
-                "SELECT d.id, d.title, d.content, bm25(documents_fts) as rank FROM documents_fts JOIN documents d ON documents_fts.rowid = d.id WHERE documents_fts MATCH ? ORDER BY rank",
+                "SELECT dc.id, dc.title, dc.content, bm25(documents_fts) as rank FROM documents_fts JOIN document_content dc ON documents_fts.rowid = dc.id WHERE documents_fts MATCH ? ORDER BY rank",
                 vec!["test query" as &dyn rusqlite::ToSql],
                 "FTS5 full-text search"
             ),
@@ -0,0 +1,122 @@
+// RAG Evaluation Report Repository
+// Stores the reports of retrieval evaluation runs so runs of the same golden set
+// can be listed and compared over time. The full report is kept as JSON; the
+// aggregate metrics are columns for listing.
+
+use crate::infrastructure::db_layer::DatabaseConnection;
+use rusqlite::{params, Row};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A stored evaluation run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredEvalReport {
+    /// Row id; ignored by `insert`
+    pub id: i64,
+    pub project_id: String,
+    /// Name of the golden set that was run
+    pub golden_set: String,
+    /// Label of the config variant that was run
+    pub label: String,
+    /// Rank cutoff of the @k metrics
+    pub k: usize,
+    pub recall_at_k: f64,
+    pub mrr: f64,
+    pub ndcg_at_k: f64,
+    /// Full report as JSON
+    pub report: String,
+    pub created_at: u64,
+}
+
+/// Repository for RAG evaluation reports
+pub struct RagEvalRepository<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> RagEvalRepository<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Store a report, returning its id. `created_at` defaults to now when 0.
+    pub fn insert(&self, report: &StoredEvalReport) -> Result<i64, String> {
+        let created_at = if report.created_at > 0 {
+            report.created_at as i64
+        } else {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64
+        };
+
+        let sql = r#"
+            INSERT INTO rag_eval_reports (
+                project_id, golden_set, label, k, recall_at_k, mrr, ndcg_at_k, report, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#;
+
+        self.db.with_connection(|conn| {
+            conn.execute(
+                sql,
+                params![
+                    report.project_id,
+                    report.golden_set,
+                    report.label,
+                    report.k as i64,
+                    report.recall_at_k,
+                    report.mrr,
+                    report.ndcg_at_k,
+                    report.report,
+                    created_at
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    /// Get a report by id
+    pub fn get(&self, id: i64) -> Result<Option<StoredEvalReport>, String> {
+        let sql = r#"
+            SELECT id, project_id, golden_set, label, k, recall_at_k, mrr, ndcg_at_k, report, created_at
+            FROM rag_eval_reports
+            WHERE id = ?1
+        "#;
+
+        let rows = self.db.query_map(sql, params![id], Self::map_row)?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// Reports of a project, newest first, optionally only those of one golden set
+    pub fn find_by_project(&self, project_id: &str, golden_set: Option<&str>, limit: usize) -> Result<Vec<StoredEvalReport>, String> {
+        let sql = r#"
+            SELECT id, project_id, golden_set, label, k, recall_at_k, mrr, ndcg_at_k, report, created_at
+            FROM rag_eval_reports
+            WHERE project_id = ?1 AND (?2 IS NULL OR golden_set = ?2)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?3
+        "#;
+
+        self.db.query_map(sql, params![project_id, golden_set, limit as i64], Self::map_row)
+    }
+
+    /// Delete a report; returns whether a row was deleted
+    pub fn delete(&self, id: i64) -> Result<bool, String> {
+        let rows_affected = self.db.execute("DELETE FROM rag_eval_reports WHERE id = ?1", params![id])?;
+        Ok(rows_affected > 0)
+    }
+
+    fn map_row(row: &Row<'_>) -> rusqlite::Result<StoredEvalReport> {
+        Ok(StoredEvalReport {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            golden_set: row.get(2)?,
+            label: row.get(3)?,
+            k: row.get::<_, i64>(4)? as usize,
+            recall_at_k: row.get(5)?,
+            mrr: row.get(6)?,
+            ndcg_at_k: row.get(7)?,
+            report: row.get(8)?,
+            created_at: row.get::<_, i64>(9)? as u64,
+        })
+    }
+}
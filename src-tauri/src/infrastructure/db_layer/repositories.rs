@@ -3,12 +3,26 @@
 
 use crate::core::{Project, Document, DocumentState};
 use crate::infrastructure::db_layer::DatabaseConnection;
+use crate::infrastructure::db_layer::flat_vector_store::FlatVectorStore;
+use crate::infrastructure::db_layer::hnsw_index::{HnswConfig, HnswIndex};
+use crate::infrastructure::db_layer::match_highlight::{locate_matches, DocumentMatches, MATCH_END, MATCH_START};
+use crate::infrastructure::db_layer::vector_math::{VectorSearchHit, VectorSourceStamp};
+use crate::infrastructure::db_layer::search_index::{SearchIndexManager, TRIGRAM_TABLE};
+use crate::infrastructure::db_layer::search_query::{escape_fts5_string, SearchQuery};
+use crate::infrastructure::db_layer::term_dictionary::{TermDictionary, TermSource};
+use crate::infrastructure::db_layer::vector_quantization::{
+    decode_stored_vector, quantize, quantized_similarity, QuantizationKind, VectorStorageSettings, VECTOR_STORAGE_SETTINGS_KEY,
+};
+use crate::infrastructure::db_layer::settings_repository::SettingsRepository;
+use crate::infrastructure::db_layer::migrations::{MigrationManager, VectorStorageReport};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashSet;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use sha2::{Sha256, Digest};
+use tracing::{info, warn};
 
 /// Document embedding representation for vector storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +100,241 @@ pub struct VectorIndex {
     pub updated_at: u64,
 }
 
+/// Strategy used by vector similarity search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum VectorSearchMode {
+    /// Brute-force cosine similarity over every stored vector
+    #[default]
+    Exact,
+    /// HNSW approximate nearest neighbour search
+    Approximate,
+    /// Scan int8-quantized vectors, then rescore the best candidates at full precision
+    Int8Rescored,
+    /// Scan binary-quantized vectors by Hamming distance, then rescore at full precision
+    BinaryRescored,
+    /// Exact search over the per-model memory-mapped flat vector store
+    Flat,
+}
+
+/// Options controlling the recall/speed trade-off of vector similarity search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorSearchOptions {
+    pub mode: VectorSearchMode,
+    /// HNSW candidate list size; higher values improve recall at the cost of speed
+    pub ef_search: Option<usize>,
+    /// Restrict the search to one embedding model (required for approximate search)
+    pub embedding_model: Option<String>,
+    /// Number of quantized candidates rescored with full-precision vectors
+    pub rescore_candidates: Option<usize>,
+    /// Approximate searches over models with fewer vectors than this use the
+    /// exact flat store instead of HNSW
+    pub flat_fallback_below: Option<usize>,
+    /// Restrict the search to documents matching this filter
+    pub filter: Option<RetrievalFilter>,
+}
+
+/// Filtered approximate searches fall back to an exact scan when fewer than
+/// one in this many vectors match the filter
+const SELECTIVE_FILTER_RATIO: usize = 10;
+
+/// Document metadata filter applied during retrieval.
+/// Each non-empty criterion must match; values within a criterion are alternatives.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalFilter {
+    /// Only documents belonging to one of these projects
+    #[serde(default)]
+    pub project_ids: Vec<String>,
+    /// Only documents in one of these lifecycle states
+    #[serde(default, with = "document_state_names")]
+    pub states: Vec<DocumentState>,
+    /// Only documents whose project-relative path starts with one of these prefixes
+    #[serde(default)]
+    pub path_prefixes: Vec<String>,
+    /// Only documents updated at or after this Unix timestamp (seconds)
+    pub updated_after: Option<u64>,
+    /// Only documents updated at or before this Unix timestamp (seconds)
+    pub updated_before: Option<u64>,
+}
+
+impl RetrievalFilter {
+    /// Filter restricted to a single project
+    pub fn for_project(project_id: &str) -> Self {
+        Self {
+            project_ids: vec![project_id.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Whether the filter matches every document
+    pub fn is_empty(&self) -> bool {
+        self.project_ids.is_empty()
+            && self.states.is_empty()
+            && self.path_prefixes.is_empty()
+            && self.updated_after.is_none()
+            && self.updated_before.is_none()
+    }
+
+    /// Filter matching the documents that match both filters, or None if no
+    /// document can match both (e.g. disjoint project lists)
+    pub fn intersect(&self, other: &RetrievalFilter) -> Option<RetrievalFilter> {
+        fn common<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Option<Vec<T>> {
+            if a.is_empty() || b.is_empty() {
+                return Some([a, b].concat());
+            }
+            let values: Vec<T> = a.iter().filter(|value| b.contains(value)).cloned().collect();
+            (!values.is_empty()).then_some(values)
+        }
+
+        // A path under a prefix of each filter is under the longer of two nested prefixes
+        let path_prefixes = if self.path_prefixes.is_empty() || other.path_prefixes.is_empty() {
+            [self.path_prefixes.as_slice(), other.path_prefixes.as_slice()].concat()
+        } else {
+            let mut prefixes: Vec<String> = Vec::new();
+            for a in &self.path_prefixes {
+                for b in &other.path_prefixes {
+                    let longer = if a.starts_with(b.as_str()) {
+                        a
+                    } else if b.starts_with(a.as_str()) {
+                        b
+                    } else {
+                        continue;
+                    };
+                    if !prefixes.contains(longer) {
+                        prefixes.push(longer.clone());
+                    }
+                }
+            }
+            if prefixes.is_empty() {
+                return None;
+            }
+            prefixes
+        };
+
+        let updated_after = self.updated_after.max(other.updated_after);
+        let updated_before = match (self.updated_before, other.updated_before) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if let (Some(after), Some(before)) = (updated_after, updated_before) {
+            if after > before {
+                return None;
+            }
+        }
+
+        Some(RetrievalFilter {
+            project_ids: common(&self.project_ids, &other.project_ids)?,
+            states: common(&self.states, &other.states)?,
+            path_prefixes,
+            updated_after,
+            updated_before,
+        })
+    }
+
+    /// SQL conditions over the documents table aliased as `alias`, each prefixed
+    /// with AND, using numbered parameters from `?{first_param}` onwards.
+    /// Returns the SQL fragment and its parameter values in order.
+    pub fn sql_conditions(&self, alias: &str, first_param: usize) -> (String, Vec<rusqlite::types::Value>) {
+        use rusqlite::types::Value;
+
+        let mut sql = String::new();
+        let mut values: Vec<Value> = Vec::new();
+        let placeholder = |value: Value, values: &mut Vec<Value>| {
+            values.push(value);
+            format!("?{}", first_param + values.len() - 1)
+        };
+
+        if !self.project_ids.is_empty() {
+            let placeholders: Vec<String> = self.project_ids
+                .iter()
+                .map(|id| placeholder(Value::Text(id.clone()), &mut values))
+                .collect();
+            sql.push_str(&format!(" AND {}.project_id IN ({})", alias, placeholders.join(", ")));
+        }
+
+        if !self.states.is_empty() {
+            let placeholders: Vec<String> = self.states
+                .iter()
+                .map(|state| placeholder(Value::Text(state.as_str().to_string()), &mut values))
+                .collect();
+            sql.push_str(&format!(" AND {}.state IN ({})", alias, placeholders.join(", ")));
+        }
+
+        if !self.path_prefixes.is_empty() {
+            // substr comparison avoids LIKE wildcards in user-supplied paths
+            let conditions: Vec<String> = self.path_prefixes
+                .iter()
+                .map(|prefix| {
+                    let p = placeholder(Value::Text(prefix.clone()), &mut values);
+                    format!("substr({alias}.path, 1, length({p})) = {p}", alias = alias, p = p)
+                })
+                .collect();
+            sql.push_str(&format!(" AND ({})", conditions.join(" OR ")));
+        }
+
+        if let Some(after) = self.updated_after {
+            let p = placeholder(Value::Integer(after as i64), &mut values);
+            sql.push_str(&format!(" AND {}.updated_at >= {}", alias, p));
+        }
+
+        if let Some(before) = self.updated_before {
+            let p = placeholder(Value::Integer(before as i64), &mut values);
+            sql.push_str(&format!(" AND {}.updated_at <= {}", alias, p));
+        }
+
+        (sql, values)
+    }
+}
+
+/// Serialize document states by their database names ("memo", "prd", ...)
+mod document_state_names {
+    use crate::core::DocumentState;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(states: &[DocumentState], serializer: S) -> Result<S::Ok, S::Error> {
+        states.iter().map(|state| state.as_str()).collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<DocumentState>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|name| {
+                DocumentState::from_str(name)
+                    .ok_or_else(|| serde::de::Error::custom(format!("Invalid document state: {}", name)))
+            })
+            .collect()
+    }
+}
+
+/// Indexed title and markdown body of a document, as last synced from disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentContent {
+    /// Document key: the integer id used as the FTS5 rowid
+    pub id: i64,
+    /// documents.id of the owning document
+    pub document_id: String,
+    pub title: String,
+    pub content: String,
+    pub content_hash: String,
+    /// Modification time of the file the content was read from
+    pub file_modified_at: Option<u64>,
+    pub updated_at: u64,
+}
+
+/// Passage (document chunk) matched by chunk-level vector search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassageMatch {
+    /// document_embeddings.id of the chunk
+    pub embedding_id: i64,
+    pub document_id: i64,
+    pub chunk_index: i32,
+    /// Byte offset of the passage in the source document
+    pub byte_start: Option<i32>,
+    /// Byte offset one past the end of the passage
+    pub byte_end: Option<i32>,
+    pub text: String,
+    pub similarity: f32,
+}
+
 /// FTS5 search result with relevance ranking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FTS5SearchResult {
@@ -96,6 +345,14 @@ pub struct FTS5SearchResult {
     pub snippet: Option<String>, // Highlighted snippet
 }
 
+/// Full-text search results, retried with a spelling correction when the query found nothing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrectedSearchResults {
+    pub results: Vec<FTS5SearchResult>,
+    /// Corrected query in search box syntax; when set, `results` are its matches
+    pub suggested_query: Option<String>,
+}
+
 /// Repository for Project entities
 pub struct ProjectRepository<'a> {
     db: &'a DatabaseConnection,
@@ -536,17 +793,18 @@ impl<'a> EmbeddingRepository<'a> {
 
     /// Create or update document embedding
     pub fn upsert_embedding(&self, embedding: &DocumentEmbedding) -> Result<i64, String> {
-        let embedding_vector_json = serde_json::to_string(&embedding.embedding_vector)
-            .map_err(|e| format!("Failed to serialize embedding vector: {}", e))?;
+        // Vectors are stored as f32 BLOBs; the legacy JSON column is left empty
+        let embedding_blob = VectorIndexRepository::new(self.db).embedding_to_blob(&embedding.embedding_vector)?;
         
         let sql = r#"
             INSERT INTO document_embeddings (
                 document_id, chunk_index, content_hash, content_text, 
-                embedding_vector, embedding_model, chunk_start, chunk_end
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                embedding_vector, embedding_blob, embedding_model, chunk_start, chunk_end
+            ) VALUES (?1, ?2, ?3, ?4, '', ?5, ?6, ?7, ?8)
             ON CONFLICT(document_id, chunk_index, content_hash) DO UPDATE SET
                 content_text = excluded.content_text,
                 embedding_vector = excluded.embedding_vector,
+                embedding_blob = excluded.embedding_blob,
                 embedding_model = excluded.embedding_model,
                 chunk_start = excluded.chunk_start,
                 chunk_end = excluded.chunk_end,
@@ -560,7 +818,7 @@ impl<'a> EmbeddingRepository<'a> {
                 embedding.chunk_index,
                 &embedding.content_hash,
                 &embedding.content_text,
-                &embedding_vector_json,
+                &embedding_blob,
                 &embedding.embedding_model,
                 embedding.chunk_start,
                 embedding.chunk_end
@@ -580,16 +838,24 @@ impl<'a> EmbeddingRepository<'a> {
         let sql = r#"
             SELECT id, document_id, chunk_index, content_hash, content_text,
                    embedding_vector, embedding_model, chunk_start, chunk_end,
-                   created_at, updated_at
+                   created_at, updated_at, embedding_blob
             FROM document_embeddings
             WHERE document_id = ?1
             ORDER BY chunk_index
         "#;
         
+        let vector_repo = VectorIndexRepository::new(self.db);
         self.db.query_map(sql, params![document_id], |row| {
-            let embedding_vector_json: String = row.get(5)?;
-            let embedding_vector: Vec<f32> = serde_json::from_str(&embedding_vector_json)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(5, "embedding_vector".to_string(), rusqlite::types::Type::Text))?;
+            // Prefer the BLOB column; rows written before migration v8 only have JSON
+            let embedding_vector: Vec<f32> = match row.get::<_, Option<Vec<u8>>>(11)? {
+                Some(blob) => vector_repo.blob_to_embedding(&blob)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(11, "embedding_blob".to_string(), rusqlite::types::Type::Blob))?,
+                None => {
+                    let embedding_vector_json: String = row.get(5)?;
+                    serde_json::from_str(&embedding_vector_json)
+                        .map_err(|_| rusqlite::Error::InvalidColumnType(5, "embedding_vector".to_string(), rusqlite::types::Type::Text))?
+                }
+            };
             
             Ok(DocumentEmbedding {
                 id: Some(row.get(0)?),
@@ -788,15 +1054,35 @@ impl<'a> VectorIndexRepository<'a> {
         Self { db }
     }
 
+    /// Stored vector storage settings, or the defaults (full precision only) if none were saved
+    pub fn storage_settings(&self) -> Result<VectorStorageSettings, String> {
+        match SettingsRepository::new(self.db).get(VECTOR_STORAGE_SETTINGS_KEY)? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid vector storage settings: {}", e)),
+            None => Ok(VectorStorageSettings::default()),
+        }
+    }
+
+    /// Save vector storage settings and rewrite existing rows to the new layout
+    pub fn apply_storage_settings(&self, settings: &VectorStorageSettings) -> Result<VectorStorageReport, String> {
+        let json = serde_json::to_string(settings)
+            .map_err(|e| format!("Failed to serialize vector storage settings: {}", e))?;
+        SettingsRepository::new(self.db).set(VECTOR_STORAGE_SETTINGS_KEY, &json)?;
+
+        MigrationManager::new(self.db).convert_vector_storage()
+    }
+
     /// Insert a new vector index entry
     pub fn insert(&self, vector_index: &VectorIndex) -> Result<i64, String> {
-        // Convert embedding vector to binary format for BLOB storage
-        let embedding_blob = self.embedding_to_blob(&vector_index.embedding)?;
+        // Encode the embedding in the columns the storage settings keep
+        let (embedding_blob, embedding_int8, embedding_binary) =
+            self.storage_settings()?.encode(&vector_index.embedding);
         
         let sql = r#"
             INSERT INTO vector_index (
-                document_id, embedding, embedding_model, dimension
-            ) VALUES (?1, ?2, ?3, ?4)
+                document_id, embedding, embedding_model, dimension,
+                embedding_int8, embedding_binary
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
         "#;
         
         self.db.execute(
@@ -805,7 +1091,9 @@ impl<'a> VectorIndexRepository<'a> {
                 vector_index.document_id,
                 &embedding_blob,
                 &vector_index.embedding_model,
-                vector_index.dimension
+                vector_index.dimension,
+                &embedding_int8,
+                &embedding_binary
             ],
         )?;
         
@@ -814,6 +1102,8 @@ impl<'a> VectorIndexRepository<'a> {
         let id: i64 = self.db.query_row(id_sql, params![], |row| Ok(row.get(0)?))?
             .map_err(|e| format!("Failed to get vector index ID: {}", e))?;
         
+        self.sync_index_entry(id)?;
+        
         Ok(id)
     }
 
@@ -821,13 +1111,15 @@ impl<'a> VectorIndexRepository<'a> {
     pub fn update(&self, vector_index: &VectorIndex) -> Result<bool, String> {
         let id = vector_index.id.ok_or("Vector index ID is required for update")?;
         
-        // Convert embedding vector to binary format for BLOB storage
-        let embedding_blob = self.embedding_to_blob(&vector_index.embedding)?;
+        // Encode the embedding in the columns the storage settings keep
+        let (embedding_blob, embedding_int8, embedding_binary) =
+            self.storage_settings()?.encode(&vector_index.embedding);
         
         let sql = r#"
             UPDATE vector_index 
-            SET embedding = ?1, embedding_model = ?2, dimension = ?3
-            WHERE id = ?4
+            SET embedding = ?1, embedding_model = ?2, dimension = ?3,
+                embedding_int8 = ?4, embedding_binary = ?5
+            WHERE id = ?6
         "#;
         
         let rows_affected = self.db.execute(
@@ -836,25 +1128,35 @@ impl<'a> VectorIndexRepository<'a> {
                 &embedding_blob,
                 &vector_index.embedding_model,
                 vector_index.dimension,
+                &embedding_int8,
+                &embedding_binary,
                 id
             ],
         )?;
         
+        if rows_affected > 0 {
+            self.sync_index_entry(id)?;
+        }
+        
         Ok(rows_affected > 0)
     }
 
     /// Upsert (insert or update) vector index entry by document_id and model
     pub fn upsert(&self, vector_index: &VectorIndex) -> Result<i64, String> {
-        // Convert embedding vector to binary format for BLOB storage
-        let embedding_blob = self.embedding_to_blob(&vector_index.embedding)?;
+        // Encode the embedding in the columns the storage settings keep
+        let (embedding_blob, embedding_int8, embedding_binary) =
+            self.storage_settings()?.encode(&vector_index.embedding);
         
         let sql = r#"
             INSERT INTO vector_index (
-                document_id, embedding, embedding_model, dimension
-            ) VALUES (?1, ?2, ?3, ?4)
+                document_id, embedding, embedding_model, dimension,
+                embedding_int8, embedding_binary
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
             ON CONFLICT(document_id, embedding_model) DO UPDATE SET
                 embedding = excluded.embedding,
                 dimension = excluded.dimension,
+                embedding_int8 = excluded.embedding_int8,
+                embedding_binary = excluded.embedding_binary,
                 updated_at = strftime('%s', 'now')
         "#;
         
@@ -864,7 +1166,9 @@ impl<'a> VectorIndexRepository<'a> {
                 vector_index.document_id,
                 &embedding_blob,
                 &vector_index.embedding_model,
-                vector_index.dimension
+                vector_index.dimension,
+                &embedding_int8,
+                &embedding_binary
             ],
         )?;
         
@@ -873,33 +1177,42 @@ impl<'a> VectorIndexRepository<'a> {
         let id: i64 = self.db.query_row(id_sql, params![], |row| Ok(row.get(0)?))?
             .map_err(|e| format!("Failed to get vector index ID: {}", e))?;
         
+        // last_insert_rowid() is not updated when the conflict branch runs,
+        // so resolve the row through its unique key before syncing the ANN index
+        if let Some(stored) = self.get_by_document_and_model(vector_index.document_id, &vector_index.embedding_model)? {
+            if let Some(stored_id) = stored.id {
+                self.sync_index_entry(stored_id)?;
+                return Ok(stored_id);
+            }
+        }
+        
         Ok(id)
     }
 
+    /// Retrieve vector index entry by row id
+    pub fn get_by_id(&self, id: i64) -> Result<Option<VectorIndex>, String> {
+        let sql = r#"
+            SELECT id, document_id, embedding, embedding_model, dimension,
+                   created_at, updated_at, embedding_int8, embedding_binary
+            FROM vector_index
+            WHERE id = ?1
+        "#;
+        
+        let results = self.db.query_map(sql, params![id], Self::row_to_vector_index)?;
+        
+        Ok(results.into_iter().next())
+    }
+
     /// Retrieve vector index entry by document_id
     pub fn get_by_document_id(&self, document_id: i64) -> Result<Option<VectorIndex>, String> {
         let sql = r#"
             SELECT id, document_id, embedding, embedding_model, dimension,
-                   created_at, updated_at
+                   created_at, updated_at, embedding_int8, embedding_binary
             FROM vector_index
             WHERE document_id = ?1
         "#;
         
-        match self.db.query_row(sql, params![document_id], |row| {
-            let embedding_blob: Vec<u8> = row.get(2)?;
-            let embedding = self.blob_to_embedding(&embedding_blob)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(2, "embedding".to_string(), rusqlite::types::Type::Blob))?;
-            
-            Ok(VectorIndex {
-                id: Some(row.get(0)?),
-                document_id: row.get(1)?,
-                embedding,
-                embedding_model: row.get(3)?,
-                dimension: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        }) {
+        match self.db.query_row(sql, params![document_id], Self::row_to_vector_index) {
             Ok(vector_index) => Ok(Some(vector_index)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(format!("Database error: {}", e)),
@@ -910,26 +1223,12 @@ impl<'a> VectorIndexRepository<'a> {
     pub fn get_by_document_and_model(&self, document_id: i64, model: &str) -> Result<Option<VectorIndex>, String> {
         let sql = r#"
             SELECT id, document_id, embedding, embedding_model, dimension,
-                   created_at, updated_at
+                   created_at, updated_at, embedding_int8, embedding_binary
             FROM vector_index
             WHERE document_id = ?1 AND embedding_model = ?2
         "#;
         
-        match self.db.query_row(sql, params![document_id, model], |row| {
-            let embedding_blob: Vec<u8> = row.get(2)?;
-            let embedding = self.blob_to_embedding(&embedding_blob)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(2, "embedding".to_string(), rusqlite::types::Type::Blob))?;
-            
-            Ok(VectorIndex {
-                id: Some(row.get(0)?),
-                document_id: row.get(1)?,
-                embedding,
-                embedding_model: row.get(3)?,
-                dimension: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        }) {
+        match self.db.query_row(sql, params![document_id, model], Self::row_to_vector_index) {
             Ok(vector_index) => Ok(Some(vector_index)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(format!("Database error: {}", e)),
@@ -940,40 +1239,42 @@ impl<'a> VectorIndexRepository<'a> {
     pub fn get_by_model(&self, model: &str) -> Result<Vec<VectorIndex>, String> {
         let sql = r#"
             SELECT id, document_id, embedding, embedding_model, dimension,
-                   created_at, updated_at
+                   created_at, updated_at, embedding_int8, embedding_binary
             FROM vector_index
             WHERE embedding_model = ?1
             ORDER BY document_id
         "#;
         
-        self.db.query_map(sql, params![model], |row| {
-            let embedding_blob: Vec<u8> = row.get(2)?;
-            let embedding = self.blob_to_embedding(&embedding_blob)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(2, "embedding".to_string(), rusqlite::types::Type::Blob))?;
-            
-            Ok(VectorIndex {
-                id: Some(row.get(0)?),
-                document_id: row.get(1)?,
-                embedding,
-                embedding_model: row.get(3)?,
-                dimension: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })
+        self.db.query_map(sql, params![model], Self::row_to_vector_index)
     }
 
     /// Delete vector index entry by document_id
     pub fn delete_by_document_id(&self, document_id: i64) -> Result<usize, String> {
+        let removed_ids = self.db.query_map(
+            "SELECT id FROM vector_index WHERE document_id = ?1",
+            params![document_id],
+            |row| row.get::<_, i64>(0),
+        )?;
+        
         let sql = "DELETE FROM vector_index WHERE document_id = ?1";
         let rows_affected = self.db.execute(sql, params![document_id])?;
+        
+        self.remove_index_entries(&removed_ids)?;
         Ok(rows_affected)
     }
 
     /// Delete vector index entry by document_id and model
     pub fn delete_by_document_and_model(&self, document_id: i64, model: &str) -> Result<usize, String> {
+        let removed_ids = self.db.query_map(
+            "SELECT id FROM vector_index WHERE document_id = ?1 AND embedding_model = ?2",
+            params![document_id, model],
+            |row| row.get::<_, i64>(0),
+        )?;
+        
         let sql = "DELETE FROM vector_index WHERE document_id = ?1 AND embedding_model = ?2";
         let rows_affected = self.db.execute(sql, params![document_id, model])?;
+        
+        self.remove_index_entries(&removed_ids)?;
         Ok(rows_affected)
     }
 
@@ -981,25 +1282,73 @@ impl<'a> VectorIndexRepository<'a> {
     pub fn get_all(&self) -> Result<Vec<VectorIndex>, String> {
         let sql = r#"
             SELECT id, document_id, embedding, embedding_model, dimension,
-                   created_at, updated_at
+                   created_at, updated_at, embedding_int8, embedding_binary
             FROM vector_index
             ORDER BY document_id
         "#;
         
-        self.db.query_map(sql, params![], |row| {
-            let embedding_blob: Vec<u8> = row.get(2)?;
-            let embedding = self.blob_to_embedding(&embedding_blob)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(2, "embedding".to_string(), rusqlite::types::Type::Blob))?;
-            
-            Ok(VectorIndex {
-                id: Some(row.get(0)?),
-                document_id: row.get(1)?,
-                embedding,
-                embedding_model: row.get(3)?,
-                dimension: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
+        self.db.query_map(sql, params![], Self::row_to_vector_index)
+    }
+
+    /// Get the vectors of documents matching a filter, optionally for one model
+    pub fn get_filtered(&self, model: Option<&str>, filter: &RetrievalFilter) -> Result<Vec<VectorIndex>, String> {
+        let (conditions, values) = filter.sql_conditions("d", 2);
+        let sql = format!(r#"
+            SELECT v.id, v.document_id, v.embedding, v.embedding_model, v.dimension,
+                   v.created_at, v.updated_at, v.embedding_int8, v.embedding_binary
+            FROM vector_index v
+            JOIN document_content dc ON dc.id = v.document_id
+            JOIN documents d ON d.id = dc.document_id
+            WHERE (?1 IS NULL OR v.embedding_model = ?1){}
+            ORDER BY v.document_id
+        "#, conditions);
+
+        let model_value = rusqlite::types::Value::from(model.map(str::to_string));
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&model_value];
+        query_params.extend(values.iter().map(|value| value as &dyn rusqlite::ToSql));
+
+        self.db.query_map(&sql, &query_params, Self::row_to_vector_index)
+    }
+
+    /// Ids of vector_index rows whose documents match a filter
+    fn filtered_vector_ids(&self, model: Option<&str>, filter: &RetrievalFilter) -> Result<HashSet<i64>, String> {
+        let (conditions, values) = filter.sql_conditions("d", 2);
+        let sql = format!(r#"
+            SELECT v.id
+            FROM vector_index v
+            JOIN document_content dc ON dc.id = v.document_id
+            JOIN documents d ON d.id = dc.document_id
+            WHERE (?1 IS NULL OR v.embedding_model = ?1){}
+        "#, conditions);
+
+        let model_value = rusqlite::types::Value::from(model.map(str::to_string));
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&model_value];
+        query_params.extend(values.iter().map(|value| value as &dyn rusqlite::ToSql));
+
+        let ids = self.db.query_map(&sql, &query_params, |row| row.get::<_, i64>(0))?;
+        Ok(ids.into_iter().collect())
+    }
+
+    /// Map a vector_index row selected as id, document_id, embedding, embedding_model,
+    /// dimension, created_at, updated_at, embedding_int8, embedding_binary
+    fn row_to_vector_index(row: &rusqlite::Row) -> rusqlite::Result<VectorIndex> {
+        let dimension: i32 = row.get(4)?;
+        let embedding = decode_stored_vector(
+            &row.get::<_, Vec<u8>>(2)?,
+            row.get::<_, Option<Vec<u8>>>(7)?.as_deref(),
+            row.get::<_, Option<Vec<u8>>>(8)?.as_deref(),
+            dimension.max(0) as usize,
+        )
+        .map_err(|_| rusqlite::Error::InvalidColumnType(2, "embedding".to_string(), rusqlite::types::Type::Blob))?;
+
+        Ok(VectorIndex {
+            id: Some(row.get(0)?),
+            document_id: row.get(1)?,
+            embedding,
+            embedding_model: row.get(3)?,
+            dimension,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
         })
     }
 
@@ -1049,11 +1398,484 @@ impl<'a> VectorIndexRepository<'a> {
     /// Find similar vectors using cosine similarity
     pub fn find_similar(&self, query_embedding: &[f32], limit: Option<i32>, threshold: Option<f32>) -> Result<Vec<(VectorIndex, f32)>, String> {
         let all_vectors = self.get_all()?;
+        Self::rank_exact(all_vectors, query_embedding, limit, threshold)
+    }
+
+    /// Find similar vectors with an explicit search mode.
+    /// Approximate search uses the per-model HNSW index (or the flat store for models
+    /// below `flat_fallback_below` vectors); without an embedding model every mode
+    /// falls back to exact search. When no limit is given, approximate search returns
+    /// at most `ef_search` results. A filter restricts every mode to vectors of
+    /// matching documents.
+    pub fn find_similar_with_options(
+        &self,
+        query_embedding: &[f32],
+        limit: Option<i32>,
+        threshold: Option<f32>,
+        options: &VectorSearchOptions,
+    ) -> Result<Vec<(VectorIndex, f32)>, String> {
+        let filter = options.filter.as_ref().filter(|filter| !filter.is_empty());
+
+        let model = match (options.mode, options.embedding_model.as_deref()) {
+            (VectorSearchMode::Int8Rescored, _) => {
+                return self.find_similar_quantized(query_embedding, limit, threshold, QuantizationKind::Int8, options);
+            }
+            (VectorSearchMode::BinaryRescored, _) => {
+                return self.find_similar_quantized(query_embedding, limit, threshold, QuantizationKind::Binary, options);
+            }
+            (VectorSearchMode::Flat, Some(model)) => {
+                return self.find_similar_flat(query_embedding, limit, threshold, model, filter);
+            }
+            (VectorSearchMode::Approximate, Some(model)) => {
+                if let Some(minimum) = options.flat_fallback_below {
+                    if (self.vector_source_stamp(model)?.row_count as usize) < minimum {
+                        return self.find_similar_flat(query_embedding, limit, threshold, model, filter);
+                    }
+                }
+                model
+            }
+            (_, model) => {
+                let vectors = match (filter, model) {
+                    (Some(filter), model) => self.get_filtered(model, filter)?,
+                    (None, Some(model)) => self.get_by_model(model)?,
+                    (None, None) => self.get_all()?,
+                };
+                return Self::rank_exact(vectors, query_embedding, limit, threshold);
+            }
+        };
+
+        let allowed = match filter {
+            Some(filter) => {
+                let allowed = self.filtered_vector_ids(Some(model), filter)?;
+                let total = self.vector_source_stamp(model)?.row_count as usize;
+                // Graph search degrades when most nodes are filtered out; scan the
+                // few matching vectors exactly instead
+                if allowed.len() * SELECTIVE_FILTER_RATIO < total {
+                    return self.find_similar_flat(query_embedding, limit, threshold, model, Some(filter));
+                }
+                Some((allowed, total))
+            }
+            None => None,
+        };
+
+        self.ensure_ann_index(model)?;
+
+        let threshold = threshold.unwrap_or(0.0);
+        let (k, hits) = self.db.with_ann_indexes(|indexes| match indexes.get(model) {
+            Some(index) if !index.is_empty() => {
+                let k = limit
+                    .map(|limit| limit.max(0) as usize)
+                    .unwrap_or_else(|| options.ef_search.unwrap_or(index.config().ef_search));
+                // Over-fetch in proportion to the share of vectors the filter removes
+                let (fetch, ef) = match &allowed {
+                    Some((ids, total)) => {
+                        let fetch = (k * total).div_ceil(ids.len().max(1)).min(*total);
+                        (fetch, Some(options.ef_search.unwrap_or(index.config().ef_search).max(fetch)))
+                    }
+                    None => (k, options.ef_search),
+                };
+                index.search(query_embedding, fetch, ef).map(|hits| (k, hits))
+            }
+            _ => Ok((0, Vec::new())),
+        })?;
+
+        let mut similarities = Vec::with_capacity(hits.len());
+        for hit in hits {
+            if hit.similarity < threshold {
+                continue;
+            }
+            if allowed.as_ref().is_some_and(|(ids, _)| !ids.contains(&hit.vector_id)) {
+                continue;
+            }
+            if let Some(vector) = self.get_by_id(hit.vector_id)? {
+                similarities.push((vector, hit.similarity));
+            }
+            if similarities.len() == k {
+                break;
+            }
+        }
+
+        Ok(similarities)
+    }
+
+    /// Two-stage search: rank every row by its quantized encoding, then rescore the
+    /// top candidates with cosine similarity on their stored vectors. Rescoring is exact
+    /// when the storage settings keep full precision; otherwise it uses the decoded
+    /// encoding. Rows without the requested encoding are scored on their stored vector.
+    pub fn find_similar_quantized(
+        &self,
+        query_embedding: &[f32],
+        limit: Option<i32>,
+        threshold: Option<f32>,
+        kind: QuantizationKind,
+        options: &VectorSearchOptions,
+    ) -> Result<Vec<(VectorIndex, f32)>, String> {
+        const DEFAULT_RESCORE_CANDIDATES: usize = 100;
+
+        let model = options.embedding_model.as_deref();
+        let allowed = match options.filter.as_ref().filter(|filter| !filter.is_empty()) {
+            Some(filter) => Some(self.filtered_vector_ids(model, filter)?),
+            None => None,
+        };
+
+        let candidate_count = options.rescore_candidates.unwrap_or_else(|| {
+            limit
+                .map(|limit| (limit.max(0) as usize * 4).max(40))
+                .unwrap_or(DEFAULT_RESCORE_CANDIDATES)
+        });
+
+        let sql = format!(
+            r#"
+            SELECT id, {column},
+                   CASE WHEN {column} IS NULL THEN embedding END,
+                   CASE WHEN {column} IS NULL THEN embedding_int8 END,
+                   CASE WHEN {column} IS NULL THEN embedding_binary END,
+                   dimension
+            FROM vector_index
+            WHERE ?1 IS NULL OR embedding_model = ?1
+            "#,
+            column = kind.column_name()
+        );
+
+        type CandidateRow = (i64, Option<Vec<u8>>, Option<Vec<u8>>, Option<Vec<u8>>, Option<Vec<u8>>, i32);
+        let rows: Vec<CandidateRow> = self.db.query_map(&sql, params![model], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        })?;
+
+        let quantized_query = quantize(query_embedding, kind);
+        let mut candidates = Vec::with_capacity(rows.len());
+        for (id, quantized, full, int8, binary, dimension) in rows {
+            if allowed.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+                continue;
+            }
+            let score = match (quantized, full) {
+                (Some(quantized), _) => quantized_similarity(&quantized_query, &quantized, kind),
+                (None, Some(full)) => decode_stored_vector(&full, int8.as_deref(), binary.as_deref(), dimension.max(0) as usize)
+                    .and_then(|stored| Self::cosine_similarity(query_embedding, &stored)),
+                (None, None) => continue,
+            };
+            // Rows of a different dimension cannot match the query
+            if let Ok(score) = score {
+                candidates.push((id, score));
+            }
+        }
+
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.truncate(candidate_count);
+
+        let mut rescored = Vec::with_capacity(candidates.len());
+        for (id, _) in candidates {
+            if let Some(vector) = self.get_by_id(id)? {
+                rescored.push(vector);
+            }
+        }
+
+        Self::rank_exact(rescored, query_embedding, limit, threshold)
+    }
+
+    /// Exact search over the memory-mapped flat store of a model.
+    /// Returns the same ranking as `find_similar` restricted to the model, without
+    /// decoding every BLOB row on each query.
+    pub fn find_similar_flat(
+        &self,
+        query_embedding: &[f32],
+        limit: Option<i32>,
+        threshold: Option<f32>,
+        model: &str,
+        filter: Option<&RetrievalFilter>,
+    ) -> Result<Vec<(VectorIndex, f32)>, String> {
+        self.ensure_flat_store(model)?;
+
+        let allowed = match filter.filter(|filter| !filter.is_empty()) {
+            Some(filter) => Some(self.filtered_vector_ids(Some(model), filter)?),
+            None => None,
+        };
+
+        let hits: Vec<VectorSearchHit> = self.db.with_flat_stores(|stores| match stores.get(model) {
+            Some(store) if !store.is_empty() => {
+                let k = limit.map(|limit| limit.max(0) as usize).unwrap_or_else(|| store.len());
+                store.search_filtered(query_embedding, k, Some(threshold.unwrap_or(0.0)), allowed.as_ref())
+            }
+            _ => Ok(Vec::new()),
+        })?;
+
+        let mut similarities = Vec::with_capacity(hits.len());
+        for hit in hits {
+            if let Some(vector) = self.get_by_id(hit.vector_id)? {
+                similarities.push((vector, hit.similarity));
+            }
+        }
+
+        Ok(similarities)
+    }
+
+    /// Memory-map the flat vector store for a model, rebuilding it from
+    /// vector_index when the file is missing, unreadable or out of date
+    pub fn ensure_flat_store(&self, model: &str) -> Result<(), String> {
+        if self.db.with_flat_stores(|stores| stores.contains_key(model)) {
+            return Ok(());
+        }
+
+        let stamp = self.vector_source_stamp(model)?;
+
+        if let Some(path) = self.flat_store_path(model) {
+            if path.exists() {
+                match FlatVectorStore::open(&path) {
+                    Ok(store) if store.source_stamp() == stamp => {
+                        self.db.with_flat_stores(|stores| stores.insert(model.to_string(), store));
+                        return Ok(());
+                    }
+                    Ok(_) => info!("Flat vector store for model '{}' is out of date, rebuilding", model),
+                    Err(e) => warn!("Failed to open flat vector store for model '{}': {}, rebuilding", model, e),
+                }
+            }
+        }
+
+        self.rebuild_flat_store(model).map(|_| ())
+    }
+
+    /// Rebuild the flat vector store for a model from the vector_index table and
+    /// persist it. Returns the number of stored vectors.
+    pub fn rebuild_flat_store(&self, model: &str) -> Result<usize, String> {
+        let stamp = self.vector_source_stamp(model)?;
+        let entries = self.model_entries(model)?;
+        let dimension = entries.first().map(|(_, _, embedding)| embedding.len()).unwrap_or(0);
+
+        let mut store = FlatVectorStore::build(dimension, entries)?;
+        store.set_source_stamp(stamp);
+
+        if let Some(path) = self.flat_store_path(model) {
+            store.save(&path)?;
+        }
+
+        let count = store.len();
+        self.db.with_flat_stores(|stores| stores.insert(model.to_string(), store));
+
+        info!("Rebuilt flat vector store for model '{}' with {} vectors", model, count);
+        Ok(count)
+    }
+
+    /// Load the HNSW index for a model, rebuilding it from vector_index when
+    /// the persisted file is missing, unreadable or out of date
+    pub fn ensure_ann_index(&self, model: &str) -> Result<(), String> {
+        if self.db.with_ann_indexes(|indexes| indexes.contains_key(model)) {
+            return Ok(());
+        }
+
+        let stamp = self.vector_source_stamp(model)?;
+
+        if let Some(path) = self.ann_index_path(model) {
+            if path.exists() {
+                match HnswIndex::load(&path) {
+                    Ok(index) if index.source_stamp() == stamp => {
+                        self.db.with_ann_indexes(|indexes| indexes.insert(model.to_string(), index));
+                        return Ok(());
+                    }
+                    Ok(_) => info!("HNSW index for model '{}' is out of date, rebuilding", model),
+                    Err(e) => warn!("Failed to load HNSW index for model '{}': {}, rebuilding", model, e),
+                }
+            }
+        }
+
+        self.rebuild_ann_index(model, None).map(|_| ())
+    }
+
+    /// Rebuild the HNSW index for a model from the vector_index table and persist it.
+    /// Returns the number of indexed vectors.
+    pub fn rebuild_ann_index(&self, model: &str, config: Option<HnswConfig>) -> Result<usize, String> {
+        let config = config
+            .or_else(|| self.db.with_ann_indexes(|indexes| indexes.get(model).map(|index| index.config().clone())))
+            .unwrap_or_default();
+
+        let stamp = self.vector_source_stamp(model)?;
+        let entries = self.model_entries(model)?;
+        let dimension = entries.first().map(|(_, _, embedding)| embedding.len()).unwrap_or(0);
+
+        let mut index = HnswIndex::build(dimension, config, entries)?;
+        index.set_source_stamp(stamp);
+
+        if let Some(path) = self.ann_index_path(model) {
+            index.save(&path)?;
+        }
+
+        let count = index.len();
+        self.db.with_ann_indexes(|indexes| indexes.insert(model.to_string(), index));
+
+        info!("Rebuilt HNSW index for model '{}' with {} vectors", model, count);
+        Ok(count)
+    }
+
+    /// (vector_id, document_id, embedding) triples of a model, skipping rows whose
+    /// dimension differs from the first row
+    fn model_entries(&self, model: &str) -> Result<Vec<(i64, i64, Vec<f32>)>, String> {
+        let vectors = self.get_by_model(model)?;
+        let dimension = vectors.first().map(|v| v.embedding.len()).unwrap_or(0);
+
+        let mut entries = Vec::with_capacity(vectors.len());
+        for vector in vectors {
+            if vector.embedding.len() != dimension {
+                warn!(
+                    "Skipping vector {:?} for model '{}': dimension {} differs from {}",
+                    vector.id, model, vector.embedding.len(), dimension
+                );
+                continue;
+            }
+            if let Some(id) = vector.id {
+                entries.push((id, vector.document_id, vector.embedding));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Row count and change generation for a model, used to detect stale HNSW and flat store files
+    fn vector_source_stamp(&self, model: &str) -> Result<VectorSourceStamp, String> {
+        let sql = r#"
+            SELECT
+                (SELECT COUNT(*) FROM vector_index WHERE embedding_model = ?1),
+                COALESCE((SELECT generation FROM vector_index_generations WHERE embedding_model = ?1), 0)
+        "#;
+
+        self.db.query_row(sql, params![model], |row| {
+            Ok(VectorSourceStamp {
+                row_count: row.get(0)?,
+                generation: row.get(1)?,
+            })
+        })
+    }
+
+    /// Location of the persisted HNSW index for a model (None for unsaved databases)
+    fn ann_index_path(&self, model: &str) -> Option<std::path::PathBuf> {
+        self.db
+            .database_path()
+            .map(|path| HnswIndex::index_path_for(path, model))
+    }
+
+    /// Location of the persisted flat vector store for a model (None for unsaved databases)
+    fn flat_store_path(&self, model: &str) -> Option<std::path::PathBuf> {
+        self.db
+            .database_path()
+            .map(|path| FlatVectorStore::path_for(path, model))
+    }
+
+    /// Models that currently have an HNSW index or flat store loaded
+    fn loaded_index_models(&self) -> Vec<String> {
+        let mut models: Vec<String> = self.db.with_ann_indexes(|indexes| indexes.keys().cloned().collect());
+        models.extend(self.db.with_flat_stores(|stores| stores.keys().cloned().collect::<Vec<_>>()));
+        models.sort();
+        models.dedup();
+        models
+    }
+
+    /// Apply a written vector_index row to any loaded HNSW index or flat store
+    fn sync_index_entry(&self, id: i64) -> Result<(), String> {
+        if self.loaded_index_models().is_empty() {
+            return Ok(());
+        }
+
+        let entry = match self.get_by_id(id)? {
+            Some(entry) => entry,
+            None => return self.remove_index_entries(&[id]),
+        };
+        let stamp = self.vector_source_stamp(&entry.embedding_model)?;
+
+        self.db.with_ann_indexes(|indexes| {
+            // The row may have moved between models through update()
+            for (model, index) in indexes.iter_mut() {
+                if *model != entry.embedding_model {
+                    index.remove(id);
+                }
+            }
+
+            let index = match indexes.get_mut(&entry.embedding_model) {
+                Some(index) => index,
+                None => return Ok(()),
+            };
+
+            if index.dimension() != entry.embedding.len() {
+                if index.is_empty() {
+                    *index = HnswIndex::new(entry.embedding.len(), index.config().clone());
+                } else {
+                    // Mixed dimensions cannot share a graph; drop it so the next search rebuilds
+                    warn!("Dimension change for model '{}', discarding HNSW index", entry.embedding_model);
+                    indexes.remove(&entry.embedding_model);
+                    return Ok(());
+                }
+            }
+
+            index.insert(id, entry.document_id, &entry.embedding)?;
+            index.set_source_stamp(stamp);
+            Ok::<(), String>(())
+        })?;
+
+        self.db.with_flat_stores(|stores| {
+            for (model, store) in stores.iter_mut() {
+                if *model != entry.embedding_model {
+                    store.remove(id);
+                }
+            }
+
+            let store = match stores.get_mut(&entry.embedding_model) {
+                Some(store) => store,
+                None => return Ok(()),
+            };
+
+            if store.dimension() != entry.embedding.len() {
+                if store.is_empty() {
+                    *store = FlatVectorStore::new(entry.embedding.len());
+                } else {
+                    warn!("Dimension change for model '{}', discarding flat vector store", entry.embedding_model);
+                    stores.remove(&entry.embedding_model);
+                    return Ok(());
+                }
+            }
+
+            store.upsert(id, entry.document_id, &entry.embedding)?;
+            store.set_source_stamp(stamp);
+            Ok(())
+        })
+    }
+
+    /// Remove deleted vector_index rows from any loaded HNSW index or flat store
+    fn remove_index_entries(&self, ids: &[i64]) -> Result<(), String> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        for model in self.loaded_index_models() {
+            let stamp = self.vector_source_stamp(&model)?;
+            self.db.with_ann_indexes(|indexes| {
+                if let Some(index) = indexes.get_mut(&model) {
+                    for &id in ids {
+                        index.remove(id);
+                    }
+                    index.set_source_stamp(stamp);
+                }
+            });
+            self.db.with_flat_stores(|stores| {
+                if let Some(store) = stores.get_mut(&model) {
+                    for &id in ids {
+                        store.remove(id);
+                    }
+                    store.set_source_stamp(stamp);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Rank vectors against a query by exact cosine similarity
+    fn rank_exact(all_vectors: Vec<VectorIndex>, query_embedding: &[f32], limit: Option<i32>, threshold: Option<f32>) -> Result<Vec<(VectorIndex, f32)>, String> {
         let threshold = threshold.unwrap_or(0.0);
         
         let mut similarities = Vec::new();
         
         for vector in all_vectors {
+            // Vectors embedded with a different dimension cannot match the query
+            if vector.embedding.len() != query_embedding.len() {
+                continue;
+            }
             let similarity = Self::cosine_similarity(query_embedding, &vector.embedding)?;
             if similarity >= threshold {
                 similarities.push((vector, similarity));
@@ -1072,6 +1894,261 @@ impl<'a> VectorIndexRepository<'a> {
     }
 }
 
+/// Repository for the chunk-level vector index
+/// Handles passage retrieval over per-chunk vectors linked to document_embeddings
+pub struct ChunkVectorRepository<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> ChunkVectorRepository<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Find the passages most similar to a query embedding.
+    /// Optionally restricted to one embedding model and/or a set of documents.
+    pub fn find_similar_passages(
+        &self,
+        query_embedding: &[f32],
+        limit: Option<i32>,
+        threshold: Option<f32>,
+        embedding_model: Option<&str>,
+        document_ids: Option<&[i64]>,
+    ) -> Result<Vec<PassageMatch>, String> {
+        let threshold = threshold.unwrap_or(0.0);
+
+        let mut sql = String::from(
+            r#"
+            SELECT c.embedding_id, e.embedding_blob
+            FROM chunk_vector_index c
+            JOIN document_embeddings e ON e.id = c.embedding_id
+            WHERE (?1 IS NULL OR c.embedding_model = ?1)
+            "#,
+        );
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&embedding_model];
+
+        if let Some(document_ids) = document_ids {
+            if document_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            let placeholders: Vec<String> = (0..document_ids.len())
+                .map(|i| format!("?{}", i + 2))
+                .collect();
+            sql.push_str(&format!(" AND c.document_id IN ({})", placeholders.join(", ")));
+            for document_id in document_ids {
+                query_params.push(document_id);
+            }
+        }
+
+        let rows = self.db.query_map(&sql, &query_params, |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })?;
+
+        let vector_repo = VectorIndexRepository::new(self.db);
+        let mut scored = Vec::new();
+        for (embedding_id, blob) in rows {
+            let embedding = vector_repo.blob_to_embedding(&blob)?;
+            // Chunks embedded with a different dimension cannot match the query
+            if embedding.len() != query_embedding.len() {
+                continue;
+            }
+            let similarity = VectorIndexRepository::cosine_similarity(query_embedding, &embedding)?;
+            if similarity >= threshold {
+                scored.push((embedding_id, similarity));
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some(limit) = limit {
+            scored.truncate(limit.max(0) as usize);
+        }
+
+        let mut passages = Vec::with_capacity(scored.len());
+        for (embedding_id, similarity) in scored {
+            if let Some(passage) = self.get_passage(embedding_id, similarity)? {
+                passages.push(passage);
+            }
+        }
+
+        Ok(passages)
+    }
+
+    /// Load passage text and offsets for a chunk
+    pub fn get_passage(&self, embedding_id: i64, similarity: f32) -> Result<Option<PassageMatch>, String> {
+        let sql = r#"
+            SELECT id, document_id, chunk_index, chunk_start, chunk_end, content_text
+            FROM document_embeddings
+            WHERE id = ?1
+        "#;
+
+        let passages = self.db.query_map(sql, params![embedding_id], |row| {
+            Ok(PassageMatch {
+                embedding_id: row.get(0)?,
+                document_id: row.get(1)?,
+                chunk_index: row.get(2)?,
+                byte_start: row.get(3)?,
+                byte_end: row.get(4)?,
+                text: row.get(5)?,
+                similarity,
+            })
+        })?;
+
+        Ok(passages.into_iter().next())
+    }
+
+    /// Number of indexed chunks for a document
+    pub fn count_for_document(&self, document_id: i64) -> Result<usize, String> {
+        let sql = "SELECT COUNT(*) FROM chunk_vector_index WHERE document_id = ?1";
+        let count: i64 = self.db.query_row(sql, params![document_id], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Rebuild the chunk index from document_embeddings
+    pub fn rebuild(&self) -> Result<usize, String> {
+        self.db.execute("DELETE FROM chunk_vector_index", params![])?;
+
+        let sql = r#"
+            INSERT INTO chunk_vector_index (
+                embedding_id, document_id, chunk_index, embedding_model, dimension
+            )
+            SELECT id, document_id, chunk_index, embedding_model, LENGTH(embedding_blob) / 4
+            FROM document_embeddings
+            WHERE embedding_blob IS NOT NULL
+        "#;
+        self.db.execute(sql, params![])
+    }
+}
+
+/// Repository for document text indexed by FTS5
+pub struct DocumentContentRepository<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> DocumentContentRepository<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Store the title and body of a document, returning its document key.
+    /// Rows whose hash and title are unchanged are left alone so FTS5 is not rewritten.
+    pub fn upsert(&self, document_id: &str, title: &str, content: &str, file_modified_at: Option<u64>) -> Result<i64, String> {
+        let content_hash = EmbeddingRepository::generate_content_hash(content);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        let sql = r#"
+            INSERT INTO document_content (
+                document_id, title, content, content_hash, file_modified_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(document_id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                content_hash = excluded.content_hash,
+                file_modified_at = excluded.file_modified_at,
+                updated_at = excluded.updated_at
+            WHERE document_content.content_hash != excluded.content_hash
+               OR document_content.title != excluded.title
+        "#;
+
+        self.db.execute(
+            sql,
+            params![
+                document_id,
+                title,
+                content,
+                content_hash,
+                file_modified_at.map(|t| t as i64),
+                now
+            ],
+        )?;
+
+        self.key_for_document(document_id)?
+            .ok_or_else(|| format!("Failed to store content for document {}", document_id))
+    }
+
+    /// Document key for a documents.id, if its content has been stored
+    pub fn key_for_document(&self, document_id: &str) -> Result<Option<i64>, String> {
+        let sql = "SELECT id FROM document_content WHERE document_id = ?1";
+        let keys = self.db.query_map(sql, params![document_id], |row| row.get::<_, i64>(0))?;
+        Ok(keys.into_iter().next())
+    }
+
+    /// Get stored content by documents.id
+    pub fn get_by_document_id(&self, document_id: &str) -> Result<Option<DocumentContent>, String> {
+        let sql = r#"
+            SELECT id, document_id, title, content, content_hash, file_modified_at, updated_at
+            FROM document_content
+            WHERE document_id = ?1
+        "#;
+
+        let rows = self.db.query_map(sql, params![document_id], Self::map_row)?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// Get stored content by document key
+    pub fn get(&self, id: i64) -> Result<Option<DocumentContent>, String> {
+        let sql = r#"
+            SELECT id, document_id, title, content, content_hash, file_modified_at, updated_at
+            FROM document_content
+            WHERE id = ?1
+        "#;
+
+        let rows = self.db.query_map(sql, params![id], Self::map_row)?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// All stored content for the documents of a project
+    pub fn find_by_project_id(&self, project_id: &str) -> Result<Vec<DocumentContent>, String> {
+        let sql = r#"
+            SELECT dc.id, dc.document_id, dc.title, dc.content, dc.content_hash,
+                   dc.file_modified_at, dc.updated_at
+            FROM document_content dc
+            JOIN documents d ON d.id = dc.document_id
+            WHERE d.project_id = ?1
+            ORDER BY d.path
+        "#;
+
+        self.db.query_map(sql, params![project_id], Self::map_row)
+    }
+
+    /// Remove the stored content of a document; returns whether a row was deleted
+    pub fn delete_by_document_id(&self, document_id: &str) -> Result<bool, String> {
+        let sql = "DELETE FROM document_content WHERE document_id = ?1";
+        let rows_affected = self.db.execute(sql, params![document_id])?;
+        Ok(rows_affected > 0)
+    }
+
+    /// Title of a markdown document: its first level-one heading, else the file stem
+    pub fn extract_title(content: &str, path: &str) -> String {
+        content
+            .lines()
+            .map(str::trim)
+            .find_map(|line| line.strip_prefix("# ").map(str::trim))
+            .filter(|title| !title.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                std::path::Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string())
+            })
+    }
+
+    fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<DocumentContent> {
+        Ok(DocumentContent {
+            id: row.get(0)?,
+            document_id: row.get(1)?,
+            title: row.get(2)?,
+            content: row.get(3)?,
+            content_hash: row.get(4)?,
+            file_modified_at: row.get::<_, Option<i64>>(5)?.map(|t| t as u64),
+            updated_at: row.get::<_, i64>(6)? as u64,
+        })
+    }
+}
+
 /// Repository for FTS5 Full-Text Search (Task 2.3.5)
 /// Handles fast lexical search for the hybrid RAG system
 pub struct FTS5Repository<'a> {
@@ -1084,203 +2161,235 @@ impl<'a> FTS5Repository<'a> {
     }
 
     /// Perform full-text search using FTS5
-    /// Returns documents ranked by relevance (BM25 scoring)
+    /// Returns documents ranked by relevance (BM25 scoring).
+    /// The query uses the search box syntax parsed by `SearchQuery`.
     pub fn search(&self, query: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
-        if query.trim().is_empty() {
-            return Ok(Vec::new());
-        }
-        
-        let limit_clause = if let Some(limit) = limit {
-            format!("LIMIT {}", limit)
-        } else {
-            String::new()
-        };
-        
-        let sql = format!(r#"
-            SELECT 
-                d.id as document_id,
-                d.title,
-                d.content,
-                bm25(documents_fts) as rank
-            FROM documents_fts 
-            JOIN documents d ON documents_fts.rowid = d.id
-            WHERE documents_fts MATCH ?
-            ORDER BY rank
-            {}
-        "#, limit_clause);
-        
-        self.db.query_map(&sql, params![query], |row| {
-            Ok(FTS5SearchResult {
-                document_id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                rank: row.get(3)?,
-                snippet: None, // Will be populated by snippet function if needed
-            })
-        })
+        self.search_filtered(query, limit, &RetrievalFilter::default())
+    }
+
+    /// Full-text search restricted to documents matching a filter
+    pub fn search_filtered(&self, query: &str, limit: Option<i32>, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
+        self.run_search(query, None, limit, false, filter)
     }
 
     /// Perform full-text search with highlighted snippets
     /// Returns documents with highlighted search terms in snippets
     pub fn search_with_snippets(&self, query: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
-        if query.trim().is_empty() {
-            return Ok(Vec::new());
+        self.search_with_snippets_filtered(query, limit, &RetrievalFilter::default())
+    }
+
+    /// Full-text search with snippets restricted to documents matching a filter
+    pub fn search_with_snippets_filtered(&self, query: &str, limit: Option<i32>, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
+        self.run_search(query, None, limit, true, filter)
+    }
+
+    /// Full-text search for a parsed query.
+    /// Field filters in the query and `filter` both apply; a filter-only query matches nothing.
+    pub fn search_query(&self, query: &SearchQuery, limit: Option<i32>, with_snippets: bool, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
+        match query.to_fts5() {
+            Some(expression) => self.run_match("documents_fts", &expression, limit, with_snippets, &[filter, &query.filter]),
+            None => Ok(Vec::new()),
         }
-        
-        let limit_clause = if let Some(limit) = limit {
-            format!("LIMIT {}", limit)
-        } else {
-            String::new()
+    }
+
+    /// Full-text search that retries once with "did you mean" corrections from the
+    /// document term dictionary when the query as typed matches nothing
+    pub fn search_with_correction(&self, query: &str, limit: Option<i32>, filter: &RetrievalFilter) -> Result<CorrectedSearchResults, String> {
+        let parsed = SearchQuery::parse(query).unwrap_or_else(|_| SearchQuery::plain_terms(query));
+        let results = self.search_query(&parsed, limit, true, filter)?;
+        if !results.is_empty() {
+            return Ok(CorrectedSearchResults { results, suggested_query: None });
+        }
+
+        match TermDictionary::new(self.db, TermSource::Documents).correct_query(&parsed)? {
+            Some(corrected) => Ok(CorrectedSearchResults {
+                results: self.search_query(&corrected, limit, true, filter)?,
+                suggested_query: Some(corrected.to_string()),
+            }),
+            None => Ok(CorrectedSearchResults { results, suggested_query: None }),
+        }
+    }
+
+    /// Substring search over the trigram index, for partial words and CJK text.
+    /// Each term matches wherever it occurs inside a word; terms shorter than three
+    /// characters match nothing. Fails if the trigram index is not enabled.
+    pub fn search_substring(&self, query: &SearchQuery, limit: Option<i32>, with_snippets: bool, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
+        if !self.has_trigram_index()? {
+            return Err("Substring search needs the trigram index; enable it in the search index settings".to_string());
+        }
+
+        match query.to_fts5() {
+            Some(expression) => self.run_match(TRIGRAM_TABLE, &expression, limit, with_snippets, &[filter, &query.filter]),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Whether the optional trigram index is enabled for this database
+    pub fn has_trigram_index(&self) -> Result<bool, String> {
+        SearchIndexManager::new(self.db).has_trigram_index()
+    }
+
+    /// Byte and line/column ranges of every match in the source markdown of each
+    /// matching document, ordered by path so the editor can step through a project.
+    /// Documents whose content has no match (e.g. a title-only hit) are left out.
+    pub fn find_match_ranges(&self, query: &SearchQuery, limit: Option<i32>, filter: &RetrievalFilter) -> Result<Vec<DocumentMatches>, String> {
+        let Some(expression) = query.to_fts5() else {
+            return Ok(Vec::new());
         };
-        
+        let limit_clause = limit.map(|limit| format!("LIMIT {}", limit)).unwrap_or_default();
+
+        let mut filter_conditions = String::new();
+        let mut filter_values = Vec::new();
+        for filter in [filter, &query.filter] {
+            let (conditions, values) = filter.sql_conditions("d", 4 + filter_values.len());
+            filter_conditions.push_str(&conditions);
+            filter_values.extend(values);
+        }
+
         let sql = format!(r#"
-            SELECT 
-                d.id as document_id,
-                d.title,
-                d.content,
-                bm25(documents_fts) as rank,
-                snippet(documents_fts, 1, '<mark>', '</mark>', '...', 32) as snippet
-            FROM documents_fts 
-            JOIN documents d ON documents_fts.rowid = d.id
-            WHERE documents_fts MATCH ?
-            ORDER BY rank
+            SELECT
+                dc.id,
+                d.id,
+                d.path,
+                dc.title,
+                dc.content,
+                highlight(documents_fts, 1, ?2, ?3)
+            FROM documents_fts
+            JOIN document_content dc ON documents_fts.rowid = dc.id
+            JOIN documents d ON d.id = dc.document_id
+            WHERE documents_fts MATCH ?1{}
+            ORDER BY d.path
             {}
-        "#, limit_clause);
-        
-        self.db.query_map(&sql, params![query], |row| {
-            Ok(FTS5SearchResult {
-                document_id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                rank: row.get(3)?,
-                snippet: Some(row.get(4)?),
-            })
-        })
+        "#, filter_conditions, limit_clause);
+
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&expression, &MATCH_START, &MATCH_END];
+        query_params.extend(filter_values.iter().map(|value| value as &dyn rusqlite::ToSql));
+
+        let rows = self.db.query_map(&sql, &query_params, |row| {
+            Ok((
+                DocumentMatches {
+                    document_id: row.get(0)?,
+                    source_document_id: row.get(1)?,
+                    path: row.get(2)?,
+                    title: row.get(3)?,
+                    matches: Vec::new(),
+                },
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut documents = Vec::new();
+        for (mut document, content, highlighted) in rows {
+            document.matches = locate_matches(&content, &highlighted)
+                .map_err(|e| format!("Failed to locate matches in {}: {}", document.path, e))?;
+            if !document.matches.is_empty() {
+                documents.push(document);
+            }
+        }
+        Ok(documents)
     }
 
-    /// Search in titles only
-    pub fn search_titles(&self, query: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
-        if query.trim().is_empty() {
+    /// Parse search box input; malformed input is searched as plain quoted words
+    fn run_search(&self, query: &str, column: Option<&str>, limit: Option<i32>, with_snippets: bool, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
+        let parsed = SearchQuery::parse(query).unwrap_or_else(|_| SearchQuery::plain_terms(query));
+        let Some(expression) = parsed.to_fts5() else {
             return Ok(Vec::new());
-        }
-        
+        };
+
+        let expression = match column {
+            Some(column) => format!("{} : ({})", column, expression),
+            None => expression,
+        };
+        self.run_match("documents_fts", &expression, limit, with_snippets, &[filter, &parsed.filter])
+    }
+
+    /// Shared FTS5 query; filters are applied in SQL so LIMIT counts only matching documents
+    fn run_match(&self, table: &str, expression: &str, limit: Option<i32>, with_snippets: bool, filters: &[&RetrievalFilter]) -> Result<Vec<FTS5SearchResult>, String> {
         let limit_clause = if let Some(limit) = limit {
             format!("LIMIT {}", limit)
         } else {
             String::new()
         };
-        
-        // Search only in title field
-        let title_query = format!("title:{}", query);
+
+        let snippet_column = if with_snippets {
+            format!("snippet({}, 1, '<mark>', '</mark>', '...', 32)", table)
+        } else {
+            "NULL".to_string()
+        };
+
+        let mut filter_conditions = String::new();
+        let mut filter_values = Vec::new();
+        for filter in filters {
+            let (conditions, values) = filter.sql_conditions("d", 2 + filter_values.len());
+            filter_conditions.push_str(&conditions);
+            filter_values.extend(values);
+        }
         
         let sql = format!(r#"
             SELECT 
-                d.id as document_id,
-                d.title,
-                d.content,
-                bm25(documents_fts) as rank
-            FROM documents_fts 
-            JOIN documents d ON documents_fts.rowid = d.id
-            WHERE documents_fts MATCH ?
+                dc.id as document_id,
+                dc.title,
+                dc.content,
+                bm25({table}) as rank,
+                {} as snippet
+            FROM {table} 
+            JOIN document_content dc ON {table}.rowid = dc.id
+            JOIN documents d ON d.id = dc.document_id
+            WHERE {table} MATCH ?1{}
             ORDER BY rank
             {}
-        "#, limit_clause);
+        "#, snippet_column, filter_conditions, limit_clause, table = table);
+
+        let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&expression];
+        query_params.extend(filter_values.iter().map(|value| value as &dyn rusqlite::ToSql));
         
-        self.db.query_map(&sql, params![title_query], |row| {
+        self.db.query_map(&sql, &query_params, |row| {
             Ok(FTS5SearchResult {
                 document_id: row.get(0)?,
                 title: row.get(1)?,
                 content: row.get(2)?,
                 rank: row.get(3)?,
-                snippet: None,
+                snippet: row.get(4)?,
             })
         })
     }
 
+    /// Search in titles only
+    pub fn search_titles(&self, query: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
+        self.run_search(query, Some("title"), limit, false, &RetrievalFilter::default())
+    }
+
     /// Search in content only
     pub fn search_content(&self, query: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
-        if query.trim().is_empty() {
-            return Ok(Vec::new());
-        }
-        
-        let limit_clause = if let Some(limit) = limit {
-            format!("LIMIT {}", limit)
-        } else {
-            String::new()
-        };
-        
-        // Search only in content field
-        let content_query = format!("content:{}", query);
-        
-        let sql = format!(r#"
-            SELECT 
-                d.id as document_id,
-                d.title,
-                d.content,
-                bm25(documents_fts) as rank
-            FROM documents_fts 
-            JOIN documents d ON documents_fts.rowid = d.id
-            WHERE documents_fts MATCH ?
-            ORDER BY rank
-            {}
-        "#, limit_clause);
-        
-        self.db.query_map(&sql, params![content_query], |row| {
-            Ok(FTS5SearchResult {
-                document_id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                rank: row.get(3)?,
-                snippet: None,
-            })
-        })
+        self.run_search(query, Some("content"), limit, false, &RetrievalFilter::default())
     }
 
     /// Perform phrase search (exact phrase matching)
     pub fn search_phrase(&self, phrase: &str, limit: Option<i32>) -> Result<Vec<FTS5SearchResult>, String> {
+        self.search_phrase_filtered(phrase, limit, &RetrievalFilter::default())
+    }
+
+    /// Phrase search restricted to documents matching a filter
+    pub fn search_phrase_filtered(&self, phrase: &str, limit: Option<i32>, filter: &RetrievalFilter) -> Result<Vec<FTS5SearchResult>, String> {
         if phrase.trim().is_empty() {
             return Ok(Vec::new());
         }
-        
-        let limit_clause = if let Some(limit) = limit {
-            format!("LIMIT {}", limit)
-        } else {
-            String::new()
-        };
-        
-        // Wrap phrase in quotes for exact matching
-        let phrase_query = format!("\"{}\"", phrase);
-        
-        let sql = format!(r#"
-            SELECT 
-                d.id as document_id,
-                d.title,
-                d.content,
-                bm25(documents_fts) as rank
-            FROM documents_fts 
-            JOIN documents d ON documents_fts.rowid = d.id
-            WHERE documents_fts MATCH ?
-            ORDER BY rank
-            {}
-        "#, limit_clause);
-        
-        self.db.query_map(&sql, params![phrase_query], |row| {
-            Ok(FTS5SearchResult {
-                document_id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                rank: row.get(3)?,
-                snippet: None,
-            })
-        })
+
+        self.run_match("documents_fts", &Self::escape_query(phrase), limit, false, &[filter])
     }
 
     /// Get candidate documents for hybrid RAG search
     /// This is the first step in the two-step retrieval process
     pub fn get_candidates(&self, query: &str, max_candidates: Option<i32>) -> Result<Vec<i64>, String> {
+        self.get_candidates_filtered(query, max_candidates, &RetrievalFilter::default())
+    }
+
+    /// Get candidate documents matching a filter for hybrid RAG search
+    pub fn get_candidates_filtered(&self, query: &str, max_candidates: Option<i32>, filter: &RetrievalFilter) -> Result<Vec<i64>, String> {
         let limit = max_candidates.unwrap_or(50); // Default to top 50 candidates
         
-        let results = self.search(query, Some(limit))?;
+        let results = self.search_filtered(query, Some(limit), filter)?;
         Ok(results.into_iter().map(|r| r.document_id).collect())
     }
 
@@ -1291,6 +2400,20 @@ impl<'a> FTS5Repository<'a> {
         Ok(())
     }
 
+    /// Compare the FTS5 index with document_content.
+    /// Returns false when the index is out of step with the stored text.
+    pub fn check_index_integrity(&self) -> Result<bool, String> {
+        // rank = 1 makes FTS5 check the index against the external content table
+        let sql = "INSERT INTO documents_fts(documents_fts, rank) VALUES('integrity-check', 1)";
+        self.db.with_connection(|conn| match conn.execute(sql, params![]) {
+            Ok(_) => Ok(true),
+            // FTS5 reports an index that disagrees with its content as SQLITE_CORRUPT_VTAB
+            Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::DatabaseCorrupt => Ok(false),
+            Err(e) => Err(e),
+        })
+        .map_err(|e| format!("FTS5 integrity check failed: {}", e))
+    }
+
     /// Optimize FTS5 index (for maintenance)
     pub fn optimize_index(&self) -> Result<(), String> {
         let sql = "INSERT INTO documents_fts(documents_fts) VALUES('optimize')";
@@ -1328,21 +2451,9 @@ impl<'a> FTS5Repository<'a> {
         }
     }
 
-    /// Escape FTS5 special characters in query
+    /// Quote a query as a single FTS5 string so none of its characters act as syntax
     pub fn escape_query(query: &str) -> String {
-        // Escape FTS5 special characters: " * ( ) [ ] { } ^ ~ :
-        query
-            .replace('"', '""')
-            .replace('*', "\\*")
-            .replace('(', "\\(")
-            .replace(')', "\\)")
-            .replace('[', "\\[")
-            .replace(']', "\\]")
-            .replace('{', "\\{")
-            .replace('}', "\\}")
-            .replace('^', "\\^")
-            .replace('~', "\\~")
-            .replace(':', "\\:")
+        escape_fts5_string(query)
     }
 }
 
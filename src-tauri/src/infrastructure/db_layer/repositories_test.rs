@@ -5,7 +5,13 @@
 mod tests {
     use super::*;
     use crate::core::{Document, DocumentState, Project};
-    use crate::infrastructure::db_layer::{DatabaseConnection, MigrationManager};
+    use crate::infrastructure::db_layer::{
+        DatabaseConnection, FlatVectorStore, FtsTokenizer, HnswIndex, MigrationManager, RetrievalFilter,
+        SearchIndexManager, SearchIndexRebuild, SearchIndexSettings, SearchQuery, SettingsRepository, TermDictionary, TermSource,
+        TextPosition,
+    };
+    use crate::synthetic_vectors::seeded_vector;
+    use rusqlite::params;
     use tempfile::NamedTempFile;
 
     /// Helper function to create a test database with migrations applied
@@ -331,6 +337,7 @@ mod tests {
     fn create_test_documents_with_content(db: &DatabaseConnection) -> Vec<i64> {
         let project_repo = ProjectRepository::new(db);
         let doc_repo = DocumentRepository::new(db);
+        let content_repo = DocumentContentRepository::new(db);
         
         // Create test project
         let project = create_test_project();
@@ -350,10 +357,10 @@ mod tests {
         for (id, title, content) in documents {
             let mut doc = create_test_document(&project.id);
             doc.id = id.to_string();
-            doc.title = title.to_string();
-            doc.content = content.to_string();
+            doc.path = format!("{}.md", id);
+            doc_repo.create(&doc).expect("Failed to create document");
             
-            let doc_id = doc_repo.create(&doc).expect("Failed to create document");
+            let doc_id = content_repo.upsert(id, title, content, None).expect("Failed to store content");
             doc_ids.push(doc_id);
         }
         
@@ -417,28 +424,870 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fts5_structured_query() {
+        let (_temp_file, db) = create_test_db();
+        let doc_ids = create_test_documents_with_content(&db);
+        let fts5_repo = FTS5Repository::new(&db);
+        let ids = |query: &str| -> Vec<i64> {
+            let mut ids = fts5_repo.get_candidates(query, Some(10)).expect("Search failed");
+            ids.sort();
+            ids
+        };
+        
+        assert_eq!(ids("title:rust"), vec![doc_ids[0]]);
+        assert_eq!(ids("\"systems programming\""), vec![doc_ids[0]]);
+        assert_eq!(ids("programming -python -javascript"), vec![doc_ids[0]]);
+        assert_eq!(ids("segfaults OR readability"), vec![doc_ids[0], doc_ids[2]]);
+        assert_eq!(ids("algorith*"), vec![doc_ids[3]]);
+        assert_eq!(ids("programming state:draft project:test-project-id").len(), 3);
+        assert!(ids("programming state:prd").is_empty());
+        assert!(ids("state:draft").is_empty());
+        
+        // Stray FTS5 syntax is matched literally instead of raising an error
+        assert_eq!(ids("programming AND ( NEAR"), Vec::<i64>::new());
+        assert_eq!(ids("rust:").len(), 1);
+        
+        // Malformed input falls back to its plain words
+        assert_eq!(ids("\"systems programming"), vec![doc_ids[0]]);
+        assert_eq!(ids("readability OR"), vec![doc_ids[2]]);
+        assert!(ids("-").is_empty());
+        
+        let phrase = fts5_repo.search_phrase("programming language", Some(10)).expect("Phrase search failed");
+        assert_eq!(phrase.len(), 3);
+        let doc2_only = RetrievalFilter { path_prefixes: vec!["doc2".to_string()], ..Default::default() };
+        let phrase = fts5_repo.search_phrase_filtered("programming language", Some(10), &doc2_only).expect("Phrase search failed");
+        assert_eq!(phrase.iter().map(|r| r.document_id).collect::<Vec<_>>(), vec![doc_ids[1]]);
+        assert_eq!(FTS5Repository::escape_query("a \"b\" *"), "\"a \"\"b\"\" *\"");
+    }
+
     #[test]
     fn test_fts5_triggers_insert() {
         let (_temp_file, db) = create_test_db();
         let project_repo = ProjectRepository::new(&db);
         let doc_repo = DocumentRepository::new(&db);
+        let content_repo = DocumentContentRepository::new(&db);
         let fts5_repo = FTS5Repository::new(&db);
         
         // Create test project
         let project = create_test_project();
         project_repo.create(&project).expect("Failed to create project");
         
-        // Create a new document
-        let mut doc = create_test_document(&project.id);
-        doc.title = "New Document".to_string();
-        doc.content = "This is a new document for testing FTS5 triggers.".to_string();
+        // Create a new document and store its text
+        let doc = create_test_document(&project.id);
+        doc_repo.create(&doc).expect("Failed to create document");
         
-        let doc_id = doc_repo.create(&doc).expect("Failed to create document");
+        let doc_id = content_repo
+            .upsert(&doc.id, "New Document", "This is a new document for testing FTS5 triggers.", None)
+            .expect("Failed to store content");
         
         // Search for the new document - should be found due to INSERT trigger
         let results = fts5_repo.search("testing", Some(10)).expect("Search failed");
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].document_id, doc_id);
+        assert_eq!(results[0].title, "New Document");
         assert!(results[0].content.contains("testing"));
     }
+
+    #[test]
+    fn test_fts5_triggers_update_and_delete() {
+        let (_temp_file, db) = create_test_db();
+        let doc_ids = create_test_documents_with_content(&db);
+        let content_repo = DocumentContentRepository::new(&db);
+        let fts5_repo = FTS5Repository::new(&db);
+        
+        // Rewriting the body keeps the document key and replaces the indexed text
+        let doc_id = content_repo
+            .upsert("doc1", "Rust Programming", "Ownership and borrowing without a garbage collector.", Some(42))
+            .expect("Failed to update content");
+        assert_eq!(doc_id, doc_ids[0]);
+        assert!(fts5_repo.search("segfaults", Some(10)).expect("Search failed").is_empty());
+        assert_eq!(fts5_repo.get_candidates("borrowing", Some(10)).expect("Search failed"), vec![doc_id]);
+        
+        let stored = content_repo.get(doc_id).expect("Failed to load content").expect("Content missing");
+        assert_eq!(stored.document_id, "doc1");
+        assert_eq!(stored.file_modified_at, Some(42));
+        
+        // Deleting the document cascades to its content and the FTS5 index
+        DocumentRepository::new(&db).delete("doc1").expect("Failed to delete document");
+        assert!(content_repo.get_by_document_id("doc1").expect("Lookup failed").is_none());
+        assert!(fts5_repo.search("borrowing", Some(10)).expect("Search failed").is_empty());
+        assert!(fts5_repo.check_index_integrity().expect("Integrity check failed"));
+    }
+
+    #[test]
+    fn test_index_integrity_detects_drift() {
+        let (_temp_file, db) = create_test_db();
+        create_test_documents_with_content(&db);
+        let fts5_repo = FTS5Repository::new(&db);
+        assert!(fts5_repo.check_index_integrity().expect("Integrity check failed"));
+
+        // Edit content without the trigger that keeps documents_fts in step
+        db.execute("DROP TRIGGER document_content_fts_update", &[]).expect("Failed to drop trigger");
+        db.execute("UPDATE document_content SET content = 'rewritten behind the index'", &[])
+            .expect("Failed to update content");
+        assert!(!fts5_repo.check_index_integrity().expect("Integrity check failed"));
+    }
+
+    #[test]
+    fn test_document_content_title_extraction() {
+        assert_eq!(DocumentContentRepository::extract_title("intro\n# Launch Plan \nbody", "specs/plan.md"), "Launch Plan");
+        assert_eq!(DocumentContentRepository::extract_title("## Only a subheading", "specs/plan.md"), "plan");
+        assert_eq!(DocumentContentRepository::extract_title("", "README"), "README");
+    }
+
+    // ===== Search Index Settings Tests =====
+
+    #[test]
+    fn test_tokenizer_setting_rebuilds_index() {
+        let (_temp_file, db) = create_test_db();
+        let doc_ids = create_test_documents_with_content(&db);
+        DocumentContentRepository::new(&db)
+            .upsert("doc5", "Café Systems", "Crème brûlée recipes stored in a database.", None)
+            .expect("Failed to update content");
+        let fts5_repo = FTS5Repository::new(&db);
+        let manager = SearchIndexManager::new(&db);
+        
+        // Migrations apply the default accent-folding tokenizer
+        assert_eq!(manager.settings().unwrap(), SearchIndexSettings::default());
+        assert_eq!(fts5_repo.get_candidates("cafe creme", Some(10)).unwrap(), vec![doc_ids[4]]);
+        assert_eq!(fts5_repo.get_candidates("Brûlée", Some(10)).unwrap(), vec![doc_ids[4]]);
+        
+        let ascii = SearchIndexSettings { tokenizer: FtsTokenizer::PorterAscii, trigram_index: false };
+        let rebuild = manager.apply(&ascii).expect("Failed to apply settings");
+        assert!(rebuild.main_index_rebuilt);
+        assert!(!rebuild.trigram_index_changed);
+        assert_eq!(manager.settings().unwrap(), ascii);
+        assert!(fts5_repo.get_candidates("cafe", Some(10)).unwrap().is_empty());
+        assert_eq!(fts5_repo.get_candidates("programming", Some(10)).unwrap().len(), 3);
+        
+        // Re-applying unchanged settings, or re-running migrations, leaves the index alone
+        assert_eq!(manager.apply(&ascii).unwrap(), SearchIndexRebuild::default());
+        MigrationManager::new(&db).migrate().expect("Failed to re-run migrations");
+        assert!(fts5_repo.get_candidates("cafe", Some(10)).unwrap().is_empty());
+        assert!(fts5_repo.check_index_integrity().unwrap());
+        
+        // The applied tokenizer is recorded; an index without a record is rebuilt once
+        let settings_repo = SettingsRepository::new(&db);
+        assert_eq!(settings_repo.get("search_index_applied_tokenizer").unwrap().as_deref(), Some("\"porter_ascii\""));
+        assert!(settings_repo.delete("search_index_applied_tokenizer").unwrap());
+        assert!(manager.ensure_current().unwrap().main_index_rebuilt);
+        assert_eq!(manager.ensure_current().unwrap(), SearchIndexRebuild::default());
+        assert!(fts5_repo.get_candidates("cafe", Some(10)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trigram_index_substring_search() {
+        let (_temp_file, db) = create_test_db();
+        let doc_ids = create_test_documents_with_content(&db);
+        let content_repo = DocumentContentRepository::new(&db);
+        content_repo
+            .upsert("doc2", "東京タワーの案内", "JavaScript is a programming language.", None)
+            .expect("Failed to update content");
+        let fts5_repo = FTS5Repository::new(&db);
+        let manager = SearchIndexManager::new(&db);
+        let substring = |query: &str| -> Vec<i64> {
+            let query = SearchQuery::parse(query).unwrap();
+            fts5_repo
+                .search_substring(&query, Some(10), false, &RetrievalFilter::default())
+                .expect("Substring search failed")
+                .into_iter()
+                .map(|r| r.document_id)
+                .collect()
+        };
+        
+        let query = SearchQuery::parse("gramm").unwrap();
+        assert!(fts5_repo.search_substring(&query, Some(10), false, &RetrievalFilter::default()).is_err());
+        
+        let settings = SearchIndexSettings { trigram_index: true, ..Default::default() };
+        let rebuild = manager.apply(&settings).expect("Failed to enable trigram index");
+        assert!(rebuild.trigram_index_changed);
+        assert!(!rebuild.main_index_rebuilt);
+        assert!(fts5_repo.has_trigram_index().unwrap());
+        
+        assert!(fts5_repo.get_candidates("京タ", Some(10)).unwrap().is_empty());
+        assert_eq!(substring("京タワ"), vec![doc_ids[1]]);
+        assert_eq!(substring("title:ワーの").len(), 1);
+        assert_eq!(substring("alytic").len(), 1);
+        assert!(substring("ql").is_empty());
+        
+        // The trigram index follows content changes through its triggers
+        content_repo.upsert("doc4", "Machine Learning", "Neural networks.", None).unwrap();
+        assert!(substring("alytic").is_empty());
+        
+        manager.apply(&SearchIndexSettings::default()).expect("Failed to disable trigram index");
+        assert!(!fts5_repo.has_trigram_index().unwrap());
+        content_repo.delete_by_document_id("doc1").expect("Failed to delete content");
+    }
+
+    #[test]
+    fn test_find_match_ranges() {
+        let (_temp_file, db) = create_test_db();
+        let doc_ids = create_test_documents_with_content(&db);
+        let content_repo = DocumentContentRepository::new(&db);
+        let source = "# Rust Programming\n\nRust programs are fast.\nPrograms écrits en Rust.";
+        content_repo.upsert("doc1", "Rust Programming", source, None).unwrap();
+        let fts5_repo = FTS5Repository::new(&db);
+        let find = |query: &str| {
+            let query = SearchQuery::parse(query).unwrap();
+            fts5_repo.find_match_ranges(&query, None, &RetrievalFilter::default()).expect("Match search failed")
+        };
+
+        let documents = find("rust");
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].document_id, doc_ids[0]);
+        assert_eq!(documents[0].source_document_id, "doc1");
+        assert_eq!(documents[0].path, "doc1.md");
+        let matches = &documents[0].matches;
+        assert_eq!(matches.len(), 3);
+        for range in matches {
+            assert_eq!(source[range.byte_start..range.byte_end].to_lowercase(), "rust");
+        }
+        assert_eq!(matches[1].start, TextPosition { line: 2, column: 0 });
+        // "é" is two bytes but one column
+        assert_eq!(matches[2].byte_start, 64);
+        assert_eq!(matches[2].start, TextPosition { line: 3, column: 19 });
+
+        // Stemmed matches cover the word as written; documents come back in path order
+        let documents = find("programs");
+        let paths: Vec<&str> = documents.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["doc1.md", "doc2.md", "doc3.md"]);
+        let words: Vec<&str> = documents[0].matches.iter().map(|r| &source[r.byte_start..r.byte_end]).collect();
+        assert_eq!(words, vec!["Programming", "programs", "Programs"]);
+
+        assert!(find("title:javascript -javascript").is_empty());
+        assert!(find("programs project:other-project").is_empty());
+    }
+
+    #[test]
+    fn test_search_with_correction() {
+        let (_temp_file, db) = create_test_db();
+        let _doc_ids = create_test_documents_with_content(&db);
+        let fts5_repo = FTS5Repository::new(&db);
+        let filter = RetrievalFilter::default();
+
+        // Known words are searched as typed
+        let exact = fts5_repo.search_with_correction("programming", Some(10), &filter).unwrap();
+        assert!(exact.suggested_query.is_none());
+        assert_eq!(exact.results.len(), 3);
+
+        // A misspelling with no hits is retried with the closest indexed term
+        let corrected = fts5_repo.search_with_correction("programing langauge", Some(10), &filter).unwrap();
+        assert_eq!(corrected.suggested_query.as_deref(), Some("programming language"));
+        assert_eq!(corrected.results.len(), 3);
+
+        // Field filters and prefix terms survive the correction untouched
+        let fielded = fts5_repo.search_with_correction("title:databse algorith*", Some(10), &filter).unwrap();
+        assert_eq!(fielded.suggested_query.as_deref(), Some("title:database algorith*"));
+        assert!(fielded.results.is_empty());
+
+        let gibberish = fts5_repo.search_with_correction("qwzxv", Some(10), &filter).unwrap();
+        assert!(gibberish.suggested_query.is_none());
+        assert!(gibberish.results.is_empty());
+
+        // The dictionary follows content changes through its triggers
+        let dictionary = TermDictionary::new(&db, TermSource::Documents);
+        assert!(dictionary.contains("segfaults").unwrap());
+        DocumentContentRepository::new(&db).delete_by_document_id("doc1").unwrap();
+        assert!(!dictionary.contains("segfaults").unwrap());
+
+        // AI Block names and descriptions feed their own dictionary
+        let blocks = TermDictionary::new(&db, TermSource::AiBlocks);
+        assert_eq!(blocks.correct_text("sumarize").unwrap().as_deref(), Some("summarize"));
+    }
+
+    // ===== HNSW Approximate Vector Search Tests =====
+
+    const TEST_MODEL: &str = "test-model";
+
+    /// Helper function to insert synthetic vectors (foreign keys are disabled
+    /// because these tests only exercise the vector index)
+    fn insert_test_vectors(db: &DatabaseConnection, count: i64, dimension: usize) {
+        db.execute("PRAGMA foreign_keys = OFF", &[]).expect("Failed to disable foreign keys");
+        let vector_repo = VectorIndexRepository::new(db);
+
+        for document_id in 1..=count {
+            let vector = VectorIndex {
+                id: None,
+                document_id,
+                embedding: seeded_vector(document_id as u64, dimension),
+                embedding_model: TEST_MODEL.to_string(),
+                dimension: dimension as i32,
+                created_at: 0,
+                updated_at: 0,
+            };
+            vector_repo.upsert(&vector).expect("Failed to insert vector");
+        }
+    }
+
+    fn approximate_options() -> VectorSearchOptions {
+        VectorSearchOptions {
+            mode: VectorSearchMode::Approximate,
+            ef_search: Some(100),
+            embedding_model: Some(TEST_MODEL.to_string()),
+            rescore_candidates: None,
+            flat_fallback_below: None,
+            filter: None,
+        }
+    }
+
+    #[test]
+    fn test_approximate_search_matches_exact() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 300, 16);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        let query = seeded_vector(42, 16);
+        let exact = vector_repo.find_similar(&query, Some(5), None)
+            .expect("Exact search failed");
+        let approximate = vector_repo.find_similar_with_options(&query, Some(5), None, &approximate_options())
+            .expect("Approximate search failed");
+
+        assert_eq!(approximate.len(), 5);
+        assert_eq!(approximate[0].0.document_id, exact[0].0.document_id);
+        assert_eq!(approximate[0].0.document_id, 42);
+    }
+
+    #[test]
+    fn test_ann_index_tracks_upsert_and_delete() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 50, 8);
+        let vector_repo = VectorIndexRepository::new(&db);
+        vector_repo.ensure_ann_index(TEST_MODEL).expect("Failed to build index");
+
+        // Move document 7 onto document 30's embedding
+        let moved = VectorIndex {
+            id: None,
+            document_id: 7,
+            embedding: seeded_vector(30, 8),
+            embedding_model: TEST_MODEL.to_string(),
+            dimension: 8,
+            created_at: 0,
+            updated_at: 0,
+        };
+        vector_repo.upsert(&moved).expect("Failed to upsert vector");
+        vector_repo.delete_by_document_id(30).expect("Failed to delete vector");
+
+        let results = vector_repo
+            .find_similar_with_options(&seeded_vector(30, 8), Some(1), None, &approximate_options())
+            .expect("Approximate search failed");
+
+        assert_eq!(results[0].0.document_id, 7);
+        assert_eq!(db.with_ann_indexes(|indexes| indexes[TEST_MODEL].len()), 49);
+    }
+
+    #[test]
+    fn test_ann_index_persisted_and_rebuilt_when_stale() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 20, 8);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        assert_eq!(vector_repo.rebuild_ann_index(TEST_MODEL, None).expect("Failed to rebuild"), 20);
+        let index_path = HnswIndex::index_path_for(db.database_path().unwrap(), TEST_MODEL);
+        assert!(index_path.exists());
+
+        // Write a row behind the repository's back, then force a reload from disk
+        let blob: Vec<u8> = seeded_vector(99, 8).iter().flat_map(|v| v.to_le_bytes()).collect();
+        db.execute(
+            "INSERT INTO vector_index (document_id, embedding, embedding_model, dimension) VALUES (99, ?1, ?2, 8)",
+            params![blob, TEST_MODEL],
+        ).expect("Failed to insert raw vector");
+        db.with_ann_indexes(|indexes| indexes.clear());
+
+        let results = vector_repo
+            .find_similar_with_options(&seeded_vector(99, 8), Some(1), None, &approximate_options())
+            .expect("Approximate search failed");
+
+        assert_eq!(results[0].0.document_id, 99);
+        assert_eq!(db.with_ann_indexes(|indexes| indexes[TEST_MODEL].len()), 21);
+    }
+
+    #[test]
+    fn test_ann_index_stale_after_same_second_update() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 20, 8);
+        let vector_repo = VectorIndexRepository::new(&db);
+        vector_repo.rebuild_ann_index(TEST_MODEL, None).expect("Failed to rebuild");
+
+        // Same row count and updated_at: only the generation counter records the change
+        let blob: Vec<u8> = seeded_vector(77, 8).iter().flat_map(|v| v.to_le_bytes()).collect();
+        db.execute(
+            "UPDATE vector_index SET embedding = ?1 WHERE document_id = 5 AND embedding_model = ?2",
+            params![blob, TEST_MODEL],
+        ).expect("Failed to update raw vector");
+        db.with_ann_indexes(|indexes| indexes.clear());
+
+        let results = vector_repo
+            .find_similar_with_options(&seeded_vector(77, 8), Some(1), None, &approximate_options())
+            .expect("Approximate search failed");
+        assert_eq!(results[0].0.document_id, 5);
+    }
+
+    // ===== Flat Vector Store Tests =====
+
+    fn flat_options() -> VectorSearchOptions {
+        VectorSearchOptions {
+            mode: VectorSearchMode::Flat,
+            embedding_model: Some(TEST_MODEL.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_flat_search_matches_exact() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 300, 16);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        let query = seeded_vector(42, 16);
+        let exact = vector_repo.find_similar(&query, Some(10), Some(0.1))
+            .expect("Exact search failed");
+        let flat = vector_repo.find_similar_with_options(&query, Some(10), Some(0.1), &flat_options())
+            .expect("Flat search failed");
+
+        assert_eq!(flat.len(), exact.len());
+        for ((flat_vector, flat_score), (exact_vector, exact_score)) in flat.iter().zip(&exact) {
+            assert_eq!(flat_vector.document_id, exact_vector.document_id);
+            assert!((flat_score - exact_score).abs() < 1e-4);
+        }
+
+        let store_path = FlatVectorStore::path_for(db.database_path().unwrap(), TEST_MODEL);
+        assert!(store_path.exists());
+    }
+
+    #[test]
+    fn test_flat_store_tracks_writes_and_reloads() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 50, 8);
+        let vector_repo = VectorIndexRepository::new(&db);
+        vector_repo.ensure_flat_store(TEST_MODEL).expect("Failed to build flat store");
+
+        let moved = VectorIndex {
+            id: None,
+            document_id: 7,
+            embedding: seeded_vector(30, 8),
+            embedding_model: TEST_MODEL.to_string(),
+            dimension: 8,
+            created_at: 0,
+            updated_at: 0,
+        };
+        vector_repo.upsert(&moved).expect("Failed to upsert vector");
+        vector_repo.delete_by_document_id(30).expect("Failed to delete vector");
+
+        let results = vector_repo
+            .find_similar_with_options(&seeded_vector(30, 8), Some(1), None, &flat_options())
+            .expect("Flat search failed");
+        assert_eq!(results[0].0.document_id, 7);
+
+        // Persist the overlay, then reopen from disk with a matching stamp
+        db.flush_flat_stores().expect("Failed to flush flat stores");
+        db.with_flat_stores(|stores| stores.clear());
+        vector_repo.ensure_flat_store(TEST_MODEL).expect("Failed to reopen flat store");
+        assert_eq!(db.with_flat_stores(|stores| stores[TEST_MODEL].len()), 49);
+    }
+
+    #[test]
+    fn test_approximate_search_falls_back_to_flat_store() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 40, 8);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        let options = VectorSearchOptions {
+            flat_fallback_below: Some(100),
+            ..approximate_options()
+        };
+        let results = vector_repo
+            .find_similar_with_options(&seeded_vector(12, 8), Some(3), None, &options)
+            .expect("Approximate search failed");
+
+        assert_eq!(results[0].0.document_id, 12);
+        assert!(db.with_ann_indexes(|indexes| indexes.is_empty()));
+        assert!(db.with_flat_stores(|stores| stores.contains_key(TEST_MODEL)));
+    }
+
+    // ===== Retrieval Filter Tests =====
+
+    /// UUID of fixture document `n`; its document_content key is also `n`, but the two
+    /// ids differ so queries joining the wrong column find nothing
+    fn fixture_id(n: u32) -> String {
+        format!("7f3c2a10-5b1e-4c2d-9a0f-{:012}", n)
+    }
+
+    fn fixture_ids(numbers: &[u32]) -> Vec<String> {
+        numbers.iter().map(|&n| fixture_id(n)).collect()
+    }
+
+    /// Documents 1..6 across two projects with varied states, paths and update times
+    fn create_filter_fixture(db: &DatabaseConnection) {
+        let project_repo = ProjectRepository::new(db);
+        let doc_repo = DocumentRepository::new(db);
+
+        for (id, path) in [("project-a", "/a"), ("project-b", "/b")] {
+            project_repo
+                .create(&Project::new(id.to_string(), id.to_string(), path.to_string()))
+                .expect("Failed to create project");
+        }
+
+        let documents = [
+            (1, "project-a", "specs/api.md", DocumentState::PRD, 100),
+            (2, "project-a", "specs/ui.md", DocumentState::Draft, 200),
+            (3, "project-a", "notes/todo.md", DocumentState::Memo, 300),
+            (4, "project-a", "specs%/odd.md", DocumentState::PRD, 400),
+            (5, "project-b", "specs/api.md", DocumentState::PRD, 500),
+            (6, "project-b", "archive/old.md", DocumentState::Archived, 600),
+        ];
+        for (n, project_id, path, state, updated_at) in documents {
+            let mut document = Document::new(fixture_id(n), project_id.to_string(), path.to_string());
+            document.state = state;
+            document.updated_at = updated_at;
+            doc_repo.create(&document).expect("Failed to create document");
+            // Stored in order so document keys 1..6 line up with the test vectors
+            let key = DocumentContentRepository::new(db)
+                .upsert(&fixture_id(n), path, "filter fixture", None)
+                .expect("Failed to store content");
+            assert_eq!(key, n as i64);
+        }
+
+        insert_test_vectors(db, 6, 8);
+    }
+
+    fn filtered_document_ids(db: &DatabaseConnection, filter: &RetrievalFilter) -> Vec<String> {
+        let (conditions, values) = filter.sql_conditions("d", 1);
+        let sql = format!("SELECT d.id FROM documents d WHERE 1 = 1{} ORDER BY d.id", conditions);
+        let query_params: Vec<&dyn rusqlite::ToSql> = values.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
+        db.query_map(&sql, &query_params, |row| row.get(0)).expect("Filter query failed")
+    }
+
+    #[test]
+    fn test_retrieval_filter_sql_conditions() {
+        let (_temp_file, db) = create_test_db();
+        create_filter_fixture(&db);
+
+        assert!(RetrievalFilter::default().is_empty());
+        assert_eq!(filtered_document_ids(&db, &RetrievalFilter::default()).len(), 6);
+        assert_eq!(filtered_document_ids(&db, &RetrievalFilter::for_project("project-b")), fixture_ids(&[5, 6]));
+
+        let filter = RetrievalFilter {
+            project_ids: vec!["project-a".to_string()],
+            states: vec![DocumentState::PRD, DocumentState::Draft],
+            ..Default::default()
+        };
+        assert_eq!(filtered_document_ids(&db, &filter), fixture_ids(&[1, 2, 4]));
+
+        // Prefixes are literal: "%" and "_" are not wildcards
+        let filter = RetrievalFilter {
+            path_prefixes: vec!["specs/".to_string(), "archive".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(filtered_document_ids(&db, &filter), fixture_ids(&[1, 2, 5, 6]));
+
+        let filter = RetrievalFilter {
+            updated_after: Some(200),
+            updated_before: Some(400),
+            ..Default::default()
+        };
+        assert_eq!(filtered_document_ids(&db, &filter), fixture_ids(&[2, 3, 4]));
+    }
+
+    #[test]
+    fn test_retrieval_filter_intersect() {
+        let (_temp_file, db) = create_test_db();
+        create_filter_fixture(&db);
+        let project_a = RetrievalFilter::for_project("project-a");
+
+        // An empty filter leaves the other unchanged
+        assert_eq!(project_a.intersect(&RetrievalFilter::default()), Some(project_a.clone()));
+
+        let drafts = RetrievalFilter {
+            states: vec![DocumentState::Draft],
+            updated_after: Some(200),
+            ..Default::default()
+        };
+        let both = project_a.intersect(&drafts).expect("Filters overlap");
+        assert_eq!(
+            filtered_document_ids(&db, &both),
+            filtered_document_ids(&db, &project_a)
+                .into_iter()
+                .filter(|id| filtered_document_ids(&db, &drafts).contains(id))
+                .collect::<Vec<_>>()
+        );
+
+        let specs = RetrievalFilter { path_prefixes: vec!["specs/".to_string()], ..Default::default() };
+        let nested = RetrievalFilter { path_prefixes: vec!["specs/api".to_string(), "notes/".to_string()], ..Default::default() };
+        assert_eq!(specs.intersect(&nested).unwrap().path_prefixes, vec!["specs/api"]);
+
+        // Filters no document can satisfy together
+        assert_eq!(project_a.intersect(&RetrievalFilter::for_project("project-b")), None);
+        let archive = RetrievalFilter { path_prefixes: vec!["archive".to_string()], ..Default::default() };
+        assert_eq!(specs.intersect(&archive), None);
+        let before = RetrievalFilter { updated_before: Some(100), ..Default::default() };
+        assert_eq!(drafts.intersect(&before), None);
+    }
+
+    #[test]
+    fn test_retrieval_filter_serde_uses_state_names() {
+        let filter: RetrievalFilter = serde_json::from_str(r#"{"states": ["prd", "epic_breakdown"]}"#)
+            .expect("Failed to parse filter");
+        assert_eq!(filter.states, vec![DocumentState::PRD, DocumentState::EpicBreakdown]);
+        assert!(filter.project_ids.is_empty());
+
+        let json = serde_json::to_value(&filter).expect("Failed to serialize filter");
+        assert_eq!(json["states"], serde_json::json!(["prd", "epic_breakdown"]));
+
+        assert!(serde_json::from_str::<RetrievalFilter>(r#"{"states": ["published"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_filtered_vector_search_in_every_mode() {
+        let (_temp_file, db) = create_test_db();
+        create_filter_fixture(&db);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        // Document 5 is the best match overall but belongs to project-b
+        let query = seeded_vector(5, 8);
+        let unfiltered = vector_repo.find_similar(&query, Some(1), None).expect("Search failed");
+        assert_eq!(unfiltered[0].0.document_id, 5);
+
+        let filter = RetrievalFilter::for_project("project-a");
+        for mode in [
+            VectorSearchMode::Exact,
+            VectorSearchMode::Approximate,
+            VectorSearchMode::Flat,
+            VectorSearchMode::Int8Rescored,
+            VectorSearchMode::BinaryRescored,
+        ] {
+            let options = VectorSearchOptions {
+                mode,
+                embedding_model: Some(TEST_MODEL.to_string()),
+                filter: Some(filter.clone()),
+                ..Default::default()
+            };
+            let results = vector_repo.find_similar_with_options(&query, Some(10), Some(-1.0), &options)
+                .expect("Filtered search failed");
+
+            let mut document_ids: Vec<i64> = results.iter().map(|(v, _)| v.document_id).collect();
+            document_ids.sort();
+            assert_eq!(document_ids, vec![1, 2, 3, 4], "mode {:?}", mode);
+        }
+
+        let filtered = vector_repo.get_filtered(Some(TEST_MODEL), &RetrievalFilter::for_project("project-b"))
+            .expect("Failed to get filtered vectors");
+        let document_ids: Vec<i64> = filtered.iter().map(|v| v.document_id).collect();
+        assert_eq!(document_ids, vec![5, 6]);
+    }
+
+    // ===== Quantized Vector Storage Tests =====
+
+    #[test]
+    fn test_quantized_search_matches_exact() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 200, 64);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        let query = seeded_vector(17, 64);
+        let exact = vector_repo.find_similar(&query, Some(5), None)
+            .expect("Exact search failed");
+
+        for (mode, kind) in [
+            (VectorSearchMode::Int8Rescored, QuantizationKind::Int8),
+            (VectorSearchMode::BinaryRescored, QuantizationKind::Binary),
+        ] {
+            let settings = VectorStorageSettings { quantization: Some(kind), keep_full_precision: true };
+            let report = vector_repo.apply_storage_settings(&settings).expect("Failed to apply storage settings");
+            assert_eq!(report.vectors_converted, 200);
+
+            let options = VectorSearchOptions {
+                mode,
+                embedding_model: Some(TEST_MODEL.to_string()),
+                ..Default::default()
+            };
+            let results = vector_repo.find_similar_with_options(&query, Some(5), None, &options)
+                .expect("Quantized search failed");
+
+            assert_eq!(results.len(), 5);
+            assert_eq!(results[0].0.document_id, 17);
+            // Rescored similarities are exact, not quantized approximations
+            assert!((results[0].1 - exact[0].1).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_quantized_storage_replaces_full_precision() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_vectors(&db, 200, 64);
+        let vector_repo = VectorIndexRepository::new(&db);
+
+        // Quantized columns are only written once opted into
+        let quantized_rows: i64 = db.query_row(
+            "SELECT COUNT(*) FROM vector_index WHERE embedding_int8 IS NOT NULL OR embedding_binary IS NOT NULL",
+            &[],
+            |row| row.get(0),
+        ).expect("Failed to count quantized rows");
+        assert_eq!(quantized_rows, 0);
+
+        let settings = VectorStorageSettings { quantization: Some(QuantizationKind::Int8), keep_full_precision: false };
+        let report = vector_repo.apply_storage_settings(&settings).expect("Failed to apply storage settings");
+        assert_eq!(report.vectors_converted, 200);
+        assert!(report.bytes_saved() > 0);
+
+        let full_rows: i64 = db.query_row(
+            "SELECT COUNT(*) FROM vector_index WHERE LENGTH(embedding) > 0",
+            &[],
+            |row| row.get(0),
+        ).expect("Failed to count full-precision rows");
+        assert_eq!(full_rows, 0);
+
+        let options = VectorSearchOptions {
+            mode: VectorSearchMode::Int8Rescored,
+            embedding_model: Some(TEST_MODEL.to_string()),
+            ..Default::default()
+        };
+        let results = vector_repo.find_similar_with_options(&seeded_vector(17, 64), Some(5), None, &options)
+            .expect("Quantized search failed");
+        assert_eq!(results[0].0.document_id, 17);
+        assert!(results[0].1 > 0.99);
+
+        // Switching back cannot restore dropped vectors, so their encodings are kept
+        let report = vector_repo.apply_storage_settings(&VectorStorageSettings::default())
+            .expect("Failed to apply storage settings");
+        assert_eq!(report.vectors_converted, 0);
+        let stored = vector_repo.get_by_document_and_model(17, TEST_MODEL)
+            .expect("Failed to read vector")
+            .expect("Vector missing");
+        assert_eq!(stored.embedding.len(), 64);
+    }
+
+    #[test]
+    fn test_convert_vector_storage_reports_savings() {
+        let (_temp_file, db) = create_test_db();
+        db.execute("PRAGMA foreign_keys = OFF", &[]).expect("Failed to disable foreign keys");
+
+        // Simulate a row written before migration v8
+        let vector = seeded_vector(5, 384);
+        let json = serde_json::to_string(&vector).unwrap();
+        db.execute(
+            "INSERT INTO document_embeddings (document_id, chunk_index, content_hash, content_text, embedding_vector) VALUES (1, 0, 'hash', 'text', ?1)",
+            params![json],
+        ).expect("Failed to insert legacy embedding");
+
+        let report = MigrationManager::new(&db).convert_vector_storage()
+            .expect("Failed to convert vector storage");
+        assert_eq!(report.embeddings_converted, 1);
+        assert!(report.bytes_saved() > 0);
+
+        let embeddings = EmbeddingRepository::new(&db).get_embeddings_for_document(1)
+            .expect("Failed to read embeddings");
+        assert_eq!(embeddings[0].embedding_vector, vector);
+
+        // Already converted rows are not touched again
+        let report = MigrationManager::new(&db).convert_vector_storage()
+            .expect("Failed to convert vector storage");
+        assert_eq!(report.embeddings_converted, 0);
+        assert_eq!(report.bytes_saved(), 0);
+    }
+
+    // ===== Chunk-Level Vector Index Tests =====
+
+    /// Helper function to store one embedded chunk per (document, chunk) pair
+    fn insert_test_chunks(db: &DatabaseConnection, document_id: i64, chunks: &[(&str, u64)]) {
+        db.execute("PRAGMA foreign_keys = OFF", &[]).expect("Failed to disable foreign keys");
+        let embedding_repo = EmbeddingRepository::new(db);
+
+        let mut offset = 0;
+        for (index, (text, seed)) in chunks.iter().enumerate() {
+            let embedding = DocumentEmbedding {
+                id: None,
+                document_id,
+                chunk_index: index as i32,
+                content_hash: format!("hash-{}", document_id),
+                content_text: text.to_string(),
+                embedding_vector: seeded_vector(*seed, 16),
+                embedding_model: TEST_MODEL.to_string(),
+                chunk_start: Some(offset),
+                chunk_end: Some(offset + text.len() as i32),
+                created_at: 0,
+                updated_at: 0,
+            };
+            embedding_repo.upsert_embedding(&embedding).expect("Failed to store chunk");
+            offset += text.len() as i32 + 2;
+        }
+    }
+
+    #[test]
+    fn test_find_similar_passages() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_chunks(&db, 1, &[("Intro paragraph", 1), ("Answer paragraph", 2)]);
+        insert_test_chunks(&db, 2, &[("Other document", 3)]);
+        let chunk_repo = ChunkVectorRepository::new(&db);
+
+        assert_eq!(chunk_repo.count_for_document(1).expect("Failed to count"), 2);
+
+        let passages = chunk_repo
+            .find_similar_passages(&seeded_vector(2, 16), Some(1), None, Some(TEST_MODEL), None)
+            .expect("Passage search failed");
+
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].document_id, 1);
+        assert_eq!(passages[0].chunk_index, 1);
+        assert_eq!(passages[0].text, "Answer paragraph");
+        assert_eq!(passages[0].byte_start, Some(17));
+        assert_eq!(passages[0].byte_end, Some(33));
+    }
+
+    #[test]
+    fn test_find_similar_passages_restricted_to_documents() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_chunks(&db, 1, &[("First document", 7)]);
+        insert_test_chunks(&db, 2, &[("Second document", 8)]);
+        let chunk_repo = ChunkVectorRepository::new(&db);
+
+        let passages = chunk_repo
+            .find_similar_passages(&seeded_vector(7, 16), None, None, None, Some(&[2]))
+            .expect("Passage search failed");
+        assert!(passages.iter().all(|p| p.document_id == 2));
+
+        let passages = chunk_repo
+            .find_similar_passages(&seeded_vector(7, 16), None, None, None, Some(&[]))
+            .expect("Passage search failed");
+        assert!(passages.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_index_follows_embedding_deletes() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_chunks(&db, 1, &[("One", 1), ("Two", 2)]);
+        let chunk_repo = ChunkVectorRepository::new(&db);
+
+        EmbeddingRepository::new(&db).delete_embeddings_for_document(1)
+            .expect("Failed to delete embeddings");
+        assert_eq!(chunk_repo.count_for_document(1).expect("Failed to count"), 0);
+
+        insert_test_chunks(&db, 1, &[("One", 1)]);
+        db.execute("DELETE FROM chunk_vector_index", &[]).expect("Failed to clear chunk index");
+        assert_eq!(chunk_repo.rebuild().expect("Failed to rebuild"), 1);
+    }
+
+    #[test]
+    fn test_chunk_index_reads_vectors_from_embeddings() {
+        let (_temp_file, db) = create_test_db();
+        insert_test_chunks(&db, 1, &[("Only chunk", 4)]);
+
+        // The chunk index references document_embeddings instead of holding a copy
+        let copies: i64 = db.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('chunk_vector_index') WHERE name = 'embedding'",
+            &[],
+            |row| row.get(0),
+        ).expect("Failed to inspect chunk_vector_index");
+        assert_eq!(copies, 0);
+
+        // Re-embedding a chunk is picked up without touching chunk_vector_index
+        let blob: Vec<u8> = seeded_vector(9, 16).iter().flat_map(|v| v.to_le_bytes()).collect();
+        db.execute("UPDATE document_embeddings SET embedding_blob = ?1 WHERE document_id = 1", params![blob])
+            .expect("Failed to update embedding");
+
+        let passages = ChunkVectorRepository::new(&db)
+            .find_similar_passages(&seeded_vector(9, 16), Some(1), None, Some(TEST_MODEL), None)
+            .expect("Passage search failed");
+        assert!((passages[0].similarity - 1.0).abs() < 1e-5);
+    }
 }
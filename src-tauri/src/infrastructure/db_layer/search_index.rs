@@ -0,0 +1,250 @@
+// Search Index Settings
+// Per-database FTS5 tokenizer configuration and the optional trigram index used for
+// substring and CJK matching. Changing the settings rebuilds the affected indexes.
+
+use crate::infrastructure::db_layer::{DatabaseConnection, SettingsRepository};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// database_settings key holding the serialized SearchIndexSettings
+const SETTINGS_KEY: &str = "search_index";
+
+/// database_settings key recording the tokenizer documents_fts was last built with
+const APPLIED_TOKENIZER_KEY: &str = "search_index_applied_tokenizer";
+
+/// Secondary FTS5 table indexing document_content with the trigram tokenizer
+pub const TRIGRAM_TABLE: &str = "documents_fts_trigram";
+
+/// Tokenizer used by the main documents_fts index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FtsTokenizer {
+    /// English stemming over ASCII tokens; non-ASCII characters are kept verbatim
+    PorterAscii,
+    /// Unicode word splitting with accents folded ("café" matches "cafe")
+    Unicode61,
+    /// English stemming on top of accent-folding Unicode tokens
+    PorterUnicode61,
+}
+
+impl FtsTokenizer {
+    /// Value of the FTS5 `tokenize` option
+    pub fn tokenize_option(&self) -> &'static str {
+        match self {
+            FtsTokenizer::PorterAscii => "porter ascii",
+            FtsTokenizer::Unicode61 => "unicode61 remove_diacritics 2",
+            FtsTokenizer::PorterUnicode61 => "porter unicode61 remove_diacritics 2",
+        }
+    }
+}
+
+/// Search index configuration stored per database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndexSettings {
+    pub tokenizer: FtsTokenizer,
+    /// Maintain a secondary trigram index for substring and CJK search
+    pub trigram_index: bool,
+}
+
+impl Default for SearchIndexSettings {
+    fn default() -> Self {
+        Self {
+            tokenizer: FtsTokenizer::PorterUnicode61,
+            trigram_index: false,
+        }
+    }
+}
+
+/// Which indexes were rebuilt when settings were applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchIndexRebuild {
+    pub main_index_rebuilt: bool,
+    pub trigram_index_changed: bool,
+}
+
+/// Reads, stores and applies search index settings
+pub struct SearchIndexManager<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> SearchIndexManager<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Stored settings, or the defaults if none were saved
+    pub fn settings(&self) -> Result<SearchIndexSettings, String> {
+        let sql = "SELECT value FROM database_settings WHERE key = ?1";
+        let values = self.db.query_map(sql, &[&SETTINGS_KEY], |row| row.get::<_, String>(0))?;
+
+        match values.into_iter().next() {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid search index settings: {}", e)),
+            None => Ok(SearchIndexSettings::default()),
+        }
+    }
+
+    /// Save settings and rebuild whichever indexes no longer match them
+    pub fn apply(&self, settings: &SearchIndexSettings) -> Result<SearchIndexRebuild, String> {
+        let json = serde_json::to_string(settings)
+            .map_err(|e| format!("Failed to serialize search index settings: {}", e))?;
+        let sql = r#"
+            INSERT INTO database_settings (key, value, updated_at)
+            VALUES (?1, ?2, strftime('%s', 'now'))
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#;
+        self.db.execute(sql, &[&SETTINGS_KEY, &json])?;
+
+        self.ensure_current()
+    }
+
+    /// Rebuild indexes that differ from the stored settings.
+    /// The main index is compared by the tokenizer recorded when it was last built;
+    /// an index with no record (built before the record existed) is rebuilt once.
+    pub fn ensure_current(&self) -> Result<SearchIndexRebuild, String> {
+        let settings = self.settings()?;
+        let main_index_current = self.applied_tokenizer()? == Some(settings.tokenizer)
+            && self.table_sql("documents_fts")?.is_some();
+        let rebuild = SearchIndexRebuild {
+            main_index_rebuilt: !main_index_current,
+            trigram_index_changed: self.has_trigram_index()? != settings.trigram_index,
+        };
+
+        if rebuild.main_index_rebuilt {
+            self.rebuild_main_index(settings.tokenizer)?;
+        }
+
+        if rebuild.trigram_index_changed {
+            if settings.trigram_index {
+                info!("Building trigram index for substring search");
+                self.execute_in_transaction(&Self::create_trigram_sql())?;
+            } else {
+                info!("Dropping trigram index");
+                self.execute_in_transaction(&Self::drop_trigram_sql())?;
+            }
+        }
+
+        Ok(rebuild)
+    }
+
+    /// Whether the trigram index exists
+    pub fn has_trigram_index(&self) -> Result<bool, String> {
+        Ok(self.table_sql(TRIGRAM_TABLE)?.is_some())
+    }
+
+    /// Tokenizer documents_fts was last built with, if recorded
+    fn applied_tokenizer(&self) -> Result<Option<FtsTokenizer>, String> {
+        // An unreadable record only costs a rebuild, which rewrites it
+        Ok(SettingsRepository::new(self.db)
+            .get(APPLIED_TOKENIZER_KEY)?
+            .and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    /// Recreate documents_fts with a tokenizer and record it in the same transaction
+    fn rebuild_main_index(&self, tokenizer: FtsTokenizer) -> Result<(), String> {
+        let tokenize = tokenizer.tokenize_option();
+        info!("Rebuilding FTS5 index with tokenizer '{}'", tokenize);
+        let applied = serde_json::to_string(&tokenizer)
+            .map_err(|e| format!("Failed to serialize search index tokenizer: {}", e))?;
+        let sql = format!(
+            r#"
+            DROP TABLE IF EXISTS documents_fts;
+            CREATE VIRTUAL TABLE documents_fts
+            USING fts5(
+                title,
+                content,
+                content=document_content,
+                content_rowid=id,
+                tokenize='{}'
+            );
+            INSERT INTO documents_fts(documents_fts) VALUES('rebuild');
+            "#,
+            tokenize
+        );
+
+        self.db.with_connection(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(&sql)?;
+            tx.execute(
+                r#"
+                INSERT INTO database_settings (key, value, updated_at)
+                VALUES (?1, ?2, strftime('%s', 'now'))
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                "#,
+                params![APPLIED_TOKENIZER_KEY, applied],
+            )?;
+            tx.commit()
+        })
+    }
+
+    /// Run a rebuild script atomically so a failure leaves the old index in place
+    fn execute_in_transaction(&self, sql: &str) -> Result<(), String> {
+        self.db.with_connection(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(sql)?;
+            tx.commit()
+        })
+    }
+
+    /// CREATE statement of a table, if it exists
+    fn table_sql(&self, table: &str) -> Result<Option<String>, String> {
+        let sql = "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1";
+        let rows = self.db.query_map(sql, &[&table], |row| row.get::<_, String>(0))?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// Trigram table and the triggers keeping it in step with document_content
+    fn create_trigram_sql() -> String {
+        format!(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS {table}
+            USING fts5(
+                title,
+                content,
+                content=document_content,
+                content_rowid=id,
+                tokenize='trigram'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS document_content_trigram_insert
+            AFTER INSERT ON document_content
+            BEGIN
+                INSERT INTO {table}(rowid, title, content)
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS document_content_trigram_update
+            AFTER UPDATE OF title, content ON document_content
+            BEGIN
+                INSERT INTO {table}({table}, rowid, title, content)
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+                INSERT INTO {table}(rowid, title, content)
+                VALUES (NEW.id, NEW.title, NEW.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS document_content_trigram_delete
+            AFTER DELETE ON document_content
+            BEGIN
+                INSERT INTO {table}({table}, rowid, title, content)
+                VALUES ('delete', OLD.id, OLD.title, OLD.content);
+            END;
+
+            INSERT INTO {table}({table}) VALUES('rebuild');
+            "#,
+            table = TRIGRAM_TABLE
+        )
+    }
+
+    fn drop_trigram_sql() -> String {
+        format!(
+            r#"
+            DROP TRIGGER IF EXISTS document_content_trigram_insert;
+            DROP TRIGGER IF EXISTS document_content_trigram_update;
+            DROP TRIGGER IF EXISTS document_content_trigram_delete;
+            DROP TABLE IF EXISTS {};
+            "#,
+            TRIGRAM_TABLE
+        )
+    }
+}
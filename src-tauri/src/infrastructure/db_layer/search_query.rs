@@ -0,0 +1,455 @@
+// Search Query
+// Parser for the search box syntax: phrases, prefixes, exclusions, OR and field filters.
+// Queries compile to a quoted FTS5 expression plus a RetrievalFilter for the SQL side,
+// so user input can never inject FTS5 syntax.
+
+use crate::core::DocumentState;
+use crate::infrastructure::db_layer::RetrievalFilter;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// FTS5 columns that can be targeted with `column:term`
+const TEXT_FIELDS: [&str; 2] = ["title", "content"];
+
+/// Fields that become SQL predicates on the documents table
+const FILTER_FIELDS: [&str; 3] = ["state", "project", "path"];
+
+/// How a term is matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermKind {
+    /// A single word (or a run of words FTS5 tokenizes apart, matched as a phrase)
+    Word,
+    /// A quoted phrase
+    Phrase,
+    /// A word ending in `*`, matching any token with that prefix
+    Prefix,
+}
+
+/// A text term of a search query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryTerm {
+    pub text: String,
+    pub kind: TermKind,
+    /// FTS5 column the term is restricted to (`title:` or `content:`)
+    pub column: Option<String>,
+}
+
+impl QueryTerm {
+    /// FTS5 form of the term; the text is always quoted so it is matched literally
+    fn to_fts5(&self) -> String {
+        let mut expression = escape_fts5_string(&self.text);
+        if self.kind == TermKind::Prefix {
+            expression.push_str(" *");
+        }
+        match &self.column {
+            Some(column) => format!("{} : {}", column, expression),
+            None => expression,
+        }
+    }
+}
+
+impl fmt::Display for QueryTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(column) = &self.column {
+            write!(f, "{}:", column)?;
+        }
+        match self.kind {
+            TermKind::Prefix => write!(f, "{}*", self.text),
+            TermKind::Word if !needs_quotes(&self.text) => write!(f, "{}", self.text),
+            _ => write!(f, "{}", quote_value(&self.text)),
+        }
+    }
+}
+
+/// Malformed search input, with the byte offset where the problem was found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl QueryParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self { position, message: message.into() }
+    }
+}
+
+impl fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid search query at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+/// A parsed search query.
+///
+/// Syntax: `word`, `"exact phrase"`, `prefix*`, `-excluded`, `a OR b`,
+/// `title:word`, `content:word`, `state:prd`, `project:<id>` and `path:<prefix>`.
+/// Terms are ANDed together; repeated field filters of the same kind are ORed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    /// Required clauses; a document must match at least one term of every clause
+    pub clauses: Vec<Vec<QueryTerm>>,
+    /// Terms a matching document must not contain
+    pub excluded: Vec<QueryTerm>,
+    /// Document filters from `state:`, `project:` and `path:`
+    pub filter: RetrievalFilter,
+}
+
+/// A lexed item of the query before OR groups are assembled
+enum Item {
+    Term { term: QueryTerm, excluded: bool, position: usize },
+    Or { position: usize },
+}
+
+impl SearchQuery {
+    /// Parse search box input
+    pub fn parse(input: &str) -> Result<Self, QueryParseError> {
+        let mut query = SearchQuery::default();
+        let mut items = Vec::new();
+        let mut position = 0;
+
+        while let Some(start) = next_token_start(input, position) {
+            let (item, end) = query.lex_item(input, start)?;
+            if let Some(item) = item {
+                items.push(item);
+            }
+            position = end;
+        }
+
+        query.assemble(items)?;
+        Ok(query)
+    }
+
+    /// Every word of `input` as a literal required term, ignoring the search syntax.
+    /// Used when the input does not parse, so a stray quote or `OR` still finds the words.
+    pub fn plain_terms(input: &str) -> Self {
+        let clauses = input
+            .split_whitespace()
+            .filter(|word| *word != "OR" && word.chars().any(char::is_alphanumeric))
+            .map(|word| vec![QueryTerm { text: word.to_string(), kind: TermKind::Word, column: None }])
+            .collect();
+        SearchQuery { clauses, ..Default::default() }
+    }
+
+    /// Whether the query has no text terms and no filters
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty() && self.excluded.is_empty() && self.filter.is_empty()
+    }
+
+    /// FTS5 MATCH expression for the text terms, or None for a filter-only query
+    pub fn to_fts5(&self) -> Option<String> {
+        if self.clauses.is_empty() {
+            return None;
+        }
+
+        let clauses: Vec<String> = self.clauses
+            .iter()
+            .map(|clause| {
+                let terms: Vec<String> = clause.iter().map(QueryTerm::to_fts5).collect();
+                if terms.len() == 1 {
+                    terms.into_iter().next().unwrap_or_default()
+                } else {
+                    format!("({})", terms.join(" OR "))
+                }
+            })
+            .collect();
+
+        let mut expression = clauses.join(" AND ");
+        if !self.excluded.is_empty() {
+            expression = format!("({})", expression);
+            for term in &self.excluded {
+                expression.push_str(" NOT ");
+                expression.push_str(&term.to_fts5());
+            }
+        }
+        Some(expression)
+    }
+
+    /// The positive search words without syntax, for embedding the query
+    pub fn free_text(&self) -> String {
+        self.clauses
+            .iter()
+            .flatten()
+            .map(|term| term.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Lex one whitespace-delimited item starting at `start`.
+    /// Field filters are applied to `self.filter` directly and yield no item.
+    fn lex_item(&mut self, input: &str, start: usize) -> Result<(Option<Item>, usize), QueryParseError> {
+        let rest = &input[start..];
+
+        // A bare OR between terms; quoted "OR" is an ordinary word
+        if rest.starts_with("OR") && rest[2..].chars().next().is_none_or(char::is_whitespace) {
+            return Ok((Some(Item::Or { position: start }), start + 2));
+        }
+
+        let excluded = rest.starts_with('-') && rest.len() > 1 && !rest[1..].starts_with(char::is_whitespace);
+        let mut cursor = if excluded { start + 1 } else { start };
+
+        // field:value, where the field name is made of ASCII letters
+        let name_len = input[cursor..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(input.len() - cursor);
+        let mut column = None;
+        if name_len > 0 && input[cursor + name_len..].starts_with(':') {
+            let field = input[cursor..cursor + name_len].to_lowercase();
+            let value_start = cursor + name_len + 1;
+            let has_value = input[value_start..].chars().next().is_some_and(|c| !c.is_whitespace());
+
+            // A trailing colon on an ordinary word ("note:") is just punctuation
+            if !has_value && !TEXT_FIELDS.contains(&field.as_str()) && !FILTER_FIELDS.contains(&field.as_str()) {
+                let (value, end) = read_value(input, cursor)?;
+                let term = QueryTerm { text: value, kind: TermKind::Word, column: None };
+                return Ok((Some(Item::Term { term, excluded, position: start }), end));
+            }
+            if !has_value {
+                return Err(QueryParseError::new(start, format!("Field \"{}:\" needs a value", field)));
+            }
+
+            if TEXT_FIELDS.contains(&field.as_str()) {
+                column = Some(field);
+                cursor = value_start;
+            } else {
+                if excluded {
+                    return Err(QueryParseError::new(start, format!("Field filter \"{}:\" cannot be excluded", field)));
+                }
+                let (value, end) = read_value(input, value_start)?;
+                self.apply_field_filter(&field, value, cursor)?;
+                return Ok((None, end));
+            }
+        }
+
+        let quoted = input[cursor..].starts_with('"');
+        let (value, end) = read_value(input, cursor)?;
+        let term = if quoted {
+            QueryTerm { text: value, kind: TermKind::Phrase, column }
+        } else if let Some(prefix) = value.strip_suffix('*') {
+            if prefix.is_empty() || prefix.ends_with('*') {
+                return Err(QueryParseError::new(cursor, "A prefix search needs at least one character before \"*\""));
+            }
+            QueryTerm { text: prefix.to_string(), kind: TermKind::Prefix, column }
+        } else {
+            QueryTerm { text: value, kind: TermKind::Word, column }
+        };
+
+        Ok((Some(Item::Term { term, excluded, position: start }), end))
+    }
+
+    fn apply_field_filter(&mut self, field: &str, value: String, position: usize) -> Result<(), QueryParseError> {
+        match field {
+            "state" => {
+                let state = DocumentState::from_str(&value).ok_or_else(|| {
+                    QueryParseError::new(
+                        position,
+                        format!(
+                            "Unknown state \"{}\"; expected one of memo, prfaq, prd, epic_breakdown, draft, archived",
+                            value
+                        ),
+                    )
+                })?;
+                if !self.filter.states.contains(&state) {
+                    self.filter.states.push(state);
+                }
+            }
+            "project" => self.filter.project_ids.push(value),
+            "path" => self.filter.path_prefixes.push(value.trim_start_matches("./").to_string()),
+            _ => {
+                return Err(QueryParseError::new(
+                    position,
+                    format!(
+                        "Unknown field \"{}:\"; use title:, content:, state:, project: or path:, or quote the term",
+                        field
+                    ),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Group terms into OR clauses and exclusions
+    fn assemble(&mut self, items: Vec<Item>) -> Result<(), QueryParseError> {
+        let mut pending_or: Option<usize> = None;
+        let mut last_was_positive = false;
+
+        for item in items {
+            match item {
+                Item::Or { position } => {
+                    if !last_was_positive || pending_or.is_some() {
+                        return Err(QueryParseError::new(position, "OR must appear between two search terms"));
+                    }
+                    pending_or = Some(position);
+                }
+                Item::Term { term, excluded: true, position } => {
+                    if pending_or.is_some() {
+                        return Err(QueryParseError::new(position, "An excluded term cannot follow OR"));
+                    }
+                    self.excluded.push(term);
+                    last_was_positive = false;
+                }
+                Item::Term { term, excluded: false, .. } => {
+                    match (pending_or.take(), self.clauses.last_mut()) {
+                        (Some(_), Some(clause)) => clause.push(term),
+                        _ => self.clauses.push(vec![term]),
+                    }
+                    last_was_positive = true;
+                }
+            }
+        }
+
+        if let Some(position) = pending_or {
+            return Err(QueryParseError::new(position, "OR must appear between two search terms"));
+        }
+        if self.clauses.is_empty() && !self.excluded.is_empty() {
+            return Err(QueryParseError::new(0, "Add at least one search term for the exclusions to apply to"));
+        }
+        Ok(())
+    }
+}
+
+/// Renders the query back in search box syntax, e.g. for a "did you mean" link
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.clauses
+            .iter()
+            .map(|clause| clause.iter().map(ToString::to_string).collect::<Vec<_>>().join(" OR "))
+            .collect();
+        parts.extend(self.excluded.iter().map(|term| format!("-{}", term)));
+
+        let field_value = |value: &str| {
+            if needs_quotes(value) { quote_value(value) } else { value.to_string() }
+        };
+        parts.extend(self.filter.states.iter().map(|state| format!("state:{}", state.as_str())));
+        parts.extend(self.filter.project_ids.iter().map(|id| format!("project:{}", field_value(id))));
+        parts.extend(self.filter.path_prefixes.iter().map(|path| format!("path:{}", field_value(path))));
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Whether a bare word would be read back as something other than itself
+fn needs_quotes(text: &str) -> bool {
+    text.is_empty()
+        || text == "OR"
+        || text.starts_with('-')
+        || text.ends_with('*')
+        || text.contains(|c: char| c.is_whitespace() || c == '"' || c == ':')
+}
+
+fn quote_value(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Quote text as an FTS5 string so that every character is taken literally
+pub fn escape_fts5_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn next_token_start(input: &str, from: usize) -> Option<usize> {
+    input[from..]
+        .char_indices()
+        .find(|(_, c)| !c.is_whitespace())
+        .map(|(offset, _)| from + offset)
+}
+
+/// Read a quoted string (with `""` as an escaped quote) or a bare word starting at `start`
+fn read_value(input: &str, start: usize) -> Result<(String, usize), QueryParseError> {
+    let rest = &input[start..];
+
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            if c != '"' {
+                value.push(c);
+                continue;
+            }
+            if chars.peek().map(|&(_, next)| next) == Some('"') {
+                chars.next();
+                value.push('"');
+                continue;
+            }
+            if value.trim().is_empty() {
+                return Err(QueryParseError::new(start, "Empty quoted phrase"));
+            }
+            return Ok((value, start + 1 + offset + 1));
+        }
+        return Err(QueryParseError::new(start, "Unterminated quote; add a closing \""));
+    }
+
+    let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    Ok((rest[..len].to_string(), start + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fts(input: &str) -> Option<String> {
+        SearchQuery::parse(input).expect("query should parse").to_fts5()
+    }
+
+    #[test]
+    fn test_compiles_terms_phrases_prefixes_and_or() {
+        assert_eq!(fts("rust async").as_deref(), Some(r#""rust" AND "async""#));
+        assert_eq!(fts(r#""exact phrase" launch*"#).as_deref(), Some(r#""exact phrase" AND "launch" *"#));
+        assert_eq!(fts("a OR b c").as_deref(), Some(r#"("a" OR "b") AND "c""#));
+        assert_eq!(fts("title:roadmap -draft").as_deref(), Some(r#"(title : "roadmap") NOT "draft""#));
+        assert_eq!(fts(r#"say "he said ""hi""""#).as_deref(), Some(r#""say" AND "he said ""hi""""#));
+
+        // FTS5 operators and punctuation in plain words stay literal
+        assert_eq!(fts("NOT (x) AND y^").as_deref(), Some(r#""NOT" AND "(x)" AND "AND" AND "y^""#));
+        assert_eq!(fts("").as_deref(), None);
+    }
+
+    #[test]
+    fn test_field_filters() {
+        let query = SearchQuery::parse("state:PRD state:draft project:p1 path:./specs/ api").unwrap();
+        assert_eq!(query.filter.states, vec![DocumentState::PRD, DocumentState::Draft]);
+        assert_eq!(query.filter.project_ids, vec!["p1".to_string()]);
+        assert_eq!(query.filter.path_prefixes, vec!["specs/".to_string()]);
+        assert_eq!(query.free_text(), "api");
+
+        let filter_only = SearchQuery::parse(r#"path:"my docs/""#).unwrap();
+        assert_eq!(filter_only.filter.path_prefixes, vec!["my docs/".to_string()]);
+        assert!(filter_only.to_fts5().is_none());
+        assert!(!filter_only.is_empty());
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for input in [
+            r#"rust OR go "exact phrase" launch* -draft"#,
+            r#"title:roadmap content:"q3 plan" state:prd project:p1 path:"my docs/""#,
+            r#""OR" "-dash" "a:b""#,
+        ] {
+            let query = SearchQuery::parse(input).unwrap();
+            assert_eq!(SearchQuery::parse(&query.to_string()).unwrap(), query, "{}", input);
+        }
+        assert_eq!(SearchQuery::parse("b OR a  -c").unwrap().to_string(), "b OR a -c");
+    }
+
+    #[test]
+    fn test_malformed_queries_report_position() {
+        let cases = [
+            (r#"good "unterminated"#, 5, "Unterminated quote"),
+            ("OR rust", 0, "OR must appear"),
+            ("rust OR", 5, "OR must appear"),
+            ("rust OR OR go", 8, "OR must appear"),
+            ("state:shipped", 0, "Unknown state"),
+            ("owner:me", 0, "Unknown field"),
+            ("title: x", 0, "needs a value"),
+            ("path:", 0, "needs a value"),
+            ("-draft", 0, "at least one search term"),
+            ("-state:prd x", 0, "cannot be excluded"),
+            ("**", 0, "prefix search"),
+        ];
+
+        for (input, position, message) in cases {
+            let error = SearchQuery::parse(input).expect_err(input);
+            assert_eq!(error.position, position, "{}", input);
+            assert!(error.message.contains(message), "{}: {}", input, error.message);
+        }
+    }
+}
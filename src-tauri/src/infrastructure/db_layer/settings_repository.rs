@@ -0,0 +1,66 @@
+// Settings Repository
+// Raw JSON settings by key: database-wide values in database_settings, and
+// per-project overrides in project_settings. Callers own the (de)serialization.
+
+use crate::infrastructure::db_layer::DatabaseConnection;
+use rusqlite::params;
+
+/// Repository for database-wide and per-project settings
+pub struct SettingsRepository<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> SettingsRepository<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Database-wide value of a setting
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let sql = "SELECT value FROM database_settings WHERE key = ?1";
+        let values = self.db.query_map(sql, params![key], |row| row.get::<_, String>(0))?;
+        Ok(values.into_iter().next())
+    }
+
+    /// Store the database-wide value of a setting
+    pub fn set(&self, key: &str, value: &str) -> Result<(), String> {
+        let sql = r#"
+            INSERT INTO database_settings (key, value, updated_at)
+            VALUES (?1, ?2, strftime('%s', 'now'))
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#;
+        self.db.execute(sql, params![key, value])?;
+        Ok(())
+    }
+
+    /// Remove the database-wide value of a setting; returns whether one was stored
+    pub fn delete(&self, key: &str) -> Result<bool, String> {
+        let rows_affected = self.db.execute("DELETE FROM database_settings WHERE key = ?1", params![key])?;
+        Ok(rows_affected > 0)
+    }
+
+    /// A project's override of a setting
+    pub fn get_for_project(&self, project_id: &str, key: &str) -> Result<Option<String>, String> {
+        let sql = "SELECT value FROM project_settings WHERE project_id = ?1 AND key = ?2";
+        let values = self.db.query_map(sql, params![project_id, key], |row| row.get::<_, String>(0))?;
+        Ok(values.into_iter().next())
+    }
+
+    /// Store a project's override of a setting
+    pub fn set_for_project(&self, project_id: &str, key: &str, value: &str) -> Result<(), String> {
+        let sql = r#"
+            INSERT INTO project_settings (project_id, key, value, updated_at)
+            VALUES (?1, ?2, ?3, strftime('%s', 'now'))
+            ON CONFLICT(project_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+        "#;
+        self.db.execute(sql, params![project_id, key, value])?;
+        Ok(())
+    }
+
+    /// Remove a project's override of a setting; returns whether one was stored
+    pub fn delete_for_project(&self, project_id: &str, key: &str) -> Result<bool, String> {
+        let sql = "DELETE FROM project_settings WHERE project_id = ?1 AND key = ?2";
+        let rows_affected = self.db.execute(sql, params![project_id, key])?;
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,217 @@
+// Term Dictionary
+// "Did you mean" suggestions from the fts5vocab term dictionaries created in migration v12.
+// Unknown words are replaced by the nearest indexed term, scored by edit distance with a
+// bonus for terms that appear in many documents.
+
+use crate::infrastructure::db_layer::search_query::{escape_fts5_string, SearchQuery, TermKind};
+use crate::infrastructure::db_layer::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+/// Edit distance credited per natural-log unit of document frequency, so a term
+/// found in ~55x more documents can win over one that is a single edit closer
+const FREQUENCY_WEIGHT: f64 = 0.25;
+
+/// Words shorter than this are never corrected
+const MIN_CORRECTABLE_CHARS: usize = 3;
+
+/// Indexed text a dictionary is built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermSource {
+    /// Titles and bodies of documents (document_content)
+    Documents,
+    /// Names, descriptions and tags of AI Blocks
+    AiBlocks,
+}
+
+impl TermSource {
+    fn index_table(&self) -> &'static str {
+        match self {
+            TermSource::Documents => "document_terms",
+            TermSource::AiBlocks => "ai_block_terms",
+        }
+    }
+
+    fn vocab_table(&self) -> &'static str {
+        match self {
+            TermSource::Documents => "document_terms_vocab",
+            TermSource::AiBlocks => "ai_block_terms_vocab",
+        }
+    }
+}
+
+/// A suggested replacement for a word missing from the dictionary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TermCorrection {
+    pub word: String,
+    pub suggestion: String,
+    pub distance: usize,
+    /// Number of documents containing the suggested term
+    pub document_frequency: i64,
+}
+
+/// Spelling suggestions backed by an fts5vocab table
+pub struct TermDictionary<'a> {
+    db: &'a DatabaseConnection,
+    source: TermSource,
+}
+
+impl<'a> TermDictionary<'a> {
+    pub fn new(db: &'a DatabaseConnection, source: TermSource) -> Self {
+        Self { db, source }
+    }
+
+    /// Whether a word occurs in the indexed text (case and accents are ignored). A word
+    /// with punctuation inside, like `owner:me`, is several tokens; it occurs when each
+    /// of them does, as the index keeps no positions to match them as a phrase.
+    pub fn contains(&self, word: &str) -> Result<bool, String> {
+        let tokens: Vec<String> = word
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .map(escape_fts5_string)
+            .collect();
+        if tokens.is_empty() {
+            return Ok(false);
+        }
+
+        let sql = format!(
+            "SELECT EXISTS(SELECT 1 FROM {table} WHERE {table} MATCH ?1)",
+            table = self.source.index_table()
+        );
+        self.db.query_row(&sql, &[&tokens.join(" AND ")], |row| row.get(0))
+    }
+
+    /// Best correction for a word, or None if the word is known or nothing is close enough
+    pub fn suggest(&self, word: &str) -> Result<Option<TermCorrection>, String> {
+        let normalized = word.to_lowercase();
+        let length = normalized.chars().count();
+        if length < MIN_CORRECTABLE_CHARS
+            || !normalized.chars().any(char::is_alphabetic)
+            || self.contains(word)?
+        {
+            return Ok(None);
+        }
+
+        let max_distance = if length <= 4 { 1 } else { 2 };
+        let sql = format!(
+            "SELECT term, doc FROM {} WHERE length(term) BETWEEN ?1 AND ?2",
+            self.source.vocab_table()
+        );
+        let min_length = length.saturating_sub(max_distance) as i64;
+        let max_length = (length + max_distance) as i64;
+        let terms = self.db.query_map(&sql, &[&min_length, &max_length], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut best: Option<(f64, TermCorrection)> = None;
+        for (term, document_frequency) in terms {
+            let Some(distance) = edit_distance(&normalized, &term, max_distance) else {
+                continue;
+            };
+            if distance == 0 {
+                continue;
+            }
+
+            let score = distance as f64 - FREQUENCY_WEIGHT * (1.0 + document_frequency as f64).ln();
+            if best.as_ref().is_none_or(|(best_score, _)| score < *best_score) {
+                best = Some((
+                    score,
+                    TermCorrection {
+                        word: word.to_string(),
+                        suggestion: term,
+                        distance,
+                        document_frequency,
+                    },
+                ));
+            }
+        }
+
+        Ok(best.map(|(_, correction)| correction))
+    }
+
+    /// Correct each unknown word of plain text; None if nothing changed
+    pub fn correct_text(&self, text: &str) -> Result<Option<String>, String> {
+        let mut changed = false;
+        let mut words = Vec::new();
+        for word in text.split_whitespace() {
+            match self.suggest(word)? {
+                Some(correction) => {
+                    changed = true;
+                    words.push(correction.suggestion);
+                }
+                None => words.push(word.to_string()),
+            }
+        }
+
+        Ok(if changed { Some(words.join(" ")) } else { None })
+    }
+
+    /// Correct the words and phrases of a parsed query; prefix terms, exclusions
+    /// and field filters are kept as typed. None if nothing changed.
+    pub fn correct_query(&self, query: &SearchQuery) -> Result<Option<SearchQuery>, String> {
+        let mut corrected = query.clone();
+        let mut changed = false;
+
+        for term in corrected.clauses.iter_mut().flatten() {
+            if term.kind == TermKind::Prefix {
+                continue;
+            }
+            if let Some(text) = self.correct_text(&term.text)? {
+                term.text = text;
+                changed = true;
+            }
+        }
+
+        Ok(if changed { Some(corrected) } else { None })
+    }
+}
+
+/// Optimal string alignment distance (Levenshtein plus adjacent transpositions),
+/// or None once it is certain to exceed `max_distance`
+pub fn edit_distance(a: &str, b: &str, max_distance: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max_distance {
+        return None;
+    }
+
+    let mut two_back: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = current[0];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(two_back[j - 2] + 1);
+            }
+            row_min = row_min.min(current[j]);
+        }
+        if row_min > max_distance {
+            return None;
+        }
+        std::mem::swap(&mut two_back, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max_distance).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("database", "database", 2), Some(0));
+        assert_eq!(edit_distance("databse", "database", 2), Some(1));
+        assert_eq!(edit_distance("dtaabase", "database", 2), Some(1));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("café", "cafe", 1), Some(1));
+        assert_eq!(edit_distance("a", "abcd", 2), None);
+    }
+}
@@ -0,0 +1,84 @@
+// Vector Math
+// Shared similarity kernels and bookkeeping types for the vector indexes
+
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of the vector_index rows an on-disk index was built from.
+/// Used on load to detect whether the persisted file is stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VectorSourceStamp {
+    pub row_count: i64,
+    /// Value of the model's vector_index_generations counter, bumped on every write
+    pub generation: i64,
+}
+
+/// A vector_index row returned by an in-memory or memory-mapped index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorSearchHit {
+    pub vector_id: i64,
+    pub document_id: i64,
+    pub similarity: f32,
+}
+
+/// Dot product of two equal-length vectors.
+/// Accumulates in eight independent lanes so the compiler emits SIMD instructions.
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0.0f32; 8];
+    let a_chunks = a.chunks_exact(8);
+    let b_chunks = b.chunks_exact(8);
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+
+    for (x, y) in a_chunks.zip(b_chunks) {
+        for ((lane, x), y) in lanes.iter_mut().zip(x).zip(y) {
+            *lane += x * y;
+        }
+    }
+
+    lanes.iter().sum::<f32>() + tail
+}
+
+/// Return an L2-normalized copy of a vector (zero vectors are returned unchanged)
+pub fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm: f32 = dot_product(vector, vector).sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+/// Cosine similarity of two vectors (0.0 when either has zero length)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let norm_a = dot_product(a, a).sqrt();
+    let norm_b = dot_product(b, b).sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot_product(a, b) / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_product_matches_scalar() {
+        let a: Vec<f32> = (0..37).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let b: Vec<f32> = (0..37).map(|i| (i as f32).sin()).collect();
+
+        let scalar: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        assert!((dot_product(&a, &b) - scalar).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalized = normalize(&[3.0, 4.0]);
+        assert!((normalized[0] - 0.6).abs() < 1e-6);
+        assert!((normalized[1] - 0.8).abs() < 1e-6);
+        assert_eq!(normalize(&[0.0, 0.0]), vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,271 @@
+// Vector Quantization
+// Compact int8 and binary encodings of embeddings for fast candidate search
+
+use serde::{Deserialize, Serialize};
+
+/// database_settings key holding the serialized VectorStorageSettings
+pub const VECTOR_STORAGE_SETTINGS_KEY: &str = "vector_storage";
+
+/// Compressed representations of vector_index embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuantizationKind {
+    /// Symmetric per-vector scalar quantization to i8 (4x smaller than f32)
+    Int8,
+    /// One sign bit per dimension (32x smaller than f32)
+    Binary,
+}
+
+impl QuantizationKind {
+    /// vector_index column holding this encoding
+    pub fn column_name(&self) -> &'static str {
+        match self {
+            QuantizationKind::Int8 => "embedding_int8",
+            QuantizationKind::Binary => "embedding_binary",
+        }
+    }
+}
+
+/// How vector_index rows are stored. Quantization is opt-in: when enabled, the chosen
+/// encoding replaces the f32 column unless full precision is kept for exact rescoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorStorageSettings {
+    /// Compressed encoding to store; None stores full-precision vectors only
+    pub quantization: Option<QuantizationKind>,
+    /// Also keep the f32 vector so quantized candidates are rescored exactly
+    pub keep_full_precision: bool,
+}
+
+/// Column values of a vector_index row: f32 BLOB (empty when dropped), int8 and binary encodings
+pub type StoredVectorColumns = (Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>);
+
+impl VectorStorageSettings {
+    /// Whether rows keep their f32 embedding
+    pub fn stores_full_precision(&self) -> bool {
+        self.quantization.is_none() || self.keep_full_precision
+    }
+
+    /// Encode a vector into the columns these settings store
+    pub fn encode(&self, vector: &[f32]) -> StoredVectorColumns {
+        let full = if self.stores_full_precision() {
+            vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+        } else {
+            Vec::new()
+        };
+        let int8 = (self.quantization == Some(QuantizationKind::Int8)).then(|| quantize_int8(vector));
+        let binary = (self.quantization == Some(QuantizationKind::Binary)).then(|| quantize_binary(vector));
+        (full, int8, binary)
+    }
+}
+
+/// Decode a stored vector_index row, falling back to its quantized encoding when the
+/// f32 column was dropped. Such vectors are approximations of the original embedding.
+pub fn decode_stored_vector(
+    full: &[u8],
+    int8: Option<&[u8]>,
+    binary: Option<&[u8]>,
+    dimension: usize,
+) -> Result<Vec<f32>, String> {
+    if !full.is_empty() {
+        if full.len() % 4 != 0 {
+            return Err("Invalid BLOB size for f32 vector".to_string());
+        }
+        return Ok(full
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect());
+    }
+    match (int8, binary) {
+        (Some(int8), _) => dequantize_int8(int8),
+        (None, Some(binary)) => dequantize_binary(binary, dimension),
+        (None, None) => Err("Vector has no stored encoding".to_string()),
+    }
+}
+
+/// Encode a vector as int8: a little-endian f32 scale followed by one i8 per dimension.
+/// Each component is stored as `round(x / scale)` with `scale = max(|x|) / 127`.
+pub fn quantize_int8(vector: &[f32]) -> Vec<u8> {
+    let max_abs = vector.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+    let scale = if max_abs > 0.0 { max_abs / 127.0 } else { 1.0 };
+
+    let mut blob = Vec::with_capacity(4 + vector.len());
+    blob.extend_from_slice(&scale.to_le_bytes());
+    for &value in vector {
+        let quantized = (value / scale).round().clamp(-127.0, 127.0) as i8;
+        blob.push(quantized as u8);
+    }
+    blob
+}
+
+/// Decode an int8 blob back to an approximate f32 vector
+pub fn dequantize_int8(blob: &[u8]) -> Result<Vec<f32>, String> {
+    let (scale, values) = split_int8(blob)?;
+    Ok(values.iter().map(|&v| v as i8 as f32 * scale).collect())
+}
+
+/// Approximate cosine similarity between two int8 blobs.
+/// The per-vector scales cancel out, so only the integer components are used.
+pub fn int8_cosine_similarity(a: &[u8], b: &[u8]) -> Result<f32, String> {
+    let (_, a) = split_int8(a)?;
+    let (_, b) = split_int8(b)?;
+    if a.len() != b.len() {
+        return Err("Embedding dimensions must match".to_string());
+    }
+
+    let mut dot = 0i64;
+    let mut norm_a = 0i64;
+    let mut norm_b = 0i64;
+    for (&x, &y) in a.iter().zip(b) {
+        let (x, y) = (x as i8 as i64, y as i8 as i64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0 || norm_b == 0 {
+        return Ok(0.0);
+    }
+    Ok(dot as f32 / ((norm_a as f32).sqrt() * (norm_b as f32).sqrt()))
+}
+
+/// Encode a vector as packed sign bits (bit set for non-negative components)
+pub fn quantize_binary(vector: &[f32]) -> Vec<u8> {
+    let mut blob = vec![0u8; vector.len().div_ceil(8)];
+    for (i, &value) in vector.iter().enumerate() {
+        if value >= 0.0 {
+            blob[i / 8] |= 1 << (i % 8);
+        }
+    }
+    blob
+}
+
+/// Decode sign bits to a unit vector with components of ±1/sqrt(dimension)
+pub fn dequantize_binary(blob: &[u8], dimension: usize) -> Result<Vec<f32>, String> {
+    if blob.len() != dimension.div_ceil(8) {
+        return Err("Invalid BLOB size for binary vector".to_string());
+    }
+    let magnitude = 1.0 / (dimension.max(1) as f32).sqrt();
+    Ok((0..dimension)
+        .map(|i| if blob[i / 8] & (1 << (i % 8)) != 0 { magnitude } else { -magnitude })
+        .collect())
+}
+
+/// Number of differing sign bits between two binary blobs
+pub fn hamming_distance(a: &[u8], b: &[u8]) -> Result<u32, String> {
+    if a.len() != b.len() {
+        return Err("Embedding dimensions must match".to_string());
+    }
+    Ok(a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum())
+}
+
+/// Similarity in [-1, 1] derived from the Hamming distance of two binary blobs
+pub fn binary_similarity(a: &[u8], b: &[u8]) -> Result<f32, String> {
+    let bits = (a.len() * 8) as f32;
+    if bits == 0.0 {
+        return Ok(0.0);
+    }
+    let distance = hamming_distance(a, b)? as f32;
+    Ok(1.0 - 2.0 * distance / bits)
+}
+
+/// Encode a vector in the requested form
+pub fn quantize(vector: &[f32], kind: QuantizationKind) -> Vec<u8> {
+    match kind {
+        QuantizationKind::Int8 => quantize_int8(vector),
+        QuantizationKind::Binary => quantize_binary(vector),
+    }
+}
+
+/// Approximate similarity between a quantized query and a stored quantized vector
+pub fn quantized_similarity(query: &[u8], stored: &[u8], kind: QuantizationKind) -> Result<f32, String> {
+    match kind {
+        QuantizationKind::Int8 => int8_cosine_similarity(query, stored),
+        QuantizationKind::Binary => binary_similarity(query, stored),
+    }
+}
+
+fn split_int8(blob: &[u8]) -> Result<(f32, &[u8]), String> {
+    if blob.len() < 4 {
+        return Err("Invalid BLOB size for int8 vector".to_string());
+    }
+    let scale = f32::from_le_bytes([blob[0], blob[1], blob[2], blob[3]]);
+    Ok((scale, &blob[4..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db_layer::vector_math::cosine_similarity;
+
+    #[test]
+    fn test_int8_roundtrip() {
+        let vector = vec![0.5, -0.25, 1.0, 0.0, -1.0, 0.125];
+        let blob = quantize_int8(&vector);
+        assert_eq!(blob.len(), 4 + vector.len());
+
+        let decoded = dequantize_int8(&blob).unwrap();
+        for (original, decoded) in vector.iter().zip(&decoded) {
+            assert!((original - decoded).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_int8_similarity_close_to_exact() {
+        let a = vec![0.3, -0.7, 0.2, 0.9, -0.1, 0.4, 0.05, -0.6];
+        let b = vec![0.25, -0.5, 0.3, 0.8, 0.1, 0.2, -0.05, -0.7];
+
+        let exact = cosine_similarity(&a, &b);
+        let approximate = int8_cosine_similarity(&quantize_int8(&a), &quantize_int8(&b)).unwrap();
+        assert!((exact - approximate).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_zero_vector() {
+        let blob = quantize_int8(&[0.0, 0.0, 0.0]);
+        assert_eq!(dequantize_int8(&blob).unwrap(), vec![0.0, 0.0, 0.0]);
+        assert_eq!(int8_cosine_similarity(&blob, &blob).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_binary_quantization() {
+        let vector = vec![0.5, -0.25, 1.0, -0.1, 0.2, 0.3, -0.4, 0.6, -0.9];
+        let blob = quantize_binary(&vector);
+        assert_eq!(blob, vec![0b1011_0101, 0b0000_0000]);
+
+        assert_eq!(binary_similarity(&blob, &blob).unwrap(), 1.0);
+        let negated: Vec<f32> = vector.iter().map(|x| -x).collect();
+        assert_eq!(hamming_distance(&blob, &quantize_binary(&negated)).unwrap(), 9);
+    }
+
+    #[test]
+    fn test_storage_settings_replace_full_precision() {
+        let vector = vec![0.5, -0.25, 1.0, -0.1, 0.2, 0.3, -0.4, 0.6, -0.9];
+
+        let (full, int8, binary) = VectorStorageSettings::default().encode(&vector);
+        assert_eq!(full.len(), vector.len() * 4);
+        assert!(int8.is_none() && binary.is_none());
+
+        let int8_only = VectorStorageSettings { quantization: Some(QuantizationKind::Int8), keep_full_precision: false };
+        let (full, int8, binary) = int8_only.encode(&vector);
+        assert!(full.is_empty() && binary.is_none());
+        let decoded = decode_stored_vector(&full, int8.as_deref(), None, vector.len()).unwrap();
+        assert!(cosine_similarity(&vector, &decoded) > 0.99);
+
+        let binary_only = VectorStorageSettings { quantization: Some(QuantizationKind::Binary), keep_full_precision: false };
+        let (full, _, binary) = binary_only.encode(&vector);
+        let decoded = decode_stored_vector(&full, None, binary.as_deref(), vector.len()).unwrap();
+        assert_eq!(quantize_binary(&decoded), quantize_binary(&vector));
+
+        let rescored = VectorStorageSettings { quantization: Some(QuantizationKind::Binary), keep_full_precision: true };
+        let (full, int8, binary) = rescored.encode(&vector);
+        assert_eq!(decode_stored_vector(&full, None, binary.as_deref(), vector.len()).unwrap(), vector);
+        assert!(int8.is_none());
+    }
+
+    #[test]
+    fn test_mismatched_dimensions() {
+        assert!(int8_cosine_similarity(&quantize_int8(&[1.0, 2.0]), &quantize_int8(&[1.0])).is_err());
+        assert!(hamming_distance(&[0u8], &[0u8, 1u8]).is_err());
+        assert!(dequantize_int8(&[0u8, 1u8]).is_err());
+    }
+}
@@ -9,7 +9,7 @@
 
 use crate::application::ai_provider::{
     AiProvider, AiProviderError, AiProviderFactory, ConversationContext, Message, MessageRole,
-    ModelConfig, ProviderCapabilities, ProviderInfo, StreamChunk,
+    ModelConfig, ProviderCapabilities, ProviderInfo, StreamChunk, StreamSink, utils,
 };
 use crate::infrastructure::credential_manager::CredentialManager;
 use async_trait::async_trait;
@@ -381,10 +381,10 @@ impl GeminiProvider {
             .query(&[("key", api_key)]))
     }
 
-    /// Send streaming chunks to the frontend via Tauri
+    /// Send a streaming chunk to `sink`
     async fn send_stream_chunk(
         &self,
-        app_handle: &AppHandle,
+        sink: &StreamSink<'_>,
         content: String,
         is_final: bool,
     ) -> Result<(), AiProviderError> {
@@ -402,14 +402,7 @@ impl GeminiProvider {
             }),
         };
 
-        app_handle
-            .emit_all("ai_stream_chunk", &chunk)
-            .map_err(|e| {
-                error!("Failed to emit stream chunk: {}", e);
-                AiProviderError::ProviderError(format!("Failed to emit stream chunk: {}", e))
-            })?;
-
-        Ok(())
+        sink(chunk)
     }
 }
 
@@ -484,6 +477,17 @@ impl AiProvider for GeminiProvider {
         &self,
         context: ConversationContext,
         app_handle: &AppHandle,
+    ) -> Result<(), AiProviderError> {
+        self.invoke_model_stream_to(context, &|chunk| {
+            utils::emit_stream_chunk(app_handle, &chunk).inspect_err(|e| error!("GeminiProvider: {}", e))
+        })
+        .await
+    }
+
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
     ) -> Result<(), AiProviderError> {
         debug!("GeminiProvider: Starting streaming completion");
 
@@ -495,8 +499,8 @@ impl AiProvider for GeminiProvider {
             // Fall back to non-streaming for models that don't support it
             warn!("GeminiProvider: Model doesn't support streaming, using non-streaming mode");
             let response = self.invoke_model(context).await?;
-            self.send_stream_chunk(app_handle, response, false).await?;
-            self.send_stream_chunk(app_handle, "".to_string(), true).await?;
+            self.send_stream_chunk(sink, response, false).await?;
+            self.send_stream_chunk(sink, "".to_string(), true).await?;
             return Ok(());
         }
 
@@ -535,7 +539,7 @@ impl AiProvider for GeminiProvider {
                     match self.parse_gemini_response(&chunk_response) {
                         Ok(text) => {
                             if !text.is_empty() {
-                                self.send_stream_chunk(app_handle, text, false).await?;
+                                self.send_stream_chunk(sink, text, false).await?;
                             }
                         }
                         Err(e) => {
@@ -552,7 +556,7 @@ impl AiProvider for GeminiProvider {
         }
 
         // Send final chunk
-        self.send_stream_chunk(app_handle, "".to_string(), true).await?;
+        self.send_stream_chunk(sink, "".to_string(), true).await?;
 
         info!("GeminiProvider: Streaming completion successful");
         Ok(())
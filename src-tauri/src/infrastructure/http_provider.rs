@@ -0,0 +1,185 @@
+// HTTP Provider Support
+// Pieces shared by the providers that talk to an HTTP API: mapping error responses
+// to AiProviderError values and reading streamed responses line by line, for both
+// server-sent events and newline-delimited JSON
+
+use crate::application::ai_provider::AiProviderError;
+use futures_util::{Stream, StreamExt};
+use serde_json::Value;
+use std::fmt::Display;
+use std::pin::Pin;
+
+/// Message and error code of an error response body. `{"error": {"message": ...,
+/// "code" or "type": ...}}` and `{"error": "..."}` bodies are understood; anything
+/// else is the message verbatim.
+pub(crate) fn error_details(body: &str) -> (String, Option<String>) {
+    let parsed: Option<Value> = serde_json::from_str(body).ok();
+    let error = parsed.as_ref().and_then(|value| value.get("error"));
+    let message = error
+        .and_then(|error| error.get("message").or(Some(error)))
+        .and_then(Value::as_str)
+        .unwrap_or(body)
+        .trim()
+        .to_string();
+    let code = error
+        .and_then(|error| error.get("code").or_else(|| error.get("type")))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    (message, code)
+}
+
+/// AiProviderError for an HTTP error status, `message` already carrying the status
+pub(crate) fn status_error(status: u16, message: String) -> AiProviderError {
+    match status {
+        401 | 403 => AiProviderError::AuthenticationError(message),
+        429 => AiProviderError::RateLimitError(message),
+        400 | 404 | 413 | 422 => AiProviderError::InvalidRequest(message),
+        500..=599 => AiProviderError::ServiceUnavailable(message),
+        _ => AiProviderError::ProviderError(message),
+    }
+}
+
+/// Lines of a streamed response body, without their line endings. A last line the
+/// server did not terminate is still returned when the body ends.
+pub(crate) struct StreamLines<S> {
+    stream: Pin<Box<S>>,
+    buffer: Vec<u8>,
+    ended: bool,
+}
+
+impl<S, B, E> StreamLines<S>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Display,
+{
+    pub(crate) fn new(stream: S) -> Self {
+        Self { stream: Box::pin(stream), buffer: Vec::new(), ended: false }
+    }
+
+    /// The next line, or None once the body is exhausted
+    pub(crate) async fn next_line(&mut self) -> Result<Option<String>, AiProviderError> {
+        loop {
+            if let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+                return Ok(Some(Self::decode(&line)));
+            }
+            if self.ended {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                let line = std::mem::take(&mut self.buffer);
+                return Ok(Some(Self::decode(&line)));
+            }
+
+            match self.stream.next().await {
+                Some(bytes) => {
+                    let bytes = bytes.map_err(|e| {
+                        AiProviderError::NetworkError(format!("Failed to read streaming response: {}", e))
+                    })?;
+                    self.buffer.extend_from_slice(bytes.as_ref());
+                }
+                None => self.ended = true,
+            }
+        }
+    }
+
+    fn decode(line: &[u8]) -> String {
+        String::from_utf8_lossy(line).trim_end_matches(['\r', '\n']).to_string()
+    }
+}
+
+/// HTTP server for provider tests
+#[cfg(test)]
+pub(crate) mod stub_server {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Serves one canned `(status, content type, body)` response per connection and
+    /// returns the raw requests it received. The URL has no trailing slash.
+    pub(crate) async fn start(responses: Vec<(u16, &'static str, String)>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for (status, content_type, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                // Read the headers, then as much body as Content-Length announces
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(header_end) = text.find("\r\n\r\n") {
+                        let content_length = text[..header_end]
+                            .lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|value| value.trim().to_string()))
+                            .and_then(|value| value.parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= header_end + 4 + content_length || read == 0 {
+                            break;
+                        }
+                    }
+                }
+                requests.push(String::from_utf8_lossy(&request).to_string());
+
+                let response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+
+        (url, server)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+
+    async fn collect_lines(chunks: &[&str]) -> Vec<String> {
+        let chunks: Vec<Result<Vec<u8>, String>> = chunks.iter().map(|chunk| Ok(chunk.as_bytes().to_vec())).collect();
+        let mut lines = StreamLines::new(stream::iter(chunks));
+        let mut collected = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            collected.push(line);
+        }
+        collected
+    }
+
+    #[tokio::test]
+    async fn test_stream_lines_split_across_chunks() {
+        assert_eq!(collect_lines(&["data: a\r\n\nda", "ta: b\n", "data: c"]).await, vec!["data: a", "", "data: b", "data: c"]);
+        assert_eq!(collect_lines(&["{\"done\": true}\n"]).await, vec!["{\"done\": true}"]);
+        assert!(collect_lines(&[]).await.is_empty());
+
+        let failing: Vec<Result<Vec<u8>, String>> = vec![Ok(b"partial".to_vec()), Err("reset".to_string())];
+        let error = StreamLines::new(stream::iter(failing)).next_line().await.unwrap_err();
+        assert!(matches!(error, AiProviderError::NetworkError(ref message) if message.contains("reset")));
+    }
+
+    #[test]
+    fn test_error_details_and_status() {
+        let body = r#"{"error": {"message": " Out of credit ", "type": "insufficient_quota"}}"#;
+        assert_eq!(error_details(body), ("Out of credit".to_string(), Some("insufficient_quota".to_string())));
+        assert_eq!(error_details(r#"{"error": "model not found"}"#), ("model not found".to_string(), None));
+        assert_eq!(error_details("Bad gateway\n"), ("Bad gateway".to_string(), None));
+
+        assert!(matches!(status_error(403, String::new()), AiProviderError::AuthenticationError(_)));
+        assert!(matches!(status_error(422, String::new()), AiProviderError::InvalidRequest(_)));
+        assert!(matches!(status_error(529, String::new()), AiProviderError::ServiceUnavailable(_)));
+        assert!(matches!(status_error(418, String::new()), AiProviderError::ProviderError(_)));
+    }
+}
@@ -10,6 +10,10 @@ pub mod model_manager;
 pub mod local_provider;
 pub mod bedrock_provider;
 pub mod gemini_provider;
+pub mod openai_compatible_provider;
+pub mod ollama_provider;
+pub mod anthropic_provider;
+pub(crate) mod http_provider;
 
 // Re-export commonly used types
 pub use database::*;
@@ -20,3 +24,6 @@ pub use model_manager::*;
 pub use local_provider::*;
 pub use bedrock_provider::*;
 pub use gemini_provider::*;
+pub use openai_compatible_provider::*;
+pub use ollama_provider::*;
+pub use anthropic_provider::*;
@@ -0,0 +1,696 @@
+/// OllamaProvider Implementation
+///
+/// This module implements the OllamaProvider struct that conforms to the AiProvider trait,
+/// talking to a locally running Ollama server over its HTTP API. Any chat model pulled into
+/// Ollama can be used, not only the Phi-3 model the built-in sidecar runs.
+///
+/// Besides chat (streamed as newline-delimited JSON), the provider discovers the installed
+/// models, tests the connection and generates embeddings. OllamaEmbedder turns the
+/// embedding model into a QueryEmbedder, so it can fill the vector_index table and embed
+/// queries for the vector arm of retrieval.
+
+use crate::application::ai_provider::{
+    AiProvider, AiProviderError, AiProviderFactory, ConversationContext, MessageRole, ProviderCapabilities,
+    ProviderInfo, StreamChunk, StreamSink, utils,
+};
+use crate::application::query_embedding::QueryEmbedder;
+use crate::infrastructure::http_provider::{error_details, status_error, StreamLines};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tokio::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// How long availability checks wait for the server (seconds)
+const PROBE_TIMEOUT: u64 = 2;
+
+/// Configuration for the OllamaProvider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaProviderConfig {
+    /// Default model to use for completions
+    pub default_model: String,
+    /// Model used for embeddings
+    pub embedding_model: String,
+    /// Base URL of the Ollama server
+    pub base_url: String,
+    /// Maximum timeout for API requests (seconds); local models can be slow to load
+    pub request_timeout: u64,
+    /// Whether to use streaming by default
+    pub enable_streaming: bool,
+    /// How long Ollama keeps the model loaded after a request (e.g. "5m"); server
+    /// default when unset
+    pub keep_alive: Option<String>,
+}
+
+impl Default for OllamaProviderConfig {
+    fn default() -> Self {
+        Self {
+            default_model: "llama3.1".to_string(),
+            embedding_model: "nomic-embed-text".to_string(),
+            base_url: "http://localhost:11434".to_string(),
+            request_timeout: 120,
+            enable_streaming: true,
+            keep_alive: None,
+        }
+    }
+}
+
+/// Chat response structure; streaming responses send one per line
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaMessage {
+    content: String,
+}
+
+/// Installed models listing structure
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    models: Vec<OllamaModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModelEntry {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaVersionResponse {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Map an HTTP error response to an AiProviderError. Ollama reports errors as
+/// `{"error": "..."}` and answers 404 for models that have not been pulled.
+fn map_error_response(status: u16, body: &str) -> AiProviderError {
+    let (message, _) = error_details(body);
+    status_error(status, format!("{} - {}", status, message))
+}
+
+/// OllamaProvider struct implementing the AiProvider trait
+///
+/// This provider manages communication with a local Ollama server, providing both
+/// streaming and non-streaming interfaces plus embeddings.
+pub struct OllamaProvider {
+    /// HTTP client for API requests
+    client: Client,
+    /// Provider configuration
+    config: OllamaProviderConfig,
+    /// Provider capabilities
+    capabilities: ProviderCapabilities,
+}
+
+impl OllamaProvider {
+    /// Create a new OllamaProvider with default configuration
+    pub fn new() -> Self {
+        Self::with_config(OllamaProviderConfig::default())
+    }
+
+    /// Create a new OllamaProvider with custom configuration
+    pub fn with_config(config: OllamaProviderConfig) -> Self {
+        let capabilities = ProviderCapabilities {
+            supports_streaming: config.enable_streaming,
+            supports_functions: false, // Not implemented in current version
+            supports_images: false,    // Only text content is sent
+            supports_system_messages: true,
+            max_context_length: None,  // Depends on the model and its num_ctx
+        };
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client,
+            config,
+            capabilities,
+        }
+    }
+
+    /// Build API URL for the given endpoint path
+    fn build_api_url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Convert conversation context to a chat request payload
+    fn context_to_payload(&self, context: &ConversationContext, stream: bool) -> Value {
+        let mut messages = Vec::new();
+
+        // Use system prompt from context unless the messages carry one
+        let has_system_message = context.messages.iter().any(|message| matches!(message.role, MessageRole::System));
+        if let (false, Some(system_prompt)) = (has_system_message, &context.system_prompt) {
+            messages.push(json!({"role": "system", "content": system_prompt}));
+        }
+
+        for message in &context.messages {
+            let (role, content) = match message.role {
+                MessageRole::System => ("system", message.content.clone()),
+                MessageRole::User => ("user", message.content.clone()),
+                MessageRole::Assistant => ("assistant", message.content.clone()),
+                // Function results are passed on as user messages
+                MessageRole::Function => ("user", format!("Function result: {}", message.content)),
+            };
+            messages.push(json!({"role": role, "content": content}));
+        }
+
+        // Sampling parameters go in "options", with Ollama's names
+        let mut options = serde_json::Map::new();
+        if let Some(max_tokens) = context.config.max_tokens {
+            options.insert("num_predict".to_string(), json!(max_tokens));
+        }
+        if let Some(temperature) = context.config.temperature {
+            options.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(top_p) = context.config.top_p {
+            options.insert("top_p".to_string(), json!(top_p));
+        }
+        if let Some(stop_sequences) = &context.config.stop_sequences {
+            options.insert("stop".to_string(), json!(stop_sequences));
+        }
+        // Other model options (num_ctx, seed, ...) go through as-is
+        for (key, value) in &context.config.extra_params {
+            options.insert(key.clone(), value.clone());
+        }
+
+        let mut payload = json!({
+            "model": self.config.default_model,
+            "messages": messages,
+            "stream": stream,
+            "options": options,
+        });
+        if let Some(keep_alive) = &self.config.keep_alive {
+            payload["keep_alive"] = json!(keep_alive);
+        }
+
+        payload
+    }
+
+    /// Send a request and turn error statuses into typed errors
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AiProviderError> {
+        let response = request.send().await.map_err(|e| {
+            error!("OllamaProvider: Request failed: {}", e);
+            AiProviderError::NetworkError(format!(
+                "Ollama request to {} failed, is Ollama running? {}",
+                self.config.base_url, e
+            ))
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            error!("OllamaProvider: Request failed: {} - {}", status, error_text);
+            return Err(map_error_response(status, &error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Version of the Ollama server
+    pub async fn server_version(&self) -> Result<String, AiProviderError> {
+        let response = self.send(self.client.get(self.build_api_url("version"))).await?;
+        let version: OllamaVersionResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse version response: {}", e))
+        })?;
+        Ok(version.version)
+    }
+
+    /// Embeddings of `texts` from the configured embedding model, in the same order
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AiProviderError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut payload = json!({
+            "model": self.config.embedding_model,
+            "input": texts,
+        });
+        if let Some(keep_alive) = &self.config.keep_alive {
+            payload["keep_alive"] = json!(keep_alive);
+        }
+
+        let response = self.send(self.client.post(self.build_api_url("embed")).json(&payload)).await?;
+        let embedded: OllamaEmbedResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse embedding response: {}", e))
+        })?;
+
+        if embedded.embeddings.len() != texts.len() {
+            return Err(AiProviderError::ProviderError(format!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                embedded.embeddings.len()
+            )));
+        }
+        Ok(embedded.embeddings)
+    }
+
+    /// `embedding_model` the vectors of the configured embedding model are stored under
+    pub fn index_model(&self) -> String {
+        format!("ollama:{}", self.config.embedding_model)
+    }
+
+    /// Send a streaming chunk to `sink`
+    fn send_stream_chunk(
+        &self,
+        sink: &StreamSink<'_>,
+        content: String,
+        is_final: bool,
+    ) -> Result<(), AiProviderError> {
+        let chunk = StreamChunk {
+            content,
+            is_final,
+            metadata: Some({
+                let mut metadata = HashMap::new();
+                metadata.insert("provider".to_string(), Value::String("ollama".to_string()));
+                metadata.insert("model".to_string(), Value::String(self.config.default_model.clone()));
+                metadata.insert("timestamp".to_string(), Value::Number(
+                    serde_json::Number::from(chrono::Utc::now().timestamp())
+                ));
+                metadata
+            }),
+        };
+
+        sink(chunk)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    async fn get_provider_info(&self) -> ProviderInfo {
+        // Available when the server answers quickly
+        let probe = self.client.get(self.build_api_url("version")).timeout(Duration::from_secs(PROBE_TIMEOUT));
+        let is_available = matches!(probe.send().await, Ok(response) if response.status().is_success());
+
+        ProviderInfo {
+            name: "ollama".to_string(),
+            display_name: "Ollama".to_string(),
+            description: format!("Models served by Ollama at {}", self.config.base_url),
+            is_available,
+            supported_models: vec![self.config.default_model.clone()],
+            capabilities: self.capabilities.clone(),
+        }
+    }
+
+    async fn validate_configuration(&self) -> Result<bool, AiProviderError> {
+        let version = self.server_version().await?;
+        debug!("OllamaProvider: Connected to Ollama {}", version);
+
+        // Ollama lists models with their tag ("llama3.1:latest")
+        let models = self.get_available_models().await?;
+        let installed = |model: &str| {
+            models.iter().any(|name| name == model || name.strip_suffix(":latest") == Some(model))
+        };
+        if !installed(&self.config.default_model) {
+            return Err(AiProviderError::ConfigurationError(format!(
+                "Model {} is not installed in Ollama; run `ollama pull {}`",
+                self.config.default_model, self.config.default_model
+            )));
+        }
+        if !installed(&self.config.embedding_model) {
+            warn!("OllamaProvider: Embedding model {} is not installed", self.config.embedding_model);
+        }
+        Ok(true)
+    }
+
+    async fn invoke_model_stream(
+        &self,
+        context: ConversationContext,
+        app_handle: &AppHandle,
+    ) -> Result<(), AiProviderError> {
+        self.invoke_model_stream_to(context, &|chunk| {
+            utils::emit_stream_chunk(app_handle, &chunk).inspect_err(|e| error!("OllamaProvider: {}", e))
+        })
+        .await
+    }
+
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
+    ) -> Result<(), AiProviderError> {
+        debug!("OllamaProvider: Starting streaming completion");
+
+        if !self.config.enable_streaming {
+            warn!("OllamaProvider: Streaming disabled, using non-streaming mode");
+            let response = self.invoke_model(context).await?;
+            self.send_stream_chunk(sink, response, false)?;
+            return self.send_stream_chunk(sink, String::new(), true);
+        }
+
+        let payload = self.context_to_payload(&context, true);
+        let response = self.send(self.client.post(self.build_api_url("chat")).json(&payload)).await?;
+
+        // Newline-delimited JSON, ended by an object with "done": true
+        let mut lines = StreamLines::new(response.bytes_stream());
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let chunk: OllamaChatResponse = serde_json::from_str(line.trim()).map_err(|e| {
+                AiProviderError::ProviderError(format!("Failed to parse stream line: {}", e))
+            })?;
+            if let Some(error) = chunk.error {
+                return Err(AiProviderError::ProviderError(format!("Ollama stream failed: {}", error)));
+            }
+            if let Some(message) = chunk.message.filter(|message| !message.content.is_empty()) {
+                self.send_stream_chunk(sink, message.content, false)?;
+            }
+            if chunk.done {
+                break;
+            }
+        }
+
+        // Send final chunk
+        self.send_stream_chunk(sink, String::new(), true)?;
+
+        info!("OllamaProvider: Streaming completion successful");
+        Ok(())
+    }
+
+    async fn invoke_model(&self, context: ConversationContext) -> Result<String, AiProviderError> {
+        debug!("OllamaProvider: Starting non-streaming completion");
+
+        let payload = self.context_to_payload(&context, false);
+        let response = self.send(self.client.post(self.build_api_url("chat")).json(&payload)).await?;
+
+        let chat: OllamaChatResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse response JSON: {}", e))
+        })?;
+        if let Some(error) = chat.error {
+            return Err(AiProviderError::ProviderError(format!("Ollama request failed: {}", error)));
+        }
+        let message = chat.message.ok_or_else(|| {
+            AiProviderError::ProviderError("No valid response content found".to_string())
+        })?;
+
+        info!("OllamaProvider: Non-streaming completion successful");
+        Ok(message.content)
+    }
+
+    async fn get_available_models(&self) -> Result<Vec<String>, AiProviderError> {
+        let response = self.send(self.client.get(self.build_api_url("tags"))).await?;
+        let tags: OllamaTagsResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse model list: {}", e))
+        })?;
+
+        let mut models: Vec<String> = tags.models.into_iter().map(|model| model.name).collect();
+        models.sort();
+        Ok(models)
+    }
+
+    async fn test_connection(&self) -> Result<bool, AiProviderError> {
+        self.server_version().await.map(|_| true)
+    }
+
+    fn get_config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "default_model": {
+                    "type": "string",
+                    "description": "Default model to use for completions",
+                    "default": "llama3.1"
+                },
+                "embedding_model": {
+                    "type": "string",
+                    "description": "Model used for embeddings",
+                    "default": "nomic-embed-text"
+                },
+                "base_url": {
+                    "type": "string",
+                    "description": "Base URL of the Ollama server",
+                    "default": "http://localhost:11434"
+                },
+                "request_timeout": {
+                    "type": "integer",
+                    "description": "Maximum timeout for API requests in seconds",
+                    "default": 120,
+                    "minimum": 10,
+                    "maximum": 600
+                },
+                "enable_streaming": {
+                    "type": "boolean",
+                    "description": "Whether to use streaming by default",
+                    "default": true
+                },
+                "keep_alive": {
+                    "type": ["string", "null"],
+                    "description": "How long Ollama keeps the model loaded after a request, e.g. \"5m\"",
+                    "default": null
+                }
+            },
+            "required": ["default_model"]
+        })
+    }
+
+    fn clone_provider(&self) -> Box<dyn AiProvider> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for OllamaProvider {
+    fn clone(&self) -> Self {
+        Self::with_config(self.config.clone())
+    }
+}
+
+/// Factory for creating OllamaProvider instances
+pub struct OllamaProviderFactory;
+
+impl AiProviderFactory for OllamaProviderFactory {
+    fn create_provider(&self, config: serde_json::Value) -> Result<Box<dyn AiProvider>, AiProviderError> {
+        let provider_config: OllamaProviderConfig = serde_json::from_value(config)
+            .map_err(|e| AiProviderError::ConfigurationError(format!("Invalid configuration: {}", e)))?;
+
+        Ok(Box::new(OllamaProvider::with_config(provider_config)))
+    }
+
+    fn provider_type(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        OllamaProvider::new().get_config_schema()
+    }
+}
+
+impl Default for OllamaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Query embedder backed by an Ollama embedding model. Vectors are stored under
+/// `OllamaProvider::index_model`, and the dimension is pinned by a probe embedding when
+/// connecting, so a model swapped behind the same name is caught instead of mixed in.
+pub struct OllamaEmbedder {
+    provider: OllamaProvider,
+    model: String,
+    dimension: usize,
+    /// Runtime the requests run on; `embed` is called from synchronous retrieval code
+    runtime: tokio::runtime::Handle,
+}
+
+impl OllamaEmbedder {
+    /// Connect to the configured embedding model and learn its dimension
+    pub async fn connect(config: OllamaProviderConfig) -> Result<Self, AiProviderError> {
+        let provider = OllamaProvider::with_config(config);
+        let probe = provider.embed(&["dimension probe".to_string()]).await?;
+        let dimension = probe.first().map(Vec::len).filter(|dimension| *dimension > 0).ok_or_else(|| {
+            AiProviderError::ConfigurationError(format!(
+                "Embedding model {} returned an empty embedding",
+                provider.config.embedding_model
+            ))
+        })?;
+        info!("OllamaEmbedder: {} embeds into {} dimensions", provider.config.embedding_model, dimension);
+
+        Ok(Self {
+            model: provider.index_model(),
+            provider,
+            dimension,
+            runtime: tokio::runtime::Handle::current(),
+        })
+    }
+}
+
+impl QueryEmbedder for OllamaEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Blocks until Ollama answers, at most the provider's request timeout. The request
+    /// runs on a scoped thread, so this is safe to call from commands on the runtime.
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        let texts = [text.to_string()];
+        let embeddings = std::thread::scope(|scope| {
+            scope
+                .spawn(|| self.runtime.block_on(self.provider.embed(&texts)))
+                .join()
+                .map_err(|_| "Embedding thread panicked".to_string())
+        })?;
+        embeddings
+            .map_err(|e| e.to_string())?
+            .pop()
+            .ok_or_else(|| "Ollama returned no embedding".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::query_embedding::embed_query_text;
+    use crate::infrastructure::http_provider::stub_server;
+    use std::sync::Mutex;
+    use tokio::task::JoinHandle;
+
+    /// Stub server answering every request with JSON
+    async fn stub_server(responses: Vec<(u16, String)>) -> (String, JoinHandle<Vec<String>>) {
+        let responses = responses.into_iter().map(|(status, body)| (status, "application/json", body)).collect();
+        stub_server::start(responses).await
+    }
+
+    fn provider_for(base_url: String) -> OllamaProvider {
+        OllamaProvider::with_config(OllamaProviderConfig {
+            default_model: "qwen2.5".to_string(),
+            base_url,
+            keep_alive: Some("10m".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_context_to_payload() {
+        let provider = OllamaProvider::new();
+        let mut context = utils::create_simple_context("Hello!".to_string(), Some("You are helpful.".to_string()));
+        context.config.extra_params.insert("num_ctx".to_string(), json!(8192));
+
+        let payload = provider.context_to_payload(&context, false);
+        assert_eq!(payload["model"], "llama3.1");
+        assert_eq!(payload["stream"], false);
+        assert_eq!(payload["messages"][0]["role"], "system");
+        assert_eq!(payload["messages"][1]["content"], "Hello!");
+        assert_eq!(payload["options"]["num_predict"], json!(4096));
+        assert_eq!(payload["options"]["num_ctx"], json!(8192));
+        assert!(payload.get("keep_alive").is_none());
+        assert_eq!(provider.build_api_url("chat"), "http://localhost:11434/api/chat");
+    }
+
+    #[tokio::test]
+    async fn test_chat_models_and_connection_against_stub_server() {
+        let chat = json!({"model": "qwen2.5", "message": {"role": "assistant", "content": "Hi!"}, "done": true});
+        let tags = json!({"models": [{"name": "qwen2.5:latest"}, {"name": "nomic-embed-text:latest"}]});
+        let (base_url, server) = stub_server(vec![
+            (200, chat.to_string()),
+            (200, json!({"version": "0.3.12"}).to_string()),
+            (200, tags.to_string()),
+            (404, json!({"error": "model \"qwen2.5\" not found, try pulling it first"}).to_string()),
+        ])
+        .await;
+        let provider = provider_for(base_url);
+        let context = utils::create_simple_context("Hello".to_string(), None);
+
+        assert_eq!(provider.invoke_model(context.clone()).await.unwrap(), "Hi!");
+        assert!(provider.validate_configuration().await.unwrap());
+        let error = provider.invoke_model(context).await.unwrap_err();
+        assert!(matches!(error, AiProviderError::InvalidRequest(ref message) if message.contains("try pulling it first")));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/chat HTTP/1.1"));
+        assert!(requests[0].contains(r#""keep_alive":"10m""#));
+        assert!(requests[1].starts_with("GET /api/version HTTP/1.1"));
+        assert!(requests[2].starts_with("GET /api/tags HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_against_stub_server() {
+        let lines = [
+            json!({"message": {"role": "assistant", "content": "Hel"}, "done": false}),
+            json!({"message": {"role": "assistant", "content": "lo"}, "done": false}),
+            json!({"message": {"role": "assistant", "content": ""}, "done": true, "eval_count": 2}),
+        ];
+        // The last line is not newline-terminated, which must not lose it
+        let body = lines.iter().map(Value::to_string).collect::<Vec<_>>().join("\n");
+        let (base_url, server) = stub_server(vec![(200, body)]).await;
+        let provider = provider_for(base_url);
+
+        let chunks = Mutex::new(Vec::new());
+        let context = utils::create_simple_context("Hi".to_string(), None);
+        provider
+            .invoke_model_stream_to(context, &|chunk| {
+                chunks.lock().unwrap().push((chunk.content, chunk.is_final));
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let chunks = chunks.into_inner().unwrap();
+        assert_eq!(chunks, vec![("Hel".to_string(), false), ("lo".to_string(), false), (String::new(), true)]);
+        assert!(server.await.unwrap()[0].contains(r#""stream":true"#));
+    }
+
+    // Multi-threaded, so the embedder's blocking call can be served by another worker
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_embedder_pins_model_and_dimension() {
+        let embeddings = |vectors: Value| (200, json!({"model": "nomic-embed-text", "embeddings": vectors}).to_string());
+        let (base_url, server) = stub_server(vec![
+            embeddings(json!([[0.1, 0.2, 0.3], [0.4, 0.5, 0.6]])),
+            embeddings(json!([[1.0, 0.0, 0.0]])),
+            embeddings(json!([[0.0, 1.0, 0.0]])),
+            embeddings(json!([[1.0, 0.0]])),
+            embeddings(json!([])),
+        ])
+        .await;
+        let provider = provider_for(base_url.clone());
+
+        let batch = provider.embed(&["first".to_string(), "second".to_string()]).await.unwrap();
+        assert_eq!(batch, vec![vec![0.1, 0.2, 0.3], vec![0.4, 0.5, 0.6]]);
+        assert!(provider.embed(&[]).await.unwrap().is_empty());
+
+        // Connecting probes the dimension; later vectors of another size are rejected
+        let embedder = OllamaEmbedder::connect(provider.config.clone()).await.unwrap();
+        assert_eq!((embedder.model(), embedder.dimension()), ("ollama:nomic-embed-text", 3));
+        assert_eq!(embed_query_text(&embedder, "query").unwrap(), vec![0.0, 1.0, 0.0]);
+        assert!(embed_query_text(&embedder, "query").unwrap_err().contains("expected 3"));
+        assert!(embedder.embed("query").is_err());
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/embed HTTP/1.1"));
+        assert!(requests[0].contains(r#""input":["first","second"]"#));
+        assert!(requests[2].contains(r#""input":["query"]"#));
+    }
+
+    #[test]
+    fn test_factory_creation() {
+        let factory = OllamaProviderFactory;
+        assert_eq!(factory.provider_type(), "ollama");
+
+        let config = serde_json::json!({
+            "default_model": "mistral",
+            "enable_streaming": false
+        });
+
+        let provider = factory.create_provider(config).unwrap();
+        let schema = provider.get_config_schema();
+        assert!(schema["properties"]["embedding_model"].is_object());
+        assert!(matches!(map_error_response(503, "loading"), AiProviderError::ServiceUnavailable(_)));
+    }
+}
@@ -0,0 +1,650 @@
+/// OpenAiCompatibleProvider Implementation
+///
+/// This module implements the OpenAiCompatibleProvider struct that conforms to the AiProvider
+/// trait, speaking the OpenAI chat completions API over HTTP. The base URL is configurable,
+/// so the same provider works with OpenAI itself, Azure-style gateways, llama.cpp server,
+/// vLLM and LM Studio. The API key is read from the OS keychain through the
+/// credential_manager module.
+///
+/// Streaming uses server-sent events, models are listed from the server's `/models`
+/// endpoint, and HTTP error responses are mapped to typed AiProviderError values.
+
+use crate::application::ai_provider::{
+    AiProvider, AiProviderError, AiProviderFactory, ConversationContext, MessageRole, ProviderCapabilities,
+    ProviderInfo, StreamChunk, StreamSink, utils,
+};
+use crate::infrastructure::credential_manager::CredentialManager;
+use crate::infrastructure::http_provider::{error_details, status_error, StreamLines};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tokio::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Configuration for the OpenAiCompatibleProvider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAiCompatibleProviderConfig {
+    /// Default model to use for completions
+    pub default_model: String,
+    /// Base URL of the API, up to and including the version segment
+    /// (e.g. "https://api.openai.com/v1" or "http://localhost:8080/v1")
+    pub base_url: String,
+    /// Maximum timeout for API requests (seconds)
+    pub request_timeout: u64,
+    /// Whether to use streaming by default
+    pub enable_streaming: bool,
+    /// Whether requests carry the API key stored in the keychain; local servers
+    /// usually run without one
+    pub use_api_key: bool,
+    /// Header the API key is sent in as-is (e.g. "api-key" for Azure); when unset the
+    /// key is sent as "Authorization: Bearer <key>"
+    pub api_key_header: Option<String>,
+    /// Query parameters added to every request (e.g. "api-version" for Azure)
+    pub query_params: HashMap<String, String>,
+}
+
+impl Default for OpenAiCompatibleProviderConfig {
+    fn default() -> Self {
+        Self {
+            default_model: "gpt-4o-mini".to_string(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            request_timeout: 60,
+            enable_streaming: true,
+            use_api_key: true,
+            api_key_header: None,
+            query_params: HashMap::new(),
+        }
+    }
+}
+
+/// Chat completions response structure
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionChoice {
+    message: Option<ChatCompletionMessage>,
+    delta: Option<ChatCompletionMessage>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+}
+
+/// Model listing response structure
+#[derive(Debug, Deserialize)]
+struct ModelListResponse {
+    data: Vec<ModelListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelListEntry {
+    id: String,
+}
+
+/// Map an HTTP error response to an AiProviderError. Besides the usual status
+/// mapping, a 429 for an exhausted quota is a QuotaExceeded error.
+fn map_error_response(status: u16, body: &str) -> AiProviderError {
+    let (message, code) = error_details(body);
+    let message = format!("{} - {}", status, message);
+    match (status, code.as_deref()) {
+        (429, Some("insufficient_quota")) => AiProviderError::QuotaExceeded(message),
+        _ => status_error(status, message),
+    }
+}
+
+/// Text of one server-sent event's data from a streaming completion. Returns None
+/// for the terminating `[DONE]` event.
+fn stream_delta(data: &str) -> Result<Option<String>, AiProviderError> {
+    if data == "[DONE]" {
+        return Ok(None);
+    }
+    let value: Value = serde_json::from_str(data)
+        .map_err(|e| AiProviderError::ProviderError(format!("Failed to parse stream event: {}", e)))?;
+    if value.get("error").is_some() {
+        return Err(map_error_response(500, data));
+    }
+    let response: ChatCompletionResponse = serde_json::from_value(value)
+        .map_err(|e| AiProviderError::ProviderError(format!("Failed to parse stream event: {}", e)))?;
+    let text = response
+        .choices
+        .into_iter()
+        .filter_map(|choice| choice.delta.and_then(|delta| delta.content))
+        .collect();
+    Ok(Some(text))
+}
+
+/// OpenAiCompatibleProvider struct implementing the AiProvider trait
+///
+/// This provider manages communication with any server implementing the OpenAI chat
+/// completions API, providing both streaming and non-streaming interfaces.
+pub struct OpenAiCompatibleProvider {
+    /// HTTP client for API requests
+    client: Client,
+    /// Provider configuration
+    config: OpenAiCompatibleProviderConfig,
+    /// Credential manager for API key
+    credential_manager: CredentialManager,
+    /// Provider capabilities
+    capabilities: ProviderCapabilities,
+}
+
+impl OpenAiCompatibleProvider {
+    /// Create a new OpenAiCompatibleProvider with default configuration
+    pub fn new() -> Self {
+        Self::with_config(OpenAiCompatibleProviderConfig::default())
+    }
+
+    /// Create a new OpenAiCompatibleProvider with custom configuration
+    pub fn with_config(config: OpenAiCompatibleProviderConfig) -> Self {
+        let capabilities = ProviderCapabilities {
+            supports_streaming: config.enable_streaming,
+            supports_functions: false, // Not implemented in current version
+            supports_images: false,    // Only text content is sent
+            supports_system_messages: true,
+            max_context_length: None,  // Depends on the model the server runs
+        };
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client,
+            config,
+            credential_manager: CredentialManager::new(),
+            capabilities,
+        }
+    }
+
+    /// Get API key from credential manager
+    fn get_api_key(&self) -> Result<String, AiProviderError> {
+        self.credential_manager
+            .retrieve_openai_api_key()
+            .map_err(|e| {
+                error!("Failed to retrieve OpenAI API key: {}", e);
+                AiProviderError::AuthenticationError(format!(
+                    "Failed to retrieve OpenAI API key: {}",
+                    e
+                ))
+            })
+    }
+
+    /// Build API URL for the given endpoint path
+    fn build_api_url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Header name and value carrying `api_key`
+    fn auth_header(&self, api_key: &str) -> (String, String) {
+        match &self.config.api_key_header {
+            Some(header) => (header.clone(), api_key.to_string()),
+            None => ("Authorization".to_string(), format!("Bearer {}", api_key)),
+        }
+    }
+
+    /// Add the configured query parameters and, when enabled, the API key
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, AiProviderError> {
+        let request = request.query(&self.config.query_params);
+        if !self.config.use_api_key {
+            return Ok(request);
+        }
+        let (header, value) = self.auth_header(&self.get_api_key()?);
+        Ok(request.header(header, value))
+    }
+
+    /// Convert conversation context to a chat completions request payload
+    fn context_to_payload(&self, context: &ConversationContext, stream: bool) -> Value {
+        let mut messages = Vec::new();
+
+        // Use system prompt from context unless the messages carry one
+        let has_system_message = context.messages.iter().any(|message| matches!(message.role, MessageRole::System));
+        if let (false, Some(system_prompt)) = (has_system_message, &context.system_prompt) {
+            messages.push(json!({"role": "system", "content": system_prompt}));
+        }
+
+        for message in &context.messages {
+            let (role, content) = match message.role {
+                MessageRole::System => ("system", message.content.clone()),
+                MessageRole::User => ("user", message.content.clone()),
+                MessageRole::Assistant => ("assistant", message.content.clone()),
+                // Function results are passed on as user messages
+                MessageRole::Function => ("user", format!("Function result: {}", message.content)),
+            };
+            messages.push(json!({"role": role, "content": content}));
+        }
+
+        let mut payload = json!({
+            "model": self.config.default_model,
+            "messages": messages,
+            "stream": stream,
+        });
+
+        // Add optional parameters
+        if let Some(max_tokens) = context.config.max_tokens {
+            payload["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(temperature) = context.config.temperature {
+            payload["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = context.config.top_p {
+            payload["top_p"] = json!(top_p);
+        }
+        if let Some(stop_sequences) = &context.config.stop_sequences {
+            payload["stop"] = json!(stop_sequences);
+        }
+        // Server-specific parameters go through as-is
+        for (key, value) in &context.config.extra_params {
+            payload[key.as_str()] = value.clone();
+        }
+
+        payload
+    }
+
+    /// Send a request and turn error statuses into typed errors
+    async fn send(&self, request: RequestBuilder) -> Result<Response, AiProviderError> {
+        let response = request.send().await.map_err(|e| {
+            error!("OpenAiCompatibleProvider: Request failed: {}", e);
+            AiProviderError::NetworkError(format!("OpenAI-compatible request failed: {}", e))
+        })?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            error!("OpenAiCompatibleProvider: Request failed: {} - {}", status, error_text);
+            return Err(map_error_response(status, &error_text));
+        }
+
+        Ok(response)
+    }
+
+    /// Post a chat completions request
+    async fn post_chat_completions(&self, payload: &Value) -> Result<Response, AiProviderError> {
+        let request = self.client.post(self.build_api_url("chat/completions")).json(payload);
+        self.send(self.authorize(request)?).await
+    }
+
+    /// Send a streaming chunk to `sink`
+    fn send_stream_chunk(
+        &self,
+        sink: &StreamSink<'_>,
+        content: String,
+        is_final: bool,
+    ) -> Result<(), AiProviderError> {
+        let chunk = StreamChunk {
+            content,
+            is_final,
+            metadata: Some({
+                let mut metadata = HashMap::new();
+                metadata.insert("provider".to_string(), Value::String("openai_compatible".to_string()));
+                metadata.insert("model".to_string(), Value::String(self.config.default_model.clone()));
+                metadata.insert("timestamp".to_string(), Value::Number(
+                    serde_json::Number::from(chrono::Utc::now().timestamp())
+                ));
+                metadata
+            }),
+        };
+
+        sink(chunk)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiCompatibleProvider {
+    async fn get_provider_info(&self) -> ProviderInfo {
+        // Servers without keys are available as far as credentials go
+        let is_available = !self.config.use_api_key || self.credential_manager.retrieve_openai_api_key().is_ok();
+
+        ProviderInfo {
+            name: "openai_compatible".to_string(),
+            display_name: "OpenAI-compatible".to_string(),
+            description: format!("OpenAI chat completions API at {}", self.config.base_url),
+            is_available,
+            supported_models: vec![self.config.default_model.clone()],
+            capabilities: self.capabilities.clone(),
+        }
+    }
+
+    async fn validate_configuration(&self) -> Result<bool, AiProviderError> {
+        // Listing models checks the URL and the API key without generating anything
+        let models = self.get_available_models().await?;
+        if !models.is_empty() && !models.contains(&self.config.default_model) {
+            warn!(
+                "OpenAiCompatibleProvider: Model {} is not listed by {}",
+                self.config.default_model, self.config.base_url
+            );
+        }
+        Ok(true)
+    }
+
+    async fn invoke_model_stream(
+        &self,
+        context: ConversationContext,
+        app_handle: &AppHandle,
+    ) -> Result<(), AiProviderError> {
+        self.invoke_model_stream_to(context, &|chunk| {
+            utils::emit_stream_chunk(app_handle, &chunk).inspect_err(|e| error!("OpenAiCompatibleProvider: {}", e))
+        })
+        .await
+    }
+
+    async fn invoke_model_stream_to(
+        &self,
+        context: ConversationContext,
+        sink: &StreamSink<'_>,
+    ) -> Result<(), AiProviderError> {
+        debug!("OpenAiCompatibleProvider: Starting streaming completion");
+
+        if !self.config.enable_streaming {
+            warn!("OpenAiCompatibleProvider: Streaming disabled, using non-streaming mode");
+            let response = self.invoke_model(context).await?;
+            self.send_stream_chunk(sink, response, false)?;
+            return self.send_stream_chunk(sink, String::new(), true);
+        }
+
+        let payload = self.context_to_payload(&context, true);
+        let response = self.post_chat_completions(&payload).await?;
+
+        // Server-sent events: "data: <json>" lines, ended by "data: [DONE]"
+        let mut lines = StreamLines::new(response.bytes_stream());
+        while let Some(line) = lines.next_line().await? {
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                // Comments, event names and blank separators
+                continue;
+            };
+            match stream_delta(data.trim())? {
+                Some(text) if !text.is_empty() => self.send_stream_chunk(sink, text, false)?,
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        // Send final chunk
+        self.send_stream_chunk(sink, String::new(), true)?;
+
+        info!("OpenAiCompatibleProvider: Streaming completion successful");
+        Ok(())
+    }
+
+    async fn invoke_model(&self, context: ConversationContext) -> Result<String, AiProviderError> {
+        debug!("OpenAiCompatibleProvider: Starting non-streaming completion");
+
+        let payload = self.context_to_payload(&context, false);
+        let response = self.post_chat_completions(&payload).await?;
+
+        let completion: ChatCompletionResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse response JSON: {}", e))
+        })?;
+
+        let choice = completion.choices.into_iter().next().ok_or_else(|| {
+            AiProviderError::ProviderError("No valid response content found".to_string())
+        })?;
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(AiProviderError::ProviderError(
+                "Response blocked by content filter".to_string(),
+            ));
+        }
+        let result = choice.message.and_then(|message| message.content).unwrap_or_default();
+
+        info!("OpenAiCompatibleProvider: Non-streaming completion successful");
+        Ok(result)
+    }
+
+    async fn get_available_models(&self) -> Result<Vec<String>, AiProviderError> {
+        let request = self.client.get(self.build_api_url("models"));
+        let response = self.send(self.authorize(request)?).await?;
+
+        let listing: ModelListResponse = response.json().await.map_err(|e| {
+            AiProviderError::ProviderError(format!("Failed to parse model list: {}", e))
+        })?;
+
+        let mut models: Vec<String> = listing.data.into_iter().map(|model| model.id).collect();
+        models.sort();
+        Ok(models)
+    }
+
+    async fn test_connection(&self) -> Result<bool, AiProviderError> {
+        self.validate_configuration().await
+    }
+
+    fn get_config_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "default_model": {
+                    "type": "string",
+                    "description": "Default model to use for completions",
+                    "default": "gpt-4o-mini"
+                },
+                "base_url": {
+                    "type": "string",
+                    "description": "Base URL of the API, including the version segment",
+                    "default": "https://api.openai.com/v1"
+                },
+                "request_timeout": {
+                    "type": "integer",
+                    "description": "Maximum timeout for API requests in seconds",
+                    "default": 60,
+                    "minimum": 10,
+                    "maximum": 300
+                },
+                "enable_streaming": {
+                    "type": "boolean",
+                    "description": "Whether to use streaming by default",
+                    "default": true
+                },
+                "use_api_key": {
+                    "type": "boolean",
+                    "description": "Whether to send the API key stored in the keychain",
+                    "default": true
+                },
+                "api_key_header": {
+                    "type": ["string", "null"],
+                    "description": "Header to send the API key in as-is; defaults to a Bearer Authorization header",
+                    "default": null
+                },
+                "query_params": {
+                    "type": "object",
+                    "description": "Query parameters added to every request",
+                    "additionalProperties": {"type": "string"},
+                    "default": {}
+                }
+            },
+            "required": ["default_model", "base_url"]
+        })
+    }
+
+    fn clone_provider(&self) -> Box<dyn AiProvider> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for OpenAiCompatibleProvider {
+    fn clone(&self) -> Self {
+        Self::with_config(self.config.clone())
+    }
+}
+
+/// Factory for creating OpenAiCompatibleProvider instances
+pub struct OpenAiCompatibleProviderFactory;
+
+impl AiProviderFactory for OpenAiCompatibleProviderFactory {
+    fn create_provider(&self, config: serde_json::Value) -> Result<Box<dyn AiProvider>, AiProviderError> {
+        let provider_config: OpenAiCompatibleProviderConfig = serde_json::from_value(config)
+            .map_err(|e| AiProviderError::ConfigurationError(format!("Invalid configuration: {}", e)))?;
+
+        Ok(Box::new(OpenAiCompatibleProvider::with_config(provider_config)))
+    }
+
+    fn provider_type(&self) -> &'static str {
+        "openai_compatible"
+    }
+
+    fn config_schema(&self) -> serde_json::Value {
+        OpenAiCompatibleProvider::new().get_config_schema()
+    }
+}
+
+impl Default for OpenAiCompatibleProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::http_provider::stub_server;
+    use std::sync::Mutex;
+    use tokio::task::JoinHandle;
+
+    /// Stub server answering under a `/v1` base URL
+    async fn mock_server(responses: Vec<(u16, &'static str, String)>) -> (String, JoinHandle<Vec<String>>) {
+        let (url, server) = stub_server::start(responses).await;
+        (format!("{}/v1", url), server)
+    }
+
+    fn provider_for(base_url: String) -> OpenAiCompatibleProvider {
+        OpenAiCompatibleProvider::with_config(OpenAiCompatibleProviderConfig {
+            default_model: "local-model".to_string(),
+            base_url,
+            use_api_key: false,
+            query_params: HashMap::from([("api-version".to_string(), "2024-06-01".to_string())]),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_context_to_payload() {
+        let provider = OpenAiCompatibleProvider::new();
+        let mut context = utils::create_simple_context("Hello!".to_string(), Some("You are helpful.".to_string()));
+        context.config.extra_params.insert("repetition_penalty".to_string(), json!(1.1));
+
+        let payload = provider.context_to_payload(&context, true);
+        assert_eq!(payload["model"], "gpt-4o-mini");
+        assert_eq!(payload["stream"], true);
+        assert_eq!(payload["messages"][0]["role"], "system");
+        assert_eq!(payload["messages"][0]["content"], "You are helpful.");
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(payload["repetition_penalty"], json!(1.1));
+        assert_eq!(payload["max_tokens"], json!(4096));
+    }
+
+    #[test]
+    fn test_auth_header_and_urls() {
+        let provider = OpenAiCompatibleProvider::new();
+        assert_eq!(provider.build_api_url("chat/completions"), "https://api.openai.com/v1/chat/completions");
+        assert_eq!(provider.auth_header("sk-1"), ("Authorization".to_string(), "Bearer sk-1".to_string()));
+
+        let azure = OpenAiCompatibleProvider::with_config(OpenAiCompatibleProviderConfig {
+            base_url: "https://example.openai.azure.com/openai/deployments/chat/".to_string(),
+            api_key_header: Some("api-key".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(azure.build_api_url("models"), "https://example.openai.azure.com/openai/deployments/chat/models");
+        assert_eq!(azure.auth_header("secret"), ("api-key".to_string(), "secret".to_string()));
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let unauthorized = map_error_response(401, r#"{"error": {"message": "Incorrect API key", "code": "invalid_api_key"}}"#);
+        assert!(matches!(unauthorized, AiProviderError::AuthenticationError(ref message) if message == "401 - Incorrect API key"));
+        let quota = map_error_response(429, r#"{"error": {"message": "Out of credit", "type": "insufficient_quota"}}"#);
+        assert!(matches!(quota, AiProviderError::QuotaExceeded(_)));
+        assert!(matches!(map_error_response(429, r#"{"error": "Slow down"}"#), AiProviderError::RateLimitError(_)));
+        assert!(matches!(map_error_response(404, "model not found"), AiProviderError::InvalidRequest(_)));
+        assert!(matches!(map_error_response(503, "Loading model"), AiProviderError::ServiceUnavailable(_)));
+        assert!(matches!(map_error_response(418, ""), AiProviderError::ProviderError(_)));
+    }
+
+    #[tokio::test]
+    async fn test_completion_and_models_against_mock_server() {
+        let completion = json!({
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hello there"}, "finish_reason": "stop"}]
+        });
+        let models = json!({"object": "list", "data": [{"id": "local-model"}, {"id": "embedder"}]});
+        let (base_url, server) = mock_server(vec![
+            (200, "application/json", completion.to_string()),
+            (200, "application/json", models.to_string()),
+            (401, "application/json", r#"{"error": {"message": "Bad key"}}"#.to_string()),
+        ])
+        .await;
+        let provider = provider_for(base_url);
+        let context = utils::create_simple_context("Hi".to_string(), None);
+
+        assert_eq!(provider.invoke_model(context.clone()).await.unwrap(), "Hello there");
+        assert_eq!(provider.get_available_models().await.unwrap(), vec!["embedder", "local-model"]);
+        let error = provider.invoke_model(context).await.unwrap_err();
+        assert!(matches!(error, AiProviderError::AuthenticationError(_)));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /v1/chat/completions?api-version=2024-06-01 HTTP/1.1"));
+        assert!(requests[0].contains(r#""model":"local-model""#));
+        assert!(!requests[0].to_lowercase().contains("authorization:"));
+        assert!(requests[1].starts_with("GET /v1/models?api-version=2024-06-01 HTTP/1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_against_mock_server() {
+        let events = [
+            ": keep-alive",
+            r#"data: {"choices": [{"index": 0, "delta": {"role": "assistant"}}]}"#,
+            r#"data: {"choices": [{"index": 0, "delta": {"content": "Hel"}}]}"#,
+            r#"data: {"choices": [{"index": 0, "delta": {"content": "lo"}, "finish_reason": "stop"}]}"#,
+            "data: [DONE]",
+        ];
+        let body = events.iter().map(|event| format!("{}\n\n", event)).collect::<String>();
+        // A server that ends the body without [DONE] or a final newline
+        let unterminated = events[2..4].join("\n\n");
+        let (base_url, server) = mock_server(vec![
+            (200, "text/event-stream", body),
+            (200, "text/event-stream", unterminated),
+        ])
+        .await;
+        let provider = provider_for(base_url);
+
+        let stream = |context: ConversationContext| async {
+            let chunks = Mutex::new(Vec::new());
+            provider
+                .invoke_model_stream_to(context, &|chunk| {
+                    chunks.lock().unwrap().push((chunk.content, chunk.is_final));
+                    Ok(())
+                })
+                .await
+                .unwrap();
+            chunks.into_inner().unwrap()
+        };
+        let expected = vec![("Hel".to_string(), false), ("lo".to_string(), false), (String::new(), true)];
+        let context = utils::create_simple_context("Hi".to_string(), None);
+        assert_eq!(stream(context.clone()).await, expected);
+        assert_eq!(stream(context).await, expected);
+        assert!(server.await.unwrap()[0].contains(r#""stream":true"#));
+    }
+
+    #[test]
+    fn test_factory_creation() {
+        let factory = OpenAiCompatibleProviderFactory;
+        assert_eq!(factory.provider_type(), "openai_compatible");
+
+        let config = serde_json::json!({
+            "default_model": "llama-3.1-8b",
+            "base_url": "http://localhost:1234/v1",
+            "use_api_key": false
+        });
+
+        let provider = factory.create_provider(config).unwrap();
+        let info = tokio_test::block_on(provider.get_provider_info());
+        assert_eq!(info.name, "openai_compatible");
+        assert!(info.is_available);
+        assert_eq!(info.supported_models, vec!["llama-3.1-8b"]);
+    }
+}
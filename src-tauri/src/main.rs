@@ -19,14 +19,19 @@ use application::model_commands::{
 // Import AI credential management commands
 use application::ai_credential_commands::{
     set_bedrock_credentials, validate_bedrock_credentials,
-    set_gemini_credentials, validate_gemini_credentials,
+    set_gemini_credentials, validate_gemini_credentials, set_openai_credentials, set_anthropic_credentials,
+    validate_anthropic_credentials,
     select_active_provider, list_ai_providers, get_active_provider_info
 };
 
 // Import hybrid RAG commands
 use commands::hybrid_rag::{
-    retrieve_context, test_hybrid_rag, get_hybrid_rag_config,
-    validate_hybrid_rag_config, get_hybrid_rag_stats
+    retrieve_context, retrieve_passages, test_hybrid_rag, get_hybrid_rag_config,
+    validate_hybrid_rag_config, get_hybrid_rag_stats, sync_project_content,
+    check_content_consistency, parse_search_query, find_match_ranges, search_documents, get_search_index_settings,
+    update_search_index_settings, run_rag_evaluation, get_rag_eval_reports, compare_rag_eval_reports,
+    set_hybrid_rag_config, reset_hybrid_rag_config, diff_hybrid_rag_config, ask_project, use_ollama_embeddings,
+    embed_project_documents
 };
 
 // Import updater commands
@@ -38,7 +43,8 @@ use commands::updater::{
 use commands::database_optimization::{
     optimize_database, analyze_rag_performance, get_database_performance_metrics,
     apply_index_recommendations, validate_database_health, analyze_specific_query,
-    get_optimization_history
+    get_optimization_history, compact_vector_storage, get_vector_storage_settings,
+    set_vector_storage_settings
 };
 
 // Import AI Blocks commands
@@ -52,6 +58,8 @@ use commands::ai_blocks::{
 
 // Import AI provider manager
 use application::ai_provider_manager::initialize_ai_provider_manager;
+use application::query_embedding::QueryEmbedderSlot;
+use application::query_expansion::QueryExpansionCache;
 
 // Import model versioning commands
 mod commands;
@@ -69,9 +77,11 @@ use services::{DatabaseOptimizationService, AiBlocksService};
 
 // Import performance profiler
 mod performance_profiler;
+mod synthetic_vectors;
 use performance_profiler::{
     start_performance_profiling, benchmark_database_operations,
-    benchmark_file_operations, benchmark_ai_operations, get_system_performance_info
+    benchmark_file_operations, benchmark_ai_operations, benchmark_vector_search,
+    get_system_performance_info
 };
 use infrastructure::{DatabaseManager, FilesystemManager, CredentialManager};
 use infrastructure::db_layer::DatabaseConnection;
@@ -114,15 +124,35 @@ fn main() {
             validate_bedrock_credentials,
             set_gemini_credentials,
             validate_gemini_credentials,
+            set_openai_credentials,
+            set_anthropic_credentials,
+            validate_anthropic_credentials,
             select_active_provider,
             list_ai_providers,
             get_active_provider_info,
             // Hybrid RAG commands
             retrieve_context,
+            retrieve_passages,
             test_hybrid_rag,
             get_hybrid_rag_config,
+            set_hybrid_rag_config,
+            reset_hybrid_rag_config,
+            diff_hybrid_rag_config,
+            ask_project,
             validate_hybrid_rag_config,
             get_hybrid_rag_stats,
+            sync_project_content,
+            check_content_consistency,
+            use_ollama_embeddings,
+            embed_project_documents,
+            parse_search_query,
+            find_match_ranges,
+            search_documents,
+            get_search_index_settings,
+            update_search_index_settings,
+            run_rag_evaluation,
+            get_rag_eval_reports,
+            compare_rag_eval_reports,
             // Updater commands
             check_for_updates,
             install_update,
@@ -133,6 +163,7 @@ fn main() {
             benchmark_database_operations,
             benchmark_file_operations,
             benchmark_ai_operations,
+            benchmark_vector_search,
             get_system_performance_info,
             // Database optimization commands
             optimize_database,
@@ -142,6 +173,9 @@ fn main() {
             validate_database_health,
             analyze_specific_query,
             get_optimization_history,
+            compact_vector_storage,
+            get_vector_storage_settings,
+            set_vector_storage_settings,
             // AI Blocks commands
             create_ai_block,
             update_ai_block,
@@ -225,6 +259,8 @@ fn main() {
             app.manage(db_connection_arc.clone());
             app.manage(optimization_service);
             app.manage(ai_blocks_service);
+            app.manage(QueryExpansionCache::default());
+            app.manage(QueryEmbedderSlot::default());
             
             Ok(())
         })
@@ -2,6 +2,8 @@ use std::time::{Duration, Instant};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use tauri::command;
+use crate::infrastructure::db_layer::{FlatVectorStore, HnswConfig, HnswIndex};
+use crate::synthetic_vectors::{exact_top_k, SyntheticVectors};
 
 /// Performance profiler for backend Rust operations
 /// Task 3.1.1: Conduct performance profiling on large documents and projects
@@ -252,6 +254,107 @@ pub async fn benchmark_ai_operations() -> Result<PerformanceBenchmark, String> {
     Ok(profiler.generate_benchmark("ai"))
 }
 
+/// Compare brute-force vector search against the flat store and HNSW index on synthetic data.
+/// Defaults to 10k and 100k vectors of 384 dimensions (the MiniLM embedding size)
+/// and reports recall@10 for a range of ef_search values unless one is given.
+#[command]
+pub async fn benchmark_vector_search(
+    sizes: Option<Vec<usize>>,
+    dimension: Option<usize>,
+    ef_search: Option<usize>,
+) -> Result<PerformanceBenchmark, String> {
+    let sizes = sizes.unwrap_or_else(|| vec![10_000, 100_000]);
+    let dimension = dimension.unwrap_or(384);
+    let ef_values = ef_search.map(|ef| vec![ef]).unwrap_or_else(|| vec![32, 64, 128, 256]);
+
+    tokio::task::spawn_blocking(move || run_vector_search_benchmark(&sizes, dimension, &ef_values))
+        .await
+        .map_err(|e| format!("Vector search benchmark failed: {}", e))?
+}
+
+fn run_vector_search_benchmark(sizes: &[usize], dimension: usize, ef_values: &[usize]) -> Result<PerformanceBenchmark, String> {
+    const QUERY_COUNT: usize = 50;
+    const TOP_K: usize = 10;
+    const CLUSTER_COUNT: usize = 100;
+
+    let mut profiler = PerformanceProfiler::new();
+    let mut rng = SyntheticVectors::new(0x853C_49E6_748F_EA9B);
+
+    // Real embeddings cluster by topic, so synthetic vectors are drawn around random centroids
+    let centroids = rng.vectors(CLUSTER_COUNT, dimension);
+    let mut clustered_vector = |i: usize| -> Vec<f32> {
+        centroids[i % CLUSTER_COUNT]
+            .iter()
+            .zip(rng.next_vector(dimension, 0.5))
+            .map(|(c, noise)| c + noise)
+            .collect()
+    };
+
+    for &size in sizes {
+        let vectors: Vec<Vec<f32>> = (0..size).map(&mut clustered_vector).collect();
+        let queries: Vec<Vec<f32>> = (0..QUERY_COUNT).map(|i| clustered_vector(i * 7)).collect();
+
+        let timer = profiler.start_operation(&format!("vector_hnsw_build_{}", size));
+        let index = HnswIndex::build(
+            dimension,
+            HnswConfig::default(),
+            vectors.iter().enumerate().map(|(i, v)| (i as i64, i as i64, v.clone())),
+        )?;
+        let mut metadata = HashMap::new();
+        metadata.insert("vectors".to_string(), size.to_string());
+        profiler.record_metric(timer.finish_with_metadata(metadata));
+
+        let mut exact_results = Vec::with_capacity(QUERY_COUNT);
+        let timer = profiler.start_operation(&format!("vector_brute_force_{}", size));
+        for query in &queries {
+            exact_results.push(exact_top_k(&vectors, query, TOP_K, 0));
+        }
+        let mut metadata = HashMap::new();
+        metadata.insert("vectors".to_string(), size.to_string());
+        metadata.insert("queries".to_string(), QUERY_COUNT.to_string());
+        profiler.record_metric(timer.finish_with_metadata(metadata));
+
+        let flat_store = FlatVectorStore::build(
+            dimension,
+            vectors.iter().enumerate().map(|(i, v)| (i as i64, i as i64, v.clone())),
+        )?;
+        let mut found = 0;
+        let timer = profiler.start_operation(&format!("vector_flat_search_{}", size));
+        for (query, expected) in queries.iter().zip(&exact_results) {
+            let hits = flat_store.search(query, TOP_K, None)?;
+            found += hits.iter().filter(|hit| expected.contains(&hit.vector_id)).count();
+        }
+        let mut metadata = HashMap::new();
+        metadata.insert("vectors".to_string(), size.to_string());
+        metadata.insert("queries".to_string(), QUERY_COUNT.to_string());
+        metadata.insert(
+            "recall_at_10".to_string(),
+            format!("{:.4}", found as f64 / (QUERY_COUNT * TOP_K) as f64),
+        );
+        profiler.record_metric(timer.finish_with_metadata(metadata));
+
+        for &ef in ef_values {
+            let mut found = 0;
+            let timer = profiler.start_operation(&format!("vector_hnsw_search_{}_ef{}", size, ef));
+            for (query, expected) in queries.iter().zip(&exact_results) {
+                let hits = index.search(query, TOP_K, Some(ef))?;
+                found += hits.iter().filter(|hit| expected.contains(&hit.vector_id)).count();
+            }
+            let mut metadata = HashMap::new();
+            metadata.insert("vectors".to_string(), size.to_string());
+            metadata.insert("queries".to_string(), QUERY_COUNT.to_string());
+            metadata.insert("ef_search".to_string(), ef.to_string());
+            metadata.insert(
+                "recall_at_10".to_string(),
+                format!("{:.4}", found as f64 / (QUERY_COUNT * TOP_K) as f64),
+            );
+            profiler.record_metric(timer.finish_with_metadata(metadata));
+        }
+    }
+
+    Ok(profiler.generate_benchmark("vector"))
+}
+
 #[command]
 pub async fn get_system_performance_info() -> Result<HashMap<String, String>, String> {
     let mut info = HashMap::new();
@@ -323,4 +426,24 @@ mod tests {
         assert_eq!(benchmark.summary.total_operations, 2);
         assert_eq!(benchmark.summary.average_duration_ms, 15.0);
     }
+
+    #[test]
+    fn test_vector_search_benchmark_reports_recall() {
+        let benchmark = run_vector_search_benchmark(&[500], 32, &[64])
+            .expect("Benchmark failed");
+
+        assert_eq!(benchmark.summary.total_operations, 4);
+        let flat = benchmark.metrics
+            .iter()
+            .find(|m| m.operation == "vector_flat_search_500")
+            .expect("Missing flat search metric");
+        assert_eq!(flat.metadata["recall_at_10"], "1.0000");
+
+        let search = benchmark.metrics
+            .iter()
+            .find(|m| m.operation == "vector_hnsw_search_500_ef64")
+            .expect("Missing HNSW search metric");
+        let recall: f64 = search.metadata["recall_at_10"].parse().unwrap();
+        assert!(recall > 0.8);
+    }
 }
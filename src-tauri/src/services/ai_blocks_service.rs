@@ -4,7 +4,7 @@
 // This service provides high-level business logic for AI Blocks (reusable prompts)
 // including template processing, variable substitution, and workflow management.
 
-use crate::infrastructure::db_layer::{DatabaseConnection, ai_blocks_repository::*};
+use crate::infrastructure::db_layer::{DatabaseConnection, TermDictionary, TermSource, ai_blocks_repository::*};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -130,10 +130,24 @@ impl AiBlocksService {
         repo.find_all(filter.as_ref(), sort_by, sort_direction, limit)
     }
 
-    /// Search AI Blocks
-    pub async fn search_ai_blocks(&self, query: &str, limit: Option<i32>) -> Result<Vec<AiBlock>, String> {
+    /// Search AI Blocks, retrying with a spelling-corrected query when nothing matches
+    pub async fn search_ai_blocks(&self, query: &str, limit: Option<i32>) -> Result<AiBlockSearchResult, String> {
         let repo = AiBlocksRepository::new(&self.db);
-        repo.search(query, limit)
+        let blocks = repo.search(query, limit)?;
+        if !blocks.is_empty() {
+            return Ok(AiBlockSearchResult { blocks, suggested_query: None });
+        }
+
+        match TermDictionary::new(&self.db, TermSource::AiBlocks).correct_text(query)? {
+            Some(corrected) => {
+                info!("No AI Blocks matched '{}', retrying with '{}'", query, corrected);
+                Ok(AiBlockSearchResult {
+                    blocks: repo.search(&corrected, limit)?,
+                    suggested_query: Some(corrected),
+                })
+            }
+            None => Ok(AiBlockSearchResult { blocks, suggested_query: None }),
+        }
     }
 
     /// Get AI Blocks by category
@@ -457,6 +471,14 @@ pub struct ProcessedPrompt {
     pub processing_timestamp: u64,
 }
 
+/// AI Blocks search result
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiBlockSearchResult {
+    pub blocks: Vec<AiBlock>,
+    /// Spelling-corrected query used when the original query matched nothing
+    pub suggested_query: Option<String>,
+}
+
 /// AI Blocks export format
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AiBlocksExport {
@@ -574,7 +596,21 @@ mod tests {
 
         // Should find system blocks from migration
         let results = service.search_ai_blocks("summarize", Some(10)).await.unwrap();
-        assert!(!results.is_empty());
+        assert!(!results.blocks.is_empty());
+        assert!(results.suggested_query.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_ai_blocks_suggests_correction() {
+        let service = setup_test_service().await;
+
+        let results = service.search_ai_blocks("summarise", Some(10)).await.unwrap();
+        assert_eq!(results.suggested_query.as_deref(), Some("summarize"));
+        assert!(results.blocks.iter().any(|block| block.id == "summarize-document"));
+
+        let results = service.search_ai_blocks("xylophone", Some(10)).await.unwrap();
+        assert!(results.blocks.is_empty());
+        assert!(results.suggested_query.is_none());
     }
 
     #[tokio::test]
@@ -4,7 +4,9 @@
 // This service provides high-level database optimization functionality
 // integrating the query optimizer with the application's database layer.
 
-use crate::infrastructure::db_layer::{DatabaseConnection, query_optimizer::*};
+use crate::infrastructure::db_layer::{
+    DatabaseConnection, MigrationManager, VectorIndexRepository, VectorStorageReport, VectorStorageSettings, query_optimizer::*,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, warn, error};
@@ -145,6 +147,42 @@ impl DatabaseOptimizationService {
         })
     }
 
+    /// Move vector rows to the configured storage layout and reclaim freed pages
+    pub async fn compact_vector_storage(&self) -> Result<VectorStorageReport, String> {
+        info!("Compacting vector storage");
+        
+        let report = MigrationManager::new(&self.db).convert_vector_storage()?;
+        self.db.execute("VACUUM", &[])?;
+        
+        info!(
+            "Vector storage compacted: {} embeddings converted, {} vectors converted, {} bytes saved",
+            report.embeddings_converted,
+            report.vectors_converted,
+            report.bytes_saved()
+        );
+        Ok(report)
+    }
+
+    /// Current vector storage settings (full precision only unless quantization was enabled)
+    pub async fn get_vector_storage_settings(&self) -> Result<VectorStorageSettings, String> {
+        VectorIndexRepository::new(&self.db).storage_settings()
+    }
+
+    /// Save vector storage settings, rewrite stored vectors to match and reclaim freed pages
+    pub async fn set_vector_storage_settings(&self, settings: VectorStorageSettings) -> Result<VectorStorageReport, String> {
+        info!("Applying vector storage settings: {:?}", settings);
+        
+        let report = VectorIndexRepository::new(&self.db).apply_storage_settings(&settings)?;
+        self.db.execute("VACUUM", &[])?;
+        
+        info!(
+            "Vector storage settings applied: {} vectors converted, {} bytes saved",
+            report.vectors_converted,
+            report.bytes_saved()
+        );
+        Ok(report)
+    }
+
     /// Validate database integrity and performance
     pub async fn validate_database_health(&self) -> Result<DatabaseHealthReport, String> {
         info!("Validating database health and integrity");
@@ -0,0 +1,345 @@
+// Document Content Service
+// Keeps document_content (and through its triggers the FTS5 index) in sync with
+// the markdown files on disk, and checks the index against those files.
+
+use crate::core::{Document, Project};
+use crate::infrastructure::db_layer::{
+    DatabaseConnection, DocumentContentRepository, DocumentRepository, EmbeddingRepository,
+    FTS5Repository, ProjectRepository,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+/// Result of syncing a single document from disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentSyncOutcome {
+    /// Content was stored or changed; carries the document key
+    Updated(i64),
+    /// Stored content already matched the file
+    Unchanged(i64),
+    /// The file no longer exists and its content was removed
+    Removed,
+}
+
+/// Summary of syncing every document of a project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentSyncReport {
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// (document id, error) for documents whose file could not be read
+    pub failed: Vec<(String, String)>,
+}
+
+/// Differences between document_content, the FTS5 index and the files on disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentConsistencyReport {
+    pub documents_checked: usize,
+    /// Documents whose file exists but has no stored content
+    pub missing_content: Vec<String>,
+    /// Documents whose stored content differs from the file
+    pub stale_content: Vec<String>,
+    /// Documents whose file is missing from disk
+    pub missing_files: Vec<String>,
+    /// Whether the FTS5 index matches document_content
+    pub fts_index_consistent: bool,
+}
+
+impl ContentConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_content.is_empty()
+            && self.stale_content.is_empty()
+            && self.missing_files.is_empty()
+            && self.fts_index_consistent
+    }
+}
+
+/// Service syncing document text from disk into the database
+pub struct DocumentContentService<'a> {
+    db: &'a DatabaseConnection,
+}
+
+impl<'a> DocumentContentService<'a> {
+    pub fn new(db: &'a DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Read a document's file and store its title and body
+    pub fn sync_document(&self, project: &Project, document: &Document) -> Result<ContentSyncOutcome, String> {
+        let content_repo = DocumentContentRepository::new(self.db);
+        let file_path = Self::file_path(project, document);
+
+        if !file_path.exists() {
+            content_repo.delete_by_document_id(&document.id)?;
+            return Ok(ContentSyncOutcome::Removed);
+        }
+
+        let content = fs::read_to_string(&file_path)
+            .map_err(|e| format!("Failed to read {}: {}", file_path.display(), e))?;
+        let title = DocumentContentRepository::extract_title(&content, &document.path);
+        let file_modified_at = Self::modified_at(&file_path);
+
+        if let Some(existing) = content_repo.get_by_document_id(&document.id)? {
+            if existing.title == title
+                && existing.content_hash == EmbeddingRepository::generate_content_hash(&content)
+            {
+                return Ok(ContentSyncOutcome::Unchanged(existing.id));
+            }
+        }
+
+        let key = content_repo.upsert(&document.id, &title, &content, file_modified_at)?;
+        Ok(ContentSyncOutcome::Updated(key))
+    }
+
+    /// Sync every document of a project
+    pub fn sync_project(&self, project_id: &str) -> Result<ContentSyncReport, String> {
+        let project = ProjectRepository::new(self.db)
+            .find_by_id(project_id)?
+            .ok_or_else(|| format!("Project {} not found", project_id))?;
+        let documents = DocumentRepository::new(self.db).find_by_project_id(project_id)?;
+
+        let mut report = ContentSyncReport::default();
+        for document in &documents {
+            match self.sync_document(&project, document) {
+                Ok(ContentSyncOutcome::Updated(_)) => report.updated += 1,
+                Ok(ContentSyncOutcome::Unchanged(_)) => report.unchanged += 1,
+                Ok(ContentSyncOutcome::Removed) => report.removed += 1,
+                Err(e) => {
+                    warn!("Failed to sync content for document {}: {}", document.id, e);
+                    report.failed.push((document.id.clone(), e));
+                }
+            }
+        }
+
+        info!(
+            "Synced content for project {}: {} updated, {} unchanged, {} removed, {} failed",
+            project_id, report.updated, report.unchanged, report.removed, report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// Sync the document stored at an absolute file path.
+    /// Returns the document key, or None if the file is not a known document.
+    pub fn sync_file(&self, path: &Path) -> Result<Option<i64>, String> {
+        let Some((project, document)) = self.resolve_document(path)? else {
+            return Ok(None);
+        };
+
+        match self.sync_document(&project, &document)? {
+            ContentSyncOutcome::Updated(key) | ContentSyncOutcome::Unchanged(key) => Ok(Some(key)),
+            ContentSyncOutcome::Removed => Ok(None),
+        }
+    }
+
+    /// Remove the stored content of the document at an absolute file path.
+    /// Returns the document key it had, if any.
+    pub fn remove_file(&self, path: &Path) -> Result<Option<i64>, String> {
+        let Some((_, document)) = self.resolve_document(path)? else {
+            return Ok(None);
+        };
+
+        let content_repo = DocumentContentRepository::new(self.db);
+        let key = content_repo.key_for_document(&document.id)?;
+        content_repo.delete_by_document_id(&document.id)?;
+        Ok(key)
+    }
+
+    /// Compare stored content and the FTS5 index with the files of a project
+    pub fn check_consistency(&self, project_id: &str) -> Result<ContentConsistencyReport, String> {
+        let project = ProjectRepository::new(self.db)
+            .find_by_id(project_id)?
+            .ok_or_else(|| format!("Project {} not found", project_id))?;
+        let documents = DocumentRepository::new(self.db).find_by_project_id(project_id)?;
+        let content_repo = DocumentContentRepository::new(self.db);
+
+        let mut report = ContentConsistencyReport {
+            documents_checked: documents.len(),
+            ..Default::default()
+        };
+
+        for document in &documents {
+            let stored = content_repo.get_by_document_id(&document.id)?;
+            let file_path = Self::file_path(&project, document);
+
+            match fs::read_to_string(&file_path) {
+                Ok(content) => match stored {
+                    None => report.missing_content.push(document.id.clone()),
+                    Some(stored) if stored.content_hash != EmbeddingRepository::generate_content_hash(&content) => {
+                        report.stale_content.push(document.id.clone())
+                    }
+                    Some(_) => {}
+                },
+                Err(_) => report.missing_files.push(document.id.clone()),
+            }
+        }
+
+        report.fts_index_consistent = FTS5Repository::new(self.db).check_index_integrity()?;
+        Ok(report)
+    }
+
+    /// Find the project containing a file and the document registered at that path
+    fn resolve_document(&self, path: &Path) -> Result<Option<(Project, Document)>, String> {
+        let projects = ProjectRepository::new(self.db).find_all()?;
+
+        // Prefer the most specific project when project directories are nested
+        let Some((project, relative)) = projects
+            .into_iter()
+            .filter_map(|project| {
+                let relative = path.strip_prefix(&project.path).ok()?.to_path_buf();
+                Some((project, relative))
+            })
+            .max_by_key(|(project, _)| project.path.len())
+        else {
+            return Ok(None);
+        };
+
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let document = DocumentRepository::new(self.db)
+            .find_by_project_id(&project.id)?
+            .into_iter()
+            .find(|document| document.path.trim_start_matches("./") == relative);
+
+        Ok(document.map(|document| (project, document)))
+    }
+
+    fn file_path(project: &Project, document: &Document) -> PathBuf {
+        Path::new(&project.path).join(&document.path)
+    }
+
+    fn modified_at(path: &Path) -> Option<u64> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DocumentState;
+    use crate::infrastructure::db_layer::MigrationManager;
+    use tempfile::{NamedTempFile, TempDir};
+
+    /// The temp file guard must outlive the connection
+    fn create_test_db() -> (NamedTempFile, DatabaseConnection) {
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let mut db = DatabaseConnection::new();
+        db.initialize(temp_file.path()).expect("Failed to create database connection");
+
+        let migration_manager = MigrationManager::new(&db);
+        migration_manager.migrate().expect("Failed to run migrations");
+
+        (temp_file, db)
+    }
+
+    fn create_project(db: &DatabaseConnection, dir: &TempDir) -> Project {
+        let project = Project {
+            id: "project-1".to_string(),
+            name: "Project".to_string(),
+            path: dir.path().to_string_lossy().to_string(),
+            created_at: 1,
+            updated_at: 1,
+        };
+        ProjectRepository::new(db).create(&project).unwrap();
+
+        for (id, path) in [("doc-1", "notes/alpha.md"), ("doc-2", "beta.md")] {
+            let document = Document {
+                id: id.to_string(),
+                project_id: project.id.clone(),
+                path: path.to_string(),
+                state: DocumentState::Draft,
+                created_at: 1,
+                updated_at: 1,
+            };
+            DocumentRepository::new(db).create(&document).unwrap();
+        }
+
+        fs::create_dir_all(dir.path().join("notes")).unwrap();
+        fs::write(dir.path().join("notes/alpha.md"), "# Alpha Plan\n\nlaunch the rocket").unwrap();
+        fs::write(dir.path().join("beta.md"), "no heading, only gardening notes").unwrap();
+
+        project
+    }
+
+    #[test]
+    fn test_sync_project_indexes_file_content() {
+        let (_temp_file, db) = create_test_db();
+        let dir = TempDir::new().unwrap();
+        create_project(&db, &dir);
+        let service = DocumentContentService::new(&db);
+
+        let report = service.sync_project("project-1").unwrap();
+        assert_eq!(report.updated, 2);
+        assert!(report.failed.is_empty());
+
+        let content = DocumentContentRepository::new(&db).get_by_document_id("doc-2").unwrap().unwrap();
+        assert_eq!(content.title, "beta");
+        assert!(content.file_modified_at.is_some());
+
+        let results = FTS5Repository::new(&db).search("rocket", Some(10)).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Alpha Plan");
+
+        // A second pass finds nothing to change
+        let report = service.sync_project("project-1").unwrap();
+        assert_eq!(report.unchanged, 2);
+        assert_eq!(report.updated, 0);
+    }
+
+    #[test]
+    fn test_sync_file_resolves_document_and_tracks_edits() {
+        let (_temp_file, db) = create_test_db();
+        let dir = TempDir::new().unwrap();
+        create_project(&db, &dir);
+        let service = DocumentContentService::new(&db);
+        let alpha = dir.path().join("notes/alpha.md");
+
+        let key = service.sync_file(&alpha).unwrap().expect("document should resolve");
+        assert!(service.sync_file(&dir.path().join("unknown.md")).unwrap().is_none());
+
+        fs::write(&alpha, "# Alpha Plan\n\nwater the garden").unwrap();
+        assert_eq!(service.sync_file(&alpha).unwrap(), Some(key));
+
+        let fts = FTS5Repository::new(&db);
+        assert!(fts.search("rocket", Some(10)).unwrap().is_empty());
+        assert_eq!(fts.search("garden", Some(10)).unwrap().len(), 1);
+
+        fs::remove_file(&alpha).unwrap();
+        assert_eq!(service.remove_file(&alpha).unwrap(), Some(key));
+        assert!(fts.search("garden", Some(10)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_consistency_reports_drift() {
+        let (_temp_file, db) = create_test_db();
+        let dir = TempDir::new().unwrap();
+        create_project(&db, &dir);
+        let service = DocumentContentService::new(&db);
+
+        let report = service.check_consistency("project-1").unwrap();
+        assert_eq!(report.documents_checked, 2);
+        assert_eq!(report.missing_content.len(), 2);
+        assert!(!report.is_consistent());
+
+        service.sync_project("project-1").unwrap();
+        let report = service.check_consistency("project-1").unwrap();
+        assert!(report.is_consistent(), "{:?}", report);
+
+        fs::write(dir.path().join("beta.md"), "edited outside the app").unwrap();
+        fs::remove_file(dir.path().join("notes/alpha.md")).unwrap();
+        let report = service.check_consistency("project-1").unwrap();
+        assert_eq!(report.stale_content, vec!["doc-2".to_string()]);
+        assert_eq!(report.missing_files, vec!["doc-1".to_string()]);
+        assert!(report.fts_index_consistent);
+    }
+}
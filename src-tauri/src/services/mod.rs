@@ -4,7 +4,9 @@
 
 pub mod database_optimization_service;
 pub mod ai_blocks_service;
+pub mod document_content_service;
 
 // Re-export commonly used types
 pub use database_optimization_service::*;
 pub use ai_blocks_service::*;
+pub use document_content_service::*;
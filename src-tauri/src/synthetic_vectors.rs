@@ -0,0 +1,64 @@
+// Synthetic Vectors
+// Deterministic pseudo-random vectors and exact top-k search shared by the vector index tests and the
+// vector search benchmark of the performance profiler
+
+use crate::infrastructure::db_layer::vector_math::{dot_product, normalize};
+
+/// Seed used by the vector index tests
+pub const DEFAULT_VECTOR_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// Xorshift generator producing components in [-1.0, 1.0)
+#[derive(Debug, Clone)]
+pub struct SyntheticVectors {
+    state: u64,
+}
+
+impl SyntheticVectors {
+    /// Create a generator from a seed (zero is remapped, xorshift would stay at zero)
+    pub fn new(seed: u64) -> Self {
+        Self { state: if seed == 0 { DEFAULT_VECTOR_SEED } else { seed } }
+    }
+
+    fn next_component(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        ((self.state % 2000) as f32 / 1000.0) - 1.0
+    }
+
+    /// Next vector with every component multiplied by `scale`
+    pub fn next_vector(&mut self, dimension: usize, scale: f32) -> Vec<f32> {
+        (0..dimension).map(|_| self.next_component() * scale).collect()
+    }
+
+    /// The next `count` unscaled vectors
+    pub fn vectors(&mut self, count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        (0..count).map(|_| self.next_vector(dimension, 1.0)).collect()
+    }
+}
+
+/// `count` vectors drawn from the default seed
+#[cfg(test)]
+pub fn synthetic_vectors(count: usize, dimension: usize) -> Vec<Vec<f32>> {
+    SyntheticVectors::new(DEFAULT_VECTOR_SEED).vectors(count, dimension)
+}
+
+/// A single vector determined by `seed`, so the same seed always yields the same embedding
+#[cfg(test)]
+pub fn seeded_vector(seed: u64, dimension: usize) -> Vec<f32> {
+    SyntheticVectors::new(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1).next_vector(dimension, 1.0)
+}
+
+/// Ids of the `k` vectors most similar to `query` by exact cosine similarity,
+/// scored the same way as the indexes (dot product of normalized vectors).
+/// The vector at position `i` has id `first_id + i`.
+pub fn exact_top_k(vectors: &[Vec<f32>], query: &[f32], k: usize, first_id: i64) -> Vec<i64> {
+    let query = normalize(query);
+    let mut scored: Vec<(i64, f32)> = vectors
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (first_id + i as i64, dot_product(&query, &normalize(v))))
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.into_iter().take(k).map(|(id, _)| id).collect()
+}
@@ -103,6 +103,11 @@ export interface TemplateValidationResult {
   warnings: string[];
 }
 
+export interface AiBlockSearchResult {
+  blocks: AiBlock[];
+  suggested_query: string | null;
+}
+
 // AI Blocks Store State
 interface AiBlocksState {
   // Data
@@ -115,6 +120,8 @@ interface AiBlocksState {
   isLoading: boolean;
   error: string | null;
   searchQuery: string;
+  // Spelling-corrected query the last search fell back to, if any
+  suggestedQuery: string | null;
   currentFilter: AiBlockFilter;
   sortBy: AiBlockSortBy;
   sortDirection: SortDirection;
@@ -170,6 +177,7 @@ export const useAiBlocksStore = create<AiBlocksState>((set, get) => ({
   isLoading: false,
   error: null,
   searchQuery: '',
+  suggestedQuery: null,
   currentFilter: {},
   sortBy: AiBlockSortBy.UpdatedAt,
   sortDirection: SortDirection.Desc,
@@ -197,13 +205,13 @@ export const useAiBlocksStore = create<AiBlocksState>((set, get) => ({
 
   // Search AI Blocks
   searchAiBlocks: async (query: string) => {
-    set({ isLoading: true, error: null, searchQuery: query });
+    set({ isLoading: true, error: null, searchQuery: query, suggestedQuery: null });
     try {
-      const aiBlocks = await invoke<AiBlock[]>('search_ai_blocks', {
+      const result = await invoke<AiBlockSearchResult>('search_ai_blocks', {
         query,
         limit: null,
       });
-      set({ aiBlocks, isLoading: false });
+      set({ aiBlocks: result.blocks, suggestedQuery: result.suggested_query, isLoading: false });
     } catch (error) {
       set({ error: String(error), isLoading: false });
     }